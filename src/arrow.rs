@@ -0,0 +1,348 @@
+//! Conversion of features into Arrow record batches (requires the `arrow` feature).
+//!
+//! The first column holds the geometry as WKB, typed as GeoArrow `geoarrow.wkb` extension, and
+//! the schema carries GeoParquet `geo` metadata, so the batches can be queried with DataFusion
+//! or written as GeoParquet by an Arrow Parquet writer.
+//!
+//! The other columns are inferred from the tags, like [`crate::polars`] does: Int64 if all values
+//! of a key are integers, Float64 if they are numeric, Binary if they are all bytes and Utf8
+//! otherwise, with lists as JSON text. Features without a tag get a null in that column. As all
+//! batches of a stream share one schema, it is inferred from the first batch: keys that first
+//! occur later are dropped, and values that do not fit their column are converted to strings
+//! for Utf8 columns and null otherwise.
+//! ```
+//! use spaten::arrow::{ArrowOptions, RecordBatches};
+//! use spaten::{Feature, Value};
+//! use std::collections::HashMap;
+//!
+//! let mut tags = HashMap::new();
+//! tags.insert("lanes".to_string(), Value::Integer(2));
+//! let fts = vec![Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags,
+//! }];
+//! let batches = RecordBatches::new(fts.into_iter(), ArrowOptions::default())?;
+//! for batch in batches {
+//!     let batch = batch.unwrap();
+//!     assert_eq!(batch.num_rows(), 1);
+//!     assert_eq!(batch.schema().field(1).name(), "lanes");
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::geojson::value_to_json;
+use crate::source::FeatureSource;
+use crate::{Feature, Value};
+use ::geojson::JsonValue;
+use arrow_array::builder::{BinaryBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch, RecordBatchReader};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct ArrowOptions {
+    /// Maximum number of rows per batch.
+    pub batch_size: usize,
+    /// Name of the geometry column.
+    pub geometry_column: String,
+}
+
+impl Default for ArrowOptions {
+    fn default() -> Self {
+        ArrowOptions {
+            batch_size: 8192,
+            geometry_column: "geometry".to_string(),
+        }
+    }
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn data_type(v: &Value) -> DataType {
+    match v {
+        Value::Integer(_) => DataType::Int64,
+        Value::Float(_) => DataType::Float64,
+        Value::Bytes(_) => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+/// Infers the schema of `fts`, with the geometry in the first column. Fails if a tag has the name
+/// of the geometry column.
+pub fn infer_schema(fts: &[Feature], geometry_column: &str) -> io::Result<Schema> {
+    let mut keys: BTreeMap<&str, DataType> = BTreeMap::new();
+    for (k, v) in fts.iter().flat_map(|ft| ft.tags.iter()) {
+        if k == geometry_column {
+            return Err(invalid_data(format!(
+                "tag {} collides with the geometry column",
+                k
+            )));
+        }
+        let dtype = data_type(v);
+        keys.entry(k)
+            .and_modify(|t| {
+                *t = match (&*t, &dtype) {
+                    (a, b) if a == b => dtype.clone(),
+                    (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => {
+                        DataType::Float64
+                    }
+                    _ => DataType::Utf8,
+                }
+            })
+            .or_insert(dtype);
+    }
+
+    let mut extension = HashMap::new();
+    extension.insert(
+        "ARROW:extension:name".to_string(),
+        "geoarrow.wkb".to_string(),
+    );
+    extension.insert("ARROW:extension:metadata".to_string(), "{}".to_string());
+    let mut fields =
+        vec![Field::new(geometry_column, DataType::Binary, false).with_metadata(extension)];
+    fields.extend(keys.into_iter().map(|(k, t)| Field::new(k, t, true)));
+
+    // no crs means OGC:CRS84, the lon/lat order of Spaten
+    let name = JsonValue::from(geometry_column).to_string();
+    let geo = format!(
+        "{{\"version\":\"1.1.0\",\"primary_column\":{},\"columns\":{{{}:{{\"encoding\":\"WKB\",\"geometry_types\":[]}}}}}}",
+        name, name
+    );
+    let mut metadata = HashMap::new();
+    metadata.insert("geo".to_string(), geo);
+    Ok(Schema::new_with_metadata(fields, metadata))
+}
+
+fn text(v: &Value) -> String {
+    match value_to_json(v) {
+        JsonValue::String(s) => s,
+        json => json.to_string(),
+    }
+}
+
+/// Converts `fts` into a batch of `schema`, which has the geometry in the first column. Tags
+/// without a column are dropped.
+pub fn to_record_batch(fts: &[Feature], schema: &SchemaRef) -> Result<RecordBatch, ArrowError> {
+    let mut geometry = BinaryBuilder::new();
+    for ft in fts {
+        let wkb = wkb::geom_to_wkb(&ft.geometry)
+            .map_err(|e| ArrowError::InvalidArgumentError(format!("{:?}", e)))?;
+        geometry.append_value(wkb);
+    }
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(geometry.finish())];
+    for field in schema.fields().iter().skip(1) {
+        let vals = fts.iter().map(|ft| ft.tags.get(field.name()));
+        columns.push(match field.data_type() {
+            DataType::Int64 => {
+                let mut b = Int64Builder::with_capacity(fts.len());
+                b.extend(vals.map(|v| match v {
+                    Some(Value::Integer(i)) => Some(*i),
+                    _ => None,
+                }));
+                Arc::new(b.finish())
+            }
+            DataType::Float64 => {
+                let mut b = Float64Builder::with_capacity(fts.len());
+                b.extend(vals.map(|v| match v {
+                    Some(Value::Integer(i)) => Some(*i as f64),
+                    Some(Value::Float(f)) => Some(*f),
+                    _ => None,
+                }));
+                Arc::new(b.finish())
+            }
+            DataType::Binary => {
+                let mut b = BinaryBuilder::new();
+                for v in vals {
+                    match v {
+                        Some(Value::Bytes(bytes)) => b.append_value(bytes),
+                        _ => b.append_null(),
+                    }
+                }
+                Arc::new(b.finish())
+            }
+            DataType::Utf8 => {
+                let mut b = StringBuilder::new();
+                for v in vals {
+                    b.append_option(v.map(text));
+                }
+                Arc::new(b.finish())
+            }
+            t => {
+                return Err(ArrowError::SchemaError(format!(
+                    "unsupported column type {}",
+                    t
+                )))
+            }
+        });
+    }
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+/// Reads a feature source as record batches of one schema.
+pub struct RecordBatches<S> {
+    src: S,
+    schema: SchemaRef,
+    batch_size: usize,
+    head: Option<Vec<Feature>>,
+}
+
+impl<S: FeatureSource> RecordBatches<S> {
+    /// Reads the first batch of `src` to infer the schema.
+    pub fn new(mut src: S, opts: ArrowOptions) -> io::Result<Self> {
+        assert!(opts.batch_size > 0, "batch size must be positive");
+        let head = read_batch(&mut src, opts.batch_size)?;
+        let schema = Arc::new(infer_schema(&head, &opts.geometry_column)?);
+        Ok(RecordBatches {
+            src,
+            schema,
+            batch_size: opts.batch_size,
+            head: Some(head),
+        })
+    }
+}
+
+fn read_batch<S: FeatureSource>(src: &mut S, n: usize) -> io::Result<Vec<Feature>> {
+    let mut fts = Vec::new();
+    while fts.len() < n {
+        match src.next_feature()? {
+            Some(ft) => fts.push(ft),
+            None => break,
+        }
+    }
+    Ok(fts)
+}
+
+impl<S: FeatureSource> Iterator for RecordBatches<S> {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let fts = match self.head.take() {
+            Some(head) => head,
+            None => match read_batch(&mut self.src, self.batch_size) {
+                Ok(fts) => fts,
+                Err(e) => return Some(Err(ArrowError::IoError(e.to_string(), e))),
+            },
+        };
+        if fts.is_empty() {
+            return None;
+        }
+        Some(to_record_batch(&fts, &self.schema))
+    }
+}
+
+impl<S: FeatureSource> RecordBatchReader for RecordBatches<S> {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArrowOptions, RecordBatches};
+    use crate::{wkbfast, Feature, Value};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, Int64Type};
+    use arrow_array::{Array, RecordBatch, RecordBatchReader};
+    use arrow_schema::DataType;
+    use geo_types::{line_string, Point};
+    use std::collections::HashMap;
+
+    fn feature(geometry: geo_types::Geometry<f64>, tags: &[(&str, Value)]) -> Feature {
+        Feature {
+            geometry,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn batches() {
+        let fts = vec![
+            feature(
+                line_string![(x: 0., y: 0.), (x: 1., y: 1.)].into(),
+                &[
+                    ("id", Value::from(1)),
+                    ("width", Value::from(3)),
+                    ("name", Value::from("Main")),
+                ],
+            ),
+            feature(
+                Point::new(3., 4.).into(),
+                &[
+                    ("id", Value::from(2)),
+                    ("width", Value::from(2.5)),
+                    ("raw", Value::Bytes(vec![0xff])),
+                ],
+            ),
+            feature(
+                Point::new(5., 6.).into(),
+                &[
+                    ("id", Value::from(2.5)),
+                    ("name", Value::from(7)),
+                    ("later", Value::from("x")),
+                ],
+            ),
+        ];
+        let opts = ArrowOptions {
+            batch_size: 2,
+            ..Default::default()
+        };
+        let batches = RecordBatches::new(fts.clone().into_iter(), opts).unwrap();
+        let schema = batches.schema();
+        let types: Vec<(&str, &DataType)> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("geometry", &DataType::Binary),
+                ("id", &DataType::Int64),
+                ("name", &DataType::Utf8),
+                ("raw", &DataType::Binary),
+                ("width", &DataType::Float64),
+            ]
+        );
+        assert!(schema.metadata()["geo"].contains("\"primary_column\":\"geometry\""));
+        assert_eq!(
+            schema.field(0).metadata()["ARROW:extension:name"],
+            "geoarrow.wkb"
+        );
+
+        let batches: Vec<RecordBatch> = batches.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].num_rows(), 1);
+        let geometry = batches[0].column(0).as_binary::<i32>();
+        assert_eq!(
+            wkbfast::decode(geometry.value(0)),
+            Ok(fts[0].geometry.clone())
+        );
+        let width = batches[0].column(4).as_primitive::<Float64Type>();
+        assert_eq!(width.values(), &[3., 2.5]);
+        assert!(batches[0].column(2).is_null(1));
+        assert!(batches[0].column(3).is_null(0));
+        // the float id does not fit, the number fits as string
+        assert!(batches[1].column(1).as_primitive::<Int64Type>().is_null(0));
+        assert_eq!(batches[1].column(2).as_string::<i32>().value(0), "7");
+    }
+
+    #[test]
+    fn empty() {
+        let batches = RecordBatches::new(Vec::new().into_iter(), ArrowOptions::default()).unwrap();
+        assert_eq!(batches.schema().fields().len(), 1);
+        assert_eq!(batches.count(), 0);
+
+        let fts = vec![feature(
+            Point::new(0., 0.).into(),
+            &[("geometry", Value::from(1))],
+        )];
+        assert!(RecordBatches::new(fts.into_iter(), ArrowOptions::default()).is_err());
+    }
+}
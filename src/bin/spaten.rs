@@ -0,0 +1,463 @@
+//! Command line tool to look inside and convert Spaten files.
+
+use geo::Intersects;
+use geo_types::Rect;
+use spaten::filter::Filter;
+use spaten::geojson::{from_geojson, to_feature_collection, GeoJsonSeqReader, GeoJsonSeqWriter};
+use spaten::hints::analyze;
+use spaten::preflight::preflight;
+use spaten::redact::Redaction;
+use spaten::source::{copy, FeatureSource};
+use spaten::stats::Summary;
+use spaten::transform::{sample, sample_stratified};
+use spaten::validate::{validate, ValidateOptions};
+use spaten::{Feature, FeatureIterator, FeatureWriter, WriterOptions};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::process::exit;
+
+const USAGE: &str = "usage:
+    spaten info FILE
+        Prints the number of features and blocks, the extent, the compression, the geometry
+        types and the tag keys with the types of their values.
+    spaten keys FILE
+        Prints the size and number of distinct values of each tag key, the vertex counts and
+        coordinate precision, with hints to shrink the file.
+    spaten validate FILE
+        Decodes all blocks on all cores and prints the problems found, sorted by offset. Exits
+        with 1 if there are errors.
+    spaten cat [--redact KEY[,KEY..]] FILE
+        Writes the features to stdout as GeoJSON text sequence. The values of the tag keys
+        given to --redact are replaced with ***.
+    spaten filter [--bbox MINX,MINY,MAXX,MAXY] [--tag KEY[=VALUE]] [--no-tag KEY] INPUT OUTPUT
+        Copies the features that intersect the bounding box and match all tag filters. VALUE is
+        read as the type of the tag, so lanes=2 matches both the string and the number 2.
+    spaten sample (-n N | --stratify-by KEY --per-class N) [--seed SEED] INPUT OUTPUT
+        Copies N randomly chosen features, or N features for every value of tag KEY so that
+        rare classes are represented. The same seed (default 0) gives the same sample.
+    spaten convert [-y] INPUT OUTPUT
+        Converts between formats. For .spaten input, first prints the number of features, the
+        extent and the estimated output size, and asks before writing more than 1 GiB unless
+        -y is given or stdin is not a terminal.
+
+Formats are chosen by file extension: .spaten, .geojsons or .geojsonl for GeoJSON text
+sequences, anything else is GeoJSON.";
+
+/// Estimated output size above which `convert` asks before it starts.
+const CONFIRM_BYTES: u64 = 1 << 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Spaten,
+    GeoJson,
+    GeoJsonSeq,
+}
+
+impl Format {
+    fn of(path: &str) -> Format {
+        match path.rsplit('.').next() {
+            Some("spaten") => Format::Spaten,
+            Some("geojsons") | Some("geojsonl") => Format::GeoJsonSeq,
+            _ => Format::GeoJson,
+        }
+    }
+}
+
+type Features<'a> = dyn Iterator<Item = io::Result<Feature>> + 'a;
+
+fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+/// Calls `f` with the features of the file at `path`.
+fn read<T>(path: &str, f: impl FnOnce(&mut Features<'_>) -> io::Result<T>) -> io::Result<T> {
+    let mut r = BufReader::new(File::open(path)?);
+    match Format::of(path) {
+        Format::Spaten => f(&mut FeatureIterator::try_new(&mut r)?.map(|ft| Ok(ft?))),
+        Format::GeoJsonSeq => f(&mut GeoJsonSeqReader::new(r)),
+        Format::GeoJson => f(&mut from_geojson(r)),
+    }
+}
+
+/// Lets [`copy`] read `Features`.
+struct Source<'a, 'b>(&'a mut Features<'b>);
+
+impl FeatureSource for Source<'_, '_> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        self.0.next().transpose()
+    }
+}
+
+/// Writes `fts` to a new file at `path` and returns their number.
+fn write(path: &str, fts: &mut Features<'_>) -> io::Result<u64> {
+    match Format::of(path) {
+        Format::Spaten => copy(
+            &mut Source(fts),
+            &mut FeatureWriter::create(path, WriterOptions::default())?,
+        ),
+        Format::GeoJsonSeq => copy(
+            &mut Source(fts),
+            &mut GeoJsonSeqWriter::new(BufWriter::new(File::create(path)?)),
+        ),
+        Format::GeoJson => {
+            let fts = fts.collect::<io::Result<Vec<_>>>()?;
+            let n = fts.len() as u64;
+            let mut w = BufWriter::new(File::create(path)?);
+            writeln!(w, "{}", to_feature_collection(fts))?;
+            w.flush()?;
+            Ok(n)
+        }
+    }
+}
+
+/// Scans a Spaten `input` and, if converting it to `output` writes a lot, asks on the terminal
+/// whether to go on. Returns false if the user declines.
+fn confirm(input: &str, output: &str) -> io::Result<bool> {
+    if Format::of(input) != Format::Spaten {
+        return Ok(true);
+    }
+    let p = preflight(&mut BufReader::new(File::open(input)?))?;
+    let ratio = match Format::of(output) {
+        Format::Spaten => 1.,
+        Format::GeoJson | Format::GeoJsonSeq => 2.5,
+    };
+    let estimate = p.estimated_output(ratio);
+    eprintln!("{}, about {} bytes of output", p, estimate);
+    if estimate <= CONFIRM_BYTES || !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    eprint!("Continue? [y/N] ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn info(path: &str) -> io::Result<()> {
+    if Format::of(path) != Format::Spaten {
+        return Err(invalid_input("info only reads .spaten files"));
+    }
+    let summary = Summary::from_reader(&mut BufReader::new(File::open(path)?))?;
+    print!("{}", summary);
+    Ok(())
+}
+
+fn keys(path: &str) -> io::Result<()> {
+    if Format::of(path) != Format::Spaten {
+        return Err(invalid_input("keys only reads .spaten files"));
+    }
+    let mut r = BufReader::new(File::open(path)?);
+    let report = analyze(&mut FeatureIterator::try_new(&mut r)?)?;
+    print!("{}", report);
+    Ok(())
+}
+
+fn check(path: &str) -> io::Result<()> {
+    let v = validate(
+        &mut BufReader::new(File::open(path)?),
+        &ValidateOptions::default(),
+    );
+    println!("{}", v);
+    if !v.is_valid() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file is invalid",
+        ));
+    }
+    Ok(())
+}
+
+fn cat(path: &str, redaction: &Redaction) -> io::Result<()> {
+    let out = io::stdout();
+    read(path, |fts| {
+        let mut redacted = fts.map(|ft| {
+            ft.map(|mut ft| {
+                redaction.apply(&mut ft);
+                ft
+            })
+        });
+        copy(
+            &mut Source(&mut redacted),
+            &mut GeoJsonSeqWriter::new(out.lock()),
+        )?;
+        Ok(())
+    })
+}
+
+/// Splits the arguments of `cat` into the redacted keys and the remaining positional arguments.
+fn parse_redaction(args: &[String]) -> io::Result<(Redaction, Vec<&str>)> {
+    let mut keys = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--redact" => {
+                let list = args
+                    .next()
+                    .ok_or_else(|| invalid_input("--redact needs a value"))?;
+                keys.extend(list.split(',').filter(|k| !k.is_empty()));
+            }
+            a if a.starts_with('-') => {
+                return Err(invalid_input(format!("unknown option {}", a)));
+            }
+            a => positional.push(a),
+        }
+    }
+    Ok((Redaction::new(keys), positional))
+}
+
+/// The selection of the `filter` command.
+#[derive(Debug, Default)]
+struct Selection {
+    bbox: Option<Rect<f64>>,
+    tags: Filter,
+}
+
+impl Selection {
+    fn matches(&self, ft: &Feature) -> bool {
+        self.tags.matches(&ft.tags) && self.bbox.is_none_or(|b| ft.geometry.intersects(&b))
+    }
+}
+
+fn parse_bbox(s: &str) -> io::Result<Rect<f64>> {
+    let v = s
+        .split(',')
+        .map(|c| c.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid_input(format!("invalid bbox {}: {}", s, e)))?;
+    match v[..] {
+        [minx, miny, maxx, maxy] => Ok(Rect::new((minx, miny), (maxx, maxy))),
+        _ => Err(invalid_input(format!("bbox needs four coordinates: {}", s))),
+    }
+}
+
+/// Splits the arguments of `filter` into the selection and the remaining positional arguments.
+fn parse_selection(args: &[String]) -> io::Result<(Selection, Vec<&str>)> {
+    let mut sel = Selection::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(a) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| invalid_input(format!("{} needs a value", a)))
+        };
+        match a.as_str() {
+            "--bbox" => sel.bbox = Some(parse_bbox(value()?)?),
+            "--tag" => {
+                let v = value()?;
+                sel.tags = match v.split_once('=') {
+                    Some((k, v)) => sel.tags.tag_text(k, v),
+                    None => sel.tags.tag_exists(v),
+                };
+            }
+            "--no-tag" => sel.tags = sel.tags.tag_missing(value()?),
+            a if a.starts_with("--") => {
+                return Err(invalid_input(format!("unknown option {}", a)));
+            }
+            a => positional.push(a),
+        }
+    }
+    Ok((sel, positional))
+}
+
+/// The options of the `sample` command.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Sampling {
+    n: Option<usize>,
+    stratify_by: Option<String>,
+    per_class: Option<usize>,
+    seed: u64,
+}
+
+impl Sampling {
+    fn apply(&self, fts: impl Iterator<Item = Feature>) -> io::Result<Vec<Feature>> {
+        match (self.n, &self.stratify_by, self.per_class) {
+            (Some(n), None, None) => Ok(sample(fts, n, self.seed)),
+            (None, Some(key), Some(n)) => Ok(sample_stratified(fts, key, n, self.seed)),
+            _ => Err(invalid_input(
+                "sample needs either -n or --stratify-by with --per-class",
+            )),
+        }
+    }
+}
+
+/// Splits the arguments of `sample` into the options and the remaining positional arguments.
+fn parse_sampling(args: &[String]) -> io::Result<(Sampling, Vec<&str>)> {
+    let mut opts = Sampling::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(a) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| invalid_input(format!("{} needs a value", a)))
+        };
+        let mut number = || {
+            let v = value()?;
+            v.parse()
+                .map_err(|_| invalid_input(format!("{} needs a number: {}", a, v)))
+        };
+        match a.as_str() {
+            "-n" => opts.n = Some(number()?),
+            "--per-class" => opts.per_class = Some(number()?),
+            "--seed" => opts.seed = number()? as u64,
+            "--stratify-by" => opts.stratify_by = Some(value()?.clone()),
+            a if a.starts_with('-') => {
+                return Err(invalid_input(format!("unknown option {}", a)));
+            }
+            a => positional.push(a),
+        }
+    }
+    Ok((opts, positional))
+}
+
+fn run(args: &[String]) -> io::Result<()> {
+    let cmd = args.first().map(String::as_str);
+    match (cmd, &args[1.min(args.len())..]) {
+        (Some("info"), [path]) => info(path),
+        (Some("keys"), [path]) => keys(path),
+        (Some("validate"), [path]) => check(path),
+        (Some("cat"), rest) => {
+            let (redaction, paths) = parse_redaction(rest)?;
+            match paths[..] {
+                [path] => cat(path, &redaction),
+                _ => Err(invalid_input(USAGE)),
+            }
+        }
+        (Some("convert"), rest) => {
+            let yes = rest.iter().any(|a| a == "-y" || a == "--yes");
+            let paths: Vec<&str> = rest
+                .iter()
+                .map(String::as_str)
+                .filter(|a| !matches!(*a, "-y" | "--yes"))
+                .collect();
+            let (input, output) = match paths[..] {
+                [input, output] => (input, output),
+                _ => return Err(invalid_input(USAGE)),
+            };
+            if !yes && !confirm(input, output)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "conversion cancelled",
+                ));
+            }
+            let n = read(input, |fts| write(output, fts))?;
+            eprintln!("{} features written", n);
+            Ok(())
+        }
+        (Some("filter"), rest) => {
+            let (sel, paths) = parse_selection(rest)?;
+            let (input, output) = match paths[..] {
+                [input, output] => (input, output),
+                _ => return Err(invalid_input(USAGE)),
+            };
+            let n = read(input, |fts| {
+                let mut selected = fts.filter(|ft| ft.as_ref().map_or(true, |ft| sel.matches(ft)));
+                write(output, &mut selected)
+            })?;
+            eprintln!("{} features written", n);
+            Ok(())
+        }
+        (Some("sample"), rest) => {
+            let (opts, paths) = parse_sampling(rest)?;
+            let (input, output) = match paths[..] {
+                [input, output] => (input, output),
+                _ => return Err(invalid_input(USAGE)),
+            };
+            let sampled = read(input, |fts| {
+                let mut err = None;
+                let sampled = opts.apply(fts.map_while(|ft| ft.map_err(|e| err = Some(e)).ok()));
+                err.map_or(sampled, Err)
+            })?;
+            let n = write(output, &mut sampled.into_iter().map(Ok))?;
+            eprintln!("{} features written", n);
+            Ok(())
+        }
+        _ => Err(invalid_input(USAGE)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        exit(match e.kind() {
+            io::ErrorKind::InvalidInput => 2,
+            _ => 1,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bbox, parse_redaction, parse_sampling, parse_selection, Format, Sampling};
+    use spaten::{Feature, Value};
+    use std::collections::HashMap;
+
+    #[test]
+    fn arguments() {
+        assert_eq!(Format::of("a.b.spaten"), Format::Spaten);
+        assert_eq!(Format::of("a.geojsonl"), Format::GeoJsonSeq);
+        assert_eq!(Format::of("a.json"), Format::GeoJson);
+        assert!(parse_bbox("1,2,3").is_err());
+        assert!(parse_bbox("1,2,3,x").is_err());
+
+        let args: Vec<String> = ["--tag", "highway=primary", "in.spaten", "--bbox", "0,0,2,2"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (sel, paths) = parse_selection(&args).unwrap();
+        assert_eq!(paths, ["in.spaten"]);
+        let mut tags = HashMap::new();
+        tags.insert("highway".to_string(), Value::from("primary"));
+        let mut ft = Feature {
+            geometry: geo_types::Point::new(1., 1.).into(),
+            tags,
+        };
+        assert!(sel.matches(&ft));
+        ft.geometry = geo_types::Point::new(3., 1.).into();
+        assert!(!sel.matches(&ft));
+        let args: Vec<String> = ["--tag", "lanes=2"].iter().map(|s| s.to_string()).collect();
+        let (sel, _) = parse_selection(&args).unwrap();
+        ft.tags.insert("lanes".to_string(), Value::Integer(2));
+        assert!(sel.matches(&ft));
+        assert!(parse_selection(&["--tag".to_string()]).is_err());
+        assert!(parse_selection(&["--limit".to_string()]).is_err());
+
+        let args: Vec<String> = ["--redact", "email,phone", "in.spaten"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (redaction, paths) = parse_redaction(&args).unwrap();
+        assert_eq!(paths, ["in.spaten"]);
+        ft.tags.insert("phone".to_string(), Value::from("+49 123"));
+        redaction.apply(&mut ft);
+        assert_eq!(ft.tags["phone"], Value::from("***"));
+        assert_eq!(ft.tags["highway"], Value::from("primary"));
+        assert!(parse_redaction(&["--redact".to_string()]).is_err());
+
+        let args: Vec<String> = [
+            "--stratify-by",
+            "highway",
+            "in",
+            "--per-class",
+            "100",
+            "out",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let (opts, paths) = parse_sampling(&args).unwrap();
+        assert_eq!(paths, ["in", "out"]);
+        assert_eq!(
+            opts,
+            Sampling {
+                n: None,
+                stratify_by: Some("highway".to_string()),
+                per_class: Some(100),
+                seed: 0,
+            }
+        );
+        assert!(parse_sampling(&["-n".to_string(), "x".to_string()]).is_err());
+        let (opts, _) = parse_sampling(&["--seed".to_string(), "3".to_string()]).unwrap();
+        assert!(opts.apply(std::iter::empty()).is_err());
+    }
+}
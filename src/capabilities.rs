@@ -0,0 +1,250 @@
+//! The capability table of the read path, which maps the flag bits and compression codes of
+//! block headers to handlers.
+//!
+//! Readers know gzip compression and the [encryption](crate::encryption) flag, and refuse blocks
+//! with other flag bits or compression codes with
+//! [`Error::UnsupportedBlock`](crate::Error::UnsupportedBlock). Crates that extend the format
+//! privately, e.g. with an experimental codec, register handlers for their bits and codes here.
+//! All readers of the process pick them up, without changes to this crate. [`capabilities`]
+//! lists what the readers support, e.g. to tell a producer which encodings it may use.
+//!
+//! A block is decrypted first, then the handlers of its flag bits are applied from the highest
+//! bit to the lowest, and finally it is decompressed. Writers of extended files apply them in
+//! the opposite order. Built-in codes and bits cannot be replaced, and handlers cannot be
+//! unregistered.
+//! ```
+//! use spaten::capabilities::{capabilities, register_compression, Capability};
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureIterator, FeatureWriter};
+//! use std::io;
+//!
+//! fn reversed(body: &[u8], _max_len: u32) -> io::Result<Vec<u8>> {
+//!     Ok(body.iter().rev().copied().collect())
+//! }
+//! register_compression(200, "reversed", reversed)?;
+//! assert!(capabilities().contains(&Capability::Compression {
+//!     code: 200,
+//!     name: "reversed"
+//! }));
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! })?;
+//! w.finish()?;
+//! let mut buf = w.into_inner();
+//! // compress the single block with the codec
+//! let end = buf.len() - 4;
+//! buf[14] = 200;
+//! buf[16..end].reverse();
+//!
+//! assert_eq!(FeatureIterator::try_new(&mut &buf[..])?.count(), 1);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::encryption;
+use std::io;
+use std::sync::RwLock;
+
+/// Decompresses a block body. Output larger than `max_len` bytes is refused by the reader, so
+/// decompression can stop as soon as it exceeds the limit.
+pub type Decompress = fn(body: &[u8], max_len: u32) -> io::Result<Vec<u8>>;
+
+/// Undoes the transformation that a flag bit stands for, e.g. a checksum or an encoding of the
+/// body.
+pub type FlagHandler = fn(body: Vec<u8>) -> io::Result<Vec<u8>>;
+
+/// A flag bit or compression code that readers support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capability {
+    Compression { code: u8, name: &'static str },
+    Flag { bit: u16, name: &'static str },
+}
+
+struct Registry {
+    compressions: Vec<(u8, &'static str, Decompress)>,
+    flags: Vec<(u16, &'static str, FlagHandler)>,
+}
+
+static REGISTRY: RwLock<Registry> = RwLock::new(Registry {
+    compressions: Vec::new(),
+    flags: Vec::new(),
+});
+
+const BUILTIN: [Capability; 3] = [
+    Capability::Compression {
+        code: 0,
+        name: "none",
+    },
+    Capability::Compression {
+        code: 1,
+        name: "gzip",
+    },
+    Capability::Flag {
+        bit: encryption::FLAG,
+        name: "encryption",
+    },
+];
+
+fn already_registered(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} is already registered", what),
+    )
+}
+
+/// Registers the decompression of blocks with compression `code`. Fails if the code is taken.
+pub fn register_compression(code: u8, name: &'static str, f: Decompress) -> io::Result<()> {
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    let taken = BUILTIN
+        .iter()
+        .any(|c| matches!(c, Capability::Compression { code: c, .. } if *c == code))
+        || registry.compressions.iter().any(|(c, _, _)| *c == code);
+    if taken {
+        return Err(already_registered(&format!("compression {}", code)));
+    }
+    registry.compressions.push((code, name, f));
+    Ok(())
+}
+
+/// Registers the handler of blocks with flag `bit`, which must have exactly one bit set. Fails
+/// if the bit is taken.
+pub fn register_flag(bit: u16, name: &'static str, f: FlagHandler) -> io::Result<()> {
+    if bit.count_ones() != 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "flag must have exactly one bit set",
+        ));
+    }
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    if bit == encryption::FLAG || registry.flags.iter().any(|(b, _, _)| *b == bit) {
+        return Err(already_registered(&format!("flag {:#06x}", bit)));
+    }
+    registry.flags.push((bit, name, f));
+    Ok(())
+}
+
+/// The built-in and registered capabilities, in this order.
+pub fn capabilities() -> Vec<Capability> {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+    let compressions = registry
+        .compressions
+        .iter()
+        .map(|&(code, name, _)| Capability::Compression { code, name });
+    let flags = registry
+        .flags
+        .iter()
+        .map(|&(bit, name, _)| Capability::Flag { bit, name });
+    BUILTIN
+        .iter()
+        .copied()
+        .chain(compressions)
+        .chain(flags)
+        .collect()
+}
+
+pub(crate) fn decompressor(code: u8) -> Option<Decompress> {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+    let found = registry.compressions.iter().find(|(c, _, _)| *c == code);
+    found.map(|&(_, _, f)| f)
+}
+
+/// The registered flag bits.
+pub(crate) fn flags() -> u16 {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+    registry.flags.iter().fold(0, |all, (bit, _, _)| all | bit)
+}
+
+/// Applies the handlers of the registered bits in `flags`, highest first.
+pub(crate) fn apply_flags(flags: u16, mut body: Vec<u8>) -> io::Result<Vec<u8>> {
+    if flags == 0 {
+        return Ok(body);
+    }
+    let mut handlers: Vec<(u16, FlagHandler)> = {
+        let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+        let set = registry.flags.iter().filter(|(bit, _, _)| flags & bit != 0);
+        set.map(|&(bit, _, f)| (bit, f)).collect()
+    };
+    handlers.sort_by_key(|&(bit, _)| std::cmp::Reverse(bit));
+    for (_, f) in handlers {
+        body = f(body)?;
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capabilities, register_compression, register_flag, Capability};
+    use crate::sink::FeatureSink;
+    use crate::{encryption, parse_frame, Feature, FeatureIterator, FeatureWriter, Limits};
+    use std::io;
+
+    fn xor(body: Vec<u8>) -> io::Result<Vec<u8>> {
+        Ok(body.into_iter().map(|b| b ^ 0x55).collect())
+    }
+
+    fn checksum_mismatch(_: Vec<u8>) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checksum mismatch",
+        ))
+    }
+
+    #[test]
+    fn flags() {
+        let mut w = FeatureWriter::new(Vec::new());
+        w.accept(Feature {
+            geometry: geo_types::Point::new(7.0, 51.0).into(),
+            tags: Default::default(),
+        })
+        .unwrap();
+        w.finish().unwrap();
+        let plain = w.into_inner();
+        let end = plain.len() - 4;
+
+        let mut buf = plain.clone();
+        buf[13] = 0x40;
+        buf[16..end].iter_mut().for_each(|b| *b ^= 0x55);
+        assert!(FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_err());
+
+        register_flag(0x4000, "xor", xor).unwrap();
+        assert_eq!(FeatureIterator::try_new(&mut &buf[..]).unwrap().count(), 1);
+        let frame = parse_frame(&buf[8..]).unwrap().unwrap();
+        assert_eq!(frame.flags, 0x4000);
+        assert_eq!(
+            frame.decompress(&Limits::default()).unwrap(),
+            &plain[16..end]
+        );
+
+        register_flag(0x2000, "checksum", checksum_mismatch).unwrap();
+        let mut buf = plain;
+        buf[13] = 0x20;
+        let err = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .next()
+            .unwrap();
+        assert!(err.unwrap_err().to_string().contains("checksum mismatch"));
+
+        assert!(register_flag(0x4000, "again", xor).is_err());
+        assert!(register_flag(encryption::FLAG, "again", xor).is_err());
+        assert!(register_flag(0x0300, "two bits", xor).is_err());
+        assert!(register_compression(1, "gzip", |b, _| Ok(b.to_vec())).is_err());
+        let caps = capabilities();
+        assert_eq!(
+            caps[1],
+            Capability::Compression {
+                code: 1,
+                name: "gzip"
+            }
+        );
+        assert!(caps.contains(&Capability::Flag {
+            bit: 0x4000,
+            name: "xor"
+        }));
+    }
+}
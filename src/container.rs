@@ -0,0 +1,321 @@
+//! Several thematic layers in one Spaten file, similar to the tables of a GeoPackage.
+//!
+//! The convention builds on block meta tags and needs no changes to the file format:
+//!
+//! * The first block is a directory. It holds no features, and its meta has one
+//!   `spaten:layers` string tag per layer, in the order of the layers in the file.
+//! * Every following block belongs to exactly one layer, named by its `spaten:layer` meta tag.
+//!   The blocks of a layer are contiguous and the layers appear in directory order.
+//!
+//! Readers that are not aware of the convention see the features of all layers in sequence.
+//! [`LayeredWriter`] writes such files, [`layers`] lists the layers and [`open_layer`] reads a
+//! single one.
+//! ```
+//! use spaten::container::{layers, open_layer, LayeredWriter};
+//! use spaten::sink::FeatureSink;
+//! use spaten::Feature;
+//!
+//! let pt = Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! };
+//! let mut w = LayeredWriter::new(Vec::new(), &["roads", "buildings"]);
+//! w.start_layer("roads")?;
+//! w.accept(pt.clone())?;
+//! w.start_layer("buildings")?;
+//! w.accept(pt.clone())?;
+//! w.accept(pt)?;
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! assert_eq!(layers(&mut &buf[..])?, ["roads", "buildings"]);
+//! let buildings = open_layer(&buf[..], "buildings")?.unwrap();
+//! assert_eq!(buildings.count(), 2);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::sink::FeatureSink;
+use crate::source::FeatureSource;
+use crate::{
+    decode_features, fileformat, open_block, read_raw_block, try_read_file_header, Error, Feature,
+    FeatureWriter, ReaderOptions, Value, WriterOptions,
+};
+use protobuf::Message;
+use std::collections::VecDeque;
+use std::io;
+
+/// Meta tag of the directory block, once per layer.
+pub const LAYERS_KEY: &str = "spaten:layers";
+/// Meta tag that names the layer of a block.
+pub const LAYER_KEY: &str = "spaten:layer";
+
+fn invalid_input(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// The string values of all meta tags with `key`, in file order.
+pub(crate) fn meta_strings(body: &fileformat::Body, key: &str) -> Vec<String> {
+    let tags = match body.meta.as_ref() {
+        Some(meta) => &meta.tags,
+        None => return Vec::new(),
+    };
+    tags.iter()
+        .filter(|tag| tag.key == key)
+        .filter_map(
+            |tag| match Value::from_bytes(tag.value.clone(), tag.field_type) {
+                Ok(Value::String(s)) => Some(s),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+fn read_body(
+    r: &mut impl io::Read,
+    options: &ReaderOptions,
+) -> Result<Option<fileformat::Body>, Error> {
+    let max_len = options.limits.max_block_size;
+    let (header, raw) = match read_raw_block(r, max_len)? {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    let body = open_block(header, raw, options.key.as_ref(), max_len)?;
+    Ok(Some(fileformat::Body::parse_from_bytes(&body)?))
+}
+
+fn read_directory(r: &mut impl io::Read, options: &ReaderOptions) -> Result<Vec<String>, Error> {
+    try_read_file_header(r)?;
+    Ok(match read_body(r, options)? {
+        Some(body) if body.feature.is_empty() => meta_strings(&body, LAYERS_KEY),
+        _ => Vec::new(),
+    })
+}
+
+/// The names of the layers in the file, in file order. Empty if the file does not follow the
+/// convention.
+pub fn layers(r: &mut impl io::Read) -> Result<Vec<String>, Error> {
+    read_directory(r, &ReaderOptions::default())
+}
+
+/// Opens the layer `name` for reading, or returns `None` if the file has no such layer.
+pub fn open_layer<R: io::Read>(r: R, name: &str) -> Result<Option<LayerReader<R>>, Error> {
+    open_layer_with_options(r, name, ReaderOptions::default())
+}
+
+pub fn open_layer_with_options<R: io::Read>(
+    mut r: R,
+    name: &str,
+    options: ReaderOptions,
+) -> Result<Option<LayerReader<R>>, Error> {
+    if !read_directory(&mut r, &options)?.iter().any(|l| l == name) {
+        return Ok(None);
+    }
+    Ok(Some(LayerReader {
+        r,
+        name: name.to_string(),
+        options,
+        queue: VecDeque::new(),
+        started: false,
+        done: false,
+    }))
+}
+
+/// The features of a single layer, returned by [`open_layer`]. Reading stops at the end of the
+/// layer's blocks.
+pub struct LayerReader<R> {
+    r: R,
+    name: String,
+    options: ReaderOptions,
+    queue: VecDeque<Feature>,
+    started: bool,
+    done: bool,
+}
+
+impl<R: io::Read> LayerReader<R> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn read_feature(&mut self) -> Result<Option<Feature>, Error> {
+        while self.queue.is_empty() && !self.done {
+            let body = match read_body(&mut self.r, &self.options)? {
+                Some(body) => body,
+                None => {
+                    self.done = true;
+                    break;
+                }
+            };
+            if meta_strings(&body, LAYER_KEY).first() != Some(&self.name) {
+                self.done = self.started;
+                continue;
+            }
+            self.started = true;
+            let fts = decode_features(body.feature.into_vec(), &self.options, None)?;
+            self.queue.extend(fts);
+        }
+        Ok(self.queue.pop_front())
+    }
+}
+
+impl<R: io::Read> Iterator for LayerReader<R> {
+    type Item = Result<Feature, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.read_feature().transpose();
+        if let Some(Err(_)) = res {
+            self.done = true;
+        }
+        res
+    }
+}
+
+impl<R: io::Read> FeatureSource for LayerReader<R> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        Ok(self.read_feature()?)
+    }
+}
+
+/// Writes a file with several layers. The layers are declared up front and then written one
+/// after another: [`start_layer`](LayeredWriter::start_layer) ends the previous layer, and all
+/// features accepted afterwards belong to the new one. Layers that are never started stay
+/// empty.
+pub struct LayeredWriter<W: io::Write> {
+    w: FeatureWriter<W>,
+    layers: Vec<String>,
+    current: Option<usize>,
+    directory_written: bool,
+}
+
+impl<W: io::Write> LayeredWriter<W> {
+    pub fn new(w: W, layers: &[&str]) -> Self {
+        Self::with_options(w, layers, WriterOptions::default())
+    }
+
+    pub fn with_options(w: W, layers: &[&str], options: WriterOptions) -> Self {
+        LayeredWriter {
+            w: FeatureWriter::with_options(w, options),
+            layers: layers.iter().map(|l| l.to_string()).collect(),
+            current: None,
+            directory_written: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w.into_inner()
+    }
+
+    fn write_directory(&mut self) -> io::Result<()> {
+        if self.directory_written {
+            return Ok(());
+        }
+        for (i, l) in self.layers.iter().enumerate() {
+            if self.layers[..i].contains(l) {
+                return Err(invalid_input("Layer names must be unique"));
+            }
+        }
+        let mut meta = fileformat::Meta::new();
+        for l in &self.layers {
+            crate::encode_tag(&mut meta.tags, LAYERS_KEY, &Value::String(l.clone()));
+        }
+        self.w.write_meta_block(meta)?;
+        self.directory_written = true;
+        Ok(())
+    }
+
+    /// Ends the current layer and starts the declared layer `name`, which must come after it.
+    pub fn start_layer(&mut self, name: &str) -> io::Result<()> {
+        let i = self
+            .layers
+            .iter()
+            .position(|l| l == name)
+            .ok_or_else(|| invalid_input("Layer is not declared"))?;
+        if self.current.is_some_and(|c| i <= c) {
+            return Err(invalid_input("Layers must be written in declaration order"));
+        }
+        self.write_directory()?;
+        self.w.write_block()?;
+        self.w.layer = Some(name.to_string());
+        self.current = Some(i);
+        Ok(())
+    }
+}
+
+impl<W: io::Write> FeatureSink for LayeredWriter<W> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        if self.current.is_none() {
+            return Err(invalid_input("No layer started"));
+        }
+        self.w.accept(ft)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_directory()?;
+        self.w.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layers, open_layer, LayeredWriter};
+    use crate::sink::FeatureSink;
+    use crate::{Feature, FeatureIterator, Value, WriterOptions};
+    use std::collections::HashMap;
+
+    fn feature(kind: &str) -> Feature {
+        let mut tags = HashMap::new();
+        tags.insert("kind".to_string(), Value::String(kind.to_string()));
+        Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags,
+        }
+    }
+
+    #[test]
+    fn layered() {
+        let opts = WriterOptions {
+            block_size: 2,
+            ..Default::default()
+        };
+        let mut w = LayeredWriter::with_options(Vec::new(), &["roads", "water", "pois"], opts);
+        assert!(w.accept(feature("road")).is_err());
+        assert!(w.start_layer("rails").is_err());
+        w.start_layer("roads").unwrap();
+        for _ in 0..5 {
+            w.accept(feature("road")).unwrap();
+        }
+        w.start_layer("pois").unwrap();
+        w.accept(feature("poi")).unwrap();
+        assert!(w.start_layer("roads").is_err());
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        assert_eq!(layers(&mut &buf[..]).unwrap(), ["roads", "water", "pois"]);
+        let kinds = |name| -> Vec<Value> {
+            open_layer(&buf[..], name)
+                .unwrap()
+                .unwrap()
+                .map(|ft| ft.unwrap().tags["kind"].clone())
+                .collect()
+        };
+        assert_eq!(kinds("roads"), vec![Value::String("road".to_string()); 5]);
+        assert!(kinds("water").is_empty());
+        assert_eq!(kinds("pois"), [Value::String("poi".to_string())]);
+        assert!(open_layer(&buf[..], "rails").unwrap().is_none());
+
+        // plain readers see all layers
+        assert_eq!(FeatureIterator::try_new(&mut &buf[..]).unwrap().count(), 6);
+
+        let mut w = LayeredWriter::new(Vec::new(), &["a", "a"]);
+        assert!(w.finish().is_err());
+    }
+
+    #[test]
+    fn plain_file() {
+        let mut w = crate::FeatureWriter::new(Vec::new());
+        w.accept(feature("road")).unwrap();
+        w.finish().unwrap();
+        let buf = w.into_inner();
+        assert!(layers(&mut &buf[..]).unwrap().is_empty());
+        assert!(open_layer(&buf[..], "roads").unwrap().is_none());
+    }
+}
@@ -0,0 +1,308 @@
+//! Import of tabular data (CSV/TSV) with a WKT or longitude/latitude geometry.
+//!
+//! Column types are inferred from the first rows: a column becomes an integer or float tag if
+//! all its non-empty values in those rows parse as such, otherwise it is a string tag. Later
+//! values that do not fit the inferred type are kept as strings. Numbers with leading zeros,
+//! such as postal codes like `007`, and non-finite values like `nan` or `inf` count as strings.
+//! Empty cells are omitted.
+
+use crate::source::FeatureSource;
+use crate::{Feature, Value};
+use geo_types::{Geometry, Point};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::io::Read;
+use wkt::TryFromWkt;
+
+/// The column(s) holding the geometry.
+#[derive(Clone, Debug)]
+pub enum GeometryColumns {
+    /// A single column with a WKT geometry.
+    Wkt(String),
+    /// Two numeric columns with the coordinates of a point.
+    LonLat { lon: String, lat: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub geometry: GeometryColumns,
+    /// Number of rows used to infer the column types.
+    pub infer_rows: usize,
+}
+
+impl CsvOptions {
+    /// Comma separated values with a WKT geometry column.
+    pub fn wkt(column: &str) -> Self {
+        CsvOptions {
+            delimiter: b',',
+            geometry: GeometryColumns::Wkt(column.to_string()),
+            infer_rows: 1000,
+        }
+    }
+
+    /// Comma separated values with longitude and latitude columns.
+    pub fn lon_lat(lon: &str, lat: &str) -> Self {
+        CsvOptions {
+            delimiter: b',',
+            geometry: GeometryColumns::LonLat {
+                lon: lon.to_string(),
+                lat: lat.to_string(),
+            },
+            infer_rows: 1000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColumnType {
+    Integer,
+    Float,
+    String,
+}
+
+/// Reads features from delimited text with a header row.
+/// ```
+/// use spaten::csv::{CsvOptions, CsvReader};
+/// use spaten::Value;
+///
+/// let data = "name,pop,lon,lat\nBonn,330000,7.1,50.7\n";
+/// let mut r = CsvReader::new(data.as_bytes(), CsvOptions::lon_lat("lon", "lat")).unwrap();
+/// let ft = r.next().unwrap().unwrap();
+/// assert_eq!(ft.tags["pop"], Value::Integer(330000));
+/// assert!(!ft.tags.contains_key("lon"));
+/// ```
+pub struct CsvReader<R: Read> {
+    records: ::csv::StringRecordsIntoIter<R>,
+    headers: Vec<String>,
+    types: Vec<ColumnType>,
+    geometry: (usize, Option<usize>),
+    buffered: VecDeque<::csv::StringRecord>,
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+impl<R: Read> CsvReader<R> {
+    pub fn new(r: R, opts: CsvOptions) -> io::Result<Self> {
+        let mut rdr = ::csv::ReaderBuilder::new()
+            .delimiter(opts.delimiter)
+            .from_reader(r);
+        let headers: Vec<String> = rdr
+            .headers()
+            .map_err(invalid_data)?
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| invalid_data(format!("missing column {}", name)))
+        };
+        let geometry = match &opts.geometry {
+            GeometryColumns::Wkt(c) => (column(c)?, None),
+            GeometryColumns::LonLat { lon, lat } => (column(lon)?, Some(column(lat)?)),
+        };
+
+        let mut records = rdr.into_records();
+        let mut buffered = VecDeque::new();
+        for rec in records.by_ref().take(opts.infer_rows) {
+            buffered.push_back(rec.map_err(invalid_data)?);
+        }
+        let types = (0..headers.len())
+            .map(|i| infer_type(buffered.iter().filter_map(|r| r.get(i))))
+            .collect();
+
+        Ok(CsvReader {
+            records,
+            headers,
+            types,
+            geometry,
+            buffered,
+        })
+    }
+
+    fn to_feature(&self, rec: ::csv::StringRecord) -> io::Result<Feature> {
+        let geometry: Geometry<f64> = match self.geometry {
+            (wkt_col, None) => {
+                let s = rec.get(wkt_col).unwrap_or_default();
+                Geometry::try_from_wkt_str(s).map_err(invalid_data)?
+            }
+            (lon_col, Some(lat_col)) => {
+                let coord = |i: usize| {
+                    rec.get(i)
+                        .unwrap_or_default()
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(invalid_data)
+                };
+                Point::new(coord(lon_col)?, coord(lat_col)?).into()
+            }
+        };
+
+        let mut tags = HashMap::with_capacity(self.headers.len());
+        for (i, val) in rec.iter().enumerate() {
+            if val.is_empty() || i == self.geometry.0 || Some(i) == self.geometry.1 {
+                continue;
+            }
+            let key = match self.headers.get(i) {
+                Some(k) => k.clone(),
+                None => continue,
+            };
+            tags.insert(key, parse_value(val, self.types[i]));
+        }
+        Ok(Feature { geometry, tags })
+    }
+}
+
+/// Whether the integer part of `s` has a leading zero, e.g. `007` or `-01.5`, but not `0.5`.
+fn leading_zero(s: &str) -> bool {
+    let digits = s.trim_start_matches(['+', '-']).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+fn parse_integer(s: &str) -> Option<i64> {
+    let s = s.trim();
+    if leading_zero(s) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_float(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if leading_zero(s) {
+        return None;
+    }
+    s.parse().ok().filter(|f: &f64| f.is_finite())
+}
+
+fn infer_type<'a>(vals: impl Iterator<Item = &'a str>) -> ColumnType {
+    let mut t = ColumnType::Integer;
+    for v in vals.filter(|v| !v.is_empty()) {
+        if t == ColumnType::Integer && parse_integer(v).is_none() {
+            t = ColumnType::Float;
+        }
+        if t == ColumnType::Float && parse_float(v).is_none() {
+            return ColumnType::String;
+        }
+    }
+    t
+}
+
+fn parse_value(s: &str, t: ColumnType) -> Value {
+    match t {
+        ColumnType::Integer => match parse_integer(s) {
+            Some(i) => Value::Integer(i),
+            None => Value::String(s.to_string()),
+        },
+        ColumnType::Float => match parse_float(s) {
+            Some(f) => Value::Float(f),
+            None => Value::String(s.to_string()),
+        },
+        ColumnType::String => Value::String(s.to_string()),
+    }
+}
+
+impl<R: Read> FeatureSource for CsvReader<R> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        let rec = match self.buffered.pop_front() {
+            Some(rec) => rec,
+            None => match self.records.next() {
+                Some(rec) => rec.map_err(invalid_data)?,
+                None => return Ok(None),
+            },
+        };
+        self.to_feature(rec).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for CsvReader<R> {
+    type Item = io::Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_feature().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsvOptions, CsvReader};
+    use crate::Value;
+    use geo_types::{line_string, Geometry};
+
+    #[test]
+    fn wkt_tsv() {
+        let data = "id\tgeom\tlength\tnote\n\
+                    1\tLINESTRING (0 0, 1 1)\t1.5\t\n\
+                    2\tPOINT (3 4)\t2\tx\n";
+        let opts = CsvOptions {
+            delimiter: b'\t',
+            ..CsvOptions::wkt("geom")
+        };
+        let fts: Vec<_> = CsvReader::new(data.as_bytes(), opts)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts.len(), 2);
+        assert_eq!(
+            fts[0].geometry,
+            Geometry::from(line_string![(x: 0., y: 0.), (x: 1., y: 1.)])
+        );
+        assert_eq!(fts[0].tags["id"], Value::Integer(1));
+        assert_eq!(fts[0].tags["length"], Value::Float(1.5));
+        assert_eq!(fts[1].tags["length"], Value::Float(2.));
+        assert!(!fts[0].tags.contains_key("note"));
+        assert_eq!(fts[1].tags["note"], Value::String("x".to_string()));
+        assert!(!fts[0].tags.contains_key("geom"));
+    }
+
+    #[test]
+    fn type_fallback_after_inference() {
+        let data = "lon,lat,code\n1,2,10\n3,4,A7\n";
+        let opts = CsvOptions {
+            infer_rows: 1,
+            ..CsvOptions::lon_lat("lon", "lat")
+        };
+        let fts: Vec<_> = CsvReader::new(data.as_bytes(), opts)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts[0].tags["code"], Value::Integer(10));
+        assert_eq!(fts[1].tags["code"], Value::String("A7".to_string()));
+    }
+
+    #[test]
+    fn strings_that_look_numeric() {
+        let data = "lon,lat,zip,height,ratio\n1,2,007,nan,0.5\n3,4,10,1.5,-0.25\n";
+        let fts: Vec<_> = CsvReader::new(data.as_bytes(), CsvOptions::lon_lat("lon", "lat"))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts[0].tags["zip"], Value::String("007".to_string()));
+        assert_eq!(fts[1].tags["zip"], Value::String("10".to_string()));
+        assert_eq!(fts[0].tags["height"], Value::String("nan".to_string()));
+        assert_eq!(fts[0].tags["ratio"], Value::Float(0.5));
+
+        let data = "lon,lat,height\n1,2,1.5\n3,4,inf\n5,6,08\n";
+        let opts = CsvOptions {
+            infer_rows: 1,
+            ..CsvOptions::lon_lat("lon", "lat")
+        };
+        let fts: Vec<_> = CsvReader::new(data.as_bytes(), opts)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts[1].tags["height"], Value::String("inf".to_string()));
+        assert_eq!(fts[2].tags["height"], Value::String("08".to_string()));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(CsvReader::new("a,b\n".as_bytes(), CsvOptions::wkt("geom")).is_err());
+        let mut r = CsvReader::new("geom\nPOINT (1\n".as_bytes(), CsvOptions::wkt("geom")).unwrap();
+        assert!(r.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! Routing the blocks of a file to handlers by message type.
+//!
+//! Besides features, a block can hold [`metadata`](crate::metadata) or data of message types
+//! that this crate does not know, e.g. an index or application specific payloads. Other readers
+//! skip metadata and refuse unknown types; a [`Dispatcher`] passes every block to the handler
+//! registered for its type instead:
+//!
+//! * [`on_features`](Dispatcher::on_features) receives the decoded features of each block,
+//! * [`on_metadata`](Dispatcher::on_metadata) the decoded metadata,
+//! * [`on`](Dispatcher::on) the payload of one message type, decrypted and decompressed but
+//!   otherwise as stored. It replaces the typed handlers for the feature and metadata types,
+//! * [`on_unknown`](Dispatcher::on_unknown) the payloads of all types without a handler.
+//!
+//! Blocks without a handler are skipped without being decoded. A handler that returns an error
+//! stops the dispatch.
+//! ```
+//! use spaten::dispatch::Dispatcher;
+//! use spaten::metadata::Metadata;
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureWriter};
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! w.write_metadata(&Metadata::now())?;
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! })?;
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let (mut features, mut producer) = (0, None);
+//! Dispatcher::new()
+//!     .on_features(|fts| {
+//!         features += fts.len();
+//!         Ok(())
+//!     })
+//!     .on_metadata(|meta| {
+//!         producer = meta.producer;
+//!         Ok(())
+//!     })
+//!     .run(&mut &buf[..])?;
+//! assert_eq!(features, 1);
+//! assert!(producer.unwrap().starts_with("spaten"));
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::metadata::Metadata;
+use crate::{
+    check_block_frame, decode_body, open_block, read_raw_checked, try_read_file_header, Feature,
+    ReaderOptions, MESSAGE_BODY, MESSAGE_META,
+};
+use std::collections::HashMap;
+use std::io;
+
+type Handler<'h, T> = Box<dyn FnMut(T) -> io::Result<()> + 'h>;
+type UnknownHandler<'h> = Box<dyn FnMut(u8, Vec<u8>) -> io::Result<()> + 'h>;
+
+/// Reads a file and passes its blocks to handlers by message type.
+pub struct Dispatcher<'h> {
+    options: ReaderOptions,
+    features: Option<Handler<'h, Vec<Feature>>>,
+    metadata: Option<Handler<'h, Metadata>>,
+    raw: HashMap<u8, Handler<'h, Vec<u8>>>,
+    unknown: Option<UnknownHandler<'h>>,
+}
+
+impl Default for Dispatcher<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'h> Dispatcher<'h> {
+    pub fn new() -> Self {
+        Self::with_options(ReaderOptions::default())
+    }
+
+    /// Decodes and decrypts according to `options`.
+    pub fn with_options(options: ReaderOptions) -> Self {
+        Dispatcher {
+            options,
+            features: None,
+            metadata: None,
+            raw: HashMap::new(),
+            unknown: None,
+        }
+    }
+
+    pub fn on_features(mut self, f: impl FnMut(Vec<Feature>) -> io::Result<()> + 'h) -> Self {
+        self.features = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_metadata(mut self, f: impl FnMut(Metadata) -> io::Result<()> + 'h) -> Self {
+        self.metadata = Some(Box::new(f));
+        self
+    }
+
+    /// Handles the payloads of `message_type`.
+    pub fn on(mut self, message_type: u8, f: impl FnMut(Vec<u8>) -> io::Result<()> + 'h) -> Self {
+        self.raw.insert(message_type, Box::new(f));
+        self
+    }
+
+    /// Handles the payloads of all message types without a handler of their own. Receives the
+    /// message type and the payload.
+    pub fn on_unknown(mut self, f: impl FnMut(u8, Vec<u8>) -> io::Result<()> + 'h) -> Self {
+        self.unknown = Some(Box::new(f));
+        self
+    }
+
+    /// Reads the file header and dispatches all blocks, and returns the number of blocks read.
+    pub fn run(&mut self, r: &mut impl io::Read) -> io::Result<u64> {
+        try_read_file_header(r)?;
+        let max_len = self.options.limits.max_block_size;
+        let mut blocks = 0;
+        while let Some((header, raw)) = read_raw_checked(r, max_len, check_block_frame)? {
+            blocks += 1;
+            let message_type = header.bytes[3];
+            let has_handler = match message_type {
+                _ if self.raw.contains_key(&message_type) => true,
+                MESSAGE_BODY => self.features.is_some(),
+                MESSAGE_META => self.metadata.is_some(),
+                _ => self.unknown.is_some(),
+            };
+            if !has_handler {
+                continue;
+            }
+            let payload = open_block(header, raw, self.options.key.as_ref(), max_len)?;
+            if let Some(f) = self.raw.get_mut(&message_type) {
+                f(payload)?;
+                continue;
+            }
+            match (message_type, &mut self.features, &mut self.metadata) {
+                (MESSAGE_BODY, Some(f), _) => f(decode_body(&payload, &self.options, None)?)?,
+                (MESSAGE_META, _, Some(f)) => f(Metadata::decode(&payload)?)?,
+                _ => {
+                    if let Some(f) = &mut self.unknown {
+                        f(message_type, payload)?;
+                    }
+                }
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dispatcher;
+    use crate::metadata::Metadata;
+    use crate::sink::FeatureSink;
+    use crate::{Feature, FeatureIterator, FeatureWriter, WriterOptions};
+
+    /// A file with a metadata block, two feature blocks and a block of message type 7 in between.
+    fn file() -> Vec<u8> {
+        let opts = WriterOptions {
+            block_size: 1,
+            gzip_level: Some(1),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        w.write_metadata(&Metadata::default()).unwrap();
+        let pt = Feature {
+            geometry: geo_types::Point::new(7.0, 51.0).into(),
+            tags: Default::default(),
+        };
+        w.accept(pt.clone()).unwrap();
+        w.write_message(b"custom".to_vec(), 7).unwrap();
+        w.accept(pt).unwrap();
+        w.finish().unwrap();
+        w.into_inner()
+    }
+
+    #[test]
+    fn routing() {
+        let buf = file();
+        assert!(FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .any(|ft| ft.is_err()));
+
+        let (mut features, mut metadata, mut unknown) = (0, 0, Vec::new());
+        let blocks = Dispatcher::new()
+            .on_features(|fts| {
+                features += fts.len();
+                Ok(())
+            })
+            .on_metadata(|_| {
+                metadata += 1;
+                Ok(())
+            })
+            .on_unknown(|t, payload| {
+                unknown.push((t, payload));
+                Ok(())
+            })
+            .run(&mut &buf[..])
+            .unwrap();
+        assert_eq!((blocks, features, metadata), (4, 2, 1));
+        assert_eq!(unknown, [(7, b"custom".to_vec())]);
+
+        let mut raw = Vec::new();
+        Dispatcher::new()
+            .on(0, |payload| {
+                raw.push(payload);
+                Ok(())
+            })
+            .run(&mut &buf[..])
+            .unwrap();
+        assert_eq!(raw.len(), 2);
+        assert_eq!(crate::parse_block_body(&raw[0]).unwrap().len(), 1);
+
+        let err = Dispatcher::new()
+            .on(7, |_| Err(std::io::Error::other("stop")))
+            .run(&mut &buf[..])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "stop");
+    }
+}
@@ -0,0 +1,163 @@
+//! Export of features for the `_bulk` API of Elasticsearch and OpenSearch.
+//!
+//! Every feature becomes an `index` action followed by its document, each on one line of
+//! newline delimited JSON. The geometry is stored as GeoJSON in a `geo_shape` field, the tags are
+//! mapped like GeoJSON properties, see [`crate::geojson`], and become the other fields of the
+//! document. A tag with the name of the geometry field is replaced by the geometry.
+//!
+//! The output can be posted to `_bulk` with any HTTP client. Large extracts should be sent in
+//! several requests of a few thousand features, e.g. by writing [`batches`] into separate
+//! buffers.
+//!
+//! [`batches`]: crate::transform::batches
+
+use crate::geojson::value_to_json;
+use crate::sink::FeatureSink;
+use crate::{Feature, Value};
+use ::geojson::{JsonObject, JsonValue};
+use std::io;
+use std::io::Write;
+
+#[derive(Clone, Debug)]
+pub struct BulkOptions {
+    /// Name of the target index.
+    pub index: String,
+    /// Tag whose value is used as document id. Features without it get an id assigned by the
+    /// server.
+    pub id_key: Option<String>,
+    /// Name of the `geo_shape` field.
+    pub geometry_field: String,
+}
+
+impl BulkOptions {
+    /// Indexes into `index`, with automatic ids and the geometry in the field `geometry`.
+    pub fn new(index: &str) -> Self {
+        BulkOptions {
+            index: index.to_string(),
+            id_key: None,
+            geometry_field: "geometry".to_string(),
+        }
+    }
+}
+
+/// Writes features as body of a `_bulk` request.
+/// ```
+/// use spaten::elasticsearch::{BulkOptions, BulkWriter};
+/// use spaten::sink::FeatureSink;
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("osm_id".to_string(), Value::Integer(42));
+/// tags.insert("name".to_string(), Value::from("Bonn"));
+/// let opts = BulkOptions {
+///     id_key: Some("osm_id".to_string()),
+///     ..BulkOptions::new("places")
+/// };
+/// let mut w = BulkWriter::new(Vec::new(), opts);
+/// w.accept(Feature {
+///     geometry: geo_types::Point::new(7.1, 50.7).into(),
+///     tags,
+/// })?;
+/// w.finish()?;
+/// let body = String::from_utf8(w.into_inner()).unwrap();
+/// assert_eq!(
+///     body,
+///     "{\"index\":{\"_id\":\"42\",\"_index\":\"places\"}}\n\
+///      {\"geometry\":{\"coordinates\":[7.1,50.7],\"type\":\"Point\"},\"name\":\"Bonn\",\"osm_id\":42}\n"
+/// );
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct BulkWriter<W: Write> {
+    w: W,
+    opts: BulkOptions,
+}
+
+impl<W: Write> BulkWriter<W> {
+    pub fn new(w: W, opts: BulkOptions) -> Self {
+        BulkWriter { w, opts }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+fn document_id(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+impl<W: Write> FeatureSink for BulkWriter<W> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        let mut action = JsonObject::new();
+        action.insert(
+            "_index".to_string(),
+            JsonValue::from(self.opts.index.as_str()),
+        );
+        let id = self.opts.id_key.as_ref().and_then(|k| ft.tags.get(k));
+        if let Some(id) = id.and_then(document_id) {
+            action.insert("_id".to_string(), JsonValue::from(id));
+        }
+        let mut index = JsonObject::new();
+        index.insert("index".to_string(), JsonValue::Object(action));
+
+        let mut doc: JsonObject = ft
+            .tags
+            .iter()
+            .map(|(k, v)| (k.clone(), value_to_json(v)))
+            .collect();
+        let geometry = ::geojson::GeometryValue::from(&ft.geometry);
+        doc.insert(self.opts.geometry_field.clone(), JsonValue::from(&geometry));
+        doc.sort_keys();
+
+        writeln!(self.w, "{}", JsonValue::Object(index))?;
+        writeln!(self.w, "{}", JsonValue::Object(doc))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BulkOptions, BulkWriter};
+    use crate::sink::FeatureSink;
+    use crate::source::copy;
+    use crate::{Feature, Value};
+    use geo_types::line_string;
+    use std::collections::HashMap;
+
+    #[test]
+    fn bulk() {
+        let mut tags = HashMap::new();
+        tags.insert("ref".to_string(), Value::Float(1.5));
+        tags.insert("shape".to_string(), Value::from("replaced"));
+        let ft = Feature {
+            geometry: line_string![(x: 1., y: 2.), (x: 3., y: 4.)].into(),
+            tags,
+        };
+        let opts = BulkOptions {
+            id_key: Some("ref".to_string()),
+            geometry_field: "shape".to_string(),
+            ..BulkOptions::new("roads")
+        };
+        let mut w = BulkWriter::new(Vec::new(), opts);
+        copy(&mut vec![ft.clone(), ft].into_iter(), &mut w).unwrap();
+        w.finish().unwrap();
+        let body = String::from_utf8(w.into_inner()).unwrap();
+
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], serde_json::json!({"index": {"_index": "roads"}}));
+        assert_eq!(lines[3]["shape"]["type"], "LineString");
+        assert_eq!(lines[3]["ref"], 1.5);
+    }
+}
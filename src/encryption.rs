@@ -0,0 +1,439 @@
+//! Authenticated encryption of block bodies, for distributing sensitive datasets such as the
+//! locations of protected species.
+//!
+//! Encrypted blocks set bit 0 of the block header flags. Their body starts with the position of
+//! the block: a random 16 byte file id shared by all blocks of a file, the u64 little endian
+//! sequence number of the block and a byte that is 1 for the last block. It is followed by a
+//! random 24 byte nonce and the XChaCha20-Poly1305 ciphertext of the (possibly compressed) body,
+//! which authenticates the block header and the position as associated data. Writers end every
+//! encrypted file with an empty last block.
+//!
+//! [`FeatureIterator`](crate::FeatureIterator) and the other streaming readers check that the
+//! blocks belong to the same file and follow each other, so that reordered, duplicated, dropped
+//! and appended blocks as well as truncated files are detected. Readers that seek to single
+//! blocks can only authenticate those. Only the block bodies are protected: the file header,
+//! the block lengths and the number of blocks stay readable.
+//!
+//! Encrypting and decrypting needs the `encryption` feature. Without it, encrypted blocks are
+//! still recognised, but reading or writing them fails with [`Error::Decrypt`].
+//! ```
+//! # #[cfg(feature = "encryption")]
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use spaten::encryption::Key;
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Error, Feature, FeatureIterator, FeatureWriter, ReaderOptions, WriterOptions};
+//!
+//! let key = Key::generate();
+//! let opts = WriterOptions {
+//!     key: Some(key.clone()),
+//!     ..Default::default()
+//! };
+//! let mut w = FeatureWriter::with_options(Vec::new(), opts);
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! })?;
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let opts = ReaderOptions {
+//!     key: Some(key),
+//!     ..Default::default()
+//! };
+//! assert_eq!(FeatureIterator::with_options(&mut &buf[..], opts)?.count(), 1);
+//! assert!(matches!(
+//!     FeatureIterator::try_new(&mut &buf[..])?.next(),
+//!     Some(Err(Error::Decrypt(_)))
+//! ));
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "encryption"))]
+//! # fn main() {}
+//! ```
+
+use crate::Error;
+#[cfg(feature = "encryption")]
+use chacha20poly1305::aead::{Aead, Generate, KeyInit, Payload};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+#[cfg(feature = "encryption")]
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Block header flag of encrypted blocks.
+pub(crate) const FLAG: u16 = 1;
+
+#[cfg(not(feature = "encryption"))]
+const DISABLED: &str = "Encryption support is not enabled";
+
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+const FILE_ID_LEN: usize = 16;
+/// File id, sequence number and last block marker.
+#[cfg(feature = "encryption")]
+const POSITION_LEN: usize = FILE_ID_LEN + 8 + 1;
+
+/// A 256 bit XChaCha20-Poly1305 key. Its `Debug` output does not reveal the key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Key(bytes)
+    }
+
+    /// Creates a random key using the random number generator of the operating system.
+    #[cfg(feature = "encryption")]
+    pub fn generate() -> Self {
+        Key(<[u8; 32]>::generate())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    #[cfg(feature = "encryption")]
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.0.into())
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// Encrypts a block body under a fresh nonce, authenticating `aad`, usually the block header.
+#[cfg(feature = "encryption")]
+pub(crate) fn seal(key: &Key, aad: &[u8], body: &[u8]) -> Vec<u8> {
+    let nonce = XNonce::generate();
+    let ciphertext = key
+        .cipher()
+        .encrypt(&nonce, Payload { msg: body, aad })
+        .expect("block bodies are shorter than the cipher limit");
+    let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    out
+}
+
+/// Decrypts a body written by [`seal`]. Fails if the body or `aad` were modified, or if the
+/// body was encrypted with another key.
+#[cfg(feature = "encryption")]
+pub(crate) fn open(key: &Key, aad: &[u8], body: &[u8]) -> Result<Vec<u8>, Error> {
+    if body.len() < NONCE_LEN {
+        return Err(Error::Decrypt("Encrypted block is too short"));
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    let nonce = XNonce::try_from(nonce).map_err(|_| Error::Decrypt("Invalid nonce"))?;
+    key.cipher()
+        .decrypt(
+            &nonce,
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::Decrypt("Block authentication failed"))
+}
+
+/// Seals the blocks of one file.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+pub(crate) struct Sealer {
+    file_id: [u8; FILE_ID_LEN],
+    next: u64,
+}
+
+impl Sealer {
+    pub(crate) fn new() -> Self {
+        #[cfg(feature = "encryption")]
+        let file_id = <[u8; FILE_ID_LEN]>::generate();
+        #[cfg(not(feature = "encryption"))]
+        let file_id = [0; FILE_ID_LEN];
+        Sealer { file_id, next: 0 }
+    }
+
+    /// Encrypts the next block body, authenticating its `header` and position.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn seal(
+        &mut self,
+        key: &Key,
+        header: &[u8; 4],
+        body: &[u8],
+        last: bool,
+    ) -> Result<Vec<u8>, Error> {
+        let mut position = Vec::with_capacity(POSITION_LEN);
+        position.extend_from_slice(&self.file_id);
+        position.extend_from_slice(&self.next.to_le_bytes());
+        position.push(u8::from(last));
+        self.next += 1;
+        let mut out = position.clone();
+        out.extend(seal(key, &aad(header, &position), body));
+        Ok(out)
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub(crate) fn seal(
+        &mut self,
+        _key: &Key,
+        _header: &[u8; 4],
+        _body: &[u8],
+        _last: bool,
+    ) -> Result<Vec<u8>, Error> {
+        Err(Error::Decrypt(DISABLED))
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn aad(header: &[u8; 4], position: &[u8]) -> Vec<u8> {
+    let mut aad = header.to_vec();
+    aad.extend_from_slice(position);
+    aad
+}
+
+/// Where a block belongs, as authenticated by [`open_block`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Position {
+    file_id: [u8; FILE_ID_LEN],
+    sequence: u64,
+    last: bool,
+}
+
+/// Decrypts a block body written by [`Sealer::seal`] and returns its position.
+#[cfg(feature = "encryption")]
+pub(crate) fn open_block(
+    key: &Key,
+    header: &[u8; 4],
+    body: &[u8],
+) -> Result<(Position, Vec<u8>), Error> {
+    if body.len() < POSITION_LEN {
+        return Err(Error::Decrypt("Encrypted block is too short"));
+    }
+    let (position, sealed) = body.split_at(POSITION_LEN);
+    let body = open(key, &aad(header, position), sealed)?;
+    let mut file_id = [0; FILE_ID_LEN];
+    file_id.copy_from_slice(&position[..FILE_ID_LEN]);
+    let mut sequence = [0; 8];
+    sequence.copy_from_slice(&position[FILE_ID_LEN..FILE_ID_LEN + 8]);
+    let position = Position {
+        file_id,
+        sequence: u64::from_le_bytes(sequence),
+        last: position[POSITION_LEN - 1] != 0,
+    };
+    Ok((position, body))
+}
+
+#[cfg(not(feature = "encryption"))]
+pub(crate) fn open_block(
+    _key: &Key,
+    _header: &[u8; 4],
+    _body: &[u8],
+) -> Result<(Position, Vec<u8>), Error> {
+    Err(Error::Decrypt(DISABLED))
+}
+
+/// Checks that the blocks read by a streaming reader follow each other.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Sequence {
+    file_id: Option<[u8; FILE_ID_LEN]>,
+    next: u64,
+    done: bool,
+}
+
+impl Sequence {
+    pub(crate) fn check(&mut self, position: &Position) -> Result<(), Error> {
+        if self.done {
+            return Err(Error::Decrypt("Block after the last encrypted block"));
+        }
+        if *self.file_id.get_or_insert(position.file_id) != position.file_id {
+            return Err(Error::Decrypt("Block belongs to another file"));
+        }
+        if position.sequence != self.next {
+            return Err(Error::Decrypt("Encrypted blocks are out of order"));
+        }
+        self.next += 1;
+        self.done = position.last;
+        Ok(())
+    }
+
+    /// Fails if the last block has not been read.
+    pub(crate) fn finish(&self) -> Result<(), Error> {
+        match self.done {
+            true => Ok(()),
+            false => Err(Error::Decrypt("Encrypted file is truncated")),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::Key;
+    use crate::sink::FeatureSink;
+    use crate::{
+        parse_block_body, parse_frame, Error, Feature, FeatureIterator, FeatureWriter, Limits,
+        ReaderOptions, Value, WriterOptions,
+    };
+    use std::collections::HashMap;
+
+    fn file(key: Option<Key>) -> Vec<u8> {
+        let opts = WriterOptions {
+            block_size: 2,
+            gzip_level: Some(6),
+            key,
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        for i in 0..5 {
+            let mut tags = HashMap::new();
+            tags.insert(
+                "species".to_string(),
+                Value::String("Lynx lynx".to_string()),
+            );
+            tags.insert("i".to_string(), Value::Integer(i));
+            w.accept(Feature {
+                geometry: geo_types::Point::new(8.5, 50.1).into(),
+                tags,
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        w.into_inner()
+    }
+
+    fn read(buf: &[u8], key: Option<Key>) -> Result<Vec<Feature>, Error> {
+        let opts = ReaderOptions {
+            key,
+            ..Default::default()
+        };
+        FeatureIterator::with_options(&mut &buf[..], opts)?.collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = Key::new([7; 32]);
+        let buf = file(Some(key.clone()));
+        assert!(!buf.windows(4).any(|w| w == b"Lynx"));
+
+        let fts = read(&buf, Some(key.clone())).unwrap();
+        assert_eq!(fts.len(), 5);
+        assert_eq!(fts[4].tags["i"], Value::Integer(4));
+
+        let frame = parse_frame(&buf[8..]).unwrap().unwrap();
+        assert!(frame.encrypted);
+        assert!(matches!(
+            frame.decompress(&Limits::default()),
+            Err(Error::Decrypt(_))
+        ));
+        let body = frame.decrypt(&key, &Limits::default()).unwrap();
+        assert_eq!(parse_block_body(&body).unwrap().len(), 2);
+
+        assert_eq!(format!("{:?}", key), "Key(..)");
+    }
+
+    #[test]
+    fn rejected() {
+        let key = Key::new([7; 32]);
+        let buf = file(Some(key.clone()));
+        let decrypt_err = |res| matches!(res, Err(Error::Decrypt(_)));
+
+        assert!(decrypt_err(read(&buf, None)));
+        assert!(decrypt_err(read(&buf, Some(Key::generate()))));
+        assert!(decrypt_err(read(&file(None), Some(key.clone()))));
+
+        // modified body
+        let mut tampered = buf.clone();
+        tampered[50] ^= 1;
+        assert!(decrypt_err(read(&tampered, Some(key.clone()))));
+
+        // modified header, which is authenticated as well
+        let mut tampered = buf;
+        tampered[14] = 0;
+        assert!(decrypt_err(read(&tampered, Some(key))));
+    }
+
+    #[test]
+    fn block_order() {
+        let key = Key::new([7; 32]);
+        let blocks = |buf: &[u8]| {
+            let mut rest = &buf[8..];
+            let mut blocks = Vec::new();
+            while let Some(frame) = parse_frame(rest).unwrap() {
+                blocks.push(rest[..frame.len].to_vec());
+                rest = &rest[frame.len..];
+            }
+            blocks
+        };
+        let assemble = |blocks: &[&Vec<u8>]| {
+            let mut buf = b"SPAT\0\0\0\0".to_vec();
+            for b in blocks {
+                buf.extend(b.iter());
+            }
+            buf.extend(b"\0\0\0\0");
+            buf
+        };
+        let a = blocks(&file(Some(key.clone())));
+        let b = blocks(&file(Some(key.clone())));
+        // three blocks of features and the empty last block
+        assert_eq!(a.len(), 4);
+        let read = |blocks: &[&Vec<u8>]| read(&assemble(blocks), Some(key.clone()));
+        assert_eq!(read(&[&a[0], &a[1], &a[2], &a[3]]).unwrap().len(), 5);
+
+        for blocks in [
+            // reordered
+            vec![&a[1], &a[0], &a[2], &a[3]],
+            // duplicated
+            vec![&a[0], &a[0], &a[1], &a[2], &a[3]],
+            // dropped
+            vec![&a[0], &a[2], &a[3]],
+            // truncated
+            vec![&a[0], &a[1], &a[2]],
+            vec![],
+            // appended
+            vec![&a[0], &a[1], &a[2], &a[3], &a[3]],
+            // from another file
+            vec![&a[0], &b[1], &a[2], &a[3]],
+        ] {
+            assert!(matches!(read(&blocks), Err(Error::Decrypt(_))));
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "encryption")))]
+mod tests {
+    use super::{Key, FLAG};
+    use crate::sink::FeatureSink;
+    use crate::{Error, Feature, FeatureIterator, FeatureWriter, ReaderOptions, WriterOptions};
+
+    #[test]
+    fn disabled() {
+        let ft = Feature {
+            geometry: geo_types::Point::new(8.5, 50.1).into(),
+            tags: Default::default(),
+        };
+        let opts = WriterOptions {
+            key: Some(Key::new([7; 32])),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        w.accept(ft.clone()).unwrap();
+        assert!(w.finish().is_err());
+
+        let mut w = FeatureWriter::new(Vec::new());
+        w.accept(ft).unwrap();
+        w.finish().unwrap();
+        let mut buf = w.into_inner();
+        buf[12..14].copy_from_slice(&FLAG.to_le_bytes());
+        let opts = ReaderOptions {
+            key: Some(Key::new([7; 32])),
+            ..Default::default()
+        };
+        assert!(matches!(
+            FeatureIterator::with_options(&mut &buf[..], opts)
+                .unwrap()
+                .next(),
+            Some(Err(Error::Decrypt(_)))
+        ));
+    }
+}
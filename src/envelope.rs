@@ -0,0 +1,283 @@
+//! Sharing an [encrypted](crate::encryption) file with several recipients, each holding their
+//! own X25519 key pair.
+//!
+//! The blocks are encrypted with a random content key as usual. The content key is then wrapped
+//! for every recipient public key and stored in an unencrypted first block, which holds no
+//! features and one `spaten:recipient` meta tag per recipient. Adding a partner only means
+//! writing another wrapped key, the payload is encrypted once.
+//!
+//! A wrapped key is the hex encoding of an ephemeral public key, followed by the content key
+//! encrypted with XChaCha20-Poly1305. The wrapping key is derived with HKDF-SHA256 from the
+//! X25519 shared secret, salted with both public keys. Public keys of low order, which would
+//! give a shared secret known to everyone, are rejected.
+//!
+//! Needs the `encryption` feature.
+//! ```
+//! use spaten::envelope::{self, SecretKey};
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, ReaderOptions, WriterOptions};
+//!
+//! let alice = SecretKey::generate();
+//! let bob = SecretKey::generate();
+//! let recipients = [alice.public_key(), bob.public_key()];
+//! let mut w = envelope::writer(Vec::new(), &recipients, WriterOptions::default())?;
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! })?;
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let mut file = &buf[..];
+//! let fts = envelope::open(&mut file, &bob, ReaderOptions::default())?;
+//! assert_eq!(fts.count(), 1);
+//! let eve = SecretKey::generate();
+//! assert!(envelope::open(&mut &buf[..], &eve, ReaderOptions::default()).is_err());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::container::meta_strings;
+use crate::encryption::{self, Key};
+use crate::{
+    decompress, encode_tag, fileformat, read_raw_block, to_hex, try_read_file_header, Error,
+    FeatureIterator, FeatureWriter, ReaderOptions, Value, WriterOptions,
+};
+use chacha20poly1305::aead::Generate;
+use hkdf::Hkdf;
+use protobuf::Message;
+use sha2::Sha256;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use x25519_dalek::StaticSecret;
+
+/// Meta tag of the envelope block, once per recipient.
+pub const RECIPIENT_KEY: &str = "spaten:recipient";
+
+/// The public key of a recipient.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        PublicKey(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// The secret key of a recipient. Its `Debug` output does not reveal the key.
+#[derive(Clone)]
+pub struct SecretKey(StaticSecret);
+
+impl SecretKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        SecretKey(StaticSecret::from(bytes))
+    }
+
+    /// Creates a random key using the random number generator of the operating system.
+    pub fn generate() -> Self {
+        Self::new(<[u8; 32]>::generate())
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(x25519_dalek::PublicKey::from(&self.0).to_bytes())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// The key that wraps the content key for one recipient.
+fn wrapping_key(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> Key {
+    let mut salt = ephemeral.0.to_vec();
+    salt.extend_from_slice(&recipient.0);
+    let mut okm = [0; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(b"spaten envelope", &mut okm)
+        .expect("32 bytes are a valid HKDF-SHA256 output length");
+    Key::new(okm)
+}
+
+fn wrap(key: &Key, recipient: &PublicKey) -> io::Result<String> {
+    let ephemeral = SecretKey::generate();
+    let shared = ephemeral
+        .0
+        .diffie_hellman(&x25519_dalek::PublicKey::from(recipient.0));
+    if !shared.was_contributory() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid recipient public key",
+        ));
+    }
+    let ephemeral = ephemeral.public_key();
+    let kek = wrapping_key(shared.as_bytes(), &ephemeral, recipient);
+    let mut wrapped = ephemeral.0.to_vec();
+    wrapped.extend(encryption::seal(&kek, &[], key.as_bytes()));
+    Ok(to_hex(&wrapped))
+}
+
+/// Returns the content key if `wrapped` was made for `secret`.
+fn unwrap(wrapped: &str, secret: &SecretKey) -> Option<Key> {
+    let wrapped = from_hex(wrapped)?;
+    if wrapped.len() < 32 {
+        return None;
+    }
+    let (ephemeral, sealed) = wrapped.split_at(32);
+    let ephemeral = PublicKey(<[u8; 32]>::try_from(ephemeral).ok()?);
+    let shared = secret
+        .0
+        .diffie_hellman(&x25519_dalek::PublicKey::from(ephemeral.0));
+    if !shared.was_contributory() {
+        return None;
+    }
+    let kek = wrapping_key(shared.as_bytes(), &ephemeral, &secret.public_key());
+    let key = encryption::open(&kek, &[], sealed).ok()?;
+    Some(Key::new(<[u8; 32]>::try_from(&key[..]).ok()?))
+}
+
+/// Creates a writer whose blocks can be decrypted by each of the `recipients`. The content key
+/// is taken from `options`, or generated if none is set. Writes the envelope block right away.
+pub fn writer<W: io::Write>(
+    w: W,
+    recipients: &[PublicKey],
+    mut options: WriterOptions,
+) -> io::Result<FeatureWriter<W>> {
+    if recipients.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No recipients given",
+        ));
+    }
+    let key = options.key.take().unwrap_or_else(Key::generate);
+    let mut w = FeatureWriter::with_options(w, options);
+    let mut meta = fileformat::Meta::new();
+    for r in recipients {
+        encode_tag(
+            &mut meta.tags,
+            RECIPIENT_KEY,
+            &Value::String(wrap(&key, r)?),
+        );
+    }
+    w.write_meta_block(meta)?;
+    w.options.key = Some(key);
+    Ok(w)
+}
+
+/// Reads the file header and the envelope block, and returns the content key wrapped for
+/// `secret`.
+pub fn content_key(r: &mut impl io::Read, secret: &SecretKey) -> Result<Key, Error> {
+    try_read_file_header(r)?;
+    read_envelope(r, secret, &ReaderOptions::default())
+}
+
+fn read_envelope(
+    r: &mut impl io::Read,
+    secret: &SecretKey,
+    options: &ReaderOptions,
+) -> Result<Key, Error> {
+    let max_len = options.limits.max_block_size;
+    let (header, raw) = read_raw_block(r, max_len)?.ok_or(Error::Decrypt("No envelope"))?;
+    if header.encrypted {
+        return Err(Error::Decrypt("No envelope"));
+    }
+    let body = decompress(header.compression, raw, max_len)?;
+    let body = fileformat::Body::parse_from_bytes(&body)?;
+    if !body.feature.is_empty() {
+        return Err(Error::Decrypt("No envelope"));
+    }
+    meta_strings(&body, RECIPIENT_KEY)
+        .iter()
+        .find_map(|wrapped| unwrap(wrapped, secret))
+        .ok_or(Error::Decrypt("The file is not encrypted for this key"))
+}
+
+/// Opens a file written by [`writer`] with the secret key of one of its recipients. The content
+/// key replaces [`ReaderOptions::key`].
+pub fn open<'a>(
+    r: &'a mut impl io::Read,
+    secret: &SecretKey,
+    mut options: ReaderOptions,
+) -> Result<FeatureIterator<'a>, Error> {
+    try_read_file_header(r)?;
+    options.key = Some(read_envelope(r, secret, &options)?);
+    Ok(FeatureIterator::after_header(r, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_key, from_hex, open, unwrap, writer, PublicKey, SecretKey};
+    use crate::encryption::Key;
+    use crate::sink::FeatureSink;
+    use crate::{Error, Feature, ReaderOptions, WriterOptions};
+
+    #[test]
+    fn hex() {
+        assert_eq!(from_hex("000fff"), Some(vec![0, 15, 255]));
+        assert_eq!(from_hex("0f0"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+
+    #[test]
+    fn recipients() {
+        let secrets: Vec<SecretKey> = (1..=3).map(|i| SecretKey::new([i; 32])).collect();
+        let public: Vec<_> = secrets[..2].iter().map(SecretKey::public_key).collect();
+        let key = Key::new([9; 32]);
+        let opts = WriterOptions {
+            block_size: 2,
+            gzip_level: Some(6),
+            key: Some(key.clone()),
+            ..Default::default()
+        };
+        let mut w = writer(Vec::new(), &public, opts).unwrap();
+        for _ in 0..5 {
+            w.accept(Feature {
+                geometry: geo_types::Point::new(1., 2.).into(),
+                tags: Default::default(),
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        for s in &secrets[..2] {
+            assert_eq!(content_key(&mut &buf[..], s).unwrap(), key);
+            let mut file = &buf[..];
+            let fts = open(&mut file, s, ReaderOptions::default()).unwrap();
+            assert_eq!(fts.map(Result::unwrap).count(), 5);
+        }
+        assert!(matches!(
+            content_key(&mut &buf[..], &secrets[2]),
+            Err(Error::Decrypt(_))
+        ));
+
+        assert!(writer(Vec::new(), &[], WriterOptions::default()).is_err());
+        // low order points give an all-zero shared secret
+        let zero = PublicKey::new([0; 32]);
+        assert!(writer(Vec::new(), &[zero], WriterOptions::default()).is_err());
+        assert!(unwrap(&crate::to_hex(&[0; 80]), &secrets[0]).is_none());
+        assert!(matches!(
+            content_key(&mut &b"SPAT\0\0\0\0\0\0\0\0"[..], &secrets[0]),
+            Err(Error::Decrypt(_))
+        ));
+    }
+}
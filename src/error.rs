@@ -0,0 +1,88 @@
+//! The error type of the read path.
+
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong while reading a Spaten file.
+#[derive(Debug)]
+pub enum Error {
+    /// The file does not start with `SPAT`.
+    InvalidMagic,
+    /// The file format version is not supported by this reader.
+    UnsupportedVersion(u32),
+    /// The input ended in the middle of a header or block.
+    Truncated,
+    /// The block header announces flags, a compression or a message type that is not supported.
+    UnsupportedBlock(&'static str),
+    /// A compressed block body could not be decompressed, or the handler of a
+    /// [registered](crate::capabilities) flag failed.
+    Decompress(io::Error),
+    /// An encrypted block could not be decrypted, or no key or the wrong key was given.
+    Decrypt(&'static str),
+    /// A block body could not be decoded.
+    Protobuf(protobuf::ProtobufError),
+    /// A geometry is not valid WKB, or uses an unsupported part of it.
+    InvalidGeometry(&'static str),
+    /// A tag value could not be decoded, or violates the configured tag policies.
+    InvalidTag(&'static str),
+    /// One of the configured [`Limits`](crate::Limits) was exceeded.
+    LimitExceeded(&'static str),
+    /// Reading from the underlying stream failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidMagic => write!(f, "not a Spaten file"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported file version {}", v),
+            Error::Truncated => write!(f, "unexpected end of input"),
+            Error::UnsupportedBlock(msg)
+            | Error::InvalidGeometry(msg)
+            | Error::Decrypt(msg)
+            | Error::InvalidTag(msg)
+            | Error::LimitExceeded(msg) => write!(f, "{}", msg),
+            Error::Decompress(e) => write!(f, "invalid compressed block: {}", e),
+            Error::Protobuf(e) => write!(f, "invalid block body: {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Protobuf(e) => Some(e),
+            Error::Decompress(e) | Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Unexpected ends of the stream are reported as [`Error::Truncated`].
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::Truncated,
+            _ => Error::Io(e),
+        }
+    }
+}
+
+impl From<protobuf::ProtobufError> for Error {
+    fn from(e: protobuf::ProtobufError) -> Self {
+        Error::Protobuf(e)
+    }
+}
+
+/// Allows `?` in functions returning `io::Result`, such as
+/// [`FeatureSource`](crate::source::FeatureSource) implementations.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            Error::Truncated => io::Error::new(io::ErrorKind::UnexpectedEof, e),
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
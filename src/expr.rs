@@ -0,0 +1,471 @@
+//! A small expression language to compute new tags from existing tags and geometry.
+//!
+//! Expressions support numbers, `'strings'`, tag references by key (`width`, `name:en`),
+//! the operators `+ - * /` with parentheses, and the functions
+//!
+//! * `area(geom)`: geodesic area in square meters,
+//! * `length(geom)`: geodesic length (or perimeter of polygons) in meters,
+//! * `lon(geom)`, `lat(geom)`: coordinates of the centroid,
+//! * `num(x)`: converts a string to a number,
+//! * `round(x)`: rounds to the nearest integer, fails outside of the range of integers.
+//!
+//! `+` concatenates if one of the operands is a string. Integer arithmetic stays integer,
+//! except for `/` which always results in a float. Expressions nested deeper than 64 levels
+//! are rejected.
+//! ```
+//! use spaten::expr::{compute, Assignment};
+//! use spaten::{Feature, Value};
+//! use std::collections::HashMap;
+//!
+//! let rule = Assignment::parse("lanes_total = lanes * 2").unwrap();
+//! let mut tags = HashMap::new();
+//! tags.insert("lanes".to_string(), Value::Integer(2));
+//! let ft = Feature {
+//!     geometry: geo_types::Point::new(0., 0.).into(),
+//!     tags,
+//! };
+//! let ft = compute(vec![ft].into_iter(), vec![rule]).next().unwrap();
+//! assert_eq!(ft.tags["lanes_total"], Value::Integer(4));
+//! ```
+
+use crate::{Feature, Value};
+use geo_types::Geometry;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(Value),
+    Str(String),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Eq,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '=' => {
+                chars.next();
+                tokens.push(Token::Eq);
+            }
+            '\'' | '"' => {
+                chars.next();
+                tokens.push(Token::Str(read_string(&mut chars, c)?));
+            }
+            c if c.is_ascii_digit() || c == '.' => tokens.push(read_number(&mut chars)?),
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == ':' {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_string(chars: &mut Peekable<Chars<'_>>, quote: char) -> Result<String, String> {
+    let mut s = String::new();
+    for c in chars.by_ref() {
+        if c == quote {
+            return Ok(s);
+        }
+        s.push(c);
+    }
+    Err("unterminated string".to_string())
+}
+
+fn read_number(chars: &mut Peekable<Chars<'_>>) -> Result<Token, String> {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() || c == '.' {
+            s.push(c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    if let Ok(i) = s.parse::<i64>() {
+        return Ok(Token::Num(Value::Integer(i)));
+    }
+    s.parse::<f64>()
+        .map(|f| Token::Num(Value::Float(f)))
+        .map_err(|_| format!("invalid number '{}'", s))
+}
+
+/// A parsed expression.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Literal(Value),
+    Tag(String),
+    Geometry,
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// Maximum depth of parentheses, signs and calls, and of the parsed expression tree, so that
+/// neither parsing nor evaluation can overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    nesting: usize,
+}
+
+/// A parsed expression with the depth of its tree.
+type Parsed = Result<(Expr, usize), String>;
+
+fn check_depth(depth: usize) -> Result<usize, String> {
+    match depth > MAX_DEPTH {
+        true => Err("expression is nested too deeply".to_string()),
+        false => Ok(depth),
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn expect(&mut self, t: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref got) if *got == t => Ok(()),
+            got => Err(format!("expected {:?}, got {:?}", t, got)),
+        }
+    }
+
+    fn expr(&mut self) -> Parsed {
+        let (mut lhs, mut depth) = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let (rhs, d) = self.term()?;
+            depth = check_depth(depth.max(d) + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok((lhs, depth))
+    }
+
+    fn term(&mut self) -> Parsed {
+        let (mut lhs, mut depth) = self.factor()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.next();
+            let (rhs, d) = self.factor()?;
+            depth = check_depth(depth.max(d) + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok((lhs, depth))
+    }
+
+    fn factor(&mut self) -> Parsed {
+        self.nesting = check_depth(self.nesting + 1)?;
+        let e = self.nested();
+        self.nesting -= 1;
+        e
+    }
+
+    fn nested(&mut self) -> Parsed {
+        match self.next() {
+            Some(Token::Num(v)) => Ok((Expr::Literal(v), 1)),
+            Some(Token::Str(s)) => Ok((Expr::Literal(Value::String(s)), 1)),
+            Some(Token::Op('-')) => {
+                let (e, depth) = self.factor()?;
+                Ok((Expr::Neg(Box::new(e)), check_depth(depth + 1)?))
+            }
+            Some(Token::LParen) => {
+                let e = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    let e = match name.as_str() {
+                        "geom" => Expr::Geometry,
+                        _ => Expr::Tag(name),
+                    };
+                    return Ok((e, 1));
+                }
+                self.next();
+                let (arg, depth) = self.expr()?;
+                self.expect(Token::RParen)?;
+                match name.as_str() {
+                    "area" | "length" | "lon" | "lat" | "num" | "round" => {
+                        Ok((Expr::Call(name, vec![arg]), check_depth(depth + 1)?))
+                    }
+                    _ => Err(format!("unknown function '{}'", name)),
+                }
+            }
+            t => Err(format!("unexpected {:?}", t)),
+        }
+    }
+}
+
+/// The intermediate result of an evaluation, which may be a geometry.
+enum Operand<'a> {
+    Value(Value),
+    Geometry(&'a Geometry<f64>),
+}
+
+impl Expr {
+    pub fn parse(s: &str) -> Result<Expr, String> {
+        let mut p = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            nesting: 0,
+        };
+        let (e, _) = p.expr()?;
+        match p.peek() {
+            None => Ok(e),
+            Some(t) => Err(format!("unexpected {:?}", t)),
+        }
+    }
+
+    /// Evaluates the expression for a feature. Returns `None` if a referenced tag is missing
+    /// or the operand types do not fit.
+    pub fn eval(&self, ft: &Feature) -> Option<Value> {
+        match self.operand(ft)? {
+            Operand::Value(v) => Some(v),
+            Operand::Geometry(_) => None,
+        }
+    }
+
+    fn operand<'a>(&self, ft: &'a Feature) -> Option<Operand<'a>> {
+        Some(Operand::Value(match self {
+            Expr::Literal(v) => v.clone(),
+            Expr::Tag(k) => ft.tags.get(k)?.clone(),
+            Expr::Geometry => return Some(Operand::Geometry(&ft.geometry)),
+            Expr::Neg(e) => match e.eval(ft)? {
+                Value::Integer(i) => Value::Integer(i.checked_neg()?),
+                Value::Float(f) => Value::Float(-f),
+                _ => return None,
+            },
+            Expr::Binary(op, a, b) => binary(*op, a.eval(ft)?, b.eval(ft)?)?,
+            Expr::Call(name, args) => call(name, args[0].operand(ft)?)?,
+        }))
+    }
+}
+
+fn as_f64(v: &Value) -> Option<f64> {
+    match v {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn to_text(v: Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Bytes(_) | Value::List(_) => None,
+    }
+}
+
+fn binary(op: char, a: Value, b: Value) -> Option<Value> {
+    if let (Value::Integer(x), Value::Integer(y)) = (&a, &b) {
+        return match op {
+            '+' => x.checked_add(*y).map(Value::Integer),
+            '-' => x.checked_sub(*y).map(Value::Integer),
+            '*' => x.checked_mul(*y).map(Value::Integer),
+            _ => Some(Value::Float(*x as f64 / *y as f64)),
+        };
+    }
+    if op == '+' && (matches!(a, Value::String(_)) || matches!(b, Value::String(_))) {
+        return Some(Value::String(to_text(a)? + &to_text(b)?));
+    }
+    let (x, y) = (as_f64(&a)?, as_f64(&b)?);
+    Some(Value::Float(match op {
+        '+' => x + y,
+        '-' => x - y,
+        '*' => x * y,
+        _ => x / y,
+    }))
+}
+
+fn call(name: &str, arg: Operand<'_>) -> Option<Value> {
+    use geo::{Centroid, Geodesic, GeodesicArea, Length};
+
+    match (name, arg) {
+        ("area", Operand::Geometry(g)) => Some(Value::Float(g.geodesic_area_unsigned())),
+        ("length", Operand::Geometry(g)) => Some(Value::Float(match g {
+            Geometry::Line(l) => Geodesic.length(l),
+            Geometry::LineString(ls) => Geodesic.length(ls),
+            Geometry::MultiLineString(mls) => Geodesic.length(mls),
+            g => g.geodesic_perimeter(),
+        })),
+        ("lon", Operand::Geometry(g)) => Some(Value::Float(g.centroid()?.x())),
+        ("lat", Operand::Geometry(g)) => Some(Value::Float(g.centroid()?.y())),
+        ("num", Operand::Value(Value::String(s))) => {
+            let s = s.trim();
+            s.parse::<i64>()
+                .map(Value::Integer)
+                .or_else(|_| s.parse::<f64>().map(Value::Float))
+                .ok()
+        }
+        ("num", Operand::Value(v)) => as_f64(&v).map(|_| v),
+        ("round", Operand::Value(v)) => {
+            let r = as_f64(&v)?.round();
+            // i64::MAX is not representable, the float is 2^63
+            match r >= i64::MIN as f64 && r < i64::MAX as f64 {
+                true => Some(Value::Integer(r as i64)),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A computed tag of the form `key = expression`.
+#[derive(Clone, Debug)]
+pub struct Assignment {
+    pub key: String,
+    pub expr: Expr,
+}
+
+impl Assignment {
+    pub fn parse(s: &str) -> Result<Assignment, String> {
+        let (key, expr) = s
+            .split_once('=')
+            .ok_or_else(|| "expected 'key = expression'".to_string())?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err("missing tag key".to_string());
+        }
+        Ok(Assignment {
+            key: key.to_string(),
+            expr: Expr::parse(expr)?,
+        })
+    }
+}
+
+/// Applies `assignments` in order to every feature. Later assignments can refer to tags that
+/// have been computed by earlier ones. If an expression cannot be evaluated for a feature,
+/// the tag is left untouched.
+pub fn compute<I: Iterator<Item = Feature>>(
+    fts: I,
+    assignments: Vec<Assignment>,
+) -> impl Iterator<Item = Feature> {
+    fts.map(move |mut ft| {
+        for a in &assignments {
+            if let Some(v) = a.expr.eval(&ft) {
+                ft.tags.insert(a.key.clone(), v);
+            }
+        }
+        ft
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Assignment, Expr};
+    use crate::{Feature, Value};
+    use geo_types::polygon;
+    use std::collections::HashMap;
+
+    fn feature() -> Feature {
+        let mut tags = HashMap::new();
+        tags.insert("width".to_string(), Value::String("3.5".to_string()));
+        tags.insert("lanes".to_string(), Value::Integer(3));
+        tags.insert("name:en".to_string(), Value::String("Main".to_string()));
+        Feature {
+            // roughly 1.1 km x 1.1 km at the equator
+            geometry:
+                polygon![(x: 0., y: 0.), (x: 0.01, y: 0.), (x: 0.01, y: 0.01), (x: 0., y: 0.01)]
+                    .into(),
+            tags,
+        }
+    }
+
+    fn eval(s: &str) -> Option<Value> {
+        Expr::parse(s).unwrap().eval(&feature())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), Some(Value::Integer(7)));
+        assert_eq!(eval("(1 + 2) * 3"), Some(Value::Integer(9)));
+        assert_eq!(eval("-lanes + 1"), Some(Value::Integer(-2)));
+        assert_eq!(eval("lanes / 2"), Some(Value::Float(1.5)));
+        assert_eq!(eval("num(width) * 2"), Some(Value::Float(7.)));
+        assert_eq!(
+            eval("name:en + ' Street'"),
+            Some(Value::String("Main Street".into()))
+        );
+        assert_eq!(eval("missing + 1"), None);
+        assert_eq!(eval("width * 2"), None);
+        assert_eq!(eval("round(-2.5)"), Some(Value::Integer(-3)));
+        assert_eq!(eval("round(10000000000 * 10000000000.0)"), None);
+        assert_eq!(eval("round(0 / 0)"), None);
+    }
+
+    #[test]
+    fn geometry_functions() {
+        match eval("round(area(geom) / 10000)") {
+            Some(Value::Integer(ha)) => assert!((122..=124).contains(&ha), "{}", ha),
+            v => panic!("unexpected {:?}", v),
+        }
+        match eval("length(geom)") {
+            Some(Value::Float(m)) => assert!((m - 4438.).abs() < 1., "{}", m),
+            v => panic!("unexpected {:?}", v),
+        }
+        match eval("lon(geom)") {
+            Some(Value::Float(lon)) => assert!((lon - 0.005).abs() < 1e-12, "{}", lon),
+            v => panic!("unexpected {:?}", v),
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("foo(1)").is_err());
+        assert!(Expr::parse("(1").is_err());
+        assert!(Expr::parse("'abc").is_err());
+        for deep in [
+            "(".repeat(100_000) + "1" + &")".repeat(100_000),
+            "-".repeat(100_000) + "1",
+            "round(".repeat(100_000) + "1" + &")".repeat(100_000),
+            vec!["1"; 100_000].join(" + "),
+        ] {
+            assert!(Expr::parse(&deep).is_err());
+        }
+        assert!(Expr::parse(&vec!["1"; 60].join(" * ")).is_ok());
+        assert!(Assignment::parse("1 + 2").is_err());
+        let a = Assignment::parse("area_ha = area(geom) / 10000").unwrap();
+        assert_eq!(a.key, "area_ha");
+    }
+}
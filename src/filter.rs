@@ -0,0 +1,156 @@
+//! Selecting features by their tags.
+//!
+//! A [`Filter`] is checked against the tags of each feature before its geometry is decoded, so
+//! rejecting a feature costs little more than reading its tags. This makes extracting a few
+//! features from country-scale files much cheaper than decoding everything and filtering
+//! afterwards.
+//! ```
+//! use spaten::filter::Filter;
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureIterator, FeatureWriter, Value};
+//! use std::collections::HashMap;
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! for (highway, r) in [("motorway", Some("A 1")), ("motorway", None), ("primary", Some("B 9"))] {
+//!     let mut tags = HashMap::new();
+//!     tags.insert("highway".to_string(), Value::from(highway));
+//!     if let Some(r) = r {
+//!         tags.insert("ref".to_string(), Value::from(r));
+//!     }
+//!     w.accept(Feature {
+//!         geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!         tags,
+//!     })?;
+//! }
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let filter = Filter::new().tag_eq("highway", "motorway").tag_exists("ref");
+//! let fts: Vec<Feature> = FeatureIterator::try_new(&mut &buf[..])?
+//!     .with_filter(filter)
+//!     .collect::<Result<_, _>>()?;
+//! assert_eq!(fts.len(), 1);
+//! assert_eq!(fts[0].tags["ref"], Value::from("A 1"));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::Value;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+enum Predicate {
+    Eq(String, Value),
+    Text(String, String),
+    Exists(String),
+    Missing(String),
+}
+
+impl Predicate {
+    fn matches(&self, tags: &HashMap<String, Value>) -> bool {
+        match self {
+            Predicate::Eq(key, value) => match tags.get(key) {
+                // keys that occurred multiple times, see `DuplicateTags::Collect`
+                Some(Value::List(vs)) if !matches!(value, Value::List(_)) => vs.contains(value),
+                Some(v) => v == value,
+                None => false,
+            },
+            Predicate::Text(key, text) => tags.get(key).is_some_and(|v| text_matches(v, text)),
+            Predicate::Exists(key) => tags.contains_key(key),
+            Predicate::Missing(key) => !tags.contains_key(key),
+        }
+    }
+}
+
+fn text_matches(v: &Value, text: &str) -> bool {
+    match v {
+        Value::String(s) => s == text,
+        Value::Integer(i) => text.parse() == Ok(*i),
+        Value::Float(f) => text.parse() == Ok(*f),
+        Value::Bytes(b) => b == text.as_bytes(),
+        Value::List(vs) => vs.iter().any(|v| text_matches(v, text)),
+    }
+}
+
+/// A conjunction of tag predicates. The empty filter matches every feature.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    predicates: Vec<Predicate>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the tag `key` to equal `value`. Values of different types are never equal, so
+    /// `Value::Integer(1)` does not match `Value::Float(1.0)`. If `key` occurred several times
+    /// and was read as a list, one of its values has to match.
+    pub fn tag_eq(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.predicates
+            .push(Predicate::Eq(key.to_string(), value.into()));
+        self
+    }
+
+    /// Requires the tag `key` to equal `text` parsed as the type of the tag's value, e.g. for
+    /// filters typed by a user. `"3"` matches the string `"3"`, the integer 3 and the float 3.0,
+    /// `"3.0"` does not match the integer.
+    pub fn tag_text(mut self, key: &str, text: &str) -> Self {
+        self.predicates
+            .push(Predicate::Text(key.to_string(), text.to_string()));
+        self
+    }
+
+    /// Requires the tag `key` to be present, regardless of its value.
+    pub fn tag_exists(mut self, key: &str) -> Self {
+        self.predicates.push(Predicate::Exists(key.to_string()));
+        self
+    }
+
+    /// Requires the tag `key` to be absent.
+    pub fn tag_missing(mut self, key: &str) -> Self {
+        self.predicates.push(Predicate::Missing(key.to_string()));
+        self
+    }
+
+    /// Whether a feature with `tags` passes all predicates.
+    pub fn matches(&self, tags: &HashMap<String, Value>) -> bool {
+        self.predicates.iter().all(|p| p.matches(tags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use crate::Value;
+    use std::collections::HashMap;
+
+    #[test]
+    fn predicates() {
+        let mut tags = HashMap::new();
+        tags.insert("highway".to_string(), Value::from("motorway"));
+        tags.insert("lanes".to_string(), Value::from(3));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::from("A 1"), Value::from("E 37")]),
+        );
+
+        assert!(Filter::new().matches(&tags));
+        assert!(Filter::new().tag_eq("highway", "motorway").matches(&tags));
+        assert!(!Filter::new().tag_eq("highway", "primary").matches(&tags));
+        assert!(Filter::new().tag_eq("lanes", 3).matches(&tags));
+        assert!(!Filter::new().tag_eq("lanes", 3.0).matches(&tags));
+        assert!(!Filter::new().tag_eq("lanes", "3").matches(&tags));
+        assert!(Filter::new().tag_eq("ref", "E 37").matches(&tags));
+        assert!(!Filter::new().tag_eq("name", "A 1").matches(&tags));
+        assert!(Filter::new().tag_text("lanes", "3").matches(&tags));
+        assert!(!Filter::new().tag_text("lanes", "3.0").matches(&tags));
+        assert!(Filter::new().tag_text("highway", "motorway").matches(&tags));
+        assert!(Filter::new().tag_text("ref", "A 1").matches(&tags));
+        assert!(!Filter::new().tag_text("name", "").matches(&tags));
+
+        let f = Filter::new().tag_exists("ref").tag_missing("name");
+        assert!(f.matches(&tags));
+        assert!(!f.clone().tag_exists("name").matches(&tags));
+        assert!(!Filter::new().tag_missing("lanes").matches(&tags));
+    }
+}
@@ -0,0 +1,296 @@
+//! Conversion between Spaten and FlatGeobuf (requires the `flatgeobuf` feature).
+//!
+//! FlatGeobuf files declare their property columns and types in the header, while Spaten tags
+//! may differ from feature to feature. When writing FlatGeobuf, the column types are therefore
+//! inferred from the first features, like [`crate::csv`] does: a key whose values are all of one
+//! type gets a column of that type, integers mixed with floats a double column and other mixtures
+//! a string column. Keys that first occur later get a column of the type of their first value.
+//! Later values are converted to their column type where this loses no information, numbers and
+//! lists to strings for example, and omitted otherwise. Lists are written as JSON columns.
+//!
+//! The geometry type is declared as unknown, so features of any geometry type can be mixed and
+//! are stored as they are. The output has a spatial index.
+//!
+//! When reading FlatGeobuf, properties are mapped like in [`crate::geozero`].
+//! ```
+//! use spaten::flatgeobuf::{flatgeobuf_to_spaten, spaten_to_flatgeobuf};
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureIterator, FeatureWriter, Value, WriterOptions};
+//! use std::collections::HashMap;
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! for lanes in [Value::Integer(2), Value::Float(2.5)] {
+//!     let mut tags = HashMap::new();
+//!     tags.insert("lanes".to_string(), lanes);
+//!     w.accept(Feature {
+//!         geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!         tags,
+//!     })?;
+//! }
+//! w.finish()?;
+//! let spaten = w.into_inner();
+//!
+//! let mut fgb = Vec::new();
+//! spaten_to_flatgeobuf(&mut &spaten[..], &mut fgb, "roads")?;
+//! let mut back = Vec::new();
+//! flatgeobuf_to_spaten(&fgb[..], &mut back, WriterOptions::default())?;
+//! let fts: Vec<Feature> = FeatureIterator::try_new(&mut &back[..])?.collect::<Result<_, _>>()?;
+//! assert_eq!(fts[0].tags["lanes"], Value::Float(2.0));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::geojson::value_to_json;
+use crate::geozero::SpatenWriter;
+use crate::{DuplicateTags, Feature, FeatureIterator, ReaderOptions, Value, WriterOptions};
+use ::flatgeobuf::{ColumnType, FgbReader, FgbWriter, FgbWriterOptions, GeometryType};
+use ::geozero::{ColumnValue, PropertyProcessor};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+/// Number of features used to infer the column types.
+pub const INFER_FEATURES: usize = 1000;
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn column_type(v: &Value) -> ColumnType {
+    match v {
+        Value::String(_) => ColumnType::String,
+        Value::Integer(_) => ColumnType::Long,
+        Value::Float(_) => ColumnType::Double,
+        Value::Bytes(_) => ColumnType::Binary,
+        Value::List(_) => ColumnType::Json,
+    }
+}
+
+/// The type of a column holding values of both types.
+fn widen(a: ColumnType, b: ColumnType) -> ColumnType {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (ColumnType::Long, ColumnType::Double) | (ColumnType::Double, ColumnType::Long) => {
+            ColumnType::Double
+        }
+        _ => ColumnType::String,
+    }
+}
+
+/// A property value converted to its column type.
+enum Property {
+    Long(i64),
+    Double(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Json(String),
+}
+
+impl Property {
+    fn convert(v: &Value, ty: ColumnType) -> Option<Property> {
+        Some(match (v, ty) {
+            (Value::Integer(n), ColumnType::Long) => Property::Long(*n),
+            (Value::Integer(n), ColumnType::Double) => Property::Double(*n as f64),
+            (Value::Float(f), ColumnType::Double) => Property::Double(*f),
+            (Value::Bytes(b), ColumnType::Binary) => Property::Binary(b.clone()),
+            (v, ColumnType::Json) => Property::Json(value_to_json(v).to_string()),
+            (Value::String(s), ColumnType::String) => Property::String(s.clone()),
+            (Value::Integer(n), ColumnType::String) => Property::String(n.to_string()),
+            (Value::Float(f), ColumnType::String) => Property::String(f.to_string()),
+            (v, ColumnType::String) => match value_to_json(v) {
+                ::geojson::JsonValue::String(s) => Property::String(s),
+                json => Property::String(json.to_string()),
+            },
+            _ => return None,
+        })
+    }
+
+    fn column_value(&self) -> ColumnValue<'_> {
+        match self {
+            Property::Long(n) => ColumnValue::Long(*n),
+            Property::Double(f) => ColumnValue::Double(*f),
+            Property::String(s) => ColumnValue::String(s),
+            Property::Binary(b) => ColumnValue::Binary(b),
+            Property::Json(s) => ColumnValue::Json(s),
+        }
+    }
+}
+
+/// The declared columns, in the order of their indices.
+#[derive(Default)]
+struct Columns {
+    names: Vec<String>,
+    index: HashMap<String, (usize, ColumnType)>,
+}
+
+impl Columns {
+    fn declare(&mut self, fgb: &mut FgbWriter<'_>, name: &str, ty: ColumnType) {
+        fgb.add_column(name, ty, |_, _| {});
+        self.index.insert(name.to_string(), (self.names.len(), ty));
+        self.names.push(name.to_string());
+    }
+
+    fn add(&mut self, fgb: &mut FgbWriter<'_>, ft: Feature) -> io::Result<()> {
+        let mut keys: Vec<&String> = ft.tags.keys().collect();
+        keys.sort();
+        let mut props = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = &ft.tags[key];
+            if !self.index.contains_key(key) {
+                self.declare(fgb, key, column_type(value));
+            }
+            let (i, ty) = self.index[key];
+            if let Some(p) = Property::convert(value, ty) {
+                props.push((i, p));
+            }
+        }
+        props.sort_by_key(|(i, _)| *i);
+        let names = &self.names;
+        let mut result = Ok(());
+        fgb.add_feature_geom(ft.geometry, |w| {
+            for (i, p) in &props {
+                if let Err(e) = w.property(*i, &names[*i], &p.column_value()) {
+                    result = Err(invalid_data(e));
+                }
+            }
+        })
+        .map_err(invalid_data)?;
+        result
+    }
+}
+
+/// Reads the Spaten file `input` and writes its features as FlatGeobuf dataset `name` to
+/// `output`. Keys that occur several times in a feature are written as lists, see
+/// [`DuplicateTags::Collect`]. The FlatGeobuf writer keeps the features in a temporary file until the index is
+/// written.
+pub fn spaten_to_flatgeobuf<W: io::Write>(
+    input: &mut impl io::Read,
+    output: W,
+    name: &str,
+) -> io::Result<()> {
+    let opts = FgbWriterOptions {
+        detect_type: false,
+        promote_to_multi: false,
+        ..Default::default()
+    };
+    let mut fgb =
+        FgbWriter::create_with_options(name, GeometryType::Unknown, opts).map_err(invalid_data)?;
+    let opts = ReaderOptions {
+        duplicate_tags: DuplicateTags::Collect,
+        ..Default::default()
+    };
+    let mut fts = FeatureIterator::with_options(input, opts)?;
+
+    let mut head = Vec::new();
+    for ft in fts.by_ref().take(INFER_FEATURES) {
+        head.push(ft?);
+    }
+    let mut types: BTreeMap<&String, ColumnType> = BTreeMap::new();
+    for (k, v) in head.iter().flat_map(|ft| &ft.tags) {
+        let ty = column_type(v);
+        types
+            .entry(k)
+            .and_modify(|t| *t = widen(*t, ty))
+            .or_insert(ty);
+    }
+    let mut columns = Columns::default();
+    for (k, ty) in types {
+        columns.declare(&mut fgb, k, ty);
+    }
+
+    for ft in head {
+        columns.add(&mut fgb, ft)?;
+    }
+    for ft in fts {
+        columns.add(&mut fgb, ft?)?;
+    }
+    fgb.write(output).map_err(invalid_data)
+}
+
+/// Reads the FlatGeobuf file `input` and writes its features as Spaten file to `output`.
+pub fn flatgeobuf_to_spaten<R: io::Read, W: io::Write>(
+    input: R,
+    output: W,
+    options: WriterOptions,
+) -> io::Result<()> {
+    let mut w = SpatenWriter::with_options(output, options);
+    FgbReader::open(input)
+        .and_then(FgbReader::select_all_seq)
+        .map_err(invalid_data)?
+        .process_features(&mut w)
+        .map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flatgeobuf_to_spaten, spaten_to_flatgeobuf, Property};
+    use crate::sink::FeatureSink;
+    use crate::{
+        DuplicateTags, Feature, FeatureIterator, FeatureWriter, ReaderOptions, Value, WriterOptions,
+    };
+    use ::flatgeobuf::ColumnType;
+    use geo_types::{line_string, Geometry};
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trip() {
+        let feature = |geometry: Geometry<f64>, tags: &[(&str, Value)]| Feature {
+            geometry,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<HashMap<_, _>>(),
+        };
+        let fts = vec![
+            feature(
+                line_string![(x: 7.0, y: 51.0), (x: 7.5, y: 51.5)].into(),
+                &[
+                    ("name", Value::from("Rhein")),
+                    ("ref", Value::from(1)),
+                    ("raw", Value::Bytes(vec![0xff])),
+                ],
+            ),
+            feature(
+                geo_types::Point::new(1., 2.).into(),
+                &[
+                    ("ref", Value::from("A 1")),
+                    ("names", Value::List(vec![Value::from("a"), Value::from(1)])),
+                    ("raw", Value::from(3)),
+                ],
+            ),
+        ];
+        let mut w = FeatureWriter::new(Vec::new());
+        for ft in fts.clone() {
+            w.accept(ft).unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let mut fgb = Vec::new();
+        spaten_to_flatgeobuf(&mut &buf[..], &mut fgb, "test").unwrap();
+        let mut out = Vec::new();
+        flatgeobuf_to_spaten(&fgb[..], &mut out, WriterOptions::default()).unwrap();
+        let opts = ReaderOptions {
+            duplicate_tags: DuplicateTags::Collect,
+            ..Default::default()
+        };
+        let back: Vec<Feature> = FeatureIterator::with_options(&mut &out[..], opts)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(back.len(), 2);
+        // the index sorts the features
+        let line = back.iter().find(|ft| ft.tags["ref"] == Value::from("1"));
+        let point = back.iter().find(|ft| ft.tags["ref"] == Value::from("A 1"));
+        let (line, point) = (line.unwrap(), point.unwrap());
+        assert_eq!(line.geometry, fts[0].geometry);
+        assert_eq!(line.tags["name"], Value::from("Rhein"));
+        assert_eq!(line.tags["raw"], Value::from("ff"));
+        assert_eq!(point.geometry, fts[1].geometry);
+        assert_eq!(point.tags["names"], fts[1].tags["names"]);
+        assert_eq!(point.tags["raw"], Value::from("3"));
+        assert!(!point.tags.contains_key("name"));
+
+        assert!(Property::convert(&Value::from("2"), ColumnType::Long).is_none());
+        assert!(Property::convert(&Value::from(2.5), ColumnType::Long).is_none());
+    }
+}
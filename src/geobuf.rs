@@ -0,0 +1,486 @@
+//! Import and export of Mapbox Geobuf, a compact protobuf encoding of GeoJSON.
+//!
+//! Coordinates are stored as delta encoded integers with a fixed number of decimal digits
+//! (6 by default), coordinates beyond the second dimension are dropped on import. Property
+//! values are mapped like in [`crate::geojson`]; lists are stored as JSON values. Feature ids
+//! and custom properties are not preserved.
+
+use crate::geobufformat::{
+    Data, Data_Feature, Data_FeatureCollection, Data_Geometry, Data_Geometry_Type, Data_Value,
+    Data_Value_oneof_value_type, Data_oneof_data_type,
+};
+use crate::geojson::{value_from_json, value_to_json};
+use crate::sink::FeatureSink;
+use crate::{Feature, Value};
+use geo_types::{
+    Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon,
+    Point, Polygon,
+};
+use protobuf::Message;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::io::{Read, Write};
+
+/// Number of decimal digits that are kept if not specified otherwise.
+pub const DEFAULT_PRECISION: u32 = 6;
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Reads all features of a Geobuf message. A bare geometry is returned as a single feature
+/// without tags.
+/// ```
+/// use spaten::geobuf::{read, GeobufWriter};
+/// use spaten::sink::FeatureSink;
+/// use spaten::Feature;
+/// use std::collections::HashMap;
+///
+/// let mut w = GeobufWriter::new(Vec::new());
+/// w.accept(Feature {
+///     geometry: geo_types::Point::new(7.1, 50.7).into(),
+///     tags: HashMap::new(),
+/// })
+/// .unwrap();
+/// w.finish().unwrap();
+/// let fts = read(&mut &w.into_inner()[..]).unwrap();
+/// assert_eq!(fts[0].geometry, geo_types::Point::new(7.1, 50.7).into());
+/// ```
+pub fn read<R: Read>(r: &mut R) -> io::Result<Vec<Feature>> {
+    let data = Data::parse_from_reader(r).map_err(invalid_data)?;
+    let dims = data.get_dimensions() as usize;
+    if dims < 2 {
+        return Err(invalid_data(
+            "Geobuf coordinates need at least two dimensions",
+        ));
+    }
+    let d = Decoder {
+        dims,
+        e: 10f64.powi(data.get_precision() as i32),
+    };
+
+    let features = match &data.data_type {
+        Some(Data_oneof_data_type::feature_collection(fc)) => fc.get_features(),
+        Some(Data_oneof_data_type::feature(ft)) => std::slice::from_ref(ft),
+        Some(Data_oneof_data_type::geometry(g)) => {
+            return Ok(vec![Feature {
+                geometry: d.geometry(g).map_err(invalid_data)?,
+                tags: HashMap::new(),
+            }])
+        }
+        None => return Ok(Vec::new()),
+    };
+    features
+        .iter()
+        .map(|ft| d.feature(ft, data.get_keys()).map_err(invalid_data))
+        .collect()
+}
+
+struct Decoder {
+    dims: usize,
+    e: f64,
+}
+
+impl Decoder {
+    fn feature(&self, ft: &Data_Feature, keys: &[String]) -> Result<Feature, &'static str> {
+        let mut tags = HashMap::new();
+        for pair in ft.get_properties().chunks(2) {
+            let (k, v) = match pair {
+                [k, v] => (*k as usize, *v as usize),
+                _ => return Err("Odd number of Geobuf property indices"),
+            };
+            let key = keys.get(k).ok_or("Geobuf property key out of range")?;
+            let val = ft
+                .get_values()
+                .get(v)
+                .ok_or("Geobuf property value out of range")?;
+            if let Some(val) = decode_value(val)? {
+                tags.insert(key.clone(), val);
+            }
+        }
+        Ok(Feature {
+            geometry: self.geometry(ft.get_geometry())?,
+            tags,
+        })
+    }
+
+    fn geometry(&self, g: &Data_Geometry) -> Result<Geometry<f64>, &'static str> {
+        let coords = g.get_coords();
+        if !coords.len().is_multiple_of(self.dims) {
+            return Err("Geobuf coordinates do not match dimensions");
+        }
+        let mut lengths = g.get_lengths().iter().map(|&l| l as usize);
+        let mut rest = coords;
+        Ok(match g.get_field_type() {
+            Data_Geometry_Type::POINT => {
+                if coords.len() < 2 {
+                    return Err("Geobuf point without coordinates");
+                }
+                Point::new(coords[0] as f64 / self.e, coords[1] as f64 / self.e).into()
+            }
+            Data_Geometry_Type::MULTIPOINT => {
+                MultiPoint::from(self.line(coords, false).into_points()).into()
+            }
+            Data_Geometry_Type::LINESTRING => self.line(coords, false).into(),
+            Data_Geometry_Type::MULTILINESTRING => {
+                if g.get_lengths().is_empty() {
+                    MultiLineString::new(vec![self.line(coords, false)]).into()
+                } else {
+                    let lines = lengths
+                        .map(|n| Ok(self.line(self.take(&mut rest, n)?, false)))
+                        .collect::<Result<_, &'static str>>()?;
+                    MultiLineString::new(lines).into()
+                }
+            }
+            Data_Geometry_Type::POLYGON => {
+                if g.get_lengths().is_empty() {
+                    Polygon::new(self.line(coords, true), vec![]).into()
+                } else {
+                    self.polygon(&mut rest, &mut lengths, g.get_lengths().len())?
+                        .into()
+                }
+            }
+            Data_Geometry_Type::MULTIPOLYGON => {
+                if g.get_lengths().is_empty() {
+                    MultiPolygon::new(vec![Polygon::new(self.line(coords, true), vec![])]).into()
+                } else {
+                    let n = lengths.next().unwrap_or(0);
+                    let mut polygons = Vec::new();
+                    for _ in 0..n {
+                        let rings = lengths.next().ok_or("Truncated Geobuf lengths")?;
+                        polygons.push(self.polygon(&mut rest, &mut lengths, rings)?);
+                    }
+                    MultiPolygon::new(polygons).into()
+                }
+            }
+            Data_Geometry_Type::GEOMETRYCOLLECTION => {
+                Geometry::GeometryCollection(GeometryCollection(
+                    g.get_geometries()
+                        .iter()
+                        .map(|g| self.geometry(g))
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+        })
+    }
+
+    fn polygon(
+        &self,
+        rest: &mut &[i64],
+        lengths: &mut impl Iterator<Item = usize>,
+        rings: usize,
+    ) -> Result<Polygon<f64>, &'static str> {
+        let mut lines = Vec::new();
+        for _ in 0..rings {
+            let n = lengths.next().ok_or("Truncated Geobuf lengths")?;
+            lines.push(self.line(self.take(rest, n)?, true));
+        }
+        if lines.is_empty() {
+            return Err("Geobuf polygon without rings");
+        }
+        let exterior = lines.remove(0);
+        Ok(Polygon::new(exterior, lines))
+    }
+
+    /// Splits off the coordinates of `n` points.
+    fn take<'a>(&self, rest: &mut &'a [i64], n: usize) -> Result<&'a [i64], &'static str> {
+        let len = n
+            .checked_mul(self.dims)
+            .filter(|&len| len <= rest.len())
+            .ok_or("Geobuf lengths exceed coordinates")?;
+        let (part, tail) = rest.split_at(len);
+        *rest = tail;
+        Ok(part)
+    }
+
+    fn line(&self, coords: &[i64], closed: bool) -> LineString<f64> {
+        let mut prev = [0i64; 2];
+        let mut pts: Vec<Coord<f64>> = coords
+            .chunks(self.dims)
+            .map(|c| {
+                prev[0] = prev[0].wrapping_add(c[0]);
+                prev[1] = prev[1].wrapping_add(c[1]);
+                Coord {
+                    x: prev[0] as f64 / self.e,
+                    y: prev[1] as f64 / self.e,
+                }
+            })
+            .collect();
+        if closed {
+            if let Some(&first) = pts.first() {
+                pts.push(first);
+            }
+        }
+        LineString::new(pts)
+    }
+}
+
+fn decode_value(v: &Data_Value) -> Result<Option<Value>, &'static str> {
+    Ok(match &v.value_type {
+        Some(Data_Value_oneof_value_type::string_value(s)) => Some(Value::String(s.clone())),
+        Some(Data_Value_oneof_value_type::double_value(f)) => Some(Value::Float(*f)),
+        Some(Data_Value_oneof_value_type::pos_int_value(i)) => Some(match i64::try_from(*i) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::Float(*i as f64),
+        }),
+        Some(Data_Value_oneof_value_type::neg_int_value(i)) => Some(match i64::try_from(*i) {
+            Ok(i) => Value::Integer(-i),
+            Err(_) => Value::Float(-(*i as f64)),
+        }),
+        Some(Data_Value_oneof_value_type::bool_value(b)) => Some(Value::Integer(*b as i64)),
+        Some(Data_Value_oneof_value_type::json_value(s)) => {
+            let json = s.parse().map_err(|_| "Invalid Geobuf JSON value")?;
+            value_from_json(&json)
+        }
+        None => None,
+    })
+}
+
+fn encode_value(v: &Value) -> Data_Value {
+    let mut out = Data_Value::new();
+    match v {
+        Value::String(s) => out.set_string_value(s.clone()),
+        Value::Integer(i) if *i >= 0 => out.set_pos_int_value(*i as u64),
+        Value::Integer(i) => out.set_neg_int_value(i.unsigned_abs()),
+        Value::Float(f) => out.set_double_value(*f),
+        Value::Bytes(_) | Value::List(_) => out.set_json_value(value_to_json(v).to_string()),
+    }
+    out
+}
+
+/// Collects features and writes them as a single Geobuf FeatureCollection on `finish`, since
+/// the key table has to precede the features.
+pub struct GeobufWriter<W: Write> {
+    w: W,
+    precision: u32,
+    keys: HashMap<String, u32>,
+    data: Data,
+}
+
+impl<W: Write> GeobufWriter<W> {
+    pub fn new(w: W) -> Self {
+        Self::with_precision(w, DEFAULT_PRECISION)
+    }
+
+    /// Keeps `precision` decimal digits of every coordinate.
+    pub fn with_precision(w: W, precision: u32) -> Self {
+        let mut data = Data::new();
+        data.set_dimensions(2);
+        data.set_precision(precision);
+        data.set_feature_collection(Data_FeatureCollection::new());
+        GeobufWriter {
+            w,
+            precision,
+            keys: HashMap::new(),
+            data,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    fn key_index(&mut self, key: &str) -> u32 {
+        if let Some(&i) = self.keys.get(key) {
+            return i;
+        }
+        let i = self.data.get_keys().len() as u32;
+        self.data.mut_keys().push(key.to_string());
+        self.keys.insert(key.to_string(), i);
+        i
+    }
+}
+
+impl<W: Write> FeatureSink for GeobufWriter<W> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        let mut out = Data_Feature::new();
+        let geom = Encoder {
+            e: 10f64.powi(self.precision as i32),
+        }
+        .geometry(&ft.geometry);
+        out.set_geometry(geom);
+
+        let mut tags: Vec<(&String, &Value)> = ft.tags.iter().collect();
+        tags.sort();
+        for (k, v) in tags {
+            let k = self.key_index(k);
+            let i = out.get_values().len() as u32;
+            out.mut_properties().extend([k, i]);
+            out.mut_values().push(encode_value(v));
+        }
+        self.data.mut_feature_collection().mut_features().push(out);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.data
+            .write_to_writer(&mut self.w)
+            .map_err(invalid_data)?;
+        self.data.mut_feature_collection().mut_features().clear();
+        self.w.flush()
+    }
+}
+
+struct Encoder {
+    e: f64,
+}
+
+impl Encoder {
+    fn geometry(&self, g: &Geometry<f64>) -> Data_Geometry {
+        let mut out = Data_Geometry::new();
+        match g {
+            Geometry::Point(p) => {
+                out.set_field_type(Data_Geometry_Type::POINT);
+                out.mut_coords().push(self.scale(p.x()));
+                out.mut_coords().push(self.scale(p.y()));
+            }
+            Geometry::MultiPoint(mp) => {
+                out.set_field_type(Data_Geometry_Type::MULTIPOINT);
+                self.line(&mut out, mp.0.iter().map(|p| &p.0));
+            }
+            Geometry::Line(l) => {
+                out.set_field_type(Data_Geometry_Type::LINESTRING);
+                self.line(&mut out, [l.start, l.end].iter());
+            }
+            Geometry::LineString(ls) => {
+                out.set_field_type(Data_Geometry_Type::LINESTRING);
+                self.line(&mut out, ls.0.iter());
+            }
+            Geometry::MultiLineString(mls) => {
+                out.set_field_type(Data_Geometry_Type::MULTILINESTRING);
+                if mls.0.len() != 1 {
+                    for ls in &mls.0 {
+                        out.mut_lengths().push(ls.0.len() as u32);
+                    }
+                }
+                for ls in &mls.0 {
+                    self.line(&mut out, ls.0.iter());
+                }
+            }
+            Geometry::Polygon(p) => {
+                out.set_field_type(Data_Geometry_Type::POLYGON);
+                self.polygon(&mut out, p, p.interiors().is_empty());
+            }
+            Geometry::MultiPolygon(mp) => {
+                out.set_field_type(Data_Geometry_Type::MULTIPOLYGON);
+                let single = mp.0.len() == 1 && mp.0[0].interiors().is_empty();
+                if !single {
+                    out.mut_lengths().push(mp.0.len() as u32);
+                }
+                for p in &mp.0 {
+                    if !single {
+                        out.mut_lengths().push(1 + p.interiors().len() as u32);
+                    }
+                    self.polygon(&mut out, p, single);
+                }
+            }
+            Geometry::Rect(r) => return self.geometry(&r.to_polygon().into()),
+            Geometry::Triangle(t) => return self.geometry(&t.to_polygon().into()),
+            Geometry::GeometryCollection(gc) => {
+                out.set_field_type(Data_Geometry_Type::GEOMETRYCOLLECTION);
+                for g in gc {
+                    out.mut_geometries().push(self.geometry(g));
+                }
+            }
+        }
+        out
+    }
+
+    /// Writes the rings of a polygon without their closing coordinate. The ring lengths are
+    /// omitted if the polygon is the only part with a single ring.
+    fn polygon(&self, out: &mut Data_Geometry, p: &Polygon<f64>, single: bool) {
+        for ring in std::iter::once(p.exterior()).chain(p.interiors()) {
+            let n = ring.0.len().saturating_sub(1);
+            if !single {
+                out.mut_lengths().push(n as u32);
+            }
+            self.line(out, ring.0.iter().take(n));
+        }
+    }
+
+    fn line<'a>(&self, out: &mut Data_Geometry, coords: impl Iterator<Item = &'a Coord<f64>>) {
+        let mut sum = [0i64; 2];
+        for c in coords {
+            for (i, v) in [c.x, c.y].iter().enumerate() {
+                let d = self.scale(*v) - sum[i];
+                out.mut_coords().push(d);
+                sum[i] += d;
+            }
+        }
+    }
+
+    fn scale(&self, v: f64) -> i64 {
+        (v * self.e).round() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, GeobufWriter};
+    use crate::source::copy;
+    use crate::{Feature, Value};
+    use geo_types::{line_string, polygon, Geometry, MultiLineString, MultiPolygon};
+    use std::collections::HashMap;
+
+    fn round_trip(fts: Vec<Feature>) -> Vec<Feature> {
+        let mut w = GeobufWriter::new(Vec::new());
+        copy(&mut fts.into_iter(), &mut w).unwrap();
+        read(&mut &w.into_inner()[..]).unwrap()
+    }
+
+    #[test]
+    fn geometries() {
+        let outer = polygon![(x: 0., y: 0.), (x: 4., y: 0.), (x: 4., y: 4.), (x: 0., y: 0.)];
+        let holed = polygon!(
+            exterior: [(x: 0., y: 0.), (x: 10., y: 0.), (x: 10., y: 10.), (x: 0., y: 0.)],
+            interiors: [[(x: 6., y: 2.), (x: 8., y: 2.), (x: 8., y: 4.), (x: 6., y: 2.)]],
+        );
+        let geoms: Vec<Geometry<f64>> = vec![
+            line_string![(x: 7.123456, y: 50.1), (x: -1.5, y: 0.25)].into(),
+            outer.clone().into(),
+            holed.clone().into(),
+            MultiPolygon::new(vec![outer.clone()]).into(),
+            MultiPolygon::new(vec![outer, holed]).into(),
+            MultiLineString::new(vec![
+                line_string![(x: 1., y: 1.), (x: 2., y: 2.)],
+                line_string![(x: 3., y: 3.), (x: 4., y: 4.), (x: 5., y: 5.)],
+            ])
+            .into(),
+        ];
+        let fts = geoms
+            .iter()
+            .map(|g| Feature {
+                geometry: g.clone(),
+                tags: HashMap::new(),
+            })
+            .collect();
+        let back: Vec<Geometry<f64>> = round_trip(fts).into_iter().map(|ft| ft.geometry).collect();
+        assert_eq!(back, geoms);
+    }
+
+    #[test]
+    fn tags() {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("Main".to_string()));
+        tags.insert("level".to_string(), Value::Integer(-2));
+        tags.insert("lanes".to_string(), Value::Integer(2));
+        tags.insert("width".to_string(), Value::Float(7.5));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::String("A1".to_string()), Value::Integer(3)]),
+        );
+        let ft = Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags,
+        };
+        let back = round_trip(vec![ft.clone(), ft.clone()]);
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[1].tags, ft.tags);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(read(&mut &b"\x0a"[..]).is_err());
+    }
+}
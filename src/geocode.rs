@@ -0,0 +1,404 @@
+//! Geocoding over features held in memory, e.g. lookups of administrative boundaries.
+//!
+//! An [`Index`] keeps the features of a file together with an R-tree over their bounding boxes,
+//! so a lookup only tests the geometries whose bounding box matches. Name searches scan all
+//! features, which is fast enough for extracts of a few hundred thousand places.
+
+use crate::source::FeatureSource;
+use crate::{Feature, Value};
+use geo::{Area, BoundingRect, Centroid, Distance, Haversine, Intersects};
+use geo_types::{Geometry, Point};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+use std::cmp::Ordering;
+use std::io;
+
+type Entry = GeomWithData<Rectangle<[f64; 2]>, usize>;
+
+/// Features with a spatial index over their bounding boxes.
+pub struct Index {
+    features: Vec<Feature>,
+    tree: RTree<Entry>,
+}
+
+impl Index {
+    /// Builds the index. Features with an empty geometry are kept, but never found.
+    pub fn new(features: Vec<Feature>) -> Self {
+        let entries = features
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ft)| {
+                let r = ft.geometry.bounding_rect()?;
+                let rect = Rectangle::from_corners(r.min().x_y().into(), r.max().x_y().into());
+                Some(GeomWithData::new(rect, i))
+            })
+            .collect();
+        Index {
+            features,
+            tree: RTree::bulk_load(entries),
+        }
+    }
+
+    /// Reads all features of `src` into an index.
+    /// ```
+    /// use spaten::geocode::{reverse, Index};
+    /// use spaten::{Feature, FeatureIterator, FeatureWriter};
+    /// use spaten::sink::FeatureSink;
+    /// use geo_types::{polygon, Point};
+    ///
+    /// let mut w = FeatureWriter::new(Vec::new());
+    /// w.accept(Feature {
+    ///     geometry: polygon![(x: 0., y: 0.), (x: 4., y: 0.), (x: 4., y: 4.), (x: 0., y: 4.)].into(),
+    ///     tags: Default::default(),
+    /// })?;
+    /// w.finish()?;
+    /// let buf = w.into_inner();
+    ///
+    /// let index = Index::from_source(&mut FeatureIterator::try_new(&mut &buf[..])?)?;
+    /// assert_eq!(reverse(&index, Point::new(1., 1.)).len(), 1);
+    /// assert!(reverse(&index, Point::new(5., 1.)).is_empty());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_source<S: FeatureSource>(src: &mut S) -> io::Result<Self> {
+        let mut features = Vec::new();
+        while let Some(ft) = src.next_feature()? {
+            features.push(ft);
+        }
+        Ok(Self::new(features))
+    }
+
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+
+    /// Features whose bounding box intersects the given box, in no particular order.
+    pub(crate) fn candidates(
+        &self,
+        min: [f64; 2],
+        max: [f64; 2],
+    ) -> impl Iterator<Item = &Feature> {
+        self.tree
+            .locate_in_envelope_intersecting(&AABB::from_corners(min, max))
+            .map(move |e| &self.features[e.data])
+    }
+}
+
+/// Returns the polygonal features that contain `point`, smallest area first, so the most
+/// specific boundary comes first. Points on a boundary count as contained.
+pub fn reverse(index: &Index, point: Point<f64>) -> Vec<&Feature> {
+    let p = [point.x(), point.y()];
+    let mut found: Vec<(f64, &Feature)> = index
+        .candidates(p, p)
+        .filter(|ft| match &ft.geometry {
+            Geometry::Polygon(_)
+            | Geometry::MultiPolygon(_)
+            | Geometry::Rect(_)
+            | Geometry::Triangle(_) => ft.geometry.intersects(&point),
+            _ => false,
+        })
+        .map(|ft| (ft.geometry.unsigned_area(), ft))
+        .collect();
+    found.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    found.into_iter().map(|(_, ft)| ft).collect()
+}
+
+/// Configuration of [`search_with_options`].
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    /// Tags that hold names, e.g. `name` and `name:en`. A feature matches if any of them does.
+    pub name_tags: Vec<String>,
+    /// Tag that holds the feature class.
+    pub class_tag: String,
+    /// Classes from most to least important. Features of unlisted classes rank last.
+    pub classes: Vec<String>,
+    /// Among equally good matches, places closer to this point rank first.
+    pub near: Option<Point<f64>>,
+    /// Maximum number of results.
+    pub limit: usize,
+}
+
+impl Default for SearchOptions {
+    /// Searches `name` and ranks by the OpenStreetMap `place` classes.
+    fn default() -> Self {
+        SearchOptions {
+            name_tags: vec!["name".to_string()],
+            class_tag: "place".to_string(),
+            classes: [
+                "country", "state", "city", "town", "suburb", "village", "hamlet",
+            ]
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+            near: None,
+            limit: 10,
+        }
+    }
+}
+
+/// Finds features by name with [default options](SearchOptions::default).
+pub fn search<'a>(index: &'a Index, query: &str) -> Vec<&'a Feature> {
+    search_with_options(index, query, &SearchOptions::default())
+}
+
+/// Finds features whose name matches `query`. Names and query are compared case-insensitive and
+/// without diacritics and punctuation. Exact matches rank before names starting with the query,
+/// then names with a word starting with the query, then names within a small edit distance
+/// (one typo for queries of 4 to 7 characters, two for longer ones). Within each of these,
+/// results are ranked by class and then by distance to [`near`](SearchOptions::near).
+/// ```
+/// use spaten::geocode::{search_with_options, Index, SearchOptions};
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let place = |name: &str, class: &str, x: f64| {
+///     let mut tags = HashMap::new();
+///     tags.insert("name".to_string(), Value::String(name.to_string()));
+///     tags.insert("place".to_string(), Value::String(class.to_string()));
+///     Feature { geometry: geo_types::Point::new(x, 50.).into(), tags }
+/// };
+/// let index = Index::new(vec![
+///     place("Frankfurt (Oder)", "town", 14.5),
+///     place("Frankfurt am Main", "city", 8.7),
+/// ]);
+/// let found = search_with_options(&index, "frankfurt", &SearchOptions::default());
+/// assert_eq!(found[0].tags["name"], Value::String("Frankfurt am Main".to_string()));
+///
+/// let opts = SearchOptions { near: Some(geo_types::Point::new(14., 52.)), ..Default::default() };
+/// let found = search_with_options(&index, "Frankfrut", &opts);
+/// assert_eq!(found.len(), 2);
+/// ```
+pub fn search_with_options<'a>(
+    index: &'a Index,
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<&'a Feature> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let query: Vec<char> = query.chars().collect();
+
+    let mut found: Vec<((u8, usize, usize), f64, &Feature)> = index
+        .features()
+        .iter()
+        .filter_map(|ft| {
+            let rank = options
+                .name_tags
+                .iter()
+                .filter_map(|key| match ft.tags.get(key) {
+                    Some(Value::String(name)) => match_rank(&query, &normalize(name)),
+                    _ => None,
+                })
+                .min()?;
+            let class = match ft.tags.get(&options.class_tag) {
+                Some(Value::String(c)) => options.classes.iter().position(|x| x == c),
+                _ => None,
+            };
+            let distance = match (options.near, ft.geometry.centroid()) {
+                (Some(near), Some(c)) => Haversine.distance(near, c),
+                _ => 0.,
+            };
+            let class = class.unwrap_or(options.classes.len());
+            Some(((rank.0, rank.1, class), distance, ft))
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    });
+    found
+        .into_iter()
+        .take(options.limit)
+        .map(|(_, _, ft)| ft)
+        .collect()
+}
+
+/// Match tier and edit distance of `name` for `query`, or `None` if it does not match.
+fn match_rank(query: &[char], name: &str) -> Option<(u8, usize)> {
+    let name_chars: Vec<char> = name.chars().collect();
+    if name_chars == query {
+        return Some((0, 0));
+    }
+    if name_chars.starts_with(query) {
+        return Some((1, 0));
+    }
+    let words: Vec<Vec<char>> = name.split(' ').map(|w| w.chars().collect()).collect();
+    if words.iter().any(|w| w.starts_with(query)) {
+        return Some((2, 0));
+    }
+    let max_edits = match query.len() {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let edits = std::iter::once(&name_chars)
+        .chain(&words)
+        .map(|w| {
+            // a typo in the query should still match a longer name that starts with it
+            let prefix = &w[..w.len().min(query.len())];
+            levenshtein(query, w).min(levenshtein(query, prefix))
+        })
+        .min()?;
+    (edits <= max_edits).then_some((3, edits))
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = diag + (ca != cb) as usize;
+            diag = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(diag + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Lower case, common Latin diacritics removed and everything but letters and digits collapsed
+/// into single spaces.
+fn normalize(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        let folded = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => "a",
+            'æ' => "ae",
+            'ç' | 'ć' | 'č' => "c",
+            'ď' | 'đ' => "d",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+            'ł' | 'ľ' => "l",
+            'ñ' | 'ń' | 'ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+            'œ' => "oe",
+            'ř' => "r",
+            'ś' | 'š' | 'ş' => "s",
+            'ß' => "ss",
+            'ť' | 'ţ' => "t",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+            'ý' | 'ÿ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            c if c.is_alphanumeric() => {
+                out.push(c);
+                continue;
+            }
+            _ => " ",
+        };
+        if folded == " " && (out.is_empty() || out.ends_with(' ')) {
+            continue;
+        }
+        out.push_str(folded);
+    }
+    let len = out.trim_end().len();
+    out.truncate(len);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, reverse, search, search_with_options, Index, SearchOptions};
+    use crate::{Feature, Value};
+    use geo_types::{line_string, polygon, Geometry, Point};
+    use std::collections::HashMap;
+
+    fn feature(name: &str, geometry: Geometry<f64>) -> Feature {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String(name.to_string()));
+        Feature { geometry, tags }
+    }
+
+    #[test]
+    fn reverse_lookup() {
+        let index = Index::new(vec![
+            feature(
+                "state",
+                polygon![(x: 0., y: 0.), (x: 10., y: 0.), (x: 10., y: 10.), (x: 0., y: 10.)].into(),
+            ),
+            feature(
+                "city",
+                polygon![(x: 1., y: 1.), (x: 3., y: 1.), (x: 3., y: 3.), (x: 1., y: 3.)].into(),
+            ),
+            // bounding box contains the point, the triangle does not
+            feature(
+                "corner",
+                polygon![(x: 4., y: 4.), (x: 8., y: 4.), (x: 8., y: 8.)].into(),
+            ),
+            feature(
+                "road",
+                line_string![(x: 0., y: 0.), (x: 10., y: 10.)].into(),
+            ),
+        ]);
+        assert_eq!(index.features().len(), 4);
+
+        let names = |p: Point<f64>| -> Vec<String> {
+            reverse(&index, p)
+                .iter()
+                .map(|ft| format!("{:?}", ft.tags["name"]))
+                .collect()
+        };
+        assert_eq!(names(Point::new(2., 2.)), ["\"city\"", "\"state\""]);
+        assert_eq!(names(Point::new(5., 7.)), ["\"state\""]);
+        assert_eq!(names(Point::new(3., 2.)), ["\"city\"", "\"state\""]);
+        assert!(names(Point::new(11., 2.)).is_empty());
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(
+            normalize("  Sankt-Pölten, Österreich!"),
+            "sankt polten osterreich"
+        );
+        assert_eq!(normalize("Straße"), "strasse");
+        assert_eq!(normalize("--"), "");
+    }
+
+    #[test]
+    fn search_ranking() {
+        let place = |name: &str, class: &str, x: f64| {
+            let mut ft = feature(name, Point::new(x, 0.).into());
+            ft.tags
+                .insert("place".to_string(), Value::String(class.to_string()));
+            ft
+        };
+        let index = Index::new(vec![
+            place("Neustadt", "village", 0.),
+            place("Neustadt an der Weinstraße", "town", 1.),
+            place("Bad Neustadt", "town", 2.),
+            place("Neustadt", "town", 3.),
+            place("Neustadt", "town", 4.),
+            place("Neuss", "city", 5.),
+        ]);
+        let xs = |fts: Vec<&Feature>| -> Vec<f64> {
+            fts.iter()
+                .map(|ft| match &ft.geometry {
+                    Geometry::Point(p) => p.x(),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        // exact before prefix before word prefix, classes rank within a tier
+        assert_eq!(xs(search(&index, "NEUSTADT")), [3., 4., 0., 1., 2.]);
+        let opts = SearchOptions {
+            near: Some(Point::new(4.1, 0.)),
+            ..Default::default()
+        };
+        assert_eq!(
+            xs(search_with_options(&index, "neustadt", &opts)),
+            [4., 3., 0., 1., 2.]
+        );
+
+        // typos
+        assert_eq!(xs(search(&index, "Nuestadt")).len(), 5);
+        assert_eq!(xs(search(&index, "neus")), [5., 1., 3., 4., 0., 2.]);
+        assert!(search(&index, "nex").is_empty());
+        assert!(search(&index, " ").is_empty());
+
+        let opts = SearchOptions {
+            limit: 2,
+            ..Default::default()
+        };
+        assert_eq!(search_with_options(&index, "neustadt", &opts).len(), 2);
+    }
+}
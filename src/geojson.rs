@@ -0,0 +1,344 @@
+//! Import and export of GeoJSON (RFC 7946) and GeoJSON text sequences (RFC 8142).
+//!
+//! [`to_feature_collection`] and [`from_geojson`] convert between features and a single
+//! FeatureCollection document. The latter reads the document incrementally, so large extracts do
+//! not have to fit into memory.
+//!
+//! In a text sequence, every record starts with an ASCII record separator (0x1E) and ends with a
+//! line feed. Unlike newline delimited JSON, records may span several lines. Records that fail to
+//! parse and lack the trailing line feed are treated as truncated and skipped, as recommended by
+//! the RFC.
+//!
+//! Features with a `null` geometry, which RFC 7946 allows for unlocated features, are skipped
+//! when reading.
+//!
+//! Properties are mapped onto [`Value`]: integral numbers become integers, other numbers floats,
+//! booleans the integers 0 and 1, arrays lists and nested objects their JSON text. Null
+//! properties are omitted. When writing, strings become JSON strings, integers and floats
+//! numbers, bytes lowercase hex strings and lists arrays. Non-finite floats have no JSON
+//! representation and are written as `null`.
+
+use crate::sink::FeatureSink;
+use crate::source::FeatureSource;
+use crate::{Feature, Value};
+use ::geojson::{GeoJson, JsonObject, JsonValue};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io;
+use std::io::{BufRead, Read, Write};
+
+/// The record separator that starts every JSON text of a sequence.
+pub const RECORD_SEPARATOR: u8 = 0x1E;
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+pub(crate) fn value_from_json(v: &JsonValue) -> Option<Value> {
+    Some(match v {
+        JsonValue::Null => return None,
+        JsonValue::Bool(b) => Value::Integer(*b as i64),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64()?),
+        },
+        JsonValue::String(s) => Value::String(s.clone()),
+        JsonValue::Array(a) => Value::List(a.iter().filter_map(value_from_json).collect()),
+        JsonValue::Object(_) => Value::String(v.to_string()),
+    })
+}
+
+/// Non-finite floats are written as `null`.
+pub(crate) fn value_to_json(v: &Value) -> JsonValue {
+    match v {
+        Value::String(s) => JsonValue::from(s.as_str()),
+        Value::Integer(i) => JsonValue::from(*i),
+        Value::Float(f) => JsonValue::from(*f),
+        Value::Bytes(b) => JsonValue::from(crate::to_hex(b)),
+        Value::List(l) => JsonValue::Array(l.iter().map(value_to_json).collect()),
+    }
+}
+
+/// Converts `ft`, or returns `None` if it has no geometry.
+fn feature_from_geojson(ft: ::geojson::Feature) -> io::Result<Option<Feature>> {
+    let geometry = match ft.geometry {
+        Some(g) => geo_types::Geometry::try_from(g).map_err(invalid_data)?,
+        None => return Ok(None),
+    };
+    let tags = ft
+        .properties
+        .iter()
+        .flatten()
+        .filter_map(|(k, v)| Some((k.clone(), value_from_json(v)?)))
+        .collect();
+    Ok(Some(Feature { geometry, tags }))
+}
+
+fn feature_to_geojson(ft: &Feature) -> ::geojson::Feature {
+    let mut properties: JsonObject = ft
+        .tags
+        .iter()
+        .map(|(k, v)| (k.clone(), value_to_json(v)))
+        .collect();
+    properties.sort_keys();
+    ::geojson::Feature {
+        bbox: None,
+        geometry: Some(::geojson::Geometry::from(&ft.geometry)),
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    }
+}
+
+/// Collects features into a FeatureCollection, e.g. for serializing with `to_string`.
+/// ```
+/// use spaten::geojson::to_feature_collection;
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("lanes".to_string(), Value::Integer(2));
+/// let fc = to_feature_collection(vec![Feature {
+///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+///     tags,
+/// }]);
+/// assert_eq!(fc.features[0].property("lanes"), Some(&2.into()));
+/// ```
+pub fn to_feature_collection(
+    fts: impl IntoIterator<Item = Feature>,
+) -> ::geojson::FeatureCollection {
+    ::geojson::FeatureCollection {
+        bbox: None,
+        features: fts.into_iter().map(|ft| feature_to_geojson(&ft)).collect(),
+        foreign_members: None,
+    }
+}
+
+/// Reads the features of a FeatureCollection one by one. Features without a geometry are
+/// skipped.
+/// ```
+/// use spaten::geojson::from_geojson;
+/// use spaten::Value;
+///
+/// let data = r#"{"type": "FeatureCollection", "features": [
+///     {"type": "Feature", "geometry": {"type": "Point", "coordinates": [7, 51]},
+///      "properties": {"name": "Bonn", "population": 330000}}
+/// ]}"#;
+/// let fts = from_geojson(data.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(fts[0].tags["population"], Value::Integer(330000));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn from_geojson<R: Read>(r: R) -> impl Iterator<Item = io::Result<Feature>> {
+    ::geojson::FeatureReader::from_reader(r)
+        .features()
+        .filter_map(|ft| {
+            ft.map_err(invalid_data)
+                .and_then(feature_from_geojson)
+                .transpose()
+        })
+}
+
+/// Reads features from a GeoJSON text sequence. Records may hold a Feature, a bare Geometry
+/// (read as a feature without tags) or a FeatureCollection. Features without a geometry are
+/// skipped.
+/// ```
+/// use spaten::geojson::GeoJsonSeqReader;
+/// use spaten::Value;
+///
+/// let data = "\x1e{\"type\":\"Feature\",\"geometry\":{\"type\":\"Point\",\"coordinates\":[7,51]},\n\
+///             \"properties\":{\"name\":\"Bonn\"}}\n";
+/// let ft = GeoJsonSeqReader::new(data.as_bytes()).next().unwrap().unwrap();
+/// assert_eq!(ft.tags["name"], Value::String("Bonn".to_string()));
+/// ```
+pub struct GeoJsonSeqReader<R: BufRead> {
+    r: R,
+    buf: Vec<u8>,
+    queue: VecDeque<::geojson::Feature>,
+}
+
+impl<R: BufRead> GeoJsonSeqReader<R> {
+    pub fn new(r: R) -> Self {
+        GeoJsonSeqReader {
+            r,
+            buf: Vec::new(),
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: BufRead> FeatureSource for GeoJsonSeqReader<R> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        loop {
+            if let Some(ft) = self.queue.pop_front() {
+                match feature_from_geojson(ft)? {
+                    Some(ft) => return Ok(Some(ft)),
+                    None => continue,
+                }
+            }
+            self.buf.clear();
+            if self.r.read_until(RECORD_SEPARATOR, &mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            if self.buf.last() == Some(&RECORD_SEPARATOR) {
+                self.buf.pop();
+            }
+            let text = std::str::from_utf8(&self.buf).map_err(invalid_data)?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let truncated = !text.ends_with('\n');
+            let gj = match text.parse::<GeoJson>() {
+                Ok(gj) => gj,
+                Err(_) if truncated => continue,
+                Err(e) => return Err(invalid_data(e)),
+            };
+            match gj {
+                GeoJson::Feature(ft) => self.queue.push_back(ft),
+                GeoJson::Geometry(g) => self.queue.push_back(::geojson::Feature::from(g)),
+                GeoJson::FeatureCollection(fc) => self.queue.extend(fc.features),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for GeoJsonSeqReader<R> {
+    type Item = io::Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_feature().transpose()
+    }
+}
+
+/// Writes features as a GeoJSON text sequence, one Feature per record.
+pub struct GeoJsonSeqWriter<W: Write> {
+    w: W,
+}
+
+impl<W: Write> GeoJsonSeqWriter<W> {
+    pub fn new(w: W) -> Self {
+        GeoJsonSeqWriter { w }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+impl<W: Write> FeatureSink for GeoJsonSeqWriter<W> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        let gj = feature_to_geojson(&ft);
+        self.w.write_all(&[RECORD_SEPARATOR])?;
+        writeln!(self.w, "{}", gj)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_geojson, to_feature_collection, GeoJsonSeqReader, GeoJsonSeqWriter};
+    use crate::sink::FeatureSink;
+    use crate::source::copy;
+    use crate::{Feature, Value};
+    use geo_types::line_string;
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trip() {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("Main".to_string()));
+        tags.insert("lanes".to_string(), Value::Integer(2));
+        tags.insert("width".to_string(), Value::Float(7.5));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::String("A1".to_string()), Value::Integer(3)]),
+        );
+        let ft = Feature {
+            geometry: line_string![(x: 1., y: 2.), (x: 3., y: 4.)].into(),
+            tags,
+        };
+
+        let mut w = GeoJsonSeqWriter::new(Vec::new());
+        copy(&mut vec![ft.clone(), ft.clone()].into_iter(), &mut w).unwrap();
+        let buf = w.into_inner();
+        assert_eq!(buf[0], 0x1E);
+        assert_eq!(buf.iter().filter(|&&b| b == 0x1E).count(), 2);
+        assert_eq!(buf.last(), Some(&b'\n'));
+
+        let fts: Vec<Feature> = GeoJsonSeqReader::new(&buf[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts.len(), 2);
+        assert_eq!(fts[1].geometry, ft.geometry);
+        assert_eq!(fts[1].tags, ft.tags);
+    }
+
+    #[test]
+    fn truncated_and_multiline() {
+        let data = "\x1e{\"type\":\"Point\",\n\"coordinates\":[1,2]}\n\
+                    \x1e{\"type\":\"Feature\",\"geom\
+                    \x1e{\"type\":\"Feature\",\"geometry\":{\"type\":\"Point\",\"coordinates\":[3,4]},\
+                    \"properties\":{\"open\":true,\"note\":null,\"x\":{\"a\":1}}}\n";
+        let fts: Vec<Feature> = GeoJsonSeqReader::new(data.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts.len(), 2);
+        assert!(fts[0].tags.is_empty());
+        assert_eq!(fts[1].tags["open"], Value::Integer(1));
+        assert!(!fts[1].tags.contains_key("note"));
+        assert_eq!(fts[1].tags["x"], Value::String("{\"a\":1}".to_string()));
+
+        // a complete but invalid record is an error
+        let mut r = GeoJsonSeqReader::new("\x1e{\"type\":\"Nope\"}\n".as_bytes());
+        assert!(r.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn feature_collection() {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("Main".to_string()));
+        tags.insert("width".to_string(), Value::Float(7.5));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::String("A1".to_string()), Value::Integer(3)]),
+        );
+        let ft = Feature {
+            geometry: line_string![(x: 1., y: 2.), (x: 3., y: 4.)].into(),
+            tags,
+        };
+        let text = to_feature_collection(vec![ft.clone(), ft.clone()]).to_string();
+        let fts: Vec<Feature> = from_geojson(text.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts.len(), 2);
+        assert_eq!(fts[0].geometry, ft.geometry);
+        assert_eq!(fts[0].tags, ft.tags);
+
+        let data = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": null, "properties": {}},
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [7, 51]},
+             "properties": {}}
+        ]}"#;
+        let fts: Vec<Feature> = from_geojson(data.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts.len(), 1);
+        let mut r = GeoJsonSeqReader::new(
+            "\x1e{\"type\":\"Feature\",\"geometry\":null,\"properties\":{}}\n".as_bytes(),
+        );
+        assert!(r.next().is_none());
+        assert!(from_geojson("{\"type\": \"Nope\"".as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn empty_sink() {
+        let mut w = GeoJsonSeqWriter::new(Vec::new());
+        w.finish().unwrap();
+        assert!(w.into_inner().is_empty());
+    }
+}
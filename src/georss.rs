@@ -0,0 +1,244 @@
+//! Export of features as a GeoRSS Atom feed.
+//!
+//! Every feature becomes an Atom entry with a GeoRSS Simple geometry. Points, LineStrings and
+//! Polygons (outer ring only) are encoded directly, all other geometry types as their
+//! bounding box. Tags are written as `spaten:tag` extension elements.
+
+use crate::sink::FeatureSink;
+use crate::{Feature, Value};
+use geo_types::{Coord, Geometry};
+use std::io;
+use std::io::Write;
+
+/// Namespace of the tag extension elements.
+pub const TAG_NAMESPACE: &str = "https://github.com/thomersch/rust-spaten";
+
+/// Feed level metadata.
+#[derive(Clone, Debug)]
+pub struct FeedOptions {
+    /// Unique identifier (IRI) of the feed. Entry ids are derived from it.
+    pub id: String,
+    pub title: String,
+    /// Name of the person or organization that publishes the feed, which Atom requires.
+    pub author: String,
+    /// RFC 3339 timestamp used for the feed and all entries.
+    pub updated: String,
+    /// Tag whose value is used as entry title, e.g. `name`.
+    pub title_key: Option<String>,
+}
+
+/// Writes features as GeoRSS Atom feed.
+/// ```
+/// use spaten::georss::{FeedOptions, GeoRssWriter};
+/// use spaten::sink::FeatureSink;
+/// use spaten::Feature;
+/// use std::collections::HashMap;
+///
+/// let opts = FeedOptions {
+///     id: "urn:example:alerts".to_string(),
+///     title: "Alerts".to_string(),
+///     author: "Example".to_string(),
+///     updated: "2021-06-01T12:00:00Z".to_string(),
+///     title_key: None,
+/// };
+/// let mut w = GeoRssWriter::new(Vec::new(), opts);
+/// w.accept(Feature {
+///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+///     tags: HashMap::new(),
+/// })
+/// .unwrap();
+/// w.finish().unwrap();
+/// let xml = String::from_utf8(w.into_inner()).unwrap();
+/// assert!(xml.contains("<georss:point>51 7</georss:point>"));
+/// ```
+pub struct GeoRssWriter<W: Write> {
+    w: W,
+    opts: FeedOptions,
+    entries: u64,
+    header_written: bool,
+}
+
+impl<W: Write> GeoRssWriter<W> {
+    pub fn new(w: W, opts: FeedOptions) -> Self {
+        GeoRssWriter {
+            w,
+            opts,
+            entries: 0,
+            header_written: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if self.header_written {
+            return Ok(());
+        }
+        self.header_written = true;
+        writeln!(self.w, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(
+            self.w,
+            r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:georss="http://www.georss.org/georss" xmlns:spaten="{}">"#,
+            TAG_NAMESPACE
+        )?;
+        writeln!(self.w, "  <id>{}</id>", escape(&self.opts.id))?;
+        writeln!(self.w, "  <title>{}</title>", escape(&self.opts.title))?;
+        writeln!(
+            self.w,
+            "  <author><name>{}</name></author>",
+            escape(&self.opts.author)
+        )?;
+        writeln!(
+            self.w,
+            "  <updated>{}</updated>",
+            escape(&self.opts.updated)
+        )
+    }
+}
+
+impl<W: Write> FeatureSink for GeoRssWriter<W> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        self.write_header()?;
+        self.entries += 1;
+
+        let title = match self.opts.title_key.as_ref().and_then(|k| ft.tags.get(k)) {
+            Some(v) => value_text(v),
+            None => format!("Feature {}", self.entries),
+        };
+        writeln!(self.w, "  <entry>")?;
+        writeln!(
+            self.w,
+            "    <id>{}/{}</id>",
+            escape(&self.opts.id),
+            self.entries
+        )?;
+        writeln!(self.w, "    <title>{}</title>", escape(&title))?;
+        writeln!(
+            self.w,
+            "    <updated>{}</updated>",
+            escape(&self.opts.updated)
+        )?;
+        if let Some((elem, coords)) = georss_geometry(&ft.geometry) {
+            writeln!(self.w, "    <georss:{0}>{1}</georss:{0}>", elem, coords)?;
+        }
+
+        let mut tags: Vec<(&String, &Value)> = ft.tags.iter().collect();
+        tags.sort();
+        for (k, v) in tags {
+            writeln!(
+                self.w,
+                r#"    <spaten:tag key="{}">{}</spaten:tag>"#,
+                escape(k),
+                escape(&value_text(v))
+            )?;
+        }
+        writeln!(self.w, "  </entry>")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        writeln!(self.w, "</feed>")?;
+        self.w.flush()
+    }
+}
+
+fn value_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// GeoRSS uses latitude first.
+fn coord_list<'a>(coords: impl Iterator<Item = &'a Coord<f64>>) -> String {
+    coords
+        .map(|c| format!("{} {}", c.y, c.x))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn georss_geometry(g: &Geometry<f64>) -> Option<(&'static str, String)> {
+    Some(match g {
+        Geometry::Point(p) => ("point", coord_list(std::iter::once(&p.0))),
+        Geometry::LineString(ls) => ("line", coord_list(ls.0.iter())),
+        Geometry::Polygon(p) => ("polygon", coord_list(p.exterior().0.iter())),
+        g => {
+            let r = crate::bounding_rect(g)?;
+            ("box", coord_list([r.min(), r.max()].iter()))
+        }
+    })
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            // not allowed in XML 1.0, not even as character references
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => out.push('\u{FFFD}'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeedOptions, GeoRssWriter};
+    use crate::sink::FeatureSink;
+    use crate::{Feature, Value};
+    use geo_types::{line_string, polygon, MultiPoint};
+    use std::collections::HashMap;
+
+    #[test]
+    fn feed() {
+        let opts = FeedOptions {
+            id: "urn:example:test".to_string(),
+            title: "Test & more".to_string(),
+            author: "Tester".to_string(),
+            updated: "2021-06-01T12:00:00Z".to_string(),
+            title_key: Some("name".to_string()),
+        };
+        let mut w = GeoRssWriter::new(Vec::new(), opts);
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("<Main>".to_string()));
+        tags.insert("lanes".to_string(), Value::Integer(2));
+        tags.insert("note".to_string(), Value::from("a\u{1}b\tc"));
+        w.accept(Feature {
+            geometry: line_string![(x: 1., y: 2.), (x: 3., y: 4.)].into(),
+            tags,
+        })
+        .unwrap();
+        w.accept(Feature {
+            geometry: polygon![(x: 0., y: 0.), (x: 1., y: 0.), (x: 1., y: 1.)].into(),
+            tags: HashMap::new(),
+        })
+        .unwrap();
+        w.accept(Feature {
+            geometry: MultiPoint::from(vec![(0., 0.), (2., 1.)]).into(),
+            tags: HashMap::new(),
+        })
+        .unwrap();
+        w.finish().unwrap();
+
+        let xml = String::from_utf8(w.into_inner()).unwrap();
+        assert!(xml.contains("<title>Test &amp; more</title>"));
+        assert!(xml.contains("<title>&lt;Main&gt;</title>"));
+        assert!(xml.contains("<id>urn:example:test/1</id>"));
+        assert!(xml.contains("<georss:line>2 1 4 3</georss:line>"));
+        assert!(xml.contains("<georss:polygon>0 0 0 1 1 1 0 0</georss:polygon>"));
+        assert!(xml.contains("<georss:box>0 0 1 2</georss:box>"));
+        assert!(xml.contains("<author><name>Tester</name></author>"));
+        assert!(xml.contains(r#"<spaten:tag key="lanes">2</spaten:tag>"#));
+        assert!(xml.contains("<spaten:tag key=\"note\">a\u{FFFD}b\tc</spaten:tag>"));
+        assert!(xml.trim_end().ends_with("</feed>"));
+        assert_eq!(xml.matches("<entry>").count(), 3);
+    }
+}
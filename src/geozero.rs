@@ -0,0 +1,307 @@
+//! Interoperability with the geozero ecosystem (requires the `geozero` feature).
+//!
+//! Spaten readers implement [`GeozeroDatasource`], so features can be converted into any geozero
+//! output, such as GeoJSON, FlatGeobuf, PostGIS or MVT. In the other direction,
+//! [`SpatenWriter`] is a [`FeatureProcessor`] that writes everything a geozero reader emits into
+//! a Spaten file.
+//!
+//! Tags are passed on as `Long`, `Double`, `String` and `Binary` columns, lists as JSON.
+//! Incoming integer and boolean columns become integers, floats become floats, binary columns
+//! bytes and JSON columns are mapped like GeoJSON properties, see [`crate::geojson`]. All other
+//! columns are kept as strings.
+
+use crate::geojson::{value_from_json, value_to_json};
+use crate::sink::FeatureSink;
+use crate::source::FeatureSource;
+use crate::{Feature, FeatureIterator, FeatureWriter, Value, WriterOptions};
+use ::geojson::JsonValue;
+use ::geozero::error::{GeozeroError, Result};
+use ::geozero::geo_types::GeoWriter;
+use ::geozero::{
+    ColumnValue, FeatureProcessor, GeomProcessor, GeozeroDatasource, GeozeroGeometry,
+    PropertyProcessor,
+};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+
+/// Makes any [`FeatureSource`] a geozero datasource.
+/// ```
+/// use geozero::geo_types::GeoWriter;
+/// use geozero::GeozeroDatasource;
+/// use spaten::geozero::Source;
+/// use spaten::Feature;
+/// use std::collections::HashMap;
+///
+/// let fts = vec![Feature {
+///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+///     tags: HashMap::new(),
+/// }];
+/// let mut geo = GeoWriter::new();
+/// Source(fts.into_iter()).process_geom(&mut geo)?;
+/// assert_eq!(geo.take_geometry(), Some(geo_types::Point::new(7.0, 51.0).into()));
+/// # Ok::<(), geozero::error::GeozeroError>(())
+/// ```
+#[derive(Debug)]
+pub struct Source<S>(pub S);
+
+impl<S: FeatureSource> GeozeroDatasource for Source<S> {
+    fn process<P: FeatureProcessor>(&mut self, processor: &mut P) -> Result<()> {
+        process(&mut self.0, processor)
+    }
+}
+
+impl GeozeroDatasource for FeatureIterator<'_> {
+    fn process<P: FeatureProcessor>(&mut self, processor: &mut P) -> Result<()> {
+        process(self, processor)
+    }
+}
+
+fn process<S, P>(src: &mut S, processor: &mut P) -> Result<()>
+where
+    S: FeatureSource + ?Sized,
+    P: FeatureProcessor,
+{
+    processor.dataset_begin(None)?;
+    let mut idx = 0;
+    while let Some(ft) = src.next_feature()? {
+        processor.feature_begin(idx)?;
+        processor.properties_begin()?;
+        let mut keys: Vec<&String> = ft.tags.keys().collect();
+        keys.sort();
+        for (i, key) in keys.into_iter().enumerate() {
+            let json;
+            let value = match &ft.tags[key] {
+                Value::String(s) => ColumnValue::String(s),
+                Value::Integer(n) => ColumnValue::Long(*n),
+                Value::Float(f) => ColumnValue::Double(*f),
+                Value::Bytes(b) => ColumnValue::Binary(b),
+                v @ Value::List(_) => {
+                    json = value_to_json(v).to_string();
+                    ColumnValue::Json(&json)
+                }
+            };
+            if processor.property(i, key, &value)? {
+                break;
+            }
+        }
+        processor.properties_end()?;
+        processor.geometry_begin()?;
+        ft.geometry.process_geom(processor)?;
+        processor.geometry_end()?;
+        processor.feature_end(idx)?;
+        idx += 1;
+    }
+    processor.dataset_end()
+}
+
+fn value_from_column(v: &ColumnValue) -> Value {
+    match *v {
+        ColumnValue::Byte(n) => Value::Integer(n.into()),
+        ColumnValue::UByte(n) => Value::Integer(n.into()),
+        ColumnValue::Bool(b) => Value::Integer(b.into()),
+        ColumnValue::Short(n) => Value::Integer(n.into()),
+        ColumnValue::UShort(n) => Value::Integer(n.into()),
+        ColumnValue::Int(n) => Value::Integer(n.into()),
+        ColumnValue::UInt(n) => Value::Integer(n.into()),
+        ColumnValue::Long(n) => Value::Integer(n),
+        ColumnValue::ULong(n) => match i64::try_from(n) {
+            Ok(n) => Value::Integer(n),
+            Err(_) => Value::Float(n as f64),
+        },
+        ColumnValue::Float(f) => Value::Float(f.into()),
+        ColumnValue::Double(f) => Value::Float(f),
+        ColumnValue::Binary(b) => Value::Bytes(b.to_vec()),
+        ColumnValue::Json(s) => s
+            .parse::<JsonValue>()
+            .ok()
+            .and_then(|j| value_from_json(&j))
+            .unwrap_or_else(|| Value::String(s.to_string())),
+        _ => Value::String(v.to_string()),
+    }
+}
+
+/// Writes the features emitted by a geozero datasource into a Spaten file. The file is
+/// finished at the end of the dataset.
+/// ```
+/// use geozero::GeozeroDatasource;
+/// use spaten::geozero::{Source, SpatenWriter};
+/// use spaten::{Feature, FeatureIterator, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("name".to_string(), Value::String("Bonn".to_string()));
+/// let fts = vec![Feature {
+///     geometry: geo_types::Point::new(7.1, 50.7).into(),
+///     tags,
+/// }];
+/// let mut w = SpatenWriter::new(Vec::new());
+/// Source(fts.into_iter()).process(&mut w)?;
+/// let buf = w.into_inner();
+/// let back: Vec<Feature> = FeatureIterator::try_new(&mut &buf[..])?.collect::<Result<_, _>>()?;
+/// assert_eq!(back[0].tags["name"], Value::String("Bonn".to_string()));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct SpatenWriter<W: io::Write> {
+    w: FeatureWriter<W>,
+    geom: GeoWriter,
+    tags: HashMap<String, Value>,
+}
+
+impl<W: io::Write> SpatenWriter<W> {
+    pub fn new(w: W) -> Self {
+        Self::with_options(w, WriterOptions::default())
+    }
+
+    pub fn with_options(w: W, options: WriterOptions) -> Self {
+        SpatenWriter {
+            w: FeatureWriter::with_options(w, options),
+            geom: GeoWriter::new(),
+            tags: HashMap::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w.into_inner()
+    }
+}
+
+impl<W: io::Write> FeatureProcessor for SpatenWriter<W> {
+    fn dataset_end(&mut self) -> Result<()> {
+        Ok(self.w.finish()?)
+    }
+
+    fn feature_begin(&mut self, _idx: u64) -> Result<()> {
+        self.tags.clear();
+        Ok(())
+    }
+
+    fn feature_end(&mut self, _idx: u64) -> Result<()> {
+        let geometry = self
+            .geom
+            .take_geometry()
+            .ok_or_else(|| GeozeroError::FeatureGeometry("missing geometry".to_string()))?;
+        let tags = std::mem::take(&mut self.tags);
+        Ok(self.w.accept(Feature { geometry, tags })?)
+    }
+}
+
+impl<W: io::Write> PropertyProcessor for SpatenWriter<W> {
+    fn property(&mut self, _idx: usize, name: &str, value: &ColumnValue) -> Result<bool> {
+        self.tags.insert(name.to_string(), value_from_column(value));
+        Ok(false)
+    }
+}
+
+impl<W: io::Write> GeomProcessor for SpatenWriter<W> {
+    fn xy(&mut self, x: f64, y: f64, idx: usize) -> Result<()> {
+        self.geom.xy(x, y, idx)
+    }
+    fn point_begin(&mut self, idx: usize) -> Result<()> {
+        self.geom.point_begin(idx)
+    }
+    fn point_end(&mut self, idx: usize) -> Result<()> {
+        self.geom.point_end(idx)
+    }
+    fn multipoint_begin(&mut self, size: usize, idx: usize) -> Result<()> {
+        self.geom.multipoint_begin(size, idx)
+    }
+    fn multipoint_end(&mut self, idx: usize) -> Result<()> {
+        self.geom.multipoint_end(idx)
+    }
+    fn linestring_begin(&mut self, tagged: bool, size: usize, idx: usize) -> Result<()> {
+        self.geom.linestring_begin(tagged, size, idx)
+    }
+    fn linestring_end(&mut self, tagged: bool, idx: usize) -> Result<()> {
+        self.geom.linestring_end(tagged, idx)
+    }
+    fn multilinestring_begin(&mut self, size: usize, idx: usize) -> Result<()> {
+        self.geom.multilinestring_begin(size, idx)
+    }
+    fn multilinestring_end(&mut self, idx: usize) -> Result<()> {
+        self.geom.multilinestring_end(idx)
+    }
+    fn polygon_begin(&mut self, tagged: bool, size: usize, idx: usize) -> Result<()> {
+        self.geom.polygon_begin(tagged, size, idx)
+    }
+    fn polygon_end(&mut self, tagged: bool, idx: usize) -> Result<()> {
+        self.geom.polygon_end(tagged, idx)
+    }
+    fn multipolygon_begin(&mut self, size: usize, idx: usize) -> Result<()> {
+        self.geom.multipolygon_begin(size, idx)
+    }
+    fn multipolygon_end(&mut self, idx: usize) -> Result<()> {
+        self.geom.multipolygon_end(idx)
+    }
+    fn geometrycollection_begin(&mut self, size: usize, idx: usize) -> Result<()> {
+        self.geom.geometrycollection_begin(size, idx)
+    }
+    fn geometrycollection_end(&mut self, idx: usize) -> Result<()> {
+        self.geom.geometrycollection_end(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Source, SpatenWriter};
+    use crate::sink::FeatureSink;
+    use crate::{DuplicateTags, Feature, FeatureIterator, FeatureWriter, ReaderOptions, Value};
+    use geo_types::{line_string, polygon, Geometry};
+    use geozero::GeozeroDatasource;
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trip() {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("Rhein".to_string()));
+        tags.insert("lanes".to_string(), Value::Integer(2));
+        tags.insert("width".to_string(), Value::Float(1.5));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::Integer(1), Value::String("a".to_string())]),
+        );
+        let fts = vec![
+            Feature {
+                geometry: line_string![(x: 7.0, y: 51.0), (x: 7.5, y: 51.5)].into(),
+                tags,
+            },
+            Feature {
+                geometry: Geometry::MultiPolygon(
+                    vec![polygon![(x: 0., y: 0.), (x: 1., y: 0.), (x: 1., y: 1.)]].into(),
+                ),
+                tags: HashMap::new(),
+            },
+        ];
+        let mut w = FeatureWriter::new(Vec::new());
+        for ft in fts.clone() {
+            w.accept(ft).unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+        let opts = ReaderOptions {
+            duplicate_tags: DuplicateTags::Collect,
+            ..Default::default()
+        };
+
+        let mut out = SpatenWriter::new(Vec::new());
+        FeatureIterator::with_options(&mut &buf[..], opts.clone())
+            .unwrap()
+            .process(&mut out)
+            .unwrap();
+        let out = out.into_inner();
+        let back: Vec<Feature> = FeatureIterator::with_options(&mut &out[..], opts)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(back.len(), 2);
+        for (a, b) in fts.iter().zip(&back) {
+            assert_eq!(a.geometry, b.geometry);
+            assert_eq!(a.tags, b.tags);
+        }
+
+        let mut w = SpatenWriter::new(Vec::new());
+        let empty: Vec<Feature> = Vec::new();
+        Source(empty.into_iter()).process(&mut w).unwrap();
+        assert_eq!(w.into_inner(), b"SPAT\0\0\0\0\0\0\0\0");
+    }
+}
@@ -0,0 +1,549 @@
+//! Files sorted along a Hilbert curve, and bounding box queries on them.
+//!
+//! [`write_sorted`] orders features by the Hilbert index of their bounding box center and
+//! stores the index range of every block in the block's meta tags, together with the margin:
+//! how far the bounding box of the largest feature reaches beyond its center. Since
+//! neighbouring places end up in neighbouring blocks, [`query`] can locate the blocks of a
+//! region, widened by the margin, by binary search and only has to read a handful of them,
+//! instead of scanning the whole file. A few large features widen the margin for all queries.
+//!
+//! [`write_sorted`] sorts in memory. With the `spill` feature, [`write_sorted_spilled`] sorts
+//! inputs of any size with temporary files.
+//!
+//! Coordinates are expected to be longitude/latitude, everything outside is clamped to it.
+
+use crate::source::FeatureSource;
+#[cfg(feature = "spill")]
+use crate::spill::SpillOptions;
+use crate::{
+    bounding_rect, check_block_header, decode_features, fileformat, may_intersect, open_block,
+    read_full, read_raw_block, try_read_file_header, Error, Feature, FeatureWriter, ReaderOptions,
+    Value, WriterOptions,
+};
+use geo_types::{Coord, Rect};
+use protobuf::Message;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Number of bits per axis, so indices fit into 32 bits.
+const ORDER: u32 = 16;
+const SIDE: u32 = 1 << ORDER;
+/// Depth up to which query boxes are refined into index ranges. Deeper cells that are only
+/// partially covered are read as a whole.
+const MAX_QUERY_DEPTH: u32 = 10;
+
+const ORDER_KEY: &str = "spaten:order";
+const MIN_KEY: &str = "spaten:hilbert_min";
+const MAX_KEY: &str = "spaten:hilbert_max";
+const MARGIN_X_KEY: &str = "spaten:hilbert_margin_x";
+const MARGIN_Y_KEY: &str = "spaten:hilbert_margin_y";
+
+fn cell(c: Coord<f64>) -> (u32, u32) {
+    let scale = |v: f64, min: f64, range: f64| {
+        (((v - min) / range) * f64::from(SIDE)).clamp(0., f64::from(SIDE - 1)) as u32
+    };
+    (scale(c.x, -180., 360.), scale(c.y, -90., 180.))
+}
+
+fn xy2d(mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0;
+    let mut s = SIDE / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        d += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = SIDE - 1 - x;
+                y = SIDE - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+/// The Hilbert index of a coordinate.
+pub fn index(c: Coord<f64>) -> u64 {
+    let (x, y) = cell(c);
+    xy2d(x, y)
+}
+
+fn center(ft: &fileformat::Feature) -> Coord<f64> {
+    Coord {
+        x: (ft.left + ft.right) / 2.,
+        y: (ft.bottom + ft.top) / 2.,
+    }
+}
+
+pub(crate) fn feature_index(ft: &Feature) -> u64 {
+    index(crate::bounding_rect(&ft.geometry).map_or(Coord::zero(), |r| r.center()))
+}
+
+/// Sorts features by the Hilbert index of their bounding box center. The sort is stable.
+pub fn sort(fts: impl IntoIterator<Item = Feature>) -> Vec<Feature> {
+    let mut fts: Vec<(u64, Feature)> = fts.into_iter().map(|ft| (feature_index(&ft), ft)).collect();
+    fts.sort_by_key(|(d, _)| *d);
+    fts.into_iter().map(|(_, ft)| ft).collect()
+}
+
+/// Widens `margin` to the distance of the bounding box edges of `ft` from its center.
+fn add_margin(margin: &mut (f64, f64), ft: &Feature) {
+    if let Some(r) = bounding_rect(&ft.geometry) {
+        margin.0 = margin.0.max(r.width() / 2.);
+        margin.1 = margin.1.max(r.height() / 2.);
+    }
+}
+
+/// Passes the features of a source through and records their margin.
+#[cfg(feature = "spill")]
+struct Margin<'a, S: ?Sized> {
+    src: &'a mut S,
+    margin: (f64, f64),
+}
+
+#[cfg(feature = "spill")]
+impl<S: FeatureSource + ?Sized> FeatureSource for Margin<'_, S> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        let ft = self.src.next_feature()?;
+        if let Some(ft) = &ft {
+            add_margin(&mut self.margin, ft);
+        }
+        Ok(ft)
+    }
+}
+
+fn write_with_margin<W: io::Write>(
+    sorted: &mut (impl FeatureSource + ?Sized),
+    margin: (f64, f64),
+    w: W,
+    options: WriterOptions,
+) -> io::Result<W> {
+    let mut w = FeatureWriter::with_options(w, options);
+    w.hilbert = Some(margin);
+    crate::source::copy(sorted, &mut w)?;
+    Ok(w.into_inner())
+}
+
+/// Writes a complete file with the features in Hilbert order, as required by [`query`]. All
+/// features are kept in memory for sorting.
+pub fn write_sorted<W: io::Write>(
+    fts: impl IntoIterator<Item = Feature>,
+    w: W,
+    options: WriterOptions,
+) -> io::Result<W> {
+    let mut margin = (0., 0.);
+    let sorted = sort(fts.into_iter().inspect(|ft| add_margin(&mut margin, ft)));
+    write_with_margin(&mut sorted.into_iter(), margin, w, options)
+}
+
+/// Like [`write_sorted`], but sorts with temporary files as set up by `spill`, so that the input
+/// does not need to fit into memory. Needs the `spill` feature.
+#[cfg(feature = "spill")]
+pub fn write_sorted_spilled<W: io::Write>(
+    src: &mut (impl FeatureSource + ?Sized),
+    w: W,
+    options: WriterOptions,
+    spill: &SpillOptions,
+) -> io::Result<W> {
+    let mut src = Margin {
+        src,
+        margin: (0., 0.),
+    };
+    let mut sorted = crate::spill::sort_hilbert(&mut src, spill)?;
+    write_with_margin(&mut sorted, src.margin, w, options)
+}
+
+pub(crate) fn block_meta(body: &fileformat::Body, margin: (f64, f64)) -> fileformat::Meta {
+    let indices = body.feature.iter().map(|ft| index(center(ft)));
+    let (min, max) = indices.fold((u64::MAX, 0), |(min, max), d| (min.min(d), max.max(d)));
+    let mut meta = fileformat::Meta::new();
+    crate::encode_tag(
+        &mut meta.tags,
+        ORDER_KEY,
+        &Value::String("hilbert".to_string()),
+    );
+    crate::encode_tag(&mut meta.tags, MIN_KEY, &Value::Integer(min as i64));
+    crate::encode_tag(&mut meta.tags, MAX_KEY, &Value::Integer(max as i64));
+    crate::encode_tag(&mut meta.tags, MARGIN_X_KEY, &Value::Float(margin.0));
+    crate::encode_tag(&mut meta.tags, MARGIN_Y_KEY, &Value::Float(margin.1));
+    meta
+}
+
+/// The index range of a block and the margin of the file, if the block is marked as Hilbert
+/// ordered.
+fn block_range(body: &fileformat::Body) -> Option<((u64, u64), (f64, f64))> {
+    let mut tags: HashMap<&str, Value> = HashMap::new();
+    for tag in body.meta.as_ref()?.tags.iter() {
+        tags.insert(
+            &tag.key,
+            Value::from_bytes(tag.value.clone(), tag.field_type).ok()?,
+        );
+    }
+    match (
+        &tags.get(ORDER_KEY)?,
+        &tags.get(MIN_KEY)?,
+        &tags.get(MAX_KEY)?,
+        &tags.get(MARGIN_X_KEY)?,
+        &tags.get(MARGIN_Y_KEY)?,
+    ) {
+        (
+            Value::String(o),
+            Value::Integer(min),
+            Value::Integer(max),
+            Value::Float(dx),
+            Value::Float(dy),
+        ) if o == "hilbert" => Some(((*min as u64, *max as u64), (*dx, *dy))),
+        _ => None,
+    }
+}
+
+/// Splits the cells covering `bbox` into contiguous, sorted index ranges.
+fn ranges(bbox: Rect<f64>) -> Vec<(u64, u64)> {
+    fn visit(x0: u32, y0: u32, depth: u32, q: ((u32, u32), (u32, u32)), out: &mut Vec<(u64, u64)>) {
+        let size = SIDE >> depth;
+        let ((qx0, qy0), (qx1, qy1)) = q;
+        let (x1, y1) = (x0 + (size - 1), y0 + (size - 1));
+        if x1 < qx0 || x0 > qx1 || y1 < qy0 || y0 > qy1 {
+            return;
+        }
+        let inside = x0 >= qx0 && x1 <= qx1 && y0 >= qy0 && y1 <= qy1;
+        if inside || depth == MAX_QUERY_DEPTH {
+            let cells = u64::from(size) * u64::from(size);
+            let start = xy2d(x0, y0) & !(cells - 1);
+            out.push((start, start + cells - 1));
+            return;
+        }
+        let half = size / 2;
+        for (dx, dy) in [(0, 0), (half, 0), (0, half), (half, half)] {
+            visit(x0 + dx, y0 + dy, depth + 1, q, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    visit(0, 0, 0, (cell(bbox.min()), cell(bbox.max())), &mut out);
+    out.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(out.len());
+    for (a, b) in out {
+        match merged.last_mut() {
+            Some(last) if last.1 + 1 >= a => last.1 = last.1.max(b),
+            _ => merged.push((a, b)),
+        }
+    }
+    merged
+}
+
+/// Block offsets of a file and the index ranges of the blocks read so far.
+struct Blocks<'a, R> {
+    r: &'a mut R,
+    options: &'a ReaderOptions,
+    offsets: Vec<u64>,
+    ranges: HashMap<usize, Option<(u64, u64)>>,
+    /// Found in every Hilbert ordered block.
+    margin: (f64, f64),
+}
+
+impl<R: Read + Seek> Blocks<'_, R> {
+    fn read(&mut self, i: usize) -> Result<fileformat::Body, Error> {
+        self.r.seek(SeekFrom::Start(self.offsets[i]))?;
+        let max_len = self.options.limits.max_block_size;
+        let (header, body) = read_raw_block(self.r, max_len)?.ok_or(Error::Truncated)?;
+        let body = open_block(header, body, self.options.key.as_ref(), max_len)?;
+        Ok(fileformat::Body::parse_from_bytes(&body)?)
+    }
+
+    fn range(&mut self, i: usize) -> Result<Option<(u64, u64)>, Error> {
+        if let Some(r) = self.ranges.get(&i) {
+            return Ok(*r);
+        }
+        let body = self.read(i)?;
+        // blocks without features, like the last block of encrypted files, sort last
+        let r = match body.feature.is_empty() {
+            true => Some((u64::MAX, u64::MAX)),
+            false => block_range(&body).map(|(r, margin)| {
+                self.margin = margin;
+                r
+            }),
+        };
+        self.ranges.insert(i, r);
+        Ok(r)
+    }
+
+    /// The first block whose range ends at or after `d`.
+    fn search(&mut self, d: u64) -> Result<usize, Error> {
+        let (mut lo, mut hi) = (0, self.offsets.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.range(mid)? {
+                Some((_, max)) if max < d => lo = mid + 1,
+                Some(_) => hi = mid,
+                None => return Err(Error::UnsupportedBlock("Block is not Hilbert ordered")),
+            }
+        }
+        Ok(lo)
+    }
+}
+
+/// Walks the block headers without reading the bodies.
+fn block_offsets<R: Read + Seek>(r: &mut R) -> Result<Vec<u64>, Error> {
+    let mut offsets = Vec::new();
+    loop {
+        let offset = r.stream_position()?;
+        let mut len = [0; 4];
+        match read_full(r, &mut len)? {
+            0 => return Ok(offsets),
+            4 => {}
+            _ => return Err(Error::Truncated),
+        }
+        let len = u32::from_le_bytes(len);
+        if len == 0 {
+            return Ok(offsets);
+        }
+        let mut header = [0; 4];
+        r.read_exact(&mut header)?;
+        if !check_block_header(header)?.meta {
+            offsets.push(offset);
+        }
+        r.seek(SeekFrom::Current(i64::from(len)))?;
+    }
+}
+
+/// Returns the features whose bounding box intersects `bbox`, in file order.
+///
+/// On files written by [`write_sorted`], only the blocks that may hold such features are read,
+/// located by binary search over the block index ranges. Other files are scanned completely.
+/// ```
+/// use spaten::hilbert::{query, write_sorted};
+/// use spaten::{Feature, WriterOptions};
+/// use geo_types::{Point, Rect};
+/// use std::io::Cursor;
+///
+/// let fts = (0..100).map(|i| Feature {
+///     geometry: Point::new(f64::from(i % 10), f64::from(i / 10)).into(),
+///     tags: Default::default(),
+/// });
+/// let opts = WriterOptions { block_size: 8, ..Default::default() };
+/// let mut file = Cursor::new(write_sorted(fts, Vec::new(), opts)?);
+/// let found = query(&mut file, Rect::new((1.5, 1.5), (3.5, 2.5)))?;
+/// assert_eq!(found.len(), 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn query<R: Read + Seek>(r: &mut R, bbox: Rect<f64>) -> Result<Vec<Feature>, Error> {
+    query_with_options(r, bbox, &ReaderOptions::default())
+}
+
+/// Like [`query`], but decodes the blocks with `options`.
+pub fn query_with_options<R: Read + Seek>(
+    r: &mut R,
+    bbox: Rect<f64>,
+    options: &ReaderOptions,
+) -> Result<Vec<Feature>, Error> {
+    r.seek(SeekFrom::Start(0))?;
+    try_read_file_header(r)?;
+    let offsets = block_offsets(r)?;
+    let mut blocks = Blocks {
+        r,
+        options,
+        offsets,
+        ranges: HashMap::new(),
+        margin: (0., 0.),
+    };
+    let n = blocks.offsets.len();
+
+    let mut candidates = BTreeSet::new();
+    if n > 0 && blocks.range(0)?.is_some() {
+        let (dx, dy) = blocks.margin;
+        let min = Coord {
+            x: bbox.min().x - dx,
+            y: bbox.min().y - dy,
+        };
+        let max = Coord {
+            x: bbox.max().x + dx,
+            y: bbox.max().y + dy,
+        };
+        for (a, b) in ranges(Rect::new(min, max)) {
+            let mut i = blocks.search(a)?;
+            while i < n {
+                match blocks.range(i)? {
+                    Some((min, _)) if min <= b => {
+                        candidates.insert(i);
+                        i += 1;
+                    }
+                    _ => break,
+                }
+            }
+        }
+    } else {
+        candidates.extend(0..n);
+    }
+
+    let mut out = Vec::new();
+    for i in candidates {
+        let mut body = blocks.read(i)?;
+        let fts = body
+            .take_feature()
+            .into_iter()
+            .filter(|ft| may_intersect(ft, &bbox))
+            .collect();
+        // features without a stored bounding box are checked after decoding
+        out.extend(
+            decode_features(fts, options, None)?
+                .into_iter()
+                .filter(|ft| bounding_rect(&ft.geometry).is_some_and(|r| intersects(r, bbox))),
+        );
+    }
+    Ok(out)
+}
+
+fn intersects(a: Rect<f64>, b: Rect<f64>) -> bool {
+    a.min().x <= b.max().x
+        && a.max().x >= b.min().x
+        && a.min().y <= b.max().y
+        && a.max().y >= b.min().y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index, query, query_with_options, ranges, write_sorted, xy2d};
+    use crate::{Error, Feature, FeatureWriter, Limits, ReaderOptions, WriterOptions};
+    use geo_types::{line_string, Coord, Geometry, Point, Rect};
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    /// Counts the bytes read through it.
+    struct Counting<R> {
+        r: R,
+        read: usize,
+    }
+
+    impl<R: Read> Read for Counting<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.r.read(buf)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    impl<R: Seek> Seek for Counting<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.r.seek(pos)
+        }
+    }
+
+    #[test]
+    fn curve() {
+        let d = |x: f64, y: f64| index(Coord { x, y });
+        assert_eq!(d(-180., -90.), 0);
+        assert_eq!(d(180., 90.) >> 30, 2);
+        let a = d(0.001, 0.001);
+
+        // every aligned square of cells is a contiguous part of the curve
+        let mut square: Vec<u64> = (0..16).map(|i| xy2d(8 + i % 4, 4 + i / 4)).collect();
+        square.sort_unstable();
+        assert_eq!(square, (square[0]..square[0] + 16).collect::<Vec<_>>());
+        assert!(square[0].is_multiple_of(16));
+
+        let r = ranges(Rect::new((-180., -90.), (180., 90.)));
+        assert_eq!(r, [(0, u64::from(u32::MAX))]);
+        let r = ranges(Rect::new((0.001, 0.001), (0.002, 0.002)));
+        assert!(r.iter().any(|&(lo, hi)| lo <= a && a <= hi));
+    }
+
+    #[test]
+    fn sorted_query() {
+        let mut fts: Vec<Feature> = (0..2000)
+            .map(|i| Feature {
+                geometry: Point::new(f64::from(i % 50) * 0.1, f64::from(i / 50) * 0.1).into(),
+                tags: Default::default(),
+            })
+            .collect();
+        // reaches into the query box, but its center does not
+        let line = Feature {
+            geometry: line_string![(x: 0.9, y: 2.1), (x: 1.1, y: 2.1)].into(),
+            tags: Default::default(),
+        };
+        fts.insert(700, line.clone());
+        let opts = WriterOptions {
+            block_size: 16,
+            ..Default::default()
+        };
+        let buf = write_sorted(fts.clone(), Vec::new(), opts.clone()).unwrap();
+        let bbox = Rect::new((1.05, 2.05), (1.55, 2.25));
+        let expected: Vec<&Feature> = fts
+            .iter()
+            .filter(|ft| match &ft.geometry {
+                Geometry::Point(p) => {
+                    p.x() >= 1.05 && p.x() <= 1.55 && p.y() >= 2.05 && p.y() <= 2.25
+                }
+                _ => false,
+            })
+            .chain(Some(&line))
+            .collect();
+        assert_eq!(expected.len(), 11);
+
+        let mut file = Counting {
+            r: Cursor::new(&buf),
+            read: 0,
+        };
+        let found = query(&mut file, bbox).unwrap();
+        assert_eq!(found.len(), expected.len());
+        assert!(found
+            .iter()
+            .all(|ft| expected.iter().any(|e| e.geometry == ft.geometry)));
+        assert!(file.read < buf.len() / 5, "{} of {}", file.read, buf.len());
+
+        // unsorted files are scanned
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        crate::source::copy(&mut fts.clone().into_iter(), &mut w).unwrap();
+        let buf = w.into_inner();
+        let found = query(&mut Cursor::new(&buf), bbox).unwrap();
+        assert_eq!(found.len(), expected.len());
+
+        #[cfg(feature = "encryption")]
+        {
+            let key = crate::encryption::Key::new([1; 32]);
+            let opts = WriterOptions {
+                block_size: 16,
+                key: Some(key.clone()),
+                ..Default::default()
+            };
+            let encrypted = write_sorted(fts.clone(), Vec::new(), opts).unwrap();
+            let opts = ReaderOptions {
+                key: Some(key),
+                ..Default::default()
+            };
+            let found = query_with_options(&mut Cursor::new(&encrypted), bbox, &opts).unwrap();
+            assert_eq!(found.len(), expected.len());
+        }
+
+        #[cfg(feature = "spill")]
+        {
+            let spill = crate::spill::SpillOptions {
+                memory_budget: 2000,
+                ..Default::default()
+            };
+            let opts = WriterOptions {
+                block_size: 16,
+                ..Default::default()
+            };
+            let spilled =
+                super::write_sorted_spilled(&mut fts.clone().into_iter(), Vec::new(), opts, &spill)
+                    .unwrap();
+            let found = query(&mut Cursor::new(&spilled), bbox).unwrap();
+            assert_eq!(found.len(), expected.len());
+        }
+
+        let opts = ReaderOptions {
+            limits: Limits {
+                max_block_size: 100,
+                ..Limits::unlimited()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            query_with_options(&mut Cursor::new(&buf), bbox, &opts),
+            Err(Error::LimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,425 @@
+//! Size statistics of geometries and tag keys, with hints to shrink files.
+//!
+//! [`analyze`] encodes features like the writer does and measures how many bytes the tags of each
+//! key take up and how many distinct values they have. The [`Report`] lists the keys by size,
+//! with a hint for keys that can be made cheaper, e.g. unique ids or long key names.
+//!
+//! For the geometries, the report counts the vertices and how many decimals the coordinates
+//! actually use. Coordinates are stored as doubles regardless, but those with fewer decimals
+//! compress better, so [`Report::geometry_hint`] suggests rounding if the coordinates are more
+//! precise than 1 cm, or [simplification](crate::transform::simplify) if the geometries have
+//! many vertices and make up most of the file.
+//!
+//! The statistics also suggest an order for the tags of each feature,
+//! [`WriterOptions::tag_order`](crate::WriterOptions::tag_order): keys with few distinct values
+//! first, so that the repetitive part of every feature is at its start and compresses well with
+//! gzip, and high cardinality keys clustered at the end.
+//! ```
+//! use spaten::hints::analyze;
+//! use spaten::{Feature, Value, WriterOptions};
+//! use std::collections::HashMap;
+//!
+//! let fts: Vec<Feature> = (0..100)
+//!     .map(|i| {
+//!         let mut tags = HashMap::new();
+//!         tags.insert("id".to_string(), Value::Integer(i));
+//!         tags.insert("highway".to_string(), Value::from("residential"));
+//!         Feature {
+//!             geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!             tags,
+//!         }
+//!     })
+//!     .collect();
+//! let report = analyze(&mut fts.into_iter())?;
+//! assert_eq!(report.keys()[0].key, "highway");
+//! assert!(report.keys()[1].is_unique());
+//! assert_eq!(report.vertices(), 100);
+//! assert_eq!(report.precision(), 0);
+//! println!("{}", report);
+//!
+//! let opts = WriterOptions {
+//!     tag_order: Some(report.tag_order()),
+//!     gzip_level: Some(6),
+//!     ..Default::default()
+//! };
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::source::FeatureSource;
+use protobuf::rt::compute_raw_varint32_size;
+use protobuf::Message;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::io;
+
+/// Distinct values are counted up to this number per key, to bound the memory use.
+pub const MAX_DISTINCT: usize = 10_000;
+
+/// Coordinates with this many decimals or more are counted together.
+pub const MAX_DECIMALS: usize = 16;
+
+/// The statistics of one key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyStats {
+    pub key: String,
+    /// Number of features with the key.
+    pub features: u64,
+    /// Encoded size of the tags, including the key names.
+    pub bytes: u64,
+    /// Encoded size of the key names alone.
+    pub key_bytes: u64,
+    /// Number of distinct values, at most [`MAX_DISTINCT`]. A list counts as one value.
+    pub distinct: usize,
+}
+
+impl KeyStats {
+    /// Whether nearly every feature has its own value, like ids or timestamps.
+    pub fn is_unique(&self) -> bool {
+        self.features >= 10 && self.distinct as u64 * 10 >= self.features * 9
+    }
+
+    /// Suggests how to make the key cheaper.
+    pub fn hint(&self) -> Option<&'static str> {
+        if self.is_unique() {
+            Some("almost every value is unique and compresses poorly; drop it if it is not needed")
+        } else if self.key_bytes * 2 >= self.bytes {
+            Some("the key name takes up most of the space; a shorter key helps")
+        } else if self.distinct <= 16 && self.features >= 100 {
+            Some("only a few distinct values, which gzip compresses well")
+        } else {
+            None
+        }
+    }
+}
+
+/// The sizes of the features of a source, see [`analyze`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    features: u64,
+    geometry_bytes: u64,
+    tag_bytes: u64,
+    vertices: u64,
+    max_vertices: u64,
+    decimals: [u64; MAX_DECIMALS + 1],
+    keys: Vec<KeyStats>,
+}
+
+impl Report {
+    pub fn features(&self) -> u64 {
+        self.features
+    }
+
+    /// Encoded size of all geometries.
+    pub fn geometry_bytes(&self) -> u64 {
+        self.geometry_bytes
+    }
+
+    /// Encoded size of all tags.
+    pub fn tag_bytes(&self) -> u64 {
+        self.tag_bytes
+    }
+
+    /// Number of vertices of all geometries.
+    pub fn vertices(&self) -> u64 {
+        self.vertices
+    }
+
+    /// Number of vertices of the largest geometry.
+    pub fn max_vertices(&self) -> u64 {
+        self.max_vertices
+    }
+
+    /// Number of coordinate values, x and y counted separately, by the number of decimals of
+    /// their shortest representation. The last entry counts [`MAX_DECIMALS`] or more.
+    pub fn decimals(&self) -> &[u64] {
+        &self.decimals
+    }
+
+    /// The number of decimals that 99% of the coordinate values do not exceed.
+    pub fn precision(&self) -> usize {
+        let total: u64 = self.decimals.iter().sum();
+        let mut seen = 0;
+        for (i, n) in self.decimals.iter().enumerate() {
+            seen += n;
+            if seen * 100 >= total * 99 {
+                return i;
+            }
+        }
+        0
+    }
+
+    /// Suggests how to make the geometries cheaper.
+    pub fn geometry_hint(&self) -> Option<&'static str> {
+        let per_feature = self.vertices / self.features.max(1);
+        if self.precision() > 7 {
+            Some("coordinates are more precise than 1 cm (7 decimals); rounding them compresses better")
+        } else if per_feature >= 100 && self.geometry_bytes >= self.tag_bytes * 4 {
+            Some("the geometries have many vertices and make up most of the file; simplification shrinks them")
+        } else {
+            None
+        }
+    }
+
+    /// The keys, largest first.
+    pub fn keys(&self) -> &[KeyStats] {
+        &self.keys
+    }
+
+    /// The keys ordered by the number of distinct values, fewest first, for
+    /// [`WriterOptions::tag_order`](crate::WriterOptions::tag_order).
+    pub fn tag_order(&self) -> Vec<String> {
+        let mut keys: Vec<&KeyStats> = self.keys.iter().collect();
+        keys.sort_by(|a, b| a.distinct.cmp(&b.distinct).then(a.key.cmp(&b.key)));
+        keys.into_iter().map(|k| k.key.clone()).collect()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = (self.geometry_bytes + self.tag_bytes).max(1) as f64;
+        writeln!(
+            f,
+            "{} features: {} bytes of geometries ({:.1}%), {} bytes of tags ({:.1}%)",
+            self.features,
+            self.geometry_bytes,
+            self.geometry_bytes as f64 * 100. / total,
+            self.tag_bytes,
+            self.tag_bytes as f64 * 100. / total,
+        )?;
+        writeln!(
+            f,
+            "{} vertices, {:.1} per feature, at most {}",
+            self.vertices,
+            self.vertices as f64 / self.features.max(1) as f64,
+            self.max_vertices
+        )?;
+        let coords: u64 = self.decimals.iter().sum();
+        if coords > 0 {
+            write!(f, "coordinate decimals:")?;
+            let used = self.decimals.iter().enumerate().filter(|(_, n)| **n > 0);
+            for (i, (decimals, n)) in used.enumerate() {
+                let sep = if i == 0 { " " } else { ", " };
+                let more = if decimals == MAX_DECIMALS { "+" } else { "" };
+                let share = *n as f64 * 100. / coords as f64;
+                write!(f, "{}{}{}: {:.1}%", sep, decimals, more, share)?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(hint) = self.geometry_hint() {
+            writeln!(f, "geometries: {}", hint)?;
+        }
+        for k in &self.keys {
+            let share = k.bytes as f64 * 100. / self.tag_bytes.max(1) as f64;
+            let more = if k.distinct >= MAX_DISTINCT { "+" } else { "" };
+            write!(
+                f,
+                "{}: {} bytes ({:.1}%), {}{} distinct values in {} features",
+                k.key, k.bytes, share, k.distinct, more, k.features
+            )?;
+            match k.hint() {
+                Some(hint) => writeln!(f, "; {}", hint)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The number of decimals of the shortest representation of `v` that reads back as `v`, at most
+/// [`MAX_DECIMALS`]. `buf` is reused for formatting.
+fn decimals(v: f64, buf: &mut String) -> usize {
+    if !v.is_finite() {
+        return MAX_DECIMALS;
+    }
+    buf.clear();
+    let _ = write!(buf, "{}", v);
+    match buf.find('.') {
+        Some(i) => (buf.len() - i - 1).min(MAX_DECIMALS),
+        None => 0,
+    }
+}
+
+/// Size of a length delimited field of `len` bytes.
+fn field_size(len: u32) -> u64 {
+    1 + compute_raw_varint32_size(len) as u64 + len as u64
+}
+
+#[derive(Default)]
+struct Counter {
+    stats: Option<KeyStats>,
+    values: HashSet<Vec<u8>>,
+}
+
+/// Reads all features of `src` and measures their encoded sizes per key.
+pub fn analyze<S: FeatureSource>(src: &mut S) -> io::Result<Report> {
+    let mut report = Report::default();
+    let mut counters: HashMap<String, Counter> = HashMap::new();
+    let mut buf = String::new();
+    while let Some(ft) = src.next_feature()? {
+        let encoded = crate::encode_feature(&ft, None)?;
+        report.features += 1;
+        report.geometry_bytes += field_size(encoded.geom.len() as u32);
+        let vertices = crate::coords_count(&ft.geometry) as u64;
+        report.vertices += vertices;
+        report.max_vertices = report.max_vertices.max(vertices);
+        crate::for_each_coord(&ft.geometry, &mut |c| {
+            report.decimals[decimals(c.x, &mut buf)] += 1;
+            report.decimals[decimals(c.y, &mut buf)] += 1;
+        });
+
+        // the tags of a list are consecutive, as the keys are sorted
+        let mut tags = encoded.tags.iter().peekable();
+        while let Some(first) = tags.next() {
+            let key = first.key.as_str();
+            let mut value = Vec::new();
+            let (mut bytes, mut key_bytes) = (0, 0);
+            let mut tag = Some(first);
+            while let Some(t) = tag {
+                bytes += field_size(t.compute_size());
+                key_bytes += field_size(t.key.len() as u32);
+                value.push(t.field_type as u8);
+                value.extend(&t.value);
+                tag = tags.next_if(|next| next.key == key);
+            }
+            report.tag_bytes += bytes;
+
+            let c = counters.entry(key.to_string()).or_default();
+            let stats = c.stats.get_or_insert_with(|| KeyStats {
+                key: key.to_string(),
+                features: 0,
+                bytes: 0,
+                key_bytes: 0,
+                distinct: 0,
+            });
+            stats.features += 1;
+            stats.bytes += bytes;
+            stats.key_bytes += key_bytes;
+            if c.values.len() < MAX_DISTINCT {
+                c.values.insert(value);
+                stats.distinct = c.values.len();
+            }
+        }
+    }
+    report.keys = counters.into_values().filter_map(|c| c.stats).collect();
+    report
+        .keys
+        .sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.key.cmp(&b.key)));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze, MAX_DECIMALS};
+    use crate::sink::FeatureSink;
+    use crate::{Feature, FeatureIterator, FeatureWriter, Value, WriterOptions};
+    use std::collections::HashMap;
+
+    fn features() -> Vec<Feature> {
+        (0..200)
+            .map(|i| {
+                let mut tags = HashMap::new();
+                tags.insert("a_id".to_string(), Value::Integer(i));
+                tags.insert("kind".to_string(), Value::from(["a", "b"][i as usize % 2]));
+                tags.insert(
+                    "names".to_string(),
+                    Value::List(vec![Value::from("x"), Value::from(i % 3)]),
+                );
+                tags.insert("a_very_long_key_name".to_string(), Value::from(1));
+                Feature {
+                    geometry: geo_types::Point::new(1., 2.).into(),
+                    tags,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn report() {
+        let report = analyze(&mut features().into_iter()).unwrap();
+        assert_eq!(report.features(), 200);
+        assert_eq!(report.geometry_bytes(), 200 * 23);
+        let keys: Vec<(&str, usize)> = report
+            .keys()
+            .iter()
+            .map(|k| (k.key.as_str(), k.distinct))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("a_very_long_key_name", 1),
+                ("names", 3),
+                ("a_id", 200),
+                ("kind", 2),
+            ]
+        );
+        assert_eq!(
+            report.keys().iter().map(|k| k.bytes).sum::<u64>(),
+            report.tag_bytes()
+        );
+        assert!(report.keys()[0].hint().unwrap().contains("shorter key"));
+        assert!(report.keys()[2].is_unique());
+        assert_eq!(
+            report.tag_order(),
+            ["a_very_long_key_name", "kind", "names", "a_id"]
+        );
+        let text = report.to_string();
+        assert!(text.starts_with("200 features: 4600 bytes of geometries"));
+        assert!(text.contains("a_id: "));
+        assert!(text.contains("\n200 vertices, 1.0 per feature, at most 1\n"));
+        assert!(text.contains("\ncoordinate decimals: 0: 100.0%\n"));
+    }
+
+    #[test]
+    fn geometry() {
+        let line = |scale: f64, y: f64| Feature {
+            geometry: geo_types::LineString::from(
+                (0..150)
+                    .map(|i| (f64::from(i) * scale, y))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            tags: HashMap::new(),
+        };
+        let report = analyze(&mut vec![line(0.25, 1. / 3.)].into_iter()).unwrap();
+        assert_eq!(report.vertices(), 150);
+        assert_eq!(report.max_vertices(), 150);
+        assert_eq!(report.decimals()[0], 38);
+        assert_eq!(report.decimals()[1], 37);
+        assert_eq!(report.decimals()[2], 75);
+        assert_eq!(report.decimals()[MAX_DECIMALS], 150);
+        assert_eq!(report.precision(), MAX_DECIMALS);
+        assert!(report.geometry_hint().unwrap().contains("rounding"));
+        assert!(report.to_string().contains(", 16+: 50.0%\n"));
+
+        let report = analyze(&mut vec![line(0.5, 0.25); 2].into_iter()).unwrap();
+        assert_eq!(report.precision(), 2);
+        assert!(report.geometry_hint().unwrap().contains("simplification"));
+    }
+
+    #[test]
+    fn tag_order() {
+        let write = |tag_order: Option<Vec<String>>| {
+            let opts = WriterOptions {
+                tag_order,
+                gzip_level: Some(9),
+                ..Default::default()
+            };
+            let mut w = FeatureWriter::with_options(Vec::new(), opts);
+            for ft in features() {
+                w.accept(ft).unwrap();
+            }
+            w.finish().unwrap();
+            w.into_inner()
+        };
+        let order = analyze(&mut features().into_iter()).unwrap().tag_order();
+        let sorted = write(None);
+        let ordered = write(Some(order));
+        assert_ne!(sorted, ordered);
+        let back: Vec<Feature> = FeatureIterator::try_new(&mut &ordered[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(back[7].tags["a_id"], Value::Integer(7));
+        assert_eq!(back.len(), 200);
+    }
+}
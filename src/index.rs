@@ -0,0 +1,342 @@
+//! A persistent R-tree over the features of a file, for repeated bounding box queries.
+//!
+//! [`build_rtree`] scans a file once and indexes the bounding box of every feature together with
+//! the offset of its block. [`write_index`] stores the tree in a sidecar file next to the data,
+//! see [`sidecar_path`], and [`read_index`] loads it again. [`query`] then seeks to the blocks
+//! with matching features and only decodes those, in any file, unlike
+//! [`hilbert::query`](crate::hilbert::query) which needs a sorted one.
+//!
+//! The index has to be rebuilt whenever the file changes. Queries check that the indexed blocks
+//! and features exist, but cannot tell all changes apart.
+//! ```
+//! use spaten::index::{build_rtree, query, read_index, write_index};
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureWriter};
+//! use geo_types::{Point, Rect};
+//! use std::io::Cursor;
+//!
+//! let mut w = FeatureWriter::with_block_size(Vec::new(), 8);
+//! for i in 0..100 {
+//!     w.accept(Feature {
+//!         geometry: Point::new(f64::from(i % 10), f64::from(i / 10)).into(),
+//!         tags: Default::default(),
+//!     })?;
+//! }
+//! w.finish()?;
+//! let mut file = Cursor::new(w.into_inner());
+//!
+//! let mut sidecar = Vec::new();
+//! write_index(&build_rtree(&mut file)?, &mut sidecar)?;
+//! let tree = read_index(&mut &sidecar[..])?;
+//! let found = query(&tree, &mut file, Rect::new((1.5, 1.5), (3.5, 2.5)))?;
+//! assert_eq!(found.len(), 2);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{
+    decode_features, fileformat, open_block, read_raw_message, try_read_file_header, wkbfast,
+    Error, Feature, ReaderOptions,
+};
+use geo::{BoundingRect, Intersects};
+use geo_types::Rect;
+use protobuf::Message;
+use rstar::{RTree, RTreeObject, AABB};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"SPIX";
+const VERSION: u32 = 0;
+
+/// The bounding box of a feature and where to find the feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndexedFeature {
+    pub bbox: Rect<f64>,
+    /// Offset of the block of the feature in the file.
+    pub block: u64,
+    /// Position of the feature within its block.
+    pub feature: u32,
+}
+
+impl RTreeObject for IndexedFeature {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(self.bbox.min().x_y().into(), self.bbox.max().x_y().into())
+    }
+}
+
+/// The path of the sidecar index of the file at `path`, which has `.idx` appended.
+pub fn sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut p = path.as_ref().as_os_str().to_owned();
+    p.push(".idx");
+    PathBuf::from(p)
+}
+
+/// The stored bounding box of `ft`, or the one of its geometry if none is stored. `None` for
+/// empty geometries.
+fn bbox(ft: &fileformat::Feature) -> Result<Option<Rect<f64>>, Error> {
+    if let Some(r) = crate::stored_bbox(ft) {
+        return Ok(Some(r));
+    }
+    let g = wkbfast::decode(&ft.geom).map_err(Error::InvalidGeometry)?;
+    Ok(g.bounding_rect())
+}
+
+/// Reads all blocks of `r` and indexes their features. Features with an empty geometry are not
+/// indexed. Fails on [encrypted](crate::encryption) files.
+pub fn build_rtree<R: Read + Seek>(r: &mut R) -> Result<RTree<IndexedFeature>, Error> {
+    build_rtree_with_options(r, &ReaderOptions::default())
+}
+
+/// Like [`build_rtree`], but reads the blocks with the key and limits of `options`.
+pub fn build_rtree_with_options<R: Read + Seek>(
+    r: &mut R,
+    options: &ReaderOptions,
+) -> Result<RTree<IndexedFeature>, Error> {
+    let max_len = options.limits.max_block_size;
+    r.seek(SeekFrom::Start(0))?;
+    try_read_file_header(r)?;
+    let mut entries = Vec::new();
+    loop {
+        let block = r.stream_position()?;
+        let (header, raw) = match read_raw_message(r, max_len)? {
+            Some(msg) => msg,
+            None => break,
+        };
+        if header.meta {
+            continue;
+        }
+        let body = open_block(header, raw, options.key.as_ref(), max_len)?;
+        let body = fileformat::Body::parse_from_bytes(&body)?;
+        for (i, ft) in body.feature.iter().enumerate() {
+            if let Some(bbox) = bbox(ft)? {
+                entries.push(IndexedFeature {
+                    bbox,
+                    block,
+                    feature: i as u32,
+                });
+            }
+        }
+    }
+    Ok(RTree::bulk_load(entries))
+}
+
+/// Writes `tree` in the sidecar format: `SPIX`, a u32 version, a u64 count and the entries as
+/// four f64 bounding box coordinates (min x, min y, max x, max y), the u64 block offset and the
+/// u32 feature position, all little endian.
+pub fn write_index(tree: &RTree<IndexedFeature>, w: &mut impl io::Write) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&(tree.size() as u64).to_le_bytes())?;
+    for e in tree.iter() {
+        let (min, max) = (e.bbox.min(), e.bbox.max());
+        for v in [min.x, min.y, max.x, max.y] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        w.write_all(&e.block.to_le_bytes())?;
+        w.write_all(&e.feature.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads a tree written by [`write_index`].
+pub fn read_index(r: &mut impl io::Read) -> io::Result<RTree<IndexedFeature>> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a Spaten index"));
+    }
+    let mut u32_b = [0; 4];
+    let mut u64_b = [0; 8];
+    r.read_exact(&mut u32_b)?;
+    if u32::from_le_bytes(u32_b) != VERSION {
+        return Err(invalid("unsupported index version"));
+    }
+    r.read_exact(&mut u64_b)?;
+    let n = u64::from_le_bytes(u64_b);
+
+    // the count is not trusted for the allocation
+    let mut entries = Vec::with_capacity(n.min(1 << 16) as usize);
+    for _ in 0..n {
+        let mut v = [0.; 4];
+        for v in &mut v {
+            r.read_exact(&mut u64_b)?;
+            *v = f64::from_le_bytes(u64_b);
+        }
+        r.read_exact(&mut u64_b)?;
+        r.read_exact(&mut u32_b)?;
+        entries.push(IndexedFeature {
+            bbox: Rect::new((v[0], v[1]), (v[2], v[3])),
+            block: u64::from_le_bytes(u64_b),
+            feature: u32::from_le_bytes(u32_b),
+        });
+    }
+    Ok(RTree::bulk_load(entries))
+}
+
+fn mismatch() -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "index does not match the file",
+    ))
+}
+
+/// Returns the features of `r` whose geometry intersects `bbox`, in file order. Only the blocks
+/// with candidates from `tree` are read.
+pub fn query<R: Read + Seek>(
+    tree: &RTree<IndexedFeature>,
+    r: &mut R,
+    bbox: Rect<f64>,
+) -> Result<Vec<Feature>, Error> {
+    query_with_options(tree, r, bbox, &ReaderOptions::default())
+}
+
+/// Like [`query`], but reads and decodes the blocks with `options`.
+pub fn query_with_options<R: Read + Seek>(
+    tree: &RTree<IndexedFeature>,
+    r: &mut R,
+    bbox: Rect<f64>,
+    options: &ReaderOptions,
+) -> Result<Vec<Feature>, Error> {
+    let max_len = options.limits.max_block_size;
+    let env = AABB::from_corners(bbox.min().x_y().into(), bbox.max().x_y().into());
+    let mut blocks: BTreeMap<u64, Vec<u32>> = BTreeMap::new();
+    for e in tree.locate_in_envelope_intersecting(&env) {
+        blocks.entry(e.block).or_default().push(e.feature);
+    }
+
+    let mut out = Vec::new();
+    for (offset, mut positions) in blocks {
+        positions.sort_unstable();
+        r.seek(SeekFrom::Start(offset))?;
+        let (header, raw) = match read_raw_message(r, max_len) {
+            Ok(Some((header, raw))) if !header.meta => (header, raw),
+            Ok(_) | Err(Error::Truncated) => return Err(mismatch()),
+            Err(e) => return Err(e),
+        };
+        let body = open_block(header, raw, options.key.as_ref(), max_len)?;
+        let mut body = fileformat::Body::parse_from_bytes(&body)?;
+        let mut fts = body.take_feature().into_vec();
+        let picked = positions
+            .iter()
+            .map(|&i| fts.get_mut(i as usize).map(std::mem::take))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(mismatch)?;
+        let found = decode_features(picked, options, None)?;
+        out.extend(found.into_iter().filter(|ft| ft.geometry.intersects(&bbox)));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_rtree, build_rtree_with_options, query, query_with_options, read_index, sidecar_path,
+        write_index,
+    };
+    use crate::metadata::Metadata;
+    use crate::sink::FeatureSink;
+    use crate::{
+        Error, Feature, FeatureIterator, FeatureWriter, Limits, ReaderOptions, Value, WriterOptions,
+    };
+    use geo::Intersects;
+    use geo_types::{line_string, Geometry, GeometryCollection, Point, Rect};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    fn file() -> Vec<u8> {
+        let opts = WriterOptions {
+            block_size: 7,
+            gzip_level: Some(1),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        w.write_metadata(&Metadata::default()).unwrap();
+        for i in 0..50 {
+            let mut tags = HashMap::new();
+            tags.insert("i".to_string(), Value::Integer(i));
+            let x = f64::from(i as i32 % 10);
+            let y = f64::from(i as i32 / 10);
+            w.accept(Feature {
+                geometry: match i % 3 {
+                    0 => Point::new(x, y).into(),
+                    1 => line_string![(x: x, y: y), (x: x + 2., y: y + 0.5)].into(),
+                    _ => Geometry::GeometryCollection(GeometryCollection(vec![])),
+                },
+                tags,
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        w.into_inner()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut file = Cursor::new(file());
+        let tree = build_rtree(&mut file).unwrap();
+        assert_eq!(tree.size(), 34);
+
+        let mut sidecar = Vec::new();
+        write_index(&tree, &mut sidecar).unwrap();
+        assert_eq!(sidecar.len(), 16 + 34 * 44);
+        let tree = read_index(&mut &sidecar[..]).unwrap();
+        assert!(read_index(&mut &sidecar[..sidecar.len() - 1]).is_err());
+        assert!(read_index(&mut &b"SPAT\0\0\0\0"[..]).is_err());
+
+        let all: Vec<Feature> = FeatureIterator::try_new(&mut &file.get_ref()[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for bbox in [
+            Rect::new((1.5, 0.), (3., 2.2)),
+            Rect::new((0., 0.), (9., 9.)),
+            Rect::new((20., 20.), (21., 21.)),
+        ] {
+            let expected: Vec<&Value> = all
+                .iter()
+                .filter(|ft| ft.geometry.intersects(&bbox))
+                .map(|ft| &ft.tags["i"])
+                .collect();
+            let found = query(&tree, &mut file, bbox).unwrap();
+            let found: Vec<&Value> = found.iter().map(|ft| &ft.tags["i"]).collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn stale() {
+        let mut file = Cursor::new(file());
+        let tree = build_rtree(&mut file).unwrap();
+        let bbox = Rect::new((0., 0.), (9., 9.));
+
+        let mut shorter = Cursor::new(file.get_ref()[..100].to_vec());
+        assert!(query(&tree, &mut shorter, bbox).is_err());
+        let mut other = Cursor::new(FeatureWriter::new(Vec::new()).into_inner());
+        assert!(query(&tree, &mut other, bbox).is_err());
+
+        let opts = ReaderOptions {
+            limits: Limits {
+                max_block_size: 10,
+                ..Limits::unlimited()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_rtree_with_options(&mut file, &opts),
+            Err(Error::LimitExceeded(_))
+        ));
+        assert!(matches!(
+            query_with_options(&tree, &mut file, bbox, &opts),
+            Err(Error::LimitExceeded(_))
+        ));
+
+        assert_eq!(
+            sidecar_path("data/roads.spaten"),
+            Path::new("data/roads.spaten.idx")
+        );
+    }
+}
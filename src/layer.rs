@@ -0,0 +1,311 @@
+//! Named views over a Spaten file, such as "roads" or "buildings".
+//!
+//! A [`Layer`] combines a predicate that selects features with a projection onto the tags that
+//! make up its schema. Layers are registered on a [`Dataset`], which reads them from its file.
+
+use crate::sink::FeatureSink;
+use crate::source::FeatureSource;
+use crate::{Error, Feature, OwnedReader, ReaderOptions, Value};
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+type Predicate = Arc<dyn Fn(&Feature) -> bool + Send + Sync>;
+
+/// A named selection of features with a fixed set of tags.
+#[derive(Clone)]
+pub struct Layer {
+    name: String,
+    predicate: Predicate,
+    keys: Option<Vec<String>>,
+}
+
+impl Layer {
+    /// A layer of the features for which `predicate` holds, with all their tags.
+    pub fn new(name: &str, predicate: impl Fn(&Feature) -> bool + Send + Sync + 'static) -> Self {
+        Layer {
+            name: name.to_string(),
+            predicate: Arc::new(predicate),
+            keys: None,
+        }
+    }
+
+    /// A layer of the features that have tag `key`, e.g. `highway` for roads.
+    pub fn tagged(name: &str, key: &str) -> Self {
+        let key = key.to_string();
+        Self::new(name, move |ft| ft.tags.contains_key(&key))
+    }
+
+    /// A layer of the features whose tag `key` has one of `values`.
+    pub fn tag_in(name: &str, key: &str, values: Vec<Value>) -> Self {
+        let key = key.to_string();
+        Self::new(name, move |ft| {
+            ft.tags.get(&key).is_some_and(|v| values.contains(v))
+        })
+    }
+
+    /// Restricts the tags of the layer's features to `keys`.
+    pub fn with_keys(mut self, keys: &[&str]) -> Self {
+        self.keys = Some(keys.iter().map(|k| k.to_string()).collect());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The tags of the layer's features, or `None` if all tags are kept.
+    pub fn keys(&self) -> Option<&[String]> {
+        self.keys.as_deref()
+    }
+
+    pub fn matches(&self, ft: &Feature) -> bool {
+        (self.predicate)(ft)
+    }
+
+    /// Removes all tags that are not part of the layer.
+    pub fn project(&self, mut ft: Feature) -> Feature {
+        if let Some(keys) = &self.keys {
+            ft.tags.retain(|k, _| keys.contains(k));
+        }
+        ft
+    }
+
+    /// Selects and projects the layer's features from any stream of features.
+    pub fn apply<'a, I: Iterator<Item = Feature> + 'a>(
+        &'a self,
+        fts: I,
+    ) -> impl Iterator<Item = Feature> + 'a {
+        fts.filter(move |ft| self.matches(ft))
+            .map(move |ft| self.project(ft))
+    }
+}
+
+impl std::fmt::Debug for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layer")
+            .field("name", &self.name)
+            .field("keys", &self.keys)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A Spaten file together with the layers defined on it.
+/// ```
+/// use spaten::layer::{Dataset, Layer};
+/// use spaten::sink::FeatureSink;
+/// use spaten::{Feature, FeatureWriter, Value};
+/// use std::collections::HashMap;
+///
+/// let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("roads.spaten");
+/// let mut w = FeatureWriter::new(std::fs::File::create(&path)?);
+/// for (key, val) in [("highway", "primary"), ("building", "yes")] {
+///     let mut tags = HashMap::new();
+///     tags.insert(key.to_string(), Value::String(val.to_string()));
+///     tags.insert("source".to_string(), Value::String("survey".to_string()));
+///     w.accept(Feature { geometry: geo_types::Point::new(0., 0.).into(), tags })?;
+/// }
+/// w.finish()?;
+///
+/// let mut dataset = Dataset::open(&path);
+/// dataset.add_layer(Layer::tagged("roads", "highway").with_keys(&["highway"]));
+/// let roads = dataset.layer("roads").unwrap().features()?;
+/// let roads: Vec<Feature> = roads.collect::<Result<_, _>>()?;
+/// assert_eq!(roads.len(), 1);
+/// assert_eq!(roads[0].tags.len(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    path: PathBuf,
+    layers: Vec<Layer>,
+    options: ReaderOptions,
+}
+
+impl Dataset {
+    /// Refers to the file at `path`, which is only opened when features are read.
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        Self::with_options(path, ReaderOptions::default())
+    }
+
+    pub fn with_options(path: impl AsRef<Path>, options: ReaderOptions) -> Self {
+        Dataset {
+            path: path.as_ref().to_path_buf(),
+            layers: Vec::new(),
+            options,
+        }
+    }
+
+    /// Registers a layer, replacing an existing one with the same name.
+    pub fn add_layer(&mut self, layer: Layer) -> &mut Self {
+        self.layers.retain(|l| l.name != layer.name);
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layer(&self, name: &str) -> Option<LayerView<'_>> {
+        let layer = self.layers.iter().find(|l| l.name == name)?;
+        Some(LayerView {
+            dataset: self,
+            layer,
+        })
+    }
+
+    fn reader(&self) -> Result<OwnedReader<BufReader<File>>, Error> {
+        let f = File::open(&self.path)?;
+        OwnedReader::new(BufReader::new(f), self.options.clone())
+    }
+
+    /// Reads the file once and writes every layer into its own sink, which `sink_for` creates
+    /// for the layer. Features can be part of several layers. Returns the number of
+    /// features written per layer, in the order of [`layers`](Dataset::layers).
+    pub fn export<S: FeatureSink>(
+        &self,
+        mut sink_for: impl FnMut(&Layer) -> io::Result<S>,
+    ) -> io::Result<Vec<u64>> {
+        let mut sinks = self
+            .layers
+            .iter()
+            .map(&mut sink_for)
+            .collect::<io::Result<Vec<S>>>()?;
+        let mut counts = vec![0; sinks.len()];
+        let mut r = self.reader()?;
+        while let Some(ft) = r.read_feature()? {
+            for (i, layer) in self.layers.iter().enumerate() {
+                if layer.matches(&ft) {
+                    sinks[i].accept(layer.project(ft.clone()))?;
+                    counts[i] += 1;
+                }
+            }
+        }
+        for sink in &mut sinks {
+            sink.finish()?;
+        }
+        Ok(counts)
+    }
+}
+
+/// A layer of a [`Dataset`].
+#[derive(Clone, Copy)]
+pub struct LayerView<'a> {
+    dataset: &'a Dataset,
+    layer: &'a Layer,
+}
+
+impl LayerView<'_> {
+    pub fn layer(&self) -> &Layer {
+        self.layer
+    }
+
+    /// Reads the layer's features from the file.
+    pub fn features(&self) -> Result<LayerFeatures, Error> {
+        Ok(LayerFeatures {
+            r: self.dataset.reader()?,
+            layer: self.layer.clone(),
+        })
+    }
+
+    /// Writes the layer's features into `dst` and finishes it. Returns the number of features.
+    pub fn export(&self, dst: &mut (impl FeatureSink + ?Sized)) -> io::Result<u64> {
+        crate::source::copy(&mut self.features()?, dst)
+    }
+}
+
+/// The features of a layer, returned by [`LayerView::features`].
+pub struct LayerFeatures {
+    r: OwnedReader<BufReader<File>>,
+    layer: Layer,
+}
+
+impl LayerFeatures {
+    fn read_feature(&mut self) -> Result<Option<Feature>, Error> {
+        while let Some(ft) = self.r.read_feature()? {
+            if self.layer.matches(&ft) {
+                return Ok(Some(self.layer.project(ft)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for LayerFeatures {
+    type Item = Result<Feature, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_feature().transpose()
+    }
+}
+
+impl FeatureSource for LayerFeatures {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        Ok(self.read_feature()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dataset, Layer};
+    use crate::sink::FeatureSink;
+    use crate::{Feature, FeatureWriter, Value};
+    use std::collections::HashMap;
+
+    #[test]
+    fn layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mixed.spaten");
+        let mut w = FeatureWriter::new(std::fs::File::create(&path).unwrap());
+        for (key, val) in [
+            ("highway", "primary"),
+            ("highway", "footway"),
+            ("building", "yes"),
+            ("natural", "tree"),
+        ] {
+            let mut tags = HashMap::new();
+            tags.insert(key.to_string(), Value::String(val.to_string()));
+            tags.insert("note".to_string(), Value::Integer(1));
+            w.accept(Feature {
+                geometry: geo_types::Point::new(0., 0.).into(),
+                tags,
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+
+        let mut dataset = Dataset::open(&path);
+        dataset
+            .add_layer(Layer::tagged("roads", "highway").with_keys(&["highway"]))
+            .add_layer(Layer::tag_in(
+                "major",
+                "highway",
+                vec![Value::String("primary".to_string())],
+            ))
+            .add_layer(Layer::new("all", |_| true));
+        assert!(dataset.layer("water").is_none());
+
+        let roads = dataset.layer("roads").unwrap();
+        assert_eq!(roads.layer().keys(), Some(&["highway".to_string()][..]));
+        let fts: Vec<Feature> = roads.features().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(fts.len(), 2);
+        assert!(fts.iter().all(|ft| !ft.tags.contains_key("note")));
+
+        let mut major: Vec<Feature> = Vec::new();
+        assert_eq!(
+            dataset.layer("major").unwrap().export(&mut major).unwrap(),
+            1
+        );
+        assert_eq!(major[0].tags.len(), 2);
+
+        let counts = dataset.export(|_| Ok(Vec::<Feature>::new())).unwrap();
+        assert_eq!(counts, [2, 1, 4]);
+
+        let missing = Dataset::open(dir.path().join("missing.spaten"));
+        assert!(missing.export(|_| Ok(Vec::<Feature>::new())).is_err());
+    }
+}
@@ -1,151 +1,2034 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod capabilities;
+pub mod container;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod dispatch;
+#[cfg(feature = "geojson")]
+pub mod elasticsearch;
+pub mod encryption;
+#[cfg(feature = "encryption")]
+pub mod envelope;
+mod error;
+#[cfg(feature = "geo")]
+pub mod expr;
+#[allow(renamed_and_removed_lints, mismatched_lifetime_syntaxes, unused_parens)]
 mod fileformat;
+pub mod filter;
+#[cfg(feature = "flatgeobuf")]
+pub mod flatgeobuf;
+#[cfg(feature = "geojson")]
+pub mod geobuf;
+#[allow(renamed_and_removed_lints, mismatched_lifetime_syntaxes, unused_parens)]
+mod geobufformat;
+#[cfg(feature = "geocode")]
+pub mod geocode;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+pub mod georss;
+#[cfg(feature = "geozero")]
+pub mod geozero;
+pub mod hilbert;
+pub mod hints;
+#[cfg(feature = "index")]
+pub mod index;
+pub mod layer;
+pub mod lineage;
+pub mod metadata;
+pub mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod names;
+#[cfg(feature = "osmpbf")]
+pub mod osm;
+pub mod page;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod preflight;
+pub mod redact;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod sink;
+pub mod source;
+#[cfg(feature = "spatialite")]
+pub mod spatialite;
+#[cfg(feature = "spill")]
+pub mod spill;
+pub mod stats;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+pub mod transform;
+pub mod units;
+pub mod validate;
+mod wkbfast;
 use protobuf::Message;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
 use std::fmt;
 use std::io;
-use std::io::Cursor;
-use wkb::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+pub use error::Error;
+
+#[derive(Clone)]
 pub enum Value {
     String(String),
     Integer(i64),
     Float(f64),
+    /// Binary data. Written like strings, and read back as bytes if they are not valid UTF-8.
+    Bytes(Vec<u8>),
+    /// All values of a key that occurred multiple times within a feature, in file order.
+    /// Only produced when reading with [`DuplicateTags::Collect`].
+    List(Vec<Value>),
 }
 
 impl Value {
-    fn from_bytes(src: Vec<u8>, field_type: fileformat::Tag_ValueType) -> Value {
+    /// Decodes a tag value. Integers may be encoded with 1, 2, 4 or 8 bytes and floats with
+    /// 4 or 8 bytes (little endian), as some writers use the smallest width that fits.
+    fn from_bytes(
+        src: Vec<u8>,
+        field_type: fileformat::Tag_ValueType,
+    ) -> Result<Value, &'static str> {
         match field_type {
-            fileformat::Tag_ValueType::STRING => {
-                Value::String(String::from_utf8_lossy(&src).to_string())
+            fileformat::Tag_ValueType::STRING => Ok(match String::from_utf8(src) {
+                Ok(s) => Value::String(s),
+                Err(e) => Value::Bytes(e.into_bytes()),
+            }),
+            fileformat::Tag_ValueType::INT => int_from_bytes(&src).map(Value::Integer),
+            fileformat::Tag_ValueType::DOUBLE => float_from_bytes(&src).map(Value::Float),
+        }
+    }
+}
+
+fn int_from_bytes(src: &[u8]) -> Result<i64, &'static str> {
+    match src.len() {
+        1 => Ok(i64::from(i8::from_le_bytes([src[0]]))),
+        2 => Ok(i64::from(i16::from_le_bytes([src[0], src[1]]))),
+        4 => {
+            let mut sf: [u8; 4] = [0; 4];
+            sf.copy_from_slice(src);
+            Ok(i64::from(i32::from_le_bytes(sf)))
+        }
+        8 => {
+            let mut sf: [u8; 8] = [0; 8];
+            sf.copy_from_slice(src);
+            Ok(i64::from_le_bytes(sf))
+        }
+        _ => Err("Invalid integer tag length"),
+    }
+}
+
+fn float_from_bytes(src: &[u8]) -> Result<f64, &'static str> {
+    match src.len() {
+        4 => {
+            let mut sf: [u8; 4] = [0; 4];
+            sf.copy_from_slice(src);
+            Ok(f64::from(f32::from_le_bytes(sf)))
+        }
+        8 => {
+            let mut sf: [u8; 8] = [0; 8];
+            sf.copy_from_slice(src);
+            Ok(f64::from_le_bytes(sf))
+        }
+        _ => Err("Invalid float tag length"),
+    }
+}
+
+impl Value {
+    /// Compares two values according to a total order, so that values can be sorted even when
+    /// floats are NaN. Values of different types are ordered by type (integers, floats,
+    /// strings, bytes, lists); floats are ordered like [`f64::total_cmp`].
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => {
+                for (x, y) in a.iter().zip(b.iter()) {
+                    match x.total_cmp(y) {
+                        Ordering::Equal => {}
+                        o => return o,
+                    }
+                }
+                a.len().cmp(&b.len())
             }
-            fileformat::Tag_ValueType::INT => {
-                let mut sf: [u8; 8] = [0; 8];
-                sf.copy_from_slice(&src[0..8]);
-                Value::Integer(i64::from_le_bytes(sf))
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Integer(_) => 0,
+            Value::Float(_) => 1,
+            Value::String(_) => 2,
+            Value::Bytes(_) => 3,
+            Value::List(_) => 4,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(b: Vec<u8>) -> Self {
+        Value::Bytes(b)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Self {
+        Value::Integer(n.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Integer(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = Error;
+
+    fn try_from(v: &'a Value) -> Result<Self, Error> {
+        match v {
+            Value::String(s) => Ok(s),
+            _ => Err(Error::InvalidTag("Tag is not a string")),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = Error;
+
+    fn try_from(v: &Value) -> Result<Self, Error> {
+        <&str>::try_from(v).map(str::to_string)
+    }
+}
+
+impl TryFrom<&Value> for i64 {
+    type Error = Error;
+
+    fn try_from(v: &Value) -> Result<Self, Error> {
+        match v {
+            Value::Integer(n) => Ok(*n),
+            _ => Err(Error::InvalidTag("Tag is not an integer")),
+        }
+    }
+}
+
+/// Integers are converted as well, and may be rounded if their magnitude exceeds 2^53.
+impl TryFrom<&Value> for f64 {
+    type Error = Error;
+
+    fn try_from(v: &Value) -> Result<Self, Error> {
+        match v {
+            Value::Float(f) => Ok(*f),
+            Value::Integer(n) => Ok(*n as f64),
+            _ => Err(Error::InvalidTag("Tag is not a number")),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a [Value] {
+    type Error = Error;
+
+    fn try_from(v: &'a Value) -> Result<Self, Error> {
+        match v {
+            Value::List(l) => Ok(l),
+            _ => Err(Error::InvalidTag("Tag is not a list")),
+        }
+    }
+}
+
+/// Equality follows [`Value::total_cmp`], i.e. NaN equals NaN with the same bit pattern.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.total_cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        self.total_cmp(other)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(v) => write!(f, "\"{}\"", v),
+            Value::Integer(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Bytes(v) => write!(f, "b\"{}\"", v.escape_ascii()),
+            Value::List(v) => f.debug_list().entries(v).finish(),
+        }
+    }
+}
+
+/// Tag with the identifier of a feature, see [`Feature::id`].
+pub const ID_KEY: &str = "@id";
+
+#[derive(Clone, Debug)]
+pub struct Feature {
+    pub geometry: geo_types::Geometry<f64>,
+    pub tags: HashMap<String, Value>,
+}
+
+/// The id stored in `tags`: a non-negative integer, or a string of digits for ids that do not
+/// fit into an `i64`.
+fn id_from_tags(tags: &HashMap<String, Value>) -> Option<u64> {
+    match tags.get(ID_KEY)? {
+        Value::Integer(i) => u64::try_from(*i).ok(),
+        Value::String(s) if s.bytes().all(|b| b.is_ascii_digit()) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+impl Feature {
+    /// The identifier of the feature, stored in the [`ID_KEY`] tag like `osmium export` and the
+    /// [`osm`](crate::osm) import write it. Ids let features be matched across snapshots of a
+    /// dataset; they are neither checked for uniqueness nor assigned automatically.
+    /// ```
+    /// use spaten::{Feature, Value, ID_KEY};
+    ///
+    /// let mut ft = Feature {
+    ///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+    ///     tags: Default::default(),
+    /// };
+    /// assert_eq!(ft.id(), None);
+    /// ft.set_id(42);
+    /// assert_eq!(ft.id(), Some(42));
+    /// assert_eq!(ft.tags[ID_KEY], Value::Integer(42));
+    /// ```
+    pub fn id(&self) -> Option<u64> {
+        id_from_tags(&self.tags)
+    }
+
+    /// Sets the [`id`](Feature::id). Ids beyond `i64::MAX` are stored as strings.
+    pub fn set_id(&mut self, id: u64) {
+        let v = match i64::try_from(id) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::String(id.to_string()),
+        };
+        self.tags.insert(ID_KEY.to_string(), v);
+    }
+
+    /// A feature with a geometry given as WKT, e.g. for tests or hand written data. Fails with
+    /// [`InvalidData`](io::ErrorKind::InvalidData) if the text is not valid WKT.
+    /// ```
+    /// use spaten::Feature;
+    ///
+    /// let ft = Feature::from_wkt("LINESTRING(7 51,7.5 51.2)", Default::default())?;
+    /// assert_eq!(ft.geometry_wkt(), "LINESTRING(7 51,7.5 51.2)");
+    /// assert!(Feature::from_wkt("POINT(7", Default::default()).is_err());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "wkt")]
+    pub fn from_wkt(wkt: &str, tags: HashMap<String, Value>) -> io::Result<Self> {
+        use wkt::TryFromWkt;
+
+        let geometry = geo_types::Geometry::try_from_wkt_str(wkt)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(Feature { geometry, tags })
+    }
+
+    /// The geometry as WKT, e.g. for logging.
+    #[cfg(feature = "wkt")]
+    pub fn geometry_wkt(&self) -> String {
+        use wkt::ToWkt;
+
+        self.geometry.wkt_string()
+    }
+
+    /// The value of tag `key` if it is a string.
+    /// ```
+    /// use spaten::{Feature, Value};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut tags = HashMap::new();
+    /// tags.insert("name".to_string(), Value::from("Rhein"));
+    /// tags.insert("lanes".to_string(), Value::from(2));
+    /// let ft = Feature {
+    ///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+    ///     tags,
+    /// };
+    /// assert_eq!(ft.get_str("name"), Some("Rhein"));
+    /// assert_eq!(ft.get_str("lanes"), None);
+    /// assert_eq!(ft.get_i64("lanes"), Some(2));
+    /// assert_eq!(ft.get_f64("lanes"), Some(2.0));
+    /// ```
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key)
+    }
+
+    /// The value of tag `key` if it is an integer.
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key)
+    }
+
+    /// The value of tag `key` if it is a number. Integers are converted to floats.
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get(key)
+    }
+
+    /// The value of tag `key` converted to any type that implements `TryFrom<&Value>`, or
+    /// `None` if the tag is missing or has another type.
+    pub fn get<'a, T: TryFrom<&'a Value>>(&'a self, key: &str) -> Option<T> {
+        self.tags.get(key).and_then(|v| T::try_from(v).ok())
+    }
+}
+
+/// A feature whose geometry is kept as WKB and only decoded on request, which makes scans
+/// that only look at tags considerably faster. See [`FeatureIterator::raw`].
+#[derive(Clone, Debug)]
+pub struct RawFeature {
+    geom: Vec<u8>,
+    pub tags: HashMap<String, Value>,
+    /// The tag keys in file order.
+    order: Vec<String>,
+}
+
+impl RawFeature {
+    /// See [`Feature::id`].
+    pub fn id(&self) -> Option<u64> {
+        id_from_tags(&self.tags)
+    }
+
+    /// Iterates over the tags in the order in which they are stored in the file, which a
+    /// [`Feature`] does not keep. Repeated keys appear once, at their first position. Tags that
+    /// were inserted into `tags` afterwards follow, sorted by key.
+    pub fn tags_iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let mut tags: Vec<(&str, &Value)> = self
+            .order
+            .iter()
+            .filter_map(|k| Some((k.as_str(), self.tags.get(k)?)))
+            .collect();
+        if tags.len() < self.tags.len() {
+            let mut added: Vec<(&str, &Value)> = self
+                .tags
+                .iter()
+                .filter(|(k, _)| !self.order.contains(k))
+                .map(|(k, v)| (k.as_str(), v))
+                .collect();
+            added.sort_unstable_by_key(|&(k, _)| k);
+            tags.extend(added);
+        }
+        tags.into_iter()
+    }
+
+    /// The geometry as stored in the file, in WKB.
+    pub fn geometry_raw(&self) -> &[u8] {
+        &self.geom
+    }
+
+    /// Decodes the geometry.
+    pub fn geometry(&self) -> Result<geo_types::Geometry<f64>, Error> {
+        wkbfast::decode(&self.geom).map_err(Error::InvalidGeometry)
+    }
+
+    pub fn into_feature(self) -> Result<Feature, Error> {
+        self.decode(None)
+    }
+
+    fn decode(self, metrics: Option<&metrics::Metrics>) -> Result<Feature, Error> {
+        let start = metrics.map(|_| Instant::now());
+        let geometry = self.geometry()?;
+        if let (Some(m), Some(start)) = (metrics, start) {
+            m.add_stage(metrics::Stage::Wkb, start.elapsed());
+        }
+        Ok(Feature {
+            geometry,
+            tags: self.tags,
+        })
+    }
+}
+
+/// Determines how a tag key that occurs more than once within a single feature is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateTags {
+    /// Keep the value that comes first in the file.
+    FirstWins,
+    /// Keep the value that comes last in the file.
+    LastWins,
+    /// Refuse to read the block.
+    Error,
+    /// Keep all values as a [`Value::List`].
+    Collect,
+}
+
+/// Determines how NaN and infinite float tag values are handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteFloats {
+    /// Refuse to read the block.
+    Reject,
+    /// Drop the tag, as Spaten has no notion of null values.
+    Nullify,
+    /// Keep the value as it is.
+    PassThrough,
+}
+
+/// Determines how tag values that cannot be decoded are handled, e.g. integers with an invalid
+/// length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagDecoding {
+    /// Refuse to read the block.
+    #[default]
+    Strict,
+    /// Drop the tag and keep the rest of the feature.
+    Lenient,
+}
+
+/// Upper bounds on the resources a file may claim. Reading fails as soon as one is exceeded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum size of a block body in bytes, checked before the body is allocated and again
+    /// after decompression.
+    pub max_block_size: u32,
+    /// Maximum number of features read by one [`FeatureIterator`].
+    pub max_features: u64,
+    /// Maximum number of features of a single block.
+    pub max_block_features: usize,
+    /// Maximum number of vertices of a single geometry.
+    pub max_vertices: usize,
+    /// Maximum number of tags of a single feature.
+    pub max_tags: usize,
+    /// Maximum length in bytes of tag keys and string values.
+    pub max_string_len: usize,
+    /// Maximum nesting depth of GeometryCollections. Deeper than 256 levels are never read.
+    pub max_nesting: usize,
+}
+
+impl Limits {
+    /// No limits, which is the default for trusted files.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_block_size: u32::MAX,
+            max_features: u64::MAX,
+            max_block_features: usize::MAX,
+            max_vertices: usize::MAX,
+            max_tags: usize::MAX,
+            max_string_len: usize::MAX,
+            max_nesting: usize::MAX,
+        }
+    }
+
+    /// Limits that are generous for real-world data, but keep memory and CPU usage of
+    /// maliciously crafted files bounded.
+    pub fn hardened() -> Self {
+        Limits {
+            max_block_size: 16 << 20,
+            max_features: 10_000_000,
+            max_block_features: 100_000,
+            max_vertices: 1_000_000,
+            max_tags: 1_000,
+            max_string_len: 64 << 10,
+            max_nesting: 8,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+/// Settings that control how files are decoded.
+#[derive(Clone, Debug)]
+pub struct ReaderOptions {
+    pub duplicate_tags: DuplicateTags,
+    pub non_finite_floats: NonFiniteFloats,
+    pub tag_decoding: TagDecoding,
+    /// Record the time spent in each decoding stage in the reader's
+    /// [`Metrics`](metrics::Metrics). Adds a small overhead per feature.
+    pub instrument: bool,
+    pub limits: Limits,
+    /// Decrypts the blocks of encrypted files. If set, unencrypted blocks are refused, so that
+    /// encrypted blocks cannot be replaced by forged plaintext ones.
+    pub key: Option<encryption::Key>,
+}
+
+impl ReaderOptions {
+    /// Profile for untrusted input, e.g. user uploads: default decoding with
+    /// [`Limits::hardened`].
+    pub fn hardened() -> Self {
+        ReaderOptions {
+            limits: Limits::hardened(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        ReaderOptions {
+            duplicate_tags: DuplicateTags::LastWins,
+            non_finite_floats: NonFiniteFloats::PassThrough,
+            tag_decoding: TagDecoding::Strict,
+            instrument: false,
+            limits: Limits::default(),
+            key: None,
+        }
+    }
+}
+
+pub struct FeatureIterator<'a> {
+    stream: &'a mut dyn io::Read,
+    queue: VecDeque<RawFeature>,
+    options: ReaderOptions,
+    metrics: Arc<metrics::Metrics>,
+    features: u64,
+    done: bool,
+    bbox: Option<geo_types::Rect<f64>>,
+    filter: Option<filter::Filter>,
+    metadata: Option<metadata::Metadata>,
+    sequence: encryption::Sequence,
+}
+
+impl FeatureIterator<'_> {
+    /// Initializes a streaming reader that can be used to iterate over the features. Fails if
+    /// the file header is invalid. Iteration stops after the first error.
+    /// ```no_run
+    /// use spaten::FeatureIterator;
+    /// use std::fs::File;
+    ///
+    /// let mut file = File::open("nrw-motorway.spaten").unwrap();
+    /// for ft in FeatureIterator::try_new(&mut file)? {
+    ///     println!("{:?}", ft?.tags)
+    /// }
+    /// # Ok::<(), spaten::Error>(())
+    /// ```
+    pub fn try_new(r: &mut impl io::Read) -> Result<FeatureIterator<'_>, Error> {
+        Self::with_options(r, ReaderOptions::default())
+    }
+
+    /// Initializes a streaming reader that yields features like in 0.1. Panics if the file
+    /// header is invalid, and the returned iterator panics on the first invalid block.
+    #[deprecated(
+        since = "0.2.0",
+        note = "use FeatureIterator::try_new, which reports errors instead of panicking"
+    )]
+    #[allow(deprecated, clippy::new_ret_no_self)]
+    pub fn new(r: &mut impl io::Read) -> LegacyFeatureIterator<'_> {
+        match Self::try_new(r) {
+            Ok(fts) => LegacyFeatureIterator(fts),
+            Err(e) => panic!("invalid file header: {}", e),
+        }
+    }
+
+    /// Like [`try_new`](FeatureIterator::try_new), but decodes according to `options`.
+    pub fn with_options(
+        r: &mut impl io::Read,
+        options: ReaderOptions,
+    ) -> Result<FeatureIterator<'_>, Error> {
+        try_read_file_header(r)?;
+        Ok(Self::after_header(r, options))
+    }
+
+    /// Starts reading at the first block, after the file header has been read.
+    fn after_header(r: &mut impl io::Read, options: ReaderOptions) -> FeatureIterator<'_> {
+        let metrics = Arc::new(metrics::Metrics::new());
+        metrics.add_bytes(8);
+        FeatureIterator {
+            stream: r,
+            queue: VecDeque::new(),
+            options,
+            metrics,
+            features: 0,
+            done: false,
+            bbox: None,
+            filter: None,
+            metadata: None,
+            sequence: encryption::Sequence::default(),
+        }
+    }
+
+    /// Only yields the features whose geometry intersects `bbox`. Features are skipped based on
+    /// the bounding boxes stored in the file before their geometries are decoded; the remaining
+    /// ones are tested exactly. [`raw`](FeatureIterator::raw) readers only apply the first step.
+    /// Needs the `geo` feature.
+    /// ```
+    /// use geo_types::{Point, Rect};
+    /// use spaten::sink::FeatureSink;
+    /// use spaten::{Feature, FeatureIterator, FeatureWriter};
+    ///
+    /// let mut w = FeatureWriter::new(Vec::new());
+    /// for x in 0..10 {
+    ///     w.accept(Feature {
+    ///         geometry: Point::new(f64::from(x), 0.).into(),
+    ///         tags: Default::default(),
+    ///     })?;
+    /// }
+    /// w.finish()?;
+    /// let buf = w.into_inner();
+    ///
+    /// let mut file = &buf[..];
+    /// let fts = FeatureIterator::try_new(&mut file)?.with_bbox(Rect::new((2.5, -1.), (5., 1.)));
+    /// assert_eq!(fts.count(), 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "geo")]
+    pub fn with_bbox(mut self, bbox: geo_types::Rect<f64>) -> Self {
+        self.bbox = Some(bbox);
+        self
+    }
+
+    /// Only yields the features whose tags match `filter`. Features are rejected before their
+    /// geometries are decoded, which applies to [`raw`](FeatureIterator::raw) readers as well.
+    /// See [`filter`] for an example.
+    pub fn with_filter(mut self, filter: filter::Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Returns a handle to the reading statistics, which can be passed to other threads.
+    pub fn metrics(&self) -> Arc<metrics::Metrics> {
+        self.metrics.clone()
+    }
+
+    /// The last [`metadata`] block read so far. Metadata at the start of the file is available
+    /// once the first feature has been read.
+    pub fn metadata(&self) -> Option<&metadata::Metadata> {
+        self.metadata.as_ref()
+    }
+}
+
+impl<'a> FeatureIterator<'a> {
+    /// Turns the reader into one that yields features with undecoded geometries.
+    /// ```
+    /// use spaten::sink::FeatureSink;
+    /// use spaten::{Feature, FeatureIterator, FeatureWriter, Value};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut tags = HashMap::new();
+    /// tags.insert("name".to_string(), Value::String("Rhein".to_string()));
+    /// let mut w = FeatureWriter::new(Vec::new());
+    /// w.accept(Feature {
+    ///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+    ///     tags,
+    /// })?;
+    /// w.finish()?;
+    /// let buf = w.into_inner();
+    ///
+    /// let mut file = &buf[..];
+    /// for ft in FeatureIterator::try_new(&mut file)?.raw() {
+    ///     let ft = ft?;
+    ///     assert_eq!(ft.tags["name"], Value::String("Rhein".to_string()));
+    ///     assert_eq!(ft.geometry_raw()[0], 1);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn raw(self) -> RawFeatureIterator<'a> {
+        RawFeatureIterator { inner: self }
+    }
+}
+
+/// Iterator returned by [`FeatureIterator::raw`].
+pub struct RawFeatureIterator<'a> {
+    inner: FeatureIterator<'a>,
+}
+
+impl RawFeatureIterator<'_> {
+    pub fn metrics(&self) -> Arc<metrics::Metrics> {
+        self.inner.metrics()
+    }
+}
+
+impl Iterator for RawFeatureIterator<'_> {
+    type Item = Result<RawFeature, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.read_raw().transpose()
+    }
+}
+
+impl FeatureIterator<'_> {
+    /// Whether `g` intersects the box of [`with_bbox`](FeatureIterator::with_bbox), if any.
+    #[cfg(feature = "geo")]
+    fn in_bbox(&self, g: &geo_types::Geometry<f64>) -> bool {
+        use geo::Intersects;
+
+        self.bbox.is_none_or(|b| g.intersects(&b))
+    }
+
+    #[cfg(not(feature = "geo"))]
+    fn in_bbox(&self, _: &geo_types::Geometry<f64>) -> bool {
+        true
+    }
+
+    fn read_feature(&mut self) -> Result<Option<Feature>, Error> {
+        loop {
+            let ft = match self.read_raw()? {
+                Some(ft) => ft,
+                None => return Ok(None),
+            };
+            let instrument = self.options.instrument.then(|| &*self.metrics);
+            match ft.decode(instrument) {
+                Ok(ft) if !self.in_bbox(&ft.geometry) => {}
+                Ok(ft) => return Ok(Some(ft)),
+                Err(e) => {
+                    self.done = true;
+                    self.queue.clear();
+                    self.metrics.add_decode_error();
+                    return Err(e);
+                }
             }
-            fileformat::Tag_ValueType::DOUBLE => {
-                let mut sf: [u8; 8] = [0; 8];
-                sf.copy_from_slice(&src[0..8]);
-                Value::Float(f64::from_le_bytes(sf))
+        }
+    }
+
+    fn read_raw(&mut self) -> Result<Option<RawFeature>, Error> {
+        while self.queue.is_empty() {
+            if self.done {
+                return Ok(None);
+            }
+            match self.read_next_block() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    self.metrics.add_bytes(4);
+                    return Ok(None);
+                }
+                Err(e) => {
+                    self.done = true;
+                    self.metrics.add_decode_error();
+                    return Err(e);
+                }
             }
         }
+        Ok(self.queue.pop_front())
     }
+
+    /// Fills the queue from the next block, returns false at the end of the file.
+    fn read_next_block(&mut self) -> Result<bool, Error> {
+        let start = self.options.instrument.then(Instant::now);
+        let max_len = self.options.limits.max_block_size;
+        let (header, raw) = match read_raw_message(&mut self.stream, max_len)? {
+            Some(block) => block,
+            None => {
+                if self.options.key.is_some() {
+                    self.sequence.finish()?;
+                }
+                return Ok(false);
+            }
+        };
+        if header.meta {
+            self.metrics.add_bytes(8 + raw.len() as u64);
+            let key = self.options.key.as_ref();
+            let body = open_block_in(header, raw, key, Some(&mut self.sequence), max_len)?;
+            self.metadata = Some(metadata::Metadata::decode(&body)?);
+            return Ok(true);
+        }
+        let fts = decode_raw_block(
+            header,
+            raw,
+            start,
+            self.bbox.as_ref(),
+            &self.options,
+            &self.metrics,
+            &mut self.sequence,
+        )?;
+        self.features += fts.len() as u64;
+        if self.features > self.options.limits.max_features {
+            return Err(Error::LimitExceeded("Feature count limit exceeded"));
+        }
+        self.metrics.add_features(fts.len() as u64);
+        self.queue = fts.into();
+        if let Some(filter) = &self.filter {
+            self.queue.retain(|ft| filter.matches(&ft.tags));
+        }
+        Ok(true)
+    }
+}
+
+/// Decodes a block returned by [`read_raw_block`], which was started to be read at `start` if
+/// instrumentation is enabled. Features whose stored bounding box does not intersect `bbox` are
+/// skipped.
+fn decode_raw_block(
+    header: BlockHeader,
+    raw: Vec<u8>,
+    start: Option<Instant>,
+    bbox: Option<&geo_types::Rect<f64>>,
+    options: &ReaderOptions,
+    metrics: &metrics::Metrics,
+    sequence: &mut encryption::Sequence,
+) -> Result<Vec<RawFeature>, Error> {
+    let max_len = options.limits.max_block_size;
+    metrics.add_block(8 + raw.len() as u64);
+    if let Some(start) = start {
+        metrics.add_stage(metrics::Stage::Frame, start.elapsed());
+    }
+    let instrument = options.instrument.then_some(metrics);
+    let start = instrument.map(|_| Instant::now());
+    let s = open_block_in(header, raw, options.key.as_ref(), Some(sequence), max_len)?;
+    if let (Some(m), Some(start)) = (instrument, start) {
+        m.add_stage(metrics::Stage::Inflate, start.elapsed());
+    }
+    let start = instrument.map(|_| Instant::now());
+    let body = fileformat::Body::parse_from_bytes(&s)?;
+    if let (Some(m), Some(start)) = (instrument, start) {
+        m.add_stage(metrics::Stage::Protobuf, start.elapsed());
+    }
+    let mut fts = body.feature.into_vec();
+    if let Some(bbox) = bbox {
+        fts.retain(|ft| may_intersect(ft, bbox));
+    }
+    decode_raw(fts, options, instrument)
+}
+
+/// The bounding box stored with `ft`, `None` if the writer left it empty (all zero).
+pub(crate) fn stored_bbox(ft: &fileformat::Feature) -> Option<geo_types::Rect<f64>> {
+    if ft.left == 0. && ft.right == 0. && ft.bottom == 0. && ft.top == 0. {
+        return None;
+    }
+    Some(geo_types::Rect::new(
+        (ft.left, ft.bottom),
+        (ft.right, ft.top),
+    ))
+}
+
+/// Whether the stored bounding box of `ft` intersects `bbox`. Features without a stored
+/// bounding box may intersect anywhere.
+pub(crate) fn may_intersect(ft: &fileformat::Feature, bbox: &geo_types::Rect<f64>) -> bool {
+    match stored_bbox(ft) {
+        Some(b) => {
+            b.min().x <= bbox.max().x
+                && b.max().x >= bbox.min().x
+                && b.min().y <= bbox.max().y
+                && b.max().y >= bbox.min().y
+        }
+        None => true,
+    }
+}
+
+impl Iterator for FeatureIterator<'_> {
+    type Item = Result<Feature, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_feature().transpose()
+    }
+}
+
+/// The iterator returned by the deprecated [`FeatureIterator::new`]. Yields features instead of
+/// results and panics on errors.
+#[deprecated(
+    since = "0.2.0",
+    note = "use FeatureIterator::try_new, which reports errors instead of panicking"
+)]
+pub struct LegacyFeatureIterator<'a>(FeatureIterator<'a>);
+
+#[allow(deprecated)]
+impl Iterator for LegacyFeatureIterator<'_> {
+    type Item = Feature;
+
+    fn next(&mut self) -> Option<Feature> {
+        self.0
+            .next()
+            .map(|ft| ft.unwrap_or_else(|e| panic!("invalid feature: {}", e)))
+    }
+}
+
+impl source::FeatureSource for FeatureIterator<'_> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        Ok(self.read_feature()?)
+    }
+}
+
+/// A minimal reader that owns its stream, for internal use where a [`FeatureIterator`] cannot
+/// borrow one.
+pub(crate) struct OwnedReader<R> {
+    r: R,
+    queue: VecDeque<Feature>,
+    options: ReaderOptions,
+    sequence: encryption::Sequence,
+}
+
+impl<R: io::Read> OwnedReader<R> {
+    pub(crate) fn new(mut r: R, options: ReaderOptions) -> Result<Self, Error> {
+        try_read_file_header(&mut r)?;
+        Ok(OwnedReader {
+            r,
+            queue: VecDeque::new(),
+            options,
+            sequence: encryption::Sequence::default(),
+        })
+    }
+
+    pub(crate) fn read_feature(&mut self) -> Result<Option<Feature>, Error> {
+        while self.queue.is_empty() {
+            let max_len = self.options.limits.max_block_size;
+            let (header, raw) = match read_raw_message(&mut self.r, max_len)? {
+                Some(block) => block,
+                None if self.options.key.is_some() => return self.sequence.finish().map(|_| None),
+                None => return Ok(None),
+            };
+            let key = self.options.key.as_ref();
+            let body = open_block_in(header, raw, key, Some(&mut self.sequence), max_len)?;
+            if !header.meta {
+                self.queue = decode_body(&body, &self.options, None)?.into();
+            }
+        }
+        Ok(self.queue.pop_front())
+    }
+}
+
+#[deprecated(
+    since = "0.2.0",
+    note = "use try_read_file_header, which returns a Result"
+)]
+pub fn read_file_header(r: &mut impl io::Read) {
+    if let Err(e) = try_read_file_header(r) {
+        panic!("invalid file header: {}", e)
+    }
+}
+
+pub fn try_read_file_header(r: &mut impl io::Read) -> Result<(), Error> {
+    let mut buf: [u8; 4] = [0, 0, 0, 0];
+    r.read_exact(&mut buf)?;
+    if &buf != b"SPAT" {
+        return Err(Error::InvalidMagic);
+    }
+
+    r.read_exact(&mut buf)?;
+    match u32::from_le_bytes(buf) {
+        0 => Ok(()),
+        v => Err(Error::UnsupportedVersion(v)),
+    }
+}
+
+/// Reads the next block body, decompressed if necessary. Returns `Ok(None)` at the terminating
+/// empty block, or if the stream ends cleanly between blocks. Fails on encrypted blocks.
+/// [`metadata`] blocks are skipped, see [`read_any_block`].
+pub fn try_read_block(r: &mut impl io::Read) -> Result<Option<Vec<u8>>, Error> {
+    read_block_with_options(r, &ReaderOptions::default())
+}
+
+#[deprecated(
+    since = "0.2.0",
+    note = "use try_read_block, which returns spaten::Error"
+)]
+pub fn read_block(r: &mut impl io::Read) -> Result<Option<Vec<u8>>, &'static str> {
+    try_read_block(r).map_err(|e| match e {
+        Error::Truncated | Error::Io(_) => "Couldn't read block",
+        _ => "Unsupported block",
+    })
+}
+
+/// Like [`try_read_block`], but decrypts the body with [`ReaderOptions::key`] and refuses blocks
+/// larger than [`Limits::max_block_size`].
+pub fn read_block_with_options(
+    r: &mut impl io::Read,
+    options: &ReaderOptions,
+) -> Result<Option<Vec<u8>>, Error> {
+    let max_len = options.limits.max_block_size;
+    match read_raw_block(r, max_len)? {
+        Some((header, body)) => open_block(header, body, options.key.as_ref(), max_len).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// A block, by message type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    /// A block body with features, see [`parse_block_body`].
+    Body(Vec<u8>),
+    /// Dataset metadata.
+    Meta(metadata::Metadata),
+}
+
+/// Like [`try_read_block`], but returns [`metadata`] blocks as well.
+pub fn read_any_block(r: &mut impl io::Read) -> Result<Option<Block>, Error> {
+    read_any_block_with_options(r, &ReaderOptions::default())
+}
+
+/// Like [`read_any_block`], with the decryption and limits of [`read_block_with_options`].
+pub fn read_any_block_with_options(
+    r: &mut impl io::Read,
+    options: &ReaderOptions,
+) -> Result<Option<Block>, Error> {
+    let max_len = options.limits.max_block_size;
+    let (header, body) = match read_raw_message(r, max_len)? {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    let body = open_block(header, body, options.key.as_ref(), max_len)?;
+    match header.meta {
+        true => metadata::Metadata::decode(&body).map(|m| Some(Block::Meta(m))),
+        false => Ok(Some(Block::Body(body))),
+    }
+}
+
+/// Like [`try_read_block`], but leaves the body as it is stored and refuses bodies larger than
+/// `max_len` before allocating them.
+fn read_raw_block(
+    r: &mut impl io::Read,
+    max_len: u32,
+) -> Result<Option<(BlockHeader, Vec<u8>)>, Error> {
+    loop {
+        match read_raw_message(r, max_len)? {
+            Some((header, _)) if header.meta => {}
+            block => return Ok(block),
+        }
+    }
+}
+
+/// Like [`read_raw_block`], but returns [`metadata`] blocks as well.
+fn read_raw_message(
+    r: &mut impl io::Read,
+    max_len: u32,
+) -> Result<Option<(BlockHeader, Vec<u8>)>, Error> {
+    read_raw_checked(r, max_len, check_block_header)
+}
+
+/// Like [`read_raw_message`], but validates the block header with `check`.
+fn read_raw_checked(
+    r: &mut impl io::Read,
+    max_len: u32,
+    check: fn([u8; 4]) -> Result<BlockHeader, Error>,
+) -> Result<Option<(BlockHeader, Vec<u8>)>, Error> {
+    use std::io::Read;
+
+    let mut bodylen_b: [u8; 4] = [0; 4];
+    match read_full(r, &mut bodylen_b)? {
+        0 => return Ok(None),
+        4 => {}
+        _ => return Err(Error::Truncated),
+    }
+    let bodylen = u32::from_le_bytes(bodylen_b);
+
+    if bodylen == 0 {
+        return Ok(None);
+    }
+    if bodylen > max_len {
+        return Err(Error::LimitExceeded("Block size limit exceeded"));
+    }
+
+    let mut header: [u8; 4] = [0; 4];
+    r.read_exact(&mut header)?;
+    let header = check(header)?;
+
+    let mut body = Vec::with_capacity(bodylen.min(MAX_PREALLOC) as usize);
+    r.take(u64::from(bodylen)).read_to_end(&mut body)?;
+    if body.len() < bodylen as usize {
+        return Err(Error::Truncated);
+    }
+
+    Ok(Some((header, body)))
+}
+
+/// Block bodies are read into a buffer of at most this size at first, which grows as data
+/// arrives. A forged block length thus cannot make a reader allocate more memory than the
+/// input holds.
+const MAX_PREALLOC: u32 = 1 << 20;
+
+/// Reads until `buf` is full or the stream ends, and returns the number of bytes read. Unlike
+/// `read_exact`, this tells a clean end of the stream apart from a partial read.
+fn read_full(r: &mut impl io::Read, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+    Ok(n)
+}
+
+/// Decrypts a block body returned by [`read_raw_block`] with `key` if necessary, then
+/// decompresses it. Blocks must be encrypted if and only if a key is given.
+fn open_block(
+    header: BlockHeader,
+    body: Vec<u8>,
+    key: Option<&encryption::Key>,
+    max_len: u32,
+) -> Result<Vec<u8>, Error> {
+    open_block_in(header, body, key, None, max_len)
+}
+
+/// Like [`open_block`], but checks that encrypted blocks follow the ones passed to `sequence`
+/// before.
+fn open_block_in(
+    header: BlockHeader,
+    body: Vec<u8>,
+    key: Option<&encryption::Key>,
+    sequence: Option<&mut encryption::Sequence>,
+    max_len: u32,
+) -> Result<Vec<u8>, Error> {
+    let body = match (key, header.encrypted) {
+        (Some(key), true) => {
+            let (position, body) = encryption::open_block(key, &header.bytes, &body)?;
+            if let Some(sequence) = sequence {
+                sequence.check(&position)?;
+            }
+            body
+        }
+        (None, false) => body,
+        (None, true) => return Err(Error::Decrypt("Block is encrypted, but no key was given")),
+        (Some(_), false) => return Err(Error::Decrypt("Block is not encrypted")),
+    };
+    let body = capabilities::apply_flags(header.flags, body).map_err(Error::Decompress)?;
+    decompress(header.compression, body, max_len)
+}
+
+/// Decompresses a block body, refusing results larger than `max_len`.
+fn decompress(compression: Compression, body: Vec<u8>, max_len: u32) -> Result<Vec<u8>, Error> {
+    #[cfg(feature = "gzip")]
+    use std::io::Read;
+
+    match compression {
+        Compression::None => Ok(body),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut out = Vec::with_capacity((body.len() * 4).min(max_len as usize));
+            flate2::read::GzDecoder::new(&body[..])
+                .take(u64::from(max_len) + 1)
+                .read_to_end(&mut out)
+                .map_err(Error::Decompress)?;
+            if out.len() > max_len as usize {
+                return Err(Error::LimitExceeded("Block size limit exceeded"));
+            }
+            Ok(out)
+        }
+        #[cfg(not(feature = "gzip"))]
+        Compression::Gzip => Err(Error::UnsupportedBlock("Gzip support is not enabled")),
+        Compression::Registered(code) => {
+            let f = capabilities::decompressor(code)
+                .ok_or(Error::UnsupportedBlock("Unsupported block compression"))?;
+            let out = f(&body, max_len).map_err(Error::Decompress)?;
+            if out.len() > max_len as usize {
+                return Err(Error::LimitExceeded("Block size limit exceeded"));
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(buf: &[u8], level: u32) -> io::Result<Vec<u8>> {
+    let mut enc = flate2::write::GzEncoder::new(
+        Vec::with_capacity(buf.len() / 2),
+        flate2::Compression::new(level),
+    );
+    io::Write::write_all(&mut enc, buf)?;
+    enc.finish()
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip(_: &[u8], _: u32) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Gzip support is not enabled",
+    ))
+}
+
+/// Compression of a block body, as announced in its header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    Gzip,
+    /// A compression code registered in the [capability table](capabilities).
+    Registered(u8),
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Gzip => 1,
+            Compression::Registered(code) => code,
+        }
+    }
+}
+
+/// Message type of blocks with features.
+const MESSAGE_BODY: u8 = 0;
+/// Message type of blocks with [`metadata`].
+const MESSAGE_META: u8 = 1;
+
+fn message_type(meta: bool) -> u8 {
+    match meta {
+        true => MESSAGE_META,
+        false => MESSAGE_BODY,
+    }
+}
+
+/// A validated block header.
+#[derive(Clone, Copy, Debug)]
+struct BlockHeader {
+    bytes: [u8; 4],
+    compression: Compression,
+    encrypted: bool,
+    /// Whether the block holds [`metadata`] instead of features.
+    meta: bool,
+    /// The [registered](capabilities) flag bits.
+    flags: u16,
+}
+
+/// Validates flags, compression and message type. Flags must be zero apart from the
+/// [encryption](encryption) flag and the [registered](capabilities) ones.
+fn check_block_header(header: [u8; 4]) -> Result<BlockHeader, Error> {
+    match header[3] {
+        MESSAGE_BODY | MESSAGE_META => check_block_frame(header),
+        _ => Err(Error::UnsupportedBlock("Unsupported block message type")),
+    }
+}
+
+/// Like [`check_block_header`], but accepts any message type, see [`dispatch`].
+fn check_block_frame(header: [u8; 4]) -> Result<BlockHeader, Error> {
+    let flags = u16::from_le_bytes([header[0], header[1]]);
+    let extensions = flags & !encryption::FLAG;
+    if extensions != 0 && extensions & !capabilities::flags() != 0 {
+        return Err(Error::UnsupportedBlock("Unsupported block flags"));
+    }
+    let compression = match header[2] {
+        0 => Compression::None,
+        1 => Compression::Gzip,
+        c if capabilities::decompressor(c).is_some() => Compression::Registered(c),
+        _ => return Err(Error::UnsupportedBlock("Unsupported block compression")),
+    };
+    Ok(BlockHeader {
+        bytes: header,
+        compression,
+        encrypted: flags & encryption::FLAG != 0,
+        meta: header[3] == MESSAGE_META,
+        flags: extensions,
+    })
+}
+
+/// A block located within a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// The body as stored, see [`decompress`](Frame::decompress).
+    pub body: &'a [u8],
+    pub compression: Compression,
+    /// Whether the body is [encrypted](encryption), see [`decrypt`](Frame::decrypt).
+    pub encrypted: bool,
+    /// Whether the block holds [`metadata`] instead of features. Its body is not suitable for
+    /// [`parse_block_body`] then.
+    pub meta: bool,
+    /// The flag bits of [registered](capabilities) extensions, which
+    /// [`decompress`](Frame::decompress) applies.
+    pub flags: u16,
+    /// Number of bytes the block occupies, including its header.
+    pub len: usize,
+}
+
+impl<'a> Frame<'a> {
+    /// Returns the body ready for [`parse_block_body`], decompressing it if necessary. Fails if
+    /// the body is encrypted, or if it decompresses to more than [`Limits::max_block_size`].
+    pub fn decompress(&self, limits: &Limits) -> Result<Cow<'a, [u8]>, Error> {
+        match self.compression {
+            _ if self.encrypted => Err(Error::Decrypt("Block is encrypted, but no key was given")),
+            Compression::None if self.flags == 0 => Ok(Cow::Borrowed(self.body)),
+            c => {
+                let body = capabilities::apply_flags(self.flags, self.body.to_vec())
+                    .map_err(Error::Decompress)?;
+                decompress(c, body, limits.max_block_size).map(Cow::Owned)
+            }
+        }
+    }
+
+    /// Like [`decompress`](Frame::decompress), but decrypts the body with `key` first. Fails if
+    /// the body is not encrypted.
+    pub fn decrypt(&self, key: &encryption::Key, limits: &Limits) -> Result<Vec<u8>, Error> {
+        let flags = (encryption::FLAG | self.flags).to_le_bytes();
+        let header = BlockHeader {
+            bytes: [
+                flags[0],
+                flags[1],
+                self.compression.to_byte(),
+                message_type(self.meta),
+            ],
+            compression: self.compression,
+            encrypted: self.encrypted,
+            meta: self.meta,
+            flags: self.flags,
+        };
+        open_block(header, self.body.to_vec(), Some(key), limits.max_block_size)
+    }
+}
+
+/// Locates the block at the start of `buf`, without decoding its body. Returns `Ok(None)` for
+/// the terminating empty block or an empty input.
+///
+/// Like [`parse_block_body`], this does no I/O and never panics, which makes it suitable as a
+/// fuzzing entry point.
+/// ```
+/// use spaten::parse_frame;
+///
+/// let buf = b"\x02\0\0\0\0\0\0\0ab\0\0\0\0";
+/// let frame = parse_frame(buf).unwrap().unwrap();
+/// assert_eq!(frame.body, b"ab");
+/// assert!(parse_frame(&buf[frame.len..]).unwrap().is_none());
+/// ```
+pub fn parse_frame(buf: &[u8]) -> Result<Option<Frame<'_>>, Error> {
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    let (len, rest) = split_array::<4>(buf).ok_or(Error::Truncated)?;
+    let bodylen = u32::from_le_bytes(len) as usize;
+    if bodylen == 0 {
+        return Ok(None);
+    }
+    let (header, rest) = split_array::<4>(rest).ok_or(Error::Truncated)?;
+    let header = check_block_header(header)?;
+    if rest.len() < bodylen {
+        return Err(Error::Truncated);
+    }
+    Ok(Some(Frame {
+        body: &rest[..bodylen],
+        compression: header.compression,
+        encrypted: header.encrypted,
+        meta: header.meta,
+        flags: header.flags,
+        len: 8 + bodylen,
+    }))
+}
+
+fn split_array<const N: usize>(buf: &[u8]) -> Option<([u8; N], &[u8])> {
+    if buf.len() < N {
+        return None;
+    }
+    let (head, rest) = buf.split_at(N);
+    let mut arr = [0; N];
+    arr.copy_from_slice(head);
+    Some((arr, rest))
+}
+
+/// Decodes a block body with the default [`ReaderOptions`]. Never panics, regardless of the
+/// input.
+pub fn parse_block_body(buf: &[u8]) -> Result<Vec<Feature>, Error> {
+    decode_body(buf, &ReaderOptions::default(), None)
+}
+
+#[deprecated(since = "0.2.0", note = "use parse_block_body, which returns a Result")]
+pub fn read_body(v: Vec<u8>) -> Vec<Feature> {
+    match parse_block_body(&v) {
+        Ok(fts) => fts,
+        Err(e) => panic!("invalid block body: {}", e),
+    }
+}
+
+pub fn read_body_with_options(v: Vec<u8>, options: &ReaderOptions) -> Result<Vec<Feature>, Error> {
+    decode_body(&v, options, None)
+}
+
+/// Decodes a block body, recording stage timings in `metrics` if given.
+fn decode_body(
+    v: &[u8],
+    options: &ReaderOptions,
+    metrics: Option<&metrics::Metrics>,
+) -> Result<Vec<Feature>, Error> {
+    let start = metrics.map(|_| Instant::now());
+    let body = fileformat::Body::parse_from_bytes(v)?;
+    if let (Some(m), Some(start)) = (metrics, start) {
+        m.add_stage(metrics::Stage::Protobuf, start.elapsed());
+    }
+    decode_features(body.feature.into_vec(), options, metrics)
+}
+
+fn decode_features(
+    fts: Vec<fileformat::Feature>,
+    options: &ReaderOptions,
+    metrics: Option<&metrics::Metrics>,
+) -> Result<Vec<Feature>, Error> {
+    decode_raw(fts, options, metrics)?
+        .into_iter()
+        .map(|ft| ft.decode(metrics))
+        .collect()
+}
+
+/// Decodes the tags and checks the limits, but leaves the geometries as they are.
+fn decode_raw(
+    fts: Vec<fileformat::Feature>,
+    options: &ReaderOptions,
+    metrics: Option<&metrics::Metrics>,
+) -> Result<Vec<RawFeature>, Error> {
+    let limits = &options.limits;
+    if fts.len() > limits.max_block_features {
+        return Err(Error::LimitExceeded("Block feature count limit exceeded"));
+    }
+    let mut features = Vec::with_capacity(fts.len());
+    let mut tags_time = Duration::ZERO;
+    let limited = *limits != Limits::unlimited();
+    for ft in fts {
+        if limited
+            && wkbfast::inspect(&ft.geom, limits.max_nesting).map_err(Error::InvalidGeometry)?
+                > limits.max_vertices
+        {
+            return Err(Error::LimitExceeded("Vertex count limit exceeded"));
+        }
+        let start = metrics.map(|_| Instant::now());
+
+        if ft.tags.len() > limits.max_tags {
+            return Err(Error::LimitExceeded("Tag count limit exceeded"));
+        }
+        let mut tags = HashMap::with_capacity(ft.tags.len());
+        let mut order = Vec::with_capacity(ft.tags.len());
+        for tag in ft.tags {
+            if tag.key.len() > limits.max_string_len
+                || (tag.field_type == fileformat::Tag_ValueType::STRING
+                    && tag.value.len() > limits.max_string_len)
+            {
+                return Err(Error::LimitExceeded("String length limit exceeded"));
+            }
+            let val = match Value::from_bytes(tag.value, tag.field_type) {
+                Ok(val) => val,
+                Err(_) if options.tag_decoding == TagDecoding::Lenient => continue,
+                Err(e) => return Err(Error::InvalidTag(e)),
+            };
+            if let Value::Float(f) = val {
+                if !f.is_finite() {
+                    match options.non_finite_floats {
+                        NonFiniteFloats::Reject => {
+                            return Err(Error::InvalidTag("Non-finite float tag value"))
+                        }
+                        NonFiniteFloats::Nullify => continue,
+                        NonFiniteFloats::PassThrough => {}
+                    }
+                }
+            }
+            if !tags.contains_key(&tag.key) {
+                order.push(tag.key.clone());
+            }
+            insert_tag(&mut tags, tag.key, val, options.duplicate_tags)
+                .map_err(Error::InvalidTag)?;
+        }
+        if let Some(start) = start {
+            tags_time += start.elapsed();
+        }
+
+        features.push(RawFeature {
+            geom: ft.geom,
+            tags,
+            order,
+        });
+    }
+    if let Some(m) = metrics {
+        m.add_stage(metrics::Stage::Tags, tags_time);
+    }
+    Ok(features)
+}
+
+fn insert_tag(
+    tags: &mut HashMap<String, Value>,
+    key: String,
+    val: Value,
+    policy: DuplicateTags,
+) -> Result<(), &'static str> {
+    use std::collections::hash_map::Entry;
+
+    match tags.entry(key) {
+        Entry::Vacant(e) => {
+            e.insert(val);
+        }
+        Entry::Occupied(mut e) => match policy {
+            DuplicateTags::FirstWins => {}
+            DuplicateTags::LastWins => {
+                e.insert(val);
+            }
+            DuplicateTags::Error => return Err("Duplicate tag key in feature"),
+            DuplicateTags::Collect => match e.get_mut() {
+                // A list can only be the result of an earlier duplicate, as lists are never
+                // decoded from a single tag.
+                Value::List(l) => l.push(val),
+                first => {
+                    let first = std::mem::replace(first, Value::List(Vec::with_capacity(2)));
+                    if let Value::List(l) = e.get_mut() {
+                        l.push(first);
+                        l.push(val);
+                    }
+                }
+            },
+        },
+    }
+    Ok(())
+}
+
+/// Number of features per block if not specified otherwise.
+pub const DEFAULT_BLOCK_SIZE: usize = 1000;
+
+/// Writes features as a Spaten file. Features are buffered and written in blocks, the file is
+/// only complete after [`finish`](sink::FeatureSink::finish) has been called.
+///
+/// [`Value::List`] tags are written as repeated tags with the same key, which can be read back
+/// with [`DuplicateTags::Collect`].
+/// ```
+/// use spaten::sink::FeatureSink;
+/// use spaten::{Feature, FeatureIterator, FeatureWriter};
+/// use std::collections::HashMap;
+///
+/// let mut w = FeatureWriter::new(Vec::new());
+/// w.accept(Feature {
+///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+///     tags: HashMap::new(),
+/// })
+/// .unwrap();
+/// w.finish().unwrap();
+/// let buf = w.into_inner();
+/// assert_eq!(FeatureIterator::try_new(&mut &buf[..]).unwrap().count(), 1);
+/// ```
+pub struct FeatureWriter<W: io::Write> {
+    w: W,
+    options: WriterOptions,
+    body: fileformat::Body,
+    header_written: bool,
+    /// Annotate blocks with their Hilbert index range and the margin of the file, see
+    /// [`hilbert::write_sorted`].
+    hilbert: Option<(f64, f64)>,
+    /// Tag blocks with the layer they belong to, see [`container`].
+    layer: Option<String>,
+    /// Forces written data to stable storage, only set for files.
+    sync: Option<fn(&W) -> io::Result<()>>,
+    /// Start of the first block write and the number of bytes written since, for pacing.
+    paced: Option<(Instant, u64)>,
+    /// Number of features accepted so far.
+    features: u64,
+    /// Encrypts the blocks once a key is set.
+    sealer: Option<encryption::Sealer>,
 }
 
-impl fmt::Debug for Value {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Value::String(v) => write!(f, "\"{}\"", v),
-            Value::Integer(v) => write!(f, "{}", v),
-            Value::Float(v) => write!(f, "{}", v),
+/// When a [`FeatureWriter`] forces written data to stable storage with fsync.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system.
+    #[default]
+    Never,
+    /// After every block. Safest, but slowest.
+    PerBlock,
+    /// Once, when the writer is finished.
+    OnFinish,
+}
+
+/// Settings that control how files are written.
+#[derive(Clone, Debug)]
+pub struct WriterOptions {
+    /// Maximum number of features per block.
+    pub block_size: usize,
+    /// Compress block bodies with gzip at the given level (0-9). Uncompressed if `None`. Needs
+    /// the `gzip` feature, which is enabled by default.
+    pub gzip_level: Option<u32>,
+    /// When to fsync. Only honoured by writers created with [`FeatureWriter::create`], as other
+    /// streams have no notion of durability.
+    pub sync: SyncPolicy,
+    /// Limits the write rate to this many bytes per second by sleeping between blocks, to keep
+    /// bulk exports from saturating shared storage.
+    pub max_bytes_per_sec: Option<u64>,
+    /// Encrypts the block bodies with this key, see [`encryption`].
+    pub key: Option<encryption::Key>,
+    /// Refuses features that do not conform to this schema, see [`schema`].
+    pub schema: Option<schema::Schema>,
+    /// Stamps every feature with provenance tags before it is validated, see [`lineage`].
+    pub lineage: Option<lineage::Lineage>,
+    /// Writes the tags with these keys first, in this order, and the others sorted by key. See
+    /// [`hints::Report::tag_order`] for an order that compresses well.
+    pub tag_order: Option<Vec<String>>,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            block_size: DEFAULT_BLOCK_SIZE,
+            gzip_level: None,
+            sync: SyncPolicy::Never,
+            max_bytes_per_sec: None,
+            key: None,
+            schema: None,
+            lineage: None,
+            tag_order: None,
         }
     }
 }
 
-pub struct Feature {
-    pub geometry: geo_types::Geometry<f64>,
-    pub tags: HashMap<String, Value>,
+impl FeatureWriter<std::fs::File> {
+    /// Creates a file at `path` and writes to it according to `options`, including
+    /// [`sync`](WriterOptions::sync).
+    pub fn create(path: impl AsRef<std::path::Path>, options: WriterOptions) -> io::Result<Self> {
+        let mut w = Self::with_options(std::fs::File::create(path)?, options);
+        w.sync = Some(std::fs::File::sync_all);
+        Ok(w)
+    }
 }
 
-pub struct FeatureIterator<'a> {
-    stream: &'a mut dyn io::Read,
-    queue: Vec<Feature>,
-}
+impl<W: io::Write> FeatureWriter<W> {
+    pub fn new(w: W) -> Self {
+        Self::with_options(w, WriterOptions::default())
+    }
 
-impl FeatureIterator<'_> {
-    /// Initializes a streaming reader that can be used to iterate over the features.
+    /// Writes blocks of up to `block_size` features.
+    pub fn with_block_size(w: W, block_size: usize) -> Self {
+        Self::with_options(
+            w,
+            WriterOptions {
+                block_size,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Like [`new`](FeatureWriter::new), but encodes according to `options`.
     /// ```
-    /// use spaten::FeatureIterator;
-    /// use std::fs::File;
+    /// use spaten::sink::FeatureSink;
+    /// use spaten::{Feature, FeatureIterator, FeatureWriter, WriterOptions};
+    /// use std::collections::HashMap;
     ///
-    /// let mut file = File::open("nrw-motorway.spaten").unwrap();
-    /// for ft in FeatureIterator::new(&mut file) {
-    ///     println!("{:?}", ft.tags)
-    /// }
+    /// let opts = WriterOptions {
+    ///     gzip_level: Some(6),
+    ///     ..Default::default()
+    /// };
+    /// let mut w = FeatureWriter::with_options(Vec::new(), opts);
+    /// w.accept(Feature {
+    ///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+    ///     tags: HashMap::new(),
+    /// })?;
+    /// w.finish()?;
+    /// let buf = w.into_inner();
+    /// assert_eq!(buf[14], 1);
+    /// assert_eq!(FeatureIterator::try_new(&mut &buf[..])?.count(), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn new(r: &mut impl io::Read) -> FeatureIterator {
-        read_file_header(r);
-        FeatureIterator {
-            stream: r,
-            queue: Vec::new(),
+    pub fn with_options(w: W, options: WriterOptions) -> Self {
+        FeatureWriter {
+            w,
+            options: WriterOptions {
+                block_size: options.block_size.max(1),
+                gzip_level: options.gzip_level.map(|l| l.min(9)),
+                max_bytes_per_sec: options.max_bytes_per_sec.map(|r| r.max(1)),
+                ..options
+            },
+            body: fileformat::Body::new(),
+            header_written: false,
+            hilbert: None,
+            layer: None,
+            sync: None,
+            paced: None,
+            features: 0,
+            sealer: None,
         }
     }
-}
 
-impl Iterator for FeatureIterator<'_> {
-    type Item = Feature;
+    pub fn into_inner(self) -> W {
+        self.w
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.queue.len() == 0 {
-            match read_block(&mut self.stream) {
-                Ok(x) => match x {
-                    Some(s) => self.queue = read_body(s),
-                    None => return None,
-                },
-                Err(e) => panic!("iterating failed: {:?}", e),
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.header_written {
+            self.header_written = true;
+            self.w.write_all(b"SPAT\0\0\0\0")?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        if self.body.feature.is_empty() {
+            return Ok(());
+        }
+        let mut meta = match self.hilbert {
+            Some(margin) => hilbert::block_meta(&self.body, margin),
+            None => fileformat::Meta::new(),
+        };
+        if let Some(layer) = &self.layer {
+            let layer = Value::String(layer.clone());
+            encode_tag(&mut meta.tags, container::LAYER_KEY, &layer);
+        }
+        self.body.meta = match meta.tags.is_empty() {
+            true => protobuf::SingularPtrField::none(),
+            false => protobuf::SingularPtrField::some(meta),
+        };
+        self.write_body()
+    }
+
+    /// Writes a block without features that only carries `meta`.
+    fn write_meta_block(&mut self, meta: fileformat::Meta) -> io::Result<()> {
+        self.write_block()?;
+        self.body.meta = protobuf::SingularPtrField::some(meta);
+        self.write_body()
+    }
+
+    /// Writes the pending features, followed by a block with `metadata`. Readers pick it up as
+    /// they pass it, see [`metadata`] for an example.
+    pub fn write_metadata(&mut self, metadata: &metadata::Metadata) -> io::Result<()> {
+        self.write_block()?;
+        let buf = metadata
+            .to_meta()
+            .write_to_bytes()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.write_message(buf, MESSAGE_META)
+    }
+
+    fn write_body(&mut self) -> io::Result<()> {
+        let buf = self
+            .body
+            .write_to_bytes()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.body.feature.clear();
+        self.write_message(buf, MESSAGE_BODY)
+    }
+
+    /// Compresses and encrypts `buf` according to the options and writes it as a block.
+    fn write_message(&mut self, buf: Vec<u8>, message_type: u8) -> io::Result<()> {
+        self.write_sealed(buf, message_type, false)
+    }
+
+    /// Like [`write_message`](FeatureWriter::write_message), but marks encrypted blocks as the
+    /// last one of the file if `last` is set.
+    fn write_sealed(&mut self, mut buf: Vec<u8>, message_type: u8, last: bool) -> io::Result<()> {
+        let mut compression = Compression::None;
+        // an empty block would read as the end of the file, unless it is encrypted
+        let level = match buf.is_empty() && self.options.key.is_none() {
+            true => self.options.gzip_level.or(Some(1)),
+            false => self.options.gzip_level,
+        };
+        if let Some(level) = level {
+            buf = gzip(&buf, level)?;
+            compression = Compression::Gzip;
+        }
+        let mut header = [0, 0, compression.to_byte(), message_type];
+        if let Some(key) = &self.options.key {
+            header[..2].copy_from_slice(&encryption::FLAG.to_le_bytes());
+            let sealer = self.sealer.get_or_insert_with(encryption::Sealer::new);
+            buf = sealer.seal(key, &header, &buf, last)?;
+        }
+        let len = u32::try_from(buf.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Block too large"))?;
+        self.w.write_all(&len.to_le_bytes())?;
+        self.w.write_all(&header)?;
+        self.w.write_all(&buf)?;
+        if self.options.sync == SyncPolicy::PerBlock {
+            self.sync()?;
+        }
+        self.pace(8 + buf.len());
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        match self.sync {
+            Some(sync) => {
+                self.w.flush()?;
+                sync(&self.w)
             }
+            None => Ok(()),
+        }
+    }
+
+    /// Sleeps until the bytes written so far fit the configured rate.
+    fn pace(&mut self, n: usize) {
+        let rate = match self.options.max_bytes_per_sec {
+            Some(rate) => rate,
+            None => return,
+        };
+        let (start, written) = self.paced.get_or_insert_with(|| (Instant::now(), 0));
+        *written += n as u64;
+        let due = Duration::from_secs_f64(*written as f64 / rate as f64);
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            std::thread::sleep(wait);
         }
-        Some(self.queue.remove(0))
     }
 }
 
-pub fn read_file_header(r: &mut impl io::Read) {
-    let mut buf: [u8; 4] = [0, 0, 0, 0];
-    r.read(&mut buf).expect("Couldn't read file header");
-    assert_eq!(&buf, b"SPAT");
+impl<W: io::Write> sink::FeatureSink for FeatureWriter<W> {
+    fn accept(&mut self, mut ft: Feature) -> io::Result<()> {
+        if let Some(lineage) = &self.options.lineage {
+            lineage.stamp(&mut ft, self.features);
+        }
+        self.features += 1;
+        let ft = match &self.options.schema {
+            Some(schema) => schema
+                .apply(ft)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            None => ft,
+        };
+        let ft = encode_feature(&ft, self.options.tag_order.as_deref())?;
+        self.body.feature.push(ft);
+        if self.body.feature.len() >= self.options.block_size {
+            self.write_block()?;
+        }
+        Ok(())
+    }
 
-    r.read(&mut buf).expect("Couldn't read file version header");
-    assert_eq!(&buf, b"\0\0\0\0");
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        if self.options.key.is_some() {
+            // an empty block that marks the end, so that truncation is detected
+            self.write_sealed(Vec::new(), MESSAGE_BODY, true)?;
+        }
+        self.w.write_all(b"\0\0\0\0")?;
+        self.w.flush()?;
+        if self.options.sync != SyncPolicy::Never {
+            self.sync()?;
+        }
+        Ok(())
+    }
 }
 
-pub fn read_block(r: &mut impl io::Read) -> Result<Option<Vec<u8>>, &'static str> {
-    let mut bodylen_b: [u8; 4] = [0; 4];
-    if let Err(_) = r.read(&mut bodylen_b) {
-        return Err("Couldn't read body length");
+/// Calls `f` with every coordinate of `g`, in the order of `geo::CoordsIter`.
+pub(crate) fn for_each_coord(g: &geo_types::Geometry<f64>, f: &mut impl FnMut(geo_types::Coord)) {
+    use geo_types::{Coord, Geometry, Polygon};
+
+    fn polygon(p: &Polygon<f64>, f: &mut impl FnMut(Coord)) {
+        for ring in std::iter::once(p.exterior()).chain(p.interiors()) {
+            ring.0.iter().copied().for_each(&mut *f);
+        }
     }
-    let bodylen = u32::from_le_bytes(bodylen_b);
 
-    if bodylen == 0 {
-        return Ok(None);
+    match g {
+        Geometry::Point(p) => f(p.0),
+        Geometry::Line(l) => {
+            f(l.start);
+            f(l.end);
+        }
+        Geometry::LineString(ls) => ls.0.iter().copied().for_each(f),
+        Geometry::Polygon(p) => polygon(p, f),
+        Geometry::MultiPoint(mp) => mp.iter().for_each(|p| f(p.0)),
+        Geometry::MultiLineString(mls) => {
+            for ls in mls {
+                ls.0.iter().copied().for_each(&mut *f);
+            }
+        }
+        Geometry::MultiPolygon(mp) => mp.iter().for_each(|p| polygon(p, f)),
+        Geometry::GeometryCollection(gc) => gc.iter().for_each(|g| for_each_coord(g, f)),
+        Geometry::Rect(r) => {
+            let (min, max) = (r.min(), r.max());
+            f(min);
+            f(Coord { x: min.x, y: max.y });
+            f(max);
+            f(Coord { x: max.x, y: min.y });
+        }
+        Geometry::Triangle(t) => t.to_array().iter().copied().for_each(f),
     }
+}
 
-    let mut flags_b: [u8; 2] = [0; 2];
-    r.read(&mut flags_b).expect("Couldn't read flags");
-    assert_eq!(&flags_b, b"\0\0");
+pub(crate) fn coords_count(g: &geo_types::Geometry<f64>) -> usize {
+    let mut n = 0;
+    for_each_coord(g, &mut |_| n += 1);
+    n
+}
+
+/// The bounding box of `g`, `None` if it is empty.
+pub(crate) fn bounding_rect(g: &geo_types::Geometry<f64>) -> Option<geo_types::Rect<f64>> {
+    let mut rect: Option<geo_types::Rect<f64>> = None;
+    for_each_coord(g, &mut |c| {
+        rect = Some(match rect {
+            Some(r) => geo_types::Rect::new(
+                (r.min().x.min(c.x), r.min().y.min(c.y)),
+                (r.max().x.max(c.x), r.max().y.max(c.y)),
+            ),
+            None => geo_types::Rect::new(c, c),
+        })
+    });
+    rect
+}
 
-    let mut compression_b: [u8; 1] = [0; 1];
-    r.read(&mut compression_b)
-        .expect("Couldn't get compression flags");
-    assert_eq!(&compression_b, b"\0");
+fn encode_feature(ft: &Feature, order: Option<&[String]>) -> io::Result<fileformat::Feature> {
+    use fileformat::Feature_GeomType;
+    use geo_types::Geometry;
 
-    let mut messagetype_b: [u8; 1] = [0; 1];
-    r.read(&mut messagetype_b)
-        .expect("Couldn't get message type");
-    assert_eq!(&messagetype_b, b"\0");
+    // the wkb crate cannot encode these types directly
+    let converted;
+    let geometry = match &ft.geometry {
+        Geometry::Line(l) => {
+            converted = geo_types::LineString::from(*l).into();
+            &converted
+        }
+        Geometry::Rect(r) => {
+            converted = r.to_polygon().into();
+            &converted
+        }
+        Geometry::Triangle(t) => {
+            converted = t.to_polygon().into();
+            &converted
+        }
+        g => g,
+    };
 
-    let mut body = vec![0; bodylen as usize];
-    r.read(&mut body).expect("Body reading failed");
+    let mut out = fileformat::Feature::new();
+    out.geomtype = match geometry {
+        Geometry::Point(_) | Geometry::MultiPoint(_) => Feature_GeomType::POINT,
+        Geometry::LineString(_) | Geometry::MultiLineString(_) => Feature_GeomType::LINE,
+        Geometry::Polygon(_) | Geometry::MultiPolygon(_) => Feature_GeomType::POLYGON,
+        _ => Feature_GeomType::UNKNOWN,
+    };
+    out.geom = wkb::geom_to_wkb(geometry)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))?;
+    if let Some(r) = bounding_rect(geometry) {
+        out.left = r.min().x;
+        out.right = r.max().x;
+        out.bottom = r.min().y;
+        out.top = r.max().y;
+    }
 
-    return Ok(Some(body));
+    let mut keys: Vec<&String> = ft.tags.keys().collect();
+    match order {
+        Some(order) => keys.sort_by_key(|k| {
+            let i = order.iter().position(|o| o == *k);
+            (i.unwrap_or(order.len()), *k)
+        }),
+        None => keys.sort(),
+    }
+    for k in keys {
+        encode_tag(&mut out.tags, k, &ft.tags[k]);
+    }
+    Ok(out)
 }
 
-pub fn read_body(v: Vec<u8>) -> Vec<Feature> {
-    let body = fileformat::Body::parse_from_bytes(&v).unwrap();
-    let mut features = Vec::with_capacity(body.feature.len() as usize);
-
-    for ft in body.feature {
-        let mut bytes_cur = Cursor::new(ft.geom);
-        let g = bytes_cur.read_wkb().unwrap();
+fn encode_tag(tags: &mut protobuf::RepeatedField<fileformat::Tag>, key: &str, val: &Value) {
+    use fileformat::Tag_ValueType;
 
-        let mut tags = HashMap::with_capacity(ft.tags.len());
-        for tag in ft.tags {
-            tags.insert(tag.key, Value::from_bytes(tag.value, tag.field_type));
+    let (field_type, value) = match val {
+        Value::String(s) => (Tag_ValueType::STRING, s.as_bytes().to_vec()),
+        Value::Bytes(b) => (Tag_ValueType::STRING, b.clone()),
+        Value::Integer(i) => (Tag_ValueType::INT, i.to_le_bytes().to_vec()),
+        Value::Float(f) => (Tag_ValueType::DOUBLE, f.to_le_bytes().to_vec()),
+        Value::List(l) => {
+            for v in l {
+                encode_tag(tags, key, v);
+            }
+            return;
         }
+    };
+    let mut tag = fileformat::Tag::new();
+    tag.key = key.to_string();
+    tag.field_type = field_type;
+    tag.value = value;
+    tags.push(tag);
+}
 
-        let ft = Feature { geometry: g, tags };
-        features.push(ft);
-    }
-    features
+#[cfg(any(feature = "encryption", feature = "geojson"))]
+fn to_hex(b: &[u8]) -> String {
+    b.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
 #[cfg(test)]
@@ -153,6 +2036,7 @@ mod tests {
     use crate::FeatureIterator;
 
     #[test]
+    #[allow(deprecated)]
     fn file_header_test() {
         use crate::read_file_header;
         use std::io::Cursor;
@@ -162,6 +2046,91 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
+    fn legacy_api() {
+        use crate::sink::FeatureSink;
+        use crate::{read_block, read_body, read_file_header, Feature, FeatureWriter};
+
+        let mut w = FeatureWriter::new(Vec::new());
+        for _ in 0..3 {
+            w.accept(Feature {
+                geometry: geo_types::Point::new(1., 2.).into(),
+                tags: Default::default(),
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        for ft in FeatureIterator::new(&mut &buf[..]) {
+            assert!(ft.tags.is_empty());
+        }
+        assert_eq!(FeatureIterator::new(&mut &buf[..]).count(), 3);
+
+        let mut r = &buf[..];
+        read_file_header(&mut r);
+        let body = read_block(&mut r).unwrap().unwrap();
+        assert_eq!(read_body(body).len(), 3);
+        assert_eq!(read_block(&mut r), Ok(None));
+        assert_eq!(read_block(&mut &buf[8..10]), Err("Couldn't read block"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    #[should_panic(expected = "invalid file header")]
+    fn legacy_panics() {
+        FeatureIterator::new(&mut &b"SPAX"[..]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    #[should_panic(expected = "invalid feature")]
+    fn legacy_panics_on_blocks() {
+        FeatureIterator::new(&mut &b"SPAT\0\0\0\0\x05\0\0\0\0\0\0\0"[..]).count();
+    }
+
+    #[test]
+    fn errors() {
+        use crate::{try_read_block, try_read_file_header, Error};
+
+        assert!(matches!(
+            try_read_file_header(&mut &b"SPAX\0\0\0\0"[..]),
+            Err(Error::InvalidMagic)
+        ));
+        assert!(matches!(
+            try_read_file_header(&mut &b"SPAT\x01\0\0\0"[..]),
+            Err(Error::UnsupportedVersion(1))
+        ));
+        assert!(matches!(
+            try_read_file_header(&mut &b"SPAT\0"[..]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            try_read_block(&mut &b"\x05\0\0\0\0\0\0\0abc"[..]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            try_read_block(&mut &b"\x01\0\0\0\0\0\x02\0a"[..]),
+            Err(Error::UnsupportedBlock(_))
+        ));
+        assert!(matches!(
+            try_read_block(&mut &b"\x01\0\0\0\0\0\x01\0a"[..]),
+            Err(Error::Decompress(_))
+        ));
+
+        // iteration ends after the first error
+        let mut file = &b"SPAT\0\0\0\0\x03\0\0\0\0\0\0\0\xff\xff\xff"[..];
+        let mut it = FeatureIterator::try_new(&mut file).unwrap();
+        assert!(matches!(it.next(), Some(Err(Error::Protobuf(_)))));
+        assert!(it.next().is_none());
+        assert_eq!(it.metrics().snapshot().decode_errors, 1);
+
+        let e = std::io::Error::from(Error::Truncated);
+        assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn file_read_test() {
         use crate::read_block;
         use crate::read_body;
@@ -195,7 +2164,828 @@ mod tests {
         }
     }
 
+    fn body_with_tags(tags: &[(&str, crate::fileformat::Tag_ValueType, Vec<u8>)]) -> Vec<u8> {
+        use crate::fileformat;
+        use protobuf::Message;
+
+        let mut ft = fileformat::Feature::new();
+        ft.geom = wkb::geom_to_wkb(&geo_types::Point::new(1.0, 2.0).into()).unwrap();
+        for (k, t, v) in tags {
+            let mut tag = fileformat::Tag::new();
+            tag.key = k.to_string();
+            tag.value = v.clone();
+            tag.field_type = *t;
+            ft.tags.push(tag);
+        }
+        let mut body = fileformat::Body::new();
+        body.feature.push(ft);
+        body.write_to_bytes().unwrap()
+    }
+
+    fn file_with_blocks(bodies: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = b"SPAT\0\0\0\0".to_vec();
+        for body in bodies {
+            buf.extend((body.len() as u32).to_le_bytes());
+            buf.extend(b"\0\0\0\0");
+            buf.extend(body);
+        }
+        buf.extend(b"\0\0\0\0");
+        buf
+    }
+
+    #[test]
+    fn metrics() {
+        use crate::fileformat::Tag_ValueType::INT;
+        use std::io::Cursor;
+
+        let body = body_with_tags(&[("a", INT, 1i64.to_le_bytes().to_vec())]);
+        let mut file = Cursor::new(file_with_blocks(&[body.clone(), body.clone()]));
+        let mut it = FeatureIterator::try_new(&mut file).unwrap();
+        let metrics = it.metrics();
+        assert!(it.next().is_some());
+        assert_eq!(metrics.snapshot().blocks, 1);
+        assert_eq!(it.count(), 1);
+
+        let s = metrics.snapshot();
+        assert_eq!(s.blocks, 2);
+        assert_eq!(s.features, 2);
+        assert_eq!(s.bytes, 8 + 2 * (8 + body.len() as u64) + 4);
+        assert_eq!(s.decode_errors, 0);
+        assert!(metrics
+            .to_prometheus()
+            .contains("\nspaten_features_read_total 2\n"));
+        assert_eq!(s.stages, crate::metrics::StageTimings::default());
+
+        // a gzip compressed block, so that there is something to inflate
+        let mut w = crate::FeatureWriter::with_options(
+            Vec::new(),
+            crate::WriterOptions {
+                gzip_level: Some(6),
+                ..Default::default()
+            },
+        );
+        crate::sink::FeatureSink::accept(
+            &mut w,
+            crate::Feature {
+                geometry: geo_types::Point::new(1., 2.).into(),
+                tags: Default::default(),
+            },
+        )
+        .unwrap();
+        crate::sink::FeatureSink::finish(&mut w).unwrap();
+        let mut file = Cursor::new(w.into_inner());
+        let opts = crate::ReaderOptions {
+            instrument: true,
+            ..Default::default()
+        };
+        let it = FeatureIterator::with_options(&mut file, opts).unwrap();
+        let metrics = it.metrics();
+        assert_eq!(it.count(), 1);
+        // the stage timings depend on the clock, only their bounds and the report are checked
+        let s = metrics.snapshot();
+        assert!(s.stages.frame + s.stages.inflate + s.stages.protobuf <= s.elapsed);
+        assert!(s.to_string().contains("features:      1"));
+        assert!(s.to_string().contains("  inflate:     "));
+    }
+
+    #[test]
+    fn duplicate_tags() {
+        use crate::fileformat::Tag_ValueType::INT;
+        use crate::{read_body_with_options, DuplicateTags, ReaderOptions, Value};
+
+        let int = |k, v: i64| (k, INT, v.to_le_bytes().to_vec());
+        let body = body_with_tags(&[int("a", 1), int("b", 2), int("a", 3)]);
+        let read = |policy| {
+            let opts = ReaderOptions {
+                duplicate_tags: policy,
+                ..Default::default()
+            };
+            read_body_with_options(body.clone(), &opts)
+        };
+
+        let fts = read(DuplicateTags::FirstWins).unwrap();
+        assert!(matches!(fts[0].tags["a"], Value::Integer(1)));
+        let fts = read(DuplicateTags::LastWins).unwrap();
+        assert!(matches!(fts[0].tags["a"], Value::Integer(3)));
+        assert!(read(DuplicateTags::Error).is_err());
+        let fts = read(DuplicateTags::Collect).unwrap();
+        assert_eq!(format!("{:?}", fts[0].tags["a"]), "[1, 3]");
+        assert!(matches!(fts[0].tags["b"], Value::Integer(2)));
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        use crate::fileformat::Tag_ValueType::DOUBLE;
+        use crate::{read_body_with_options, NonFiniteFloats, ReaderOptions, Value};
+
+        let body = body_with_tags(&[
+            ("nan", DOUBLE, f64::NAN.to_le_bytes().to_vec()),
+            ("one", DOUBLE, 1f64.to_le_bytes().to_vec()),
+        ]);
+        let read = |policy| {
+            let opts = ReaderOptions {
+                non_finite_floats: policy,
+                ..Default::default()
+            };
+            read_body_with_options(body.clone(), &opts)
+        };
+        assert!(read(NonFiniteFloats::Reject).is_err());
+        let fts = read(NonFiniteFloats::Nullify).unwrap();
+        assert!(!fts[0].tags.contains_key("nan"));
+        assert_eq!(fts[0].tags["one"], Value::Float(1.0));
+        let fts = read(NonFiniteFloats::PassThrough).unwrap();
+        assert!(matches!(fts[0].tags["nan"], Value::Float(f) if f.is_nan()));
+    }
+
+    #[test]
+    fn tag_decoding() {
+        use crate::fileformat::Tag_ValueType::INT;
+        use crate::{read_body_with_options, ReaderOptions, TagDecoding, Value};
+
+        let body = body_with_tags(&[
+            ("bad", INT, vec![0; 3]),
+            ("good", INT, 7i64.to_le_bytes().to_vec()),
+        ]);
+        let read = |tag_decoding| {
+            let opts = ReaderOptions {
+                tag_decoding,
+                ..Default::default()
+            };
+            read_body_with_options(body.clone(), &opts)
+        };
+        assert!(read(TagDecoding::Strict).is_err());
+        let fts = read(TagDecoding::Lenient).unwrap();
+        assert!(!fts[0].tags.contains_key("bad"));
+        assert_eq!(fts[0].tags["good"], Value::Integer(7));
+    }
+
+    #[test]
+    fn integer_widths() {
+        use crate::fileformat::Tag_ValueType::{DOUBLE, INT};
+        use crate::{read_body_with_options, ReaderOptions, Value};
+
+        let body = body_with_tags(&[
+            ("i8", INT, (-2i8).to_le_bytes().to_vec()),
+            ("i16", INT, (-300i16).to_le_bytes().to_vec()),
+            ("i32", INT, (1i32 << 31).to_le_bytes().to_vec()),
+            ("i64", INT, (1i64 << 40).to_le_bytes().to_vec()),
+            ("f32", DOUBLE, 0.5f32.to_le_bytes().to_vec()),
+        ]);
+        let fts = read_body_with_options(body, &ReaderOptions::default()).unwrap();
+        assert_eq!(fts[0].tags["i8"], Value::Integer(-2));
+        assert_eq!(fts[0].tags["i16"], Value::Integer(-300));
+        assert_eq!(fts[0].tags["i32"], Value::Integer(i32::MIN as i64));
+        assert_eq!(fts[0].tags["i64"], Value::Integer(1 << 40));
+        assert_eq!(fts[0].tags["f32"], Value::Float(0.5));
+
+        for bad in [vec![], vec![0; 3], vec![0; 9]] {
+            let body = body_with_tags(&[("x", INT, bad.clone())]);
+            assert!(read_body_with_options(body, &ReaderOptions::default()).is_err());
+            let body = body_with_tags(&[("x", DOUBLE, bad)]);
+            assert!(read_body_with_options(body, &ReaderOptions::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn limits() {
+        use crate::fileformat::Tag_ValueType::{INT, STRING};
+        use crate::source::FeatureSource;
+        use crate::{read_body_with_options, Error, Limits, ReaderOptions};
+        use std::io::Cursor;
+
+        let body = body_with_tags(&[
+            ("a", INT, 1i64.to_le_bytes().to_vec()),
+            ("name", STRING, b"Main Street".to_vec()),
+        ]);
+        let read = |limits| {
+            let opts = ReaderOptions {
+                limits,
+                ..Default::default()
+            };
+            read_body_with_options(body.clone(), &opts)
+        };
+        assert!(read(Limits::hardened()).is_ok());
+        for limits in [
+            Limits {
+                max_tags: 1,
+                ..Limits::hardened()
+            },
+            Limits {
+                max_string_len: 4,
+                ..Limits::hardened()
+            },
+            Limits {
+                max_vertices: 0,
+                ..Limits::hardened()
+            },
+            Limits {
+                max_block_features: 0,
+                ..Limits::hardened()
+            },
+        ] {
+            assert!(read(limits).is_err());
+        }
+
+        let mut file = Cursor::new(file_with_blocks(&[body.clone(), body.clone()]));
+        let opts = ReaderOptions {
+            limits: Limits {
+                max_features: 1,
+                ..Limits::hardened()
+            },
+            ..Default::default()
+        };
+        let mut it = FeatureIterator::with_options(&mut file, opts).unwrap();
+        assert!(it.next_feature().unwrap().is_some());
+        assert!(it.next_feature().is_err());
+
+        // the oversized body must be refused before it is allocated
+        let mut file = b"SPAT\0\0\0\0\xff\xff\xff\xff\0\0\0\0".to_vec();
+        file.extend(&body);
+        let mut file = Cursor::new(file);
+        let mut it = FeatureIterator::with_options(&mut file, ReaderOptions::hardened()).unwrap();
+        assert!(it.next_feature().is_err());
+
+        // without limits, a forged length fails when the input runs out, not by allocating it
+        file.set_position(0);
+        let mut it = FeatureIterator::try_new(&mut file).unwrap();
+        assert!(matches!(it.next(), Some(Err(Error::Truncated))));
+    }
+
+    #[test]
+    fn parse_never_panics() {
+        use crate::fileformat::Tag_ValueType::{DOUBLE, STRING};
+        use crate::{fileformat, parse_block_body, parse_frame};
+        use geo_types::{polygon, Geometry, GeometryCollection, MultiPolygon};
+        use protobuf::Message;
+
+        let mut body = fileformat::Body::parse_from_bytes(&body_with_tags(&[
+            ("name", STRING, b"x".to_vec()),
+            ("f", DOUBLE, 1f64.to_le_bytes().to_vec()),
+        ]))
+        .unwrap();
+        let p = polygon![(x: 0., y: 0.), (x: 1., y: 0.), (x: 1., y: 1.), (x: 0., y: 0.)];
+        let g = Geometry::GeometryCollection(GeometryCollection(vec![
+            MultiPolygon::new(vec![p.clone()]).into(),
+            p.into(),
+        ]));
+        let mut ft = body.feature[0].clone();
+        ft.geom = wkb::geom_to_wkb(&g).unwrap();
+        body.feature.push(ft);
+        let file = file_with_blocks(&[body.write_to_bytes().unwrap()]);
+
+        let parse_all = |mut buf: &[u8]| {
+            while let Ok(Some(frame)) = parse_frame(buf) {
+                let _ = parse_block_body(frame.body);
+                buf = &buf[frame.len..];
+            }
+        };
+        parse_all(&file[8..]);
+        assert_eq!(
+            parse_block_body(parse_frame(&file[8..]).unwrap().unwrap().body)
+                .unwrap()
+                .len(),
+            2
+        );
+        for i in 8..file.len() {
+            parse_all(&file[8..i]);
+            for flip in [0x01, 0x80, 0xff] {
+                let mut buf = file.clone();
+                buf[i] ^= flip;
+                parse_all(&buf[8..]);
+            }
+        }
+
+        // nesting that would overflow the stack of a recursive decoder
+        let mut deep = Vec::new();
+        for geomtype in [5u8, 6, 7].iter() {
+            let mut buf = Vec::new();
+            for _ in 0..200_000 {
+                buf.extend([1, *geomtype, 0, 0, 0, 1, 0, 0, 0].iter());
+            }
+            deep.push(buf);
+        }
+        let mut gc = Vec::new();
+        for _ in 0..257 {
+            gc.extend([1, 7, 0, 0, 0, 1, 0, 0, 0].iter());
+        }
+        gc.extend([1, 1, 0, 0, 0].iter());
+        gc.extend([0; 16].iter());
+        deep.push(gc);
+        for geom in deep {
+            body.feature[1].geom = geom;
+            let buf = body.write_to_bytes().unwrap();
+            assert!(parse_block_body(&buf).is_err());
+        }
+    }
+
+    #[test]
+    fn write_round_trip() {
+        use crate::sink::FeatureSink;
+        use crate::source::copy;
+        use crate::{DuplicateTags, Feature, FeatureWriter, ReaderOptions, Value};
+        use geo_types::{line_string, polygon, Geometry, Line, MultiPoint, Rect};
+        use std::collections::HashMap;
+
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("Main".to_string()));
+        tags.insert("lanes".to_string(), Value::Integer(-2));
+        tags.insert("width".to_string(), Value::Float(7.5));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::String("A1".to_string()), Value::Integer(3)]),
+        );
+        let geoms: Vec<Geometry<f64>> = vec![
+            geo_types::Point::new(1., 2.).into(),
+            line_string![(x: 0., y: 0.), (x: 1., y: 2.)].into(),
+            polygon![(x: 0., y: 0.), (x: 1., y: 0.), (x: 1., y: 1.), (x: 0., y: 0.)].into(),
+            MultiPoint::from(vec![(1., 2.), (3., 4.)]).into(),
+        ];
+        let fts: Vec<Feature> = geoms
+            .iter()
+            .map(|g| Feature {
+                geometry: g.clone(),
+                tags: tags.clone(),
+            })
+            .collect();
+
+        let mut w = FeatureWriter::with_block_size(Vec::new(), 3);
+        copy(&mut fts.clone().into_iter(), &mut w).unwrap();
+        let buf = w.into_inner();
+
+        let opts = ReaderOptions {
+            duplicate_tags: DuplicateTags::Collect,
+            ..Default::default()
+        };
+        let mut file = &buf[..];
+        let it = FeatureIterator::with_options(&mut file, opts).unwrap();
+        let metrics = it.metrics();
+        let back: Vec<Feature> = it.collect::<Result<_, _>>().unwrap();
+        assert_eq!(metrics.snapshot().blocks, 2);
+        assert_eq!(back.len(), fts.len());
+        for (a, b) in back.iter().zip(&fts) {
+            assert_eq!(a.geometry, b.geometry);
+            assert_eq!(a.tags, b.tags);
+        }
+
+        // types without a WKB representation are converted
+        let mut w = FeatureWriter::new(Vec::new());
+        for g in [
+            Geometry::Line(Line::new((0., 0.), (1., 1.))),
+            Rect::new((0., 0.), (1., 1.)).into(),
+        ] {
+            w.accept(Feature {
+                geometry: g,
+                tags: HashMap::new(),
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+        let back: Vec<Feature> = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(matches!(back[0].geometry, Geometry::LineString(_)));
+        assert!(matches!(back[1].geometry, Geometry::Polygon(_)));
+
+        // an empty file still has header and terminator
+        let mut w = FeatureWriter::new(Vec::new());
+        w.finish().unwrap();
+        assert_eq!(w.into_inner(), b"SPAT\0\0\0\0\0\0\0\0");
+    }
+
+    #[test]
+    fn gzip_blocks() {
+        use crate::source::copy;
+        use crate::{
+            parse_block_body, parse_frame, read_block_with_options, try_read_block,
+            try_read_file_header, Compression, Error, Feature, FeatureWriter, Limits,
+            ReaderOptions, WriterOptions,
+        };
+        use std::collections::HashMap;
+
+        let fts: Vec<Feature> = (0..100)
+            .map(|i| Feature {
+                geometry: geo_types::Point::new(i as f64, 0.).into(),
+                tags: HashMap::new(),
+            })
+            .collect();
+        let write = |gzip_level| {
+            let mut w = FeatureWriter::with_options(
+                Vec::new(),
+                WriterOptions {
+                    block_size: 40,
+                    gzip_level,
+                    ..Default::default()
+                },
+            );
+            copy(&mut fts.clone().into_iter(), &mut w).unwrap();
+            w.into_inner()
+        };
+        let plain = write(None);
+        let buf = write(Some(9));
+        assert!(buf.len() < plain.len());
+
+        let back: Vec<Feature> = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(back.len(), 100);
+        assert_eq!(back[99].geometry, fts[99].geometry);
+
+        let mut r = &buf[..];
+        try_read_file_header(&mut r).unwrap();
+        assert_eq!(
+            parse_block_body(&try_read_block(&mut r).unwrap().unwrap())
+                .unwrap()
+                .len(),
+            40
+        );
+
+        let frame = parse_frame(&buf[8..]).unwrap().unwrap();
+        assert_eq!(frame.compression, Compression::Gzip);
+        assert!(parse_block_body(frame.body).is_err());
+        assert_eq!(
+            parse_block_body(&frame.decompress(&Limits::default()).unwrap())
+                .unwrap()
+                .len(),
+            40
+        );
+
+        // the limit applies to the decompressed body as well
+        let frame_len = frame.body.len() as u32;
+        let opts = ReaderOptions {
+            limits: Limits {
+                max_block_size: frame_len,
+                ..Limits::unlimited()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            frame.decompress(&opts.limits),
+            Err(Error::LimitExceeded(_))
+        ));
+        let mut r = &buf[8..];
+        assert!(matches!(
+            read_block_with_options(&mut r, &opts),
+            Err(Error::LimitExceeded(_))
+        ));
+        let mut file = &buf[..];
+        let mut it = FeatureIterator::with_options(&mut file, opts).unwrap();
+        assert!(matches!(it.next(), Some(Err(Error::LimitExceeded(_)))));
+    }
+
+    /// Delivers one byte per call and is interrupted every other call, like a slow socket.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            let n = buf.len().min(self.data.len()).min(1);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn short_reads() {
+        use crate::source::copy;
+        use crate::{Error, Feature, FeatureWriter};
+        use std::collections::HashMap;
+
+        let fts: Vec<Feature> = (0..10)
+            .map(|i| {
+                let mut tags = HashMap::new();
+                tags.insert("i".to_string(), crate::Value::Integer(i));
+                Feature {
+                    geometry: geo_types::Point::new(i as f64, 0.).into(),
+                    tags,
+                }
+            })
+            .collect();
+        let mut w = FeatureWriter::with_block_size(Vec::new(), 4);
+        copy(&mut fts.clone().into_iter(), &mut w).unwrap();
+        let buf = w.into_inner();
+
+        let mut r = Trickle {
+            data: &buf,
+            interrupt: false,
+        };
+        let back: Vec<Feature> = FeatureIterator::try_new(&mut r)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(back.len(), fts.len());
+        assert_eq!(back[9].tags, fts[9].tags);
+
+        // every cut within a block is reported, only cuts between blocks end cleanly
+        let first_block = 8 + 8 + u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize;
+        for cut in 9..first_block {
+            let mut r = Trickle {
+                data: &buf[..cut],
+                interrupt: false,
+            };
+            let mut it = FeatureIterator::try_new(&mut r).unwrap();
+            assert!(matches!(it.next(), Some(Err(Error::Truncated))), "{}", cut);
+        }
+        let mut file = &buf[..first_block];
+        assert_eq!(FeatureIterator::try_new(&mut file).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn raw_features() {
+        use crate::fileformat::Tag_ValueType::INT;
+        use crate::{fileformat, Error};
+        use protobuf::Message;
+
+        let body = body_with_tags(&[("a", INT, 7i64.to_le_bytes().to_vec())]);
+        let mut broken = fileformat::Body::parse_from_bytes(&body).unwrap();
+        broken.feature[0].geom = vec![1, 99, 0, 0, 0];
+        let broken = broken.write_to_bytes().unwrap();
+        let buf = file_with_blocks(&[body, broken]);
+
+        let mut file = &buf[..];
+        let it = FeatureIterator::try_new(&mut file).unwrap().raw();
+        let metrics = it.metrics();
+        let fts: Vec<_> = it.collect::<Result<_, _>>().unwrap();
+        assert_eq!(fts.len(), 2);
+        assert_eq!(metrics.snapshot().decode_errors, 0);
+        assert_eq!(fts[1].tags["a"], crate::Value::Integer(7));
+        assert!(matches!(fts[1].geometry(), Err(Error::InvalidGeometry(_))));
+        let ft = fts[0].clone().into_feature().unwrap();
+        assert_eq!(ft.geometry, geo_types::Point::new(1.0, 2.0).into());
+        assert_eq!(
+            wkb::geom_to_wkb(&ft.geometry).unwrap(),
+            fts[0].geometry_raw()
+        );
+
+        // decoding readers fail on the broken geometry and stop
+        let mut file = &buf[..];
+        let mut it = FeatureIterator::try_new(&mut file).unwrap();
+        assert!(it.next().unwrap().is_ok());
+        assert!(matches!(it.next(), Some(Err(Error::InvalidGeometry(_)))));
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "geo")]
+    fn bbox_filter() {
+        use crate::sink::FeatureSink;
+        use crate::{Feature, FeatureWriter};
+        use geo_types::{polygon, Point, Rect};
+
+        let mut w = FeatureWriter::new(Vec::new());
+        for geometry in [
+            polygon![(x: 0., y: 0.), (x: 4., y: 0.), (x: 0., y: 4.)].into(),
+            Point::new(10., 10.).into(),
+        ] {
+            w.accept(Feature {
+                geometry,
+                tags: Default::default(),
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        // the corner is within the triangle's bounding box, but not the triangle
+        let corner = Rect::new((3., 3.), (4., 4.));
+        let mut file = &buf[..];
+        let fts = FeatureIterator::try_new(&mut file)
+            .unwrap()
+            .with_bbox(corner);
+        assert_eq!(fts.count(), 0);
+        let mut file = &buf[..];
+        let fts = FeatureIterator::try_new(&mut file)
+            .unwrap()
+            .with_bbox(corner);
+        assert_eq!(fts.raw().count(), 1);
+        let mut file = &buf[..];
+        let fts = FeatureIterator::try_new(&mut file)
+            .unwrap()
+            .with_bbox(Rect::new((1., 1.), (20., 20.)));
+        assert_eq!(fts.count(), 2);
+
+        // without stored bounding boxes, the geometries decide
+        let buf = file_with_blocks(&[body_with_tags(&[])]);
+        for (bbox, n) in [
+            (Rect::new((0., 0.), (2., 2.)), 1),
+            (Rect::new((5., 5.), (6., 6.)), 0),
+        ] {
+            let mut file = &buf[..];
+            let fts = FeatureIterator::try_new(&mut file).unwrap().with_bbox(bbox);
+            assert_eq!(fts.count(), n);
+        }
+    }
+
+    #[test]
+    fn sync_and_pacing() {
+        use crate::sink::FeatureSink;
+        use crate::{Feature, FeatureWriter, SyncPolicy, WriterOptions};
+        use std::time::{Duration, Instant};
+
+        let ft = Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags: Default::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("synced.spaten");
+        let opts = WriterOptions {
+            block_size: 1,
+            sync: SyncPolicy::PerBlock,
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::create(&path, opts).unwrap();
+        for _ in 0..3 {
+            w.accept(ft.clone()).unwrap();
+        }
+        w.finish().unwrap();
+        let mut file = std::fs::File::open(&path).unwrap();
+        assert_eq!(FeatureIterator::try_new(&mut file).unwrap().count(), 3);
+
+        // every block takes 71 bytes, so 10 blocks at 7100 bytes/s take 0.1 s
+        let opts = WriterOptions {
+            block_size: 1,
+            max_bytes_per_sec: Some(7100),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        let start = Instant::now();
+        for _ in 0..10 {
+            w.accept(ft.clone()).unwrap();
+        }
+        w.finish().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(w.into_inner().len(), 8 + 10 * 71 + 4);
+    }
+
+    #[test]
+    fn value_ordering() {
+        use crate::Value;
+
+        let mut vals = vec![
+            Value::Float(f64::NAN),
+            Value::String("a".to_string()),
+            Value::Float(-1.0),
+            Value::Integer(3),
+            Value::Float(f64::INFINITY),
+        ];
+        vals.sort();
+        assert_eq!(format!("{:?}", vals), "[3, -1, inf, NaN, \"a\"]");
+        assert_eq!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+    }
+
+    #[test]
+    #[cfg(any(feature = "encryption", feature = "geojson"))]
+    fn hex() {
+        assert_eq!(crate::to_hex(&[0, 15, 255]), "000fff");
+    }
+
+    #[test]
+    fn typed_tags() {
+        use crate::{Feature, Value};
+        use std::convert::TryFrom;
+
+        let mut tags = std::collections::HashMap::new();
+        tags.insert("name".to_string(), Value::from("Rhein"));
+        tags.insert("width".to_string(), Value::from(1.5));
+        tags.insert("ref".to_string(), Value::List(vec![Value::from(1)]));
+        let ft = Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags,
+        };
+        assert_eq!(ft.get_f64("width"), Some(1.5));
+        assert_eq!(ft.get_i64("width"), None);
+        assert_eq!(ft.get_str("missing"), None);
+        assert_eq!(ft.get::<String>("name"), Some("Rhein".to_string()));
+        assert_eq!(ft.get::<&[Value]>("ref"), Some(&[Value::from(1)][..]));
+        assert!(i64::try_from(&ft.tags["name"]).is_err());
+    }
+
+    #[test]
+    fn raw_tag_order() {
+        use crate::sink::FeatureSink;
+        use crate::{Feature, FeatureIterator, FeatureWriter, Value, WriterOptions};
+
+        let mut tags = std::collections::HashMap::new();
+        tags.insert("name".to_string(), Value::from("Rhein"));
+        tags.insert("width".to_string(), Value::from(1.5));
+        tags.insert("ref".to_string(), Value::List(vec![Value::from(1)]));
+        let opts = WriterOptions {
+            tag_order: Some(vec!["width".to_string(), "name".to_string()]),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        w.accept(Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags,
+        })
+        .unwrap();
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let mut file = &buf[..];
+        let mut ft = FeatureIterator::try_new(&mut file)
+            .unwrap()
+            .raw()
+            .next()
+            .unwrap()
+            .unwrap();
+        let keys: Vec<&str> = ft.tags_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["width", "name", "ref"]);
+
+        ft.tags.remove("name");
+        ft.tags.insert("a".to_string(), Value::from(1));
+        let keys: Vec<&str> = ft.tags_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["width", "ref", "a"]);
+    }
+
+    #[test]
+    fn feature_ids() {
+        use crate::sink::FeatureSink;
+        use crate::{Feature, FeatureWriter, Value, ID_KEY};
+
+        let mut w = FeatureWriter::new(Vec::new());
+        for id in [Some(7), Some(u64::MAX), None] {
+            let mut ft = Feature {
+                geometry: geo_types::Point::new(1., 2.).into(),
+                tags: Default::default(),
+            };
+            if let Some(id) = id {
+                ft.set_id(id);
+            }
+            w.accept(ft).unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let ids: Vec<Option<u64>> = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .raw()
+            .map(|ft| ft.unwrap().id())
+            .collect();
+        assert_eq!(ids, [Some(7), Some(u64::MAX), None]);
+
+        let mut ft = Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags: Default::default(),
+        };
+        for v in [Value::Integer(-1), Value::from("w12"), Value::from(1.0)] {
+            ft.tags.insert(ID_KEY.to_string(), v);
+            assert_eq!(ft.id(), None);
+        }
+    }
+
+    #[test]
+    fn bytes_tags() {
+        use crate::sink::FeatureSink;
+        use crate::{Feature, FeatureWriter, Value};
+
+        let mut tags = std::collections::HashMap::new();
+        tags.insert("raw".to_string(), Value::from(vec![0xff, 0, b'a']));
+        tags.insert("text".to_string(), Value::from(b"abc".to_vec()));
+        let mut w = FeatureWriter::new(Vec::new());
+        w.accept(Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags,
+        })
+        .unwrap();
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let ft = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(ft.tags["raw"], Value::Bytes(vec![0xff, 0, b'a']));
+        assert_eq!(ft.tags["text"], Value::from("abc"));
+        assert_eq!(format!("{:?}", ft.tags["raw"]), r#"b"\xff\x00a""#);
+    }
+
+    #[test]
+    fn invalid_utf8_strings() {
+        use crate::fileformat::Tag_ValueType::STRING;
+        use crate::Value;
+
+        let body = body_with_tags(&[("name", STRING, vec![b'a', 0xff])]);
+        let buf = file_with_blocks(&[body]);
+        let fts: Vec<_> = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts[0].tags["name"], Value::Bytes(vec![b'a', 0xff]));
+    }
+
     #[test]
+    #[allow(deprecated)]
     fn stream_iterator() {
         use std::fs::File;
 
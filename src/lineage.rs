@@ -0,0 +1,200 @@
+//! Stamping features with provenance tags, e.g. for audits of data pipelines.
+//!
+//! A [`Lineage`] maps tag keys to templates. Placeholders in braces are replaced by variables
+//! set with [`var`](Lineage::var), such as the source file or the pipeline version, and by the
+//! built-in variables
+//!
+//! * `{timestamp}`: the import time as RFC 3339 UTC timestamp, fixed when the lineage is
+//!   created, see [`timestamp`](Lineage::timestamp),
+//! * `{index}`: the number of the feature within the stream, starting at 0,
+//! * `{spaten_version}`: the version of this library.
+//!
+//! Unknown placeholders are kept as they are. Lineage tags replace existing tags with the same
+//! key. Set as [`WriterOptions::lineage`](crate::WriterOptions::lineage), every written feature
+//! is stamped; [`Lineage::apply`] stamps any stream of features.
+//! ```
+//! use spaten::lineage::Lineage;
+//! use spaten::{Feature, Value};
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let lineage = Lineage::new()
+//!     .tag("src", "{source}#{index}")
+//!     .tag("imported", "{timestamp} by pipeline {pipeline}")
+//!     .var("source", "nrw.osm.pbf")
+//!     .var("pipeline", "1.4")
+//!     .timestamp(UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+//! let ft = Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! };
+//! let fts: Vec<Feature> = lineage.apply(vec![ft.clone(), ft]).collect();
+//! assert_eq!(fts[1].tags["src"], Value::from("nrw.osm.pbf#1"));
+//! assert_eq!(
+//!     fts[1].tags["imported"],
+//!     Value::from("2020-09-13T12:26:40Z by pipeline 1.4")
+//! );
+//! ```
+
+use crate::{Feature, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Provenance tags and the variables of their templates.
+#[derive(Clone, Debug)]
+pub struct Lineage {
+    templates: Vec<(String, String)>,
+    vars: HashMap<String, String>,
+}
+
+impl Default for Lineage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "spaten_version".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+        Lineage {
+            templates: Vec::new(),
+            vars,
+        }
+        .timestamp(SystemTime::now())
+    }
+
+    /// Adds the tag `key` with a value rendered from `template`.
+    pub fn tag(mut self, key: &str, template: &str) -> Self {
+        self.templates.push((key.to_string(), template.to_string()));
+        self
+    }
+
+    /// Sets the variable `name`, which replaces `{name}` in templates.
+    pub fn var(mut self, name: &str, value: &str) -> Self {
+        self.vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Overrides the import time, e.g. to make the output of a pipeline reproducible.
+    pub fn timestamp(self, t: SystemTime) -> Self {
+        let t = rfc3339(t);
+        self.var("timestamp", &t)
+    }
+
+    /// Adds the lineage tags to `ft`, the `index`th feature of its stream.
+    pub fn stamp(&self, ft: &mut Feature, index: u64) {
+        for (key, template) in &self.templates {
+            let value = self.render(template, index);
+            ft.tags.insert(key.clone(), Value::String(value));
+        }
+    }
+
+    /// Stamps every feature of `fts`.
+    pub fn apply<'a>(
+        &'a self,
+        fts: impl IntoIterator<Item = Feature> + 'a,
+    ) -> impl Iterator<Item = Feature> + 'a {
+        fts.into_iter().zip(0..).map(move |(mut ft, i)| {
+            self.stamp(&mut ft, i);
+            ft
+        })
+    }
+
+    fn render(&self, template: &str, index: u64) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = match rest.find('}') {
+                Some(end) => end,
+                None => break,
+            };
+            let name = &rest[1..end];
+            match self.vars.get(name) {
+                Some(v) => out.push_str(v),
+                None if name == "index" => out.push_str(&index.to_string()),
+                None => out.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Formats `t` as RFC 3339 UTC timestamp with second precision. Times before 1970 are clamped.
+fn rfc3339(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rfc3339, Lineage};
+    use crate::sink::FeatureSink;
+    use crate::{Feature, FeatureIterator, FeatureWriter, Value, WriterOptions};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn timestamps() {
+        let at = |secs| rfc3339(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(4_107_542_399), "2100-02-28T23:59:59Z");
+        assert_eq!(rfc3339(UNIX_EPOCH - Duration::from_secs(1)), at(0));
+    }
+
+    #[test]
+    fn writer() {
+        let lineage = Lineage::new()
+            .tag("source", "{file}")
+            .tag("n", "{index}/{unknown}/{")
+            .var("file", "a.csv");
+        let opts = WriterOptions {
+            lineage: Some(lineage),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        for _ in 0..3 {
+            let mut tags = std::collections::HashMap::new();
+            tags.insert("source".to_string(), Value::from("replaced"));
+            w.accept(Feature {
+                geometry: geo_types::Point::new(1., 2.).into(),
+                tags,
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let fts: Vec<Feature> = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts[0].tags["source"], Value::from("a.csv"));
+        assert_eq!(fts[2].tags["n"], Value::from("2/{unknown}/{"));
+    }
+}
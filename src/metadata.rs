@@ -0,0 +1,228 @@
+//! Dataset metadata, such as the producer and the creation time of a file.
+//!
+//! Metadata is stored in blocks of its own message type, which hold a block meta with tags
+//! instead of features. [`FeatureWriter::write_metadata`](crate::FeatureWriter::write_metadata)
+//! writes such a block, usually before the first feature. Readers skip metadata blocks when they
+//! look for features; [`FeatureIterator::metadata`](crate::FeatureIterator::metadata) returns
+//! the metadata read so far, and [`read_any_block`](crate::read_any_block) returns the blocks
+//! of either type.
+//!
+//! Several metadata blocks can occur in a file, each one replaces the previous ones.
+//! ```
+//! use spaten::metadata::Metadata;
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureIterator, FeatureWriter, Value};
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let mut meta = Metadata {
+//!     producer: Some("importer 1.4".to_string()),
+//!     timestamp: Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+//!     ..Default::default()
+//! };
+//! meta.custom.insert("license".to_string(), Value::from("ODbL"));
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! w.write_metadata(&meta)?;
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! })?;
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let mut file = &buf[..];
+//! let mut fts = FeatureIterator::try_new(&mut file)?;
+//! assert!(fts.metadata().is_none());
+//! assert!(fts.next().is_some());
+//! assert_eq!(fts.metadata(), Some(&meta));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{encode_tag, fileformat, insert_tag, DuplicateTags, Error, Value};
+use protobuf::Message;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Meta tag with the name and version of the software that wrote the file.
+pub const PRODUCER_KEY: &str = "spaten:producer";
+/// Meta tag with the creation time of the file, in seconds since the Unix epoch.
+pub const TIMESTAMP_KEY: &str = "spaten:timestamp";
+
+/// The metadata of a dataset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    /// The software that wrote the file.
+    pub producer: Option<String>,
+    /// When the file was written. Stored with a precision of seconds.
+    pub timestamp: Option<SystemTime>,
+    /// Further key-values, e.g. a license or the source of the data.
+    pub custom: HashMap<String, Value>,
+}
+
+impl Metadata {
+    /// Metadata with this library as producer and the current time as timestamp.
+    pub fn now() -> Self {
+        Metadata {
+            producer: Some(format!("spaten {}", env!("CARGO_PKG_VERSION"))),
+            timestamp: Some(SystemTime::now()),
+            custom: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn to_meta(&self) -> fileformat::Meta {
+        let mut meta = fileformat::Meta::new();
+        if let Some(producer) = &self.producer {
+            encode_tag(
+                &mut meta.tags,
+                PRODUCER_KEY,
+                &Value::from(producer.as_str()),
+            );
+        }
+        if let Some(ts) = self.timestamp {
+            let secs = match ts.duration_since(UNIX_EPOCH) {
+                Ok(d) => d.as_secs() as i64,
+                Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+            };
+            encode_tag(&mut meta.tags, TIMESTAMP_KEY, &Value::Integer(secs));
+        }
+        let mut keys: Vec<&String> = self.custom.keys().collect();
+        keys.sort();
+        for k in keys {
+            encode_tag(&mut meta.tags, k, &self.custom[k]);
+        }
+        meta
+    }
+
+    /// Decodes the body of a metadata block.
+    pub(crate) fn decode(body: &[u8]) -> Result<Self, Error> {
+        let meta = fileformat::Meta::parse_from_bytes(body)?;
+        let mut tags = HashMap::with_capacity(meta.tags.len());
+        for tag in meta.tags {
+            let val = Value::from_bytes(tag.value, tag.field_type).map_err(Error::InvalidTag)?;
+            insert_tag(&mut tags, tag.key, val, DuplicateTags::Collect)
+                .map_err(Error::InvalidTag)?;
+        }
+        let producer = match tags.remove(PRODUCER_KEY) {
+            Some(Value::String(s)) => Some(s),
+            None => None,
+            Some(_) => return Err(Error::InvalidTag("Producer is not a string")),
+        };
+        let timestamp = match tags.remove(TIMESTAMP_KEY) {
+            Some(Value::Integer(secs)) if secs >= 0 => {
+                Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+            }
+            Some(Value::Integer(secs)) => {
+                Some(UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()))
+            }
+            None => None,
+            Some(_) => return Err(Error::InvalidTag("Timestamp is not an integer")),
+        };
+        Ok(Metadata {
+            producer,
+            timestamp,
+            custom: tags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metadata;
+    use crate::sink::FeatureSink;
+    use crate::{
+        read_any_block, try_read_block, try_read_file_header, Block, FeatureWriter, Value,
+    };
+    use crate::{Feature, FeatureIterator, WriterOptions};
+    use protobuf::Message;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn round_trip() {
+        let mut meta = Metadata {
+            producer: Some("test".to_string()),
+            timestamp: Some(UNIX_EPOCH - Duration::from_secs(86400)),
+            ..Default::default()
+        };
+        meta.custom.insert(
+            "sources".to_string(),
+            Value::List(vec![Value::from("a"), Value::from("b")]),
+        );
+        meta.custom.insert("version".to_string(), Value::from(3));
+        let buf = meta.to_meta().write_to_bytes().unwrap();
+        assert_eq!(Metadata::decode(&buf).unwrap(), meta);
+
+        let now = Metadata::now();
+        let back = Metadata::decode(&now.to_meta().write_to_bytes().unwrap()).unwrap();
+        assert_eq!(back.producer, now.producer);
+        assert!(
+            now.timestamp
+                .unwrap()
+                .duration_since(back.timestamp.unwrap())
+                .unwrap()
+                < Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let pt = Feature {
+            geometry: geo_types::Point::new(7.0, 51.0).into(),
+            tags: Default::default(),
+        };
+        let first = Metadata {
+            producer: Some("first".to_string()),
+            ..Default::default()
+        };
+        let second = Metadata {
+            producer: Some("second".to_string()),
+            ..Default::default()
+        };
+        let opts = WriterOptions {
+            gzip_level: Some(6),
+            block_size: 1,
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        w.write_metadata(&first).unwrap();
+        w.accept(pt.clone()).unwrap();
+        w.write_metadata(&second).unwrap();
+        w.accept(pt).unwrap();
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let mut r = &buf[..];
+        try_read_file_header(&mut r).unwrap();
+        assert_eq!(
+            read_any_block(&mut r).unwrap(),
+            Some(Block::Meta(first.clone()))
+        );
+        assert!(matches!(read_any_block(&mut r), Ok(Some(Block::Body(_)))));
+
+        let mut r = &buf[8..];
+        assert!(try_read_block(&mut r).unwrap().is_some());
+        assert!(try_read_block(&mut r).unwrap().is_some());
+        assert!(try_read_block(&mut r).unwrap().is_none());
+
+        let mut r = &buf[..];
+        let mut fts = FeatureIterator::try_new(&mut r).unwrap();
+        fts.next().unwrap().unwrap();
+        assert_eq!(fts.metadata(), Some(&first));
+        fts.next().unwrap().unwrap();
+        assert_eq!(fts.metadata(), Some(&second));
+        assert!(fts.next().is_none());
+
+        let mut w = FeatureWriter::new(Vec::new());
+        w.write_metadata(&Metadata::default()).unwrap();
+        w.accept(Feature {
+            geometry: geo_types::Point::new(7.0, 51.0).into(),
+            tags: Default::default(),
+        })
+        .unwrap();
+        w.finish().unwrap();
+        let buf = w.into_inner();
+        let mut r = &buf[..];
+        let mut fts = FeatureIterator::try_new(&mut r).unwrap();
+        assert!(fts.next().unwrap().is_ok());
+        assert_eq!(fts.metadata(), Some(&Metadata::default()));
+    }
+}
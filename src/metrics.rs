@@ -0,0 +1,258 @@
+//! Progress and throughput counters of a reader.
+//!
+//! The counters are updated atomically while reading, so they can be observed from another
+//! thread, e.g. by a server exposing them to Prometheus.
+//!
+//! For performance tuning, per-stage timings can be enabled with
+//! [`ReaderOptions::instrument`](crate::ReaderOptions::instrument), and heap usage can be
+//! tracked by installing [`CountingAllocator`] as the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// The steps of decoding a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading the block frame and body from the stream.
+    Frame,
+    /// Decrypting and decompressing the block body.
+    Inflate,
+    /// Parsing the protobuf message.
+    Protobuf,
+    /// Decoding geometries.
+    Wkb,
+    /// Decoding tag values.
+    Tags,
+}
+
+const STAGES: [Stage; 5] = [
+    Stage::Frame,
+    Stage::Inflate,
+    Stage::Protobuf,
+    Stage::Wkb,
+    Stage::Tags,
+];
+
+/// Time spent in each [`Stage`]. Only recorded when instrumentation is enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageTimings {
+    pub frame: Duration,
+    pub inflate: Duration,
+    pub protobuf: Duration,
+    pub wkb: Duration,
+    pub tags: Duration,
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    blocks: AtomicU64,
+    bytes: AtomicU64,
+    features: AtomicU64,
+    decode_errors: AtomicU64,
+    stage_nanos: [AtomicU64; 5],
+    started: Instant,
+}
+
+/// A copy of the counters. Each counter is read on its own, so while a reader is running they
+/// may be slightly out of step, e.g. `blocks` may already count a block whose features are not
+/// in `features` yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub blocks: u64,
+    pub bytes: u64,
+    pub features: u64,
+    pub decode_errors: u64,
+    pub elapsed: Duration,
+    pub stages: StageTimings,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            blocks: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            features: AtomicU64::new(0),
+            decode_errors: AtomicU64::new(0),
+            stage_nanos: Default::default(),
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn add_block(&self, bytes: u64) {
+        self.blocks.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_features(&self, n: u64) {
+        self.features.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_stage(&self, stage: Stage, d: Duration) {
+        let i = STAGES.iter().position(|s| *s == stage).unwrap();
+        self.stage_nanos[i].fetch_add(d.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn stage(&self, stage: Stage) -> Duration {
+        let i = STAGES.iter().position(|s| *s == stage).unwrap();
+        Duration::from_nanos(self.stage_nanos[i].load(Ordering::Relaxed))
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            blocks: self.blocks.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            features: self.features.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            elapsed: self.started.elapsed(),
+            stages: StageTimings {
+                frame: self.stage(Stage::Frame),
+                inflate: self.stage(Stage::Inflate),
+                protobuf: self.stage(Stage::Protobuf),
+                wkb: self.stage(Stage::Wkb),
+                tags: self.stage(Stage::Tags),
+            },
+        }
+    }
+
+    /// Renders the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let s = self.snapshot();
+        let mut out = String::new();
+        for (name, help, kind, val) in [
+            (
+                "spaten_blocks_read_total",
+                "Blocks read.",
+                "counter",
+                s.blocks as f64,
+            ),
+            (
+                "spaten_bytes_read_total",
+                "Bytes read.",
+                "counter",
+                s.bytes as f64,
+            ),
+            (
+                "spaten_features_read_total",
+                "Features decoded.",
+                "counter",
+                s.features as f64,
+            ),
+            (
+                "spaten_decode_errors_total",
+                "Blocks that failed to decode.",
+                "counter",
+                s.decode_errors as f64,
+            ),
+            (
+                "spaten_elapsed_seconds",
+                "Time since the reader was opened.",
+                "gauge",
+                s.elapsed.as_secs_f64(),
+            ),
+        ] {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+            writeln!(out, "{} {}", name, val).unwrap();
+        }
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders a human readable report, e.g. to be printed after a scan.
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "blocks:        {}", self.blocks)?;
+        writeln!(f, "bytes:         {}", self.bytes)?;
+        writeln!(f, "features:      {}", self.features)?;
+        writeln!(f, "decode errors: {}", self.decode_errors)?;
+        writeln!(f, "elapsed:       {:?}", self.elapsed)?;
+        writeln!(f, "  frame:       {:?}", self.stages.frame)?;
+        writeln!(f, "  inflate:     {:?}", self.stages.inflate)?;
+        writeln!(f, "  protobuf:    {:?}", self.stages.protobuf)?;
+        writeln!(f, "  wkb:         {:?}", self.stages.wkb)?;
+        write!(f, "  tags:        {:?}", self.stages.tags)
+    }
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// A global allocator that counts allocations on top of the system allocator.
+/// ```no_run
+/// use spaten::metrics::{allocation_stats, CountingAllocator};
+///
+/// #[global_allocator]
+/// static ALLOC: CountingAllocator = CountingAllocator;
+///
+/// // ... scan a file ...
+/// println!("{:?}", allocation_stats());
+/// ```
+pub struct CountingAllocator;
+
+/// Heap usage as counted by [`CountingAllocator`] since program start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocationStats {
+    pub allocations: u64,
+    pub allocated_bytes: u64,
+    /// Largest amount of memory that was allocated at the same time, an estimate of the peak
+    /// resident set size caused by heap usage.
+    pub peak_bytes: u64,
+}
+
+pub fn allocation_stats() -> AllocationStats {
+    AllocationStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+fn count_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc(layout);
+        if !p.is_null() {
+            count_alloc(layout.size());
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size() as u64, Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let p = System.realloc(ptr, layout, new_size);
+        if !p.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size() as u64, Ordering::Relaxed);
+            count_alloc(new_size);
+        }
+        p
+    }
+}
@@ -0,0 +1,515 @@
+//! Reading local files in place through a memory map (requires the `mmap` feature).
+//!
+//! [`MmapReader`] maps the file and locates the blocks in the map, like [`parse_frame`] does.
+//! The features are decoded lazily from the block bodies into [`FeatureRef`]s, whose WKB and tag
+//! strings are slices of the map. So reading allocates nothing per block or feature, while
+//! [`FeatureIterator`](crate::FeatureIterator) copies every block into a buffer and every tag
+//! into a map.
+//!
+//! Only uncompressed and unencrypted blocks can be read in place, others are refused with
+//! [`Error::UnsupportedBlock`] and have to be read with a `FeatureIterator`.
+//! ```
+//! use spaten::mmap::{MmapReader, ValueRef};
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureWriter, Value, WriterOptions};
+//! use std::collections::HashMap;
+//!
+//! let dir = tempfile::tempdir()?;
+//! let path = dir.path().join("places.spaten");
+//! let mut w = FeatureWriter::create(&path, WriterOptions::default())?;
+//! let mut tags = HashMap::new();
+//! tags.insert("name".to_string(), Value::from("Bonn"));
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.1, 50.7).into(),
+//!     tags,
+//! })?;
+//! w.finish()?;
+//!
+//! // the file is not modified while it is mapped
+//! let reader = unsafe { MmapReader::open(&path)? };
+//! for ft in reader.features() {
+//!     let ft = ft?;
+//!     assert_eq!(ft.get("name"), Some(ValueRef::String("Bonn")));
+//!     assert_eq!(ft.geometry()?, geo_types::Point::new(7.1, 50.7).into());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`parse_frame`]: crate::parse_frame
+
+use crate::fileformat::Tag_ValueType;
+use crate::{
+    float_from_bytes, insert_tag, int_from_bytes, parse_frame, try_read_file_header, wkbfast,
+    Compression, DuplicateTags, Error, Feature, Limits, Value,
+};
+use geo_types::{coord, Rect};
+use memmap2::Mmap;
+use protobuf::error::{ProtobufError, WireError};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::path::Path;
+
+/// A Spaten file mapped into memory.
+pub struct MmapReader {
+    map: Mmap,
+    limits: Limits,
+}
+
+impl MmapReader {
+    /// Maps the file at `path` and checks its header.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, by this or any other
+    /// process, as the features borrow from the map.
+    pub unsafe fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::open_with_limits(path, Limits::default())
+    }
+
+    /// Like [`open`](MmapReader::open), but the features are checked against `limits` as they
+    /// are read.
+    ///
+    /// # Safety
+    ///
+    /// See [`open`](MmapReader::open).
+    pub unsafe fn open_with_limits(path: impl AsRef<Path>, limits: Limits) -> Result<Self, Error> {
+        let map = Mmap::map(&File::open(path)?)?;
+        try_read_file_header(&mut &map[..])?;
+        Ok(MmapReader { map, limits })
+    }
+
+    /// The mapped file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Iterates over the features in file order.
+    pub fn features(&self) -> Features<'_> {
+        Features {
+            rest: &self.map[8..],
+            body: &[],
+            done: false,
+            limits: &self.limits,
+            features: 0,
+            block_features: 0,
+        }
+    }
+}
+
+/// Iterator returned by [`MmapReader::features`]. Stops after the first error.
+pub struct Features<'a> {
+    /// The blocks that have not been started yet.
+    rest: &'a [u8],
+    /// The remaining fields of the current block body.
+    body: &'a [u8],
+    done: bool,
+    limits: &'a Limits,
+    features: u64,
+    block_features: usize,
+}
+
+impl<'a> Features<'a> {
+    fn read(&mut self) -> Result<Option<FeatureRef<'a>>, Error> {
+        loop {
+            while !self.body.is_empty() {
+                if let (2, Field::Bytes(ft)) = next_field(&mut self.body)? {
+                    self.features += 1;
+                    self.block_features += 1;
+                    if self.features > self.limits.max_features {
+                        return Err(Error::LimitExceeded("Feature count limit exceeded"));
+                    }
+                    if self.block_features > self.limits.max_block_features {
+                        return Err(Error::LimitExceeded("Block feature count limit exceeded"));
+                    }
+                    return FeatureRef::parse(ft, self.limits).map(Some);
+                }
+            }
+            let frame = match parse_frame(self.rest)? {
+                Some(frame) => frame,
+                None => return Ok(None),
+            };
+            if frame.meta {
+                self.rest = &self.rest[frame.len..];
+                continue;
+            }
+            if frame.encrypted || frame.flags != 0 || frame.compression != Compression::None {
+                return Err(Error::UnsupportedBlock(
+                    "Compressed or encrypted blocks cannot be read in place",
+                ));
+            }
+            if frame.body.len() > self.limits.max_block_size as usize {
+                return Err(Error::LimitExceeded("Block size limit exceeded"));
+            }
+            self.body = frame.body;
+            self.block_features = 0;
+            self.rest = &self.rest[frame.len..];
+        }
+    }
+}
+
+impl<'a> Iterator for Features<'a> {
+    type Item = Result<FeatureRef<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let ft = self.read().transpose();
+        self.done = !matches!(ft, Some(Ok(_)));
+        ft
+    }
+}
+
+/// A tag value borrowed from the map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    String(&'a str),
+    Integer(i64),
+    Float(f64),
+    /// A string value that is not valid UTF-8.
+    Bytes(&'a [u8]),
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(v: ValueRef<'_>) -> Value {
+        match v {
+            ValueRef::String(s) => Value::String(s.to_string()),
+            ValueRef::Integer(i) => Value::Integer(i),
+            ValueRef::Float(f) => Value::Float(f),
+            ValueRef::Bytes(b) => Value::Bytes(b.to_vec()),
+        }
+    }
+}
+
+/// A feature borrowed from the map. The message is checked when the feature is read, the
+/// geometry only when it is decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct FeatureRef<'a> {
+    msg: &'a [u8],
+    geom: &'a [u8],
+    bbox: [f64; 4],
+}
+
+impl<'a> FeatureRef<'a> {
+    fn parse(msg: &'a [u8], limits: &Limits) -> Result<Self, Error> {
+        let mut ft = FeatureRef {
+            msg,
+            geom: &[],
+            bbox: [0.; 4],
+        };
+        let mut fields = msg;
+        let mut tags = 0;
+        while !fields.is_empty() {
+            match next_field(&mut fields)? {
+                (1..=2, Field::Varint(_)) => {}
+                (3, Field::Bytes(geom)) => ft.geom = geom,
+                (n @ 4..=7, Field::Fixed64(v)) => ft.bbox[n as usize - 4] = f64::from_le_bytes(v),
+                (8, Field::Bytes(tag)) => {
+                    tags += 1;
+                    if tags > limits.max_tags {
+                        return Err(Error::LimitExceeded("Tag count limit exceeded"));
+                    }
+                    let (key, value) = parse_tag(tag)?;
+                    let len = match value {
+                        ValueRef::String(s) => s.len(),
+                        ValueRef::Bytes(b) => b.len(),
+                        _ => 0,
+                    };
+                    if key.len().max(len) > limits.max_string_len {
+                        return Err(Error::LimitExceeded("String length limit exceeded"));
+                    }
+                }
+                (1..=8, _) => return Err(wire_error(WireError::Other)),
+                _ => {}
+            }
+        }
+        if *limits != Limits::unlimited()
+            && wkbfast::inspect(ft.geom, limits.max_nesting).map_err(Error::InvalidGeometry)?
+                > limits.max_vertices
+        {
+            return Err(Error::LimitExceeded("Vertex count limit exceeded"));
+        }
+        Ok(ft)
+    }
+
+    /// The geometry as stored in the file, in WKB.
+    pub fn geometry_raw(&self) -> &'a [u8] {
+        self.geom
+    }
+
+    pub fn geometry(&self) -> Result<geo_types::Geometry<f64>, Error> {
+        wkbfast::decode(self.geom).map_err(Error::InvalidGeometry)
+    }
+
+    /// The bounding box stored in the file.
+    pub fn bbox(&self) -> Rect<f64> {
+        let [left, right, top, bottom] = self.bbox;
+        Rect::new(coord! { x: left, y: bottom }, coord! { x: right, y: top })
+    }
+
+    /// Iterates over the tags in file order. Keys that occur several times are returned
+    /// several times.
+    pub fn tags(&self) -> Tags<'a> {
+        Tags { fields: self.msg }
+    }
+
+    /// The first value of `key`.
+    pub fn get(&self, key: &str) -> Option<ValueRef<'a>> {
+        self.tags().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Copies the feature, handling keys that occur several times according to `duplicates`.
+    pub fn to_feature(&self, duplicates: DuplicateTags) -> Result<Feature, Error> {
+        let mut tags = HashMap::new();
+        for (k, v) in self.tags() {
+            insert_tag(&mut tags, k.to_string(), v.into(), duplicates)
+                .map_err(Error::InvalidTag)?;
+        }
+        Ok(Feature {
+            geometry: self.geometry()?,
+            tags,
+        })
+    }
+}
+
+/// Iterator returned by [`FeatureRef::tags`].
+pub struct Tags<'a> {
+    fields: &'a [u8],
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = (&'a str, ValueRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the message was checked by FeatureRef::parse, so there are no errors here
+        while !self.fields.is_empty() {
+            match next_field(&mut self.fields) {
+                Ok((8, Field::Bytes(tag))) => return parse_tag(tag).ok(),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        None
+    }
+}
+
+fn parse_tag(mut fields: &[u8]) -> Result<(&str, ValueRef<'_>), Error> {
+    let (mut key, mut value, mut ty) = (&[][..], &[][..], 0);
+    while !fields.is_empty() {
+        match next_field(&mut fields)? {
+            (1, Field::Bytes(k)) => key = k,
+            (2, Field::Bytes(v)) => value = v,
+            (3, Field::Varint(t)) => ty = t,
+            (1..=3, _) => return Err(wire_error(WireError::Other)),
+            _ => {}
+        }
+    }
+    let key = std::str::from_utf8(key).map_err(|_| wire_error(WireError::Utf8Error))?;
+    let value = match ty {
+        t if t == Tag_ValueType::STRING as u64 => match std::str::from_utf8(value) {
+            Ok(s) => ValueRef::String(s),
+            Err(_) => ValueRef::Bytes(value),
+        },
+        t if t == Tag_ValueType::INT as u64 => {
+            ValueRef::Integer(int_from_bytes(value).map_err(Error::InvalidTag)?)
+        }
+        t if t == Tag_ValueType::DOUBLE as u64 => {
+            ValueRef::Float(float_from_bytes(value).map_err(Error::InvalidTag)?)
+        }
+        t => return Err(wire_error(WireError::InvalidEnumValue(t as i32))),
+    };
+    Ok((key, value))
+}
+
+/// A field of a protobuf message, by wire type.
+enum Field<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+fn wire_error(e: WireError) -> Error {
+    Error::Protobuf(ProtobufError::WireError(e))
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8], Error> {
+    if buf.len() < n {
+        return Err(wire_error(WireError::UnexpectedEof));
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(head)
+}
+
+fn varint(buf: &mut &[u8]) -> Result<u64, Error> {
+    let mut v = 0;
+    for i in 0..10 {
+        let b = take(buf, 1)?[0];
+        v |= u64::from(b & 0x7f) << (7 * i);
+        if b < 0x80 {
+            return Ok(v);
+        }
+    }
+    Err(wire_error(WireError::IncorrectVarint))
+}
+
+/// Reads the next field number and value from `buf`.
+fn next_field<'a>(buf: &mut &'a [u8]) -> Result<(u64, Field<'a>), Error> {
+    let key = varint(buf)?;
+    let field = match key & 7 {
+        0 => Field::Varint(varint(buf)?),
+        1 => {
+            let mut v = [0; 8];
+            v.copy_from_slice(take(buf, 8)?);
+            Field::Fixed64(v)
+        }
+        2 => {
+            let len =
+                usize::try_from(varint(buf)?).map_err(|_| wire_error(WireError::UnexpectedEof))?;
+            Field::Bytes(take(buf, len)?)
+        }
+        5 => {
+            take(buf, 4)?;
+            Field::Fixed32
+        }
+        _ => return Err(wire_error(WireError::Other)),
+    };
+    Ok((key >> 3, field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeatureRef, MmapReader, ValueRef};
+    use crate::sink::FeatureSink;
+    use crate::{
+        DuplicateTags, Error, Feature, FeatureIterator, FeatureWriter, Limits, Value, WriterOptions,
+    };
+    use geo_types::line_string;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    fn write(opts: WriterOptions) -> (tempfile::NamedTempFile, Vec<Feature>) {
+        let fts: Vec<Feature> = (0..5)
+            .map(|i| {
+                let mut tags = HashMap::new();
+                tags.insert("i".to_string(), Value::Integer(i));
+                tags.insert("name".to_string(), Value::from(format!("n{}", i)));
+                tags.insert("raw".to_string(), Value::Bytes(vec![0xff, i as u8]));
+                tags.insert(
+                    "refs".to_string(),
+                    Value::List(vec![Value::from(0.5), Value::from(1.5)]),
+                );
+                let x = i as f64;
+                Feature {
+                    geometry: line_string![(x: x, y: 0.), (x: x + 1., y: 2.)].into(),
+                    tags,
+                }
+            })
+            .collect();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut w = FeatureWriter::create(file.path(), opts).unwrap();
+        for ft in fts.clone() {
+            w.accept(ft).unwrap();
+        }
+        w.finish().unwrap();
+        (file, fts)
+    }
+
+    #[test]
+    fn read_in_place() {
+        let opts = WriterOptions {
+            block_size: 2,
+            ..Default::default()
+        };
+        let (file, fts) = write(opts);
+        let reader = unsafe { MmapReader::open(file.path()) }.unwrap();
+        let map = reader.as_bytes().as_ptr_range();
+        let read: Vec<FeatureRef<'_>> = reader.features().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read.len(), 5);
+
+        let ft = &read[3];
+        assert!(map.contains(&ft.geometry_raw().as_ptr()));
+        match ft.get("name") {
+            Some(ValueRef::String(s)) => assert!(map.contains(&s.as_ptr())),
+            v => panic!("unexpected value {:?}", v),
+        }
+        assert_eq!(ft.get("raw"), Some(ValueRef::Bytes(&[0xff, 3])));
+        assert_eq!(ft.get("missing"), None);
+        let refs: Vec<ValueRef<'_>> = ft
+            .tags()
+            .filter(|(k, _)| *k == "refs")
+            .map(|(_, v)| v)
+            .collect();
+        assert_eq!(refs, [ValueRef::Float(0.5), ValueRef::Float(1.5)]);
+        assert_eq!(ft.bbox(), geo_types::Rect::new((3., 0.), (4., 2.)));
+
+        let copied = ft.to_feature(DuplicateTags::Collect).unwrap();
+        assert_eq!(copied.geometry, fts[3].geometry);
+        assert_eq!(copied.tags, fts[3].tags);
+        let mut f = std::fs::File::open(file.path()).unwrap();
+        let streamed: Vec<Feature> = FeatureIterator::try_new(&mut f)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for (a, b) in read.iter().zip(streamed) {
+            assert_eq!(a.to_feature(DuplicateTags::LastWins).unwrap().tags, b.tags);
+        }
+    }
+
+    #[test]
+    fn refused() {
+        let opts = WriterOptions {
+            gzip_level: Some(1),
+            ..Default::default()
+        };
+        let (file, _) = write(opts);
+        let reader = unsafe { MmapReader::open(file.path()) }.unwrap();
+        let mut fts = reader.features();
+        assert!(matches!(fts.next(), Some(Err(Error::UnsupportedBlock(_)))));
+        assert!(fts.next().is_none());
+
+        let (file, _) = write(WriterOptions::default());
+        let len = std::fs::metadata(file.path()).unwrap().len();
+        file.as_file().set_len(len - 10).unwrap();
+        let reader = unsafe { MmapReader::open(file.path()) }.unwrap();
+        assert!(matches!(
+            reader.features().last(),
+            Some(Err(Error::Truncated))
+        ));
+
+        let (file, _) = write(WriterOptions::default());
+        for limits in [
+            Limits {
+                max_block_size: 100,
+                ..Limits::unlimited()
+            },
+            Limits {
+                max_features: 4,
+                ..Limits::unlimited()
+            },
+            Limits {
+                max_tags: 3,
+                ..Limits::unlimited()
+            },
+            Limits {
+                max_vertices: 1,
+                ..Limits::unlimited()
+            },
+        ] {
+            let reader = unsafe { MmapReader::open_with_limits(file.path(), limits) }.unwrap();
+            assert!(matches!(
+                reader.features().last(),
+                Some(Err(Error::LimitExceeded(_)))
+            ));
+        }
+
+        let mut other = tempfile::NamedTempFile::new().unwrap();
+        other.write_all(b"not spaten").unwrap();
+        assert!(matches!(
+            unsafe { MmapReader::open(other.path()) },
+            Err(Error::InvalidMagic)
+        ));
+    }
+}
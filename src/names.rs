@@ -0,0 +1,68 @@
+//! Selection of localized display names.
+
+use crate::{Feature, Value};
+
+/// Returns the value of the first tag in `preferences` that is present on the feature and
+/// holds a non-empty string, e.g. with `["name:de", "name:en", "name"]`.
+pub fn resolve<'a>(ft: &'a Feature, preferences: &[&str]) -> Option<&'a str> {
+    preferences.iter().find_map(|key| match ft.tags.get(*key) {
+        Some(Value::String(s)) if !s.is_empty() => Some(s.as_str()),
+        _ => None,
+    })
+}
+
+/// Rewrites the `name` tag of every feature to the best name according to `preferences`.
+/// Features without any matching name are passed through unchanged.
+/// ```
+/// use spaten::names::localize;
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("name".to_string(), Value::String("Köln".to_string()));
+/// tags.insert("name:en".to_string(), Value::String("Cologne".to_string()));
+/// let ft = Feature {
+///     geometry: geo_types::Point::new(6.96, 50.94).into(),
+///     tags,
+/// };
+///
+/// let ft = localize(vec![ft].into_iter(), &["name:en", "name"]).next().unwrap();
+/// assert_eq!(ft.tags["name"], Value::String("Cologne".to_string()));
+/// ```
+pub fn localize<'a, I: Iterator<Item = Feature> + 'a>(
+    fts: I,
+    preferences: &'a [&'a str],
+) -> impl Iterator<Item = Feature> + 'a {
+    fts.map(move |mut ft| {
+        if let Some(name) = resolve(&ft, preferences) {
+            let name = name.to_string();
+            ft.tags.insert("name".to_string(), Value::String(name));
+        }
+        ft
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve;
+    use crate::{Feature, Value};
+    use std::collections::HashMap;
+
+    #[test]
+    fn resolve_preferences() {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::String("Bruxelles".to_string()));
+        tags.insert("name:de".to_string(), Value::String(String::new()));
+        tags.insert("name:nl".to_string(), Value::String("Brussel".to_string()));
+        let ft = Feature {
+            geometry: geo_types::Point::new(4.35, 50.85).into(),
+            tags,
+        };
+        assert_eq!(
+            resolve(&ft, &["name:de", "name:nl", "name"]),
+            Some("Brussel")
+        );
+        assert_eq!(resolve(&ft, &["name:de", "name"]), Some("Bruxelles"));
+        assert_eq!(resolve(&ft, &["name:fr"]), None);
+    }
+}
@@ -0,0 +1,531 @@
+//! Converting OpenStreetMap PBF extracts to Spaten (requires the `osmpbf` feature).
+//!
+//! The extract is read in a single pass, relying on the usual order of nodes before ways before
+//! relations, each sorted by id. Features are written as soon as they are assembled:
+//!
+//! * tagged nodes become points,
+//! * tagged ways become lines, or polygons if they are closed and look like areas, see
+//!   [`is_area`],
+//! * multipolygon and boundary relations become multipolygons, if
+//!   [`OsmOptions::multipolygons`] is set.
+//!
+//! The locations of all nodes are written to a temporary file to assemble the ways, so memory
+//! use does not grow with the size of the extract. Only the node lists of all ways, needed to
+//! assemble the relations, are kept in memory. Ways with nodes that are missing from the extract
+//! are skipped. Relations are assembled from the ways that are present, or skipped if no closed
+//! ring is left.
+//!
+//! OSM tags are written as string tags. With [`OsmOptions::ids`], features additionally get
+//! the tags `@type` (`node`, `way` or `relation`) and `@id`, like `osmium export` writes them.
+//! ```no_run
+//! use spaten::osm::{osm_to_spaten, OsmOptions};
+//! use std::fs::File;
+//! use std::io::{BufReader, BufWriter};
+//!
+//! let input = BufReader::new(File::open("nrw.osm.pbf")?);
+//! let output = BufWriter::new(File::create("nrw.spaten")?);
+//! let n = osm_to_spaten(input, output, OsmOptions::default())?;
+//! println!("{} features", n);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::sink::FeatureSink;
+use crate::{Feature, FeatureWriter, Value, WriterOptions, ID_KEY};
+use geo::Contains;
+use geo_types::{Coord, LineString, MultiPolygon, Point, Polygon};
+use osmpbf::{Element, ElementReader, RelMemberType};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// Keys that make a closed way an area, unless it is tagged `area=no`.
+pub const AREA_KEYS: &[&str] = &[
+    "amenity", "building", "landuse", "leisure", "natural", "place", "shop", "tourism",
+];
+
+#[derive(Clone, Debug)]
+pub struct OsmOptions {
+    pub writer: WriterOptions,
+    /// Assemble multipolygon and boundary relations. Requires keeping the node lists of all ways
+    /// in memory.
+    pub multipolygons: bool,
+    /// Add the `@type` and `@id` tags.
+    pub ids: bool,
+    /// Directory for the temporary file with the node locations.
+    pub temp_dir: PathBuf,
+}
+
+impl Default for OsmOptions {
+    fn default() -> Self {
+        OsmOptions {
+            writer: WriterOptions::default(),
+            multipolygons: true,
+            ids: true,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Whether a closed way with `tags` is a polygon rather than a line.
+pub fn is_area(tags: &HashMap<String, Value>) -> bool {
+    match tags.get("area") {
+        Some(Value::String(v)) if v == "no" => false,
+        Some(Value::String(v)) if v == "yes" => true,
+        _ => AREA_KEYS.iter().any(|k| tags.contains_key(*k)),
+    }
+}
+
+/// Reads the OSM PBF extract `input` and writes its features as Spaten file to `output`.
+/// Returns the number of written features.
+pub fn osm_to_spaten<R: io::Read + Send, W: io::Write>(
+    input: R,
+    output: W,
+    opts: OsmOptions,
+) -> io::Result<u64> {
+    let mut w = FeatureWriter::with_options(output, opts.writer.clone());
+    let mut a = Assembler::new(&opts)?;
+    let mut written = 0;
+    let mut result = Ok(());
+    ElementReader::new(input).for_each(|el| {
+        if result.is_err() {
+            return;
+        }
+        let ft = match el {
+            Element::Node(n) => a.node(n.id(), n.lon(), n.lat(), osm_tags(n.tags())),
+            Element::DenseNode(n) => a.node(n.id(), n.lon(), n.lat(), osm_tags(n.tags())),
+            Element::Way(way) => a.way(way.id(), way.refs().collect(), osm_tags(way.tags())),
+            Element::Relation(r) => {
+                let ways = r
+                    .members()
+                    .filter(|m| m.member_type == RelMemberType::Way)
+                    .map(|m| (m.member_id, m.role().unwrap_or("").to_string()))
+                    .collect();
+                a.relation(r.id(), ways, osm_tags(r.tags()))
+            }
+        };
+        result = match ft {
+            Ok(Some(ft)) => w.accept(ft).map(|()| written += 1),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+    })?;
+    result?;
+    w.finish()?;
+    Ok(written)
+}
+
+fn osm_tags<'a>(tags: impl Iterator<Item = (&'a str, &'a str)>) -> HashMap<String, Value> {
+    tags.map(|(k, v)| (k.to_string(), Value::from(v))).collect()
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Number of node locations per page of the [`NodeStore`] file.
+const PAGE_NODES: usize = 4096;
+/// Number of pages the [`NodeStore`] keeps in memory.
+const CACHED_PAGES: usize = 64;
+/// Size of a node location in the file: the id and both coordinates.
+const NODE_LEN: usize = 24;
+
+/// The locations of all nodes in a temporary file, sorted by id. Only the first id of every page
+/// and the most recently used pages are kept in memory.
+struct NodeStore {
+    /// Set until the first lookup, after which no more nodes can be added.
+    writer: Option<BufWriter<File>>,
+    reader: Option<File>,
+    len: usize,
+    last: Option<i64>,
+    first_ids: Vec<i64>,
+    cache: VecDeque<(usize, Vec<(i64, Coord)>)>,
+}
+
+impl NodeStore {
+    fn new(opts: &OsmOptions) -> io::Result<Self> {
+        let f = tempfile::tempfile_in(&opts.temp_dir)?;
+        Ok(NodeStore {
+            writer: Some(BufWriter::new(f)),
+            reader: None,
+            len: 0,
+            last: None,
+            first_ids: Vec::new(),
+            cache: VecDeque::new(),
+        })
+    }
+
+    fn insert(&mut self, id: i64, c: Coord) -> io::Result<()> {
+        let w = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| invalid_data("Nodes must come before ways and relations"))?;
+        if self.last.is_some_and(|last| id <= last) {
+            return Err(invalid_data("Nodes must be sorted by id"));
+        }
+        if self.len.is_multiple_of(PAGE_NODES) {
+            self.first_ids.push(id);
+        }
+        w.write_all(&id.to_le_bytes())?;
+        w.write_all(&c.x.to_bits().to_le_bytes())?;
+        w.write_all(&c.y.to_bits().to_le_bytes())?;
+        self.last = Some(id);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn page(&mut self, i: usize) -> io::Result<&[(i64, Coord)]> {
+        if let Some(w) = self.writer.take() {
+            self.reader = Some(w.into_inner().map_err(|e| e.into_error())?);
+        }
+        match self.cache.iter().position(|(p, _)| *p == i) {
+            Some(pos) => {
+                let page = self.cache.remove(pos).unwrap();
+                self.cache.push_back(page);
+            }
+            None => {
+                let f = self.reader.as_mut().unwrap();
+                let n = PAGE_NODES.min(self.len - i * PAGE_NODES);
+                let mut buf = vec![0; n * NODE_LEN];
+                f.seek(SeekFrom::Start((i * PAGE_NODES * NODE_LEN) as u64))?;
+                f.read_exact(&mut buf)?;
+                let field = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap());
+                let page = buf
+                    .chunks_exact(NODE_LEN)
+                    .map(|b| {
+                        let c = Coord {
+                            x: f64::from_bits(field(&b[8..16])),
+                            y: f64::from_bits(field(&b[16..24])),
+                        };
+                        (field(&b[..8]) as i64, c)
+                    })
+                    .collect();
+                if self.cache.len() == CACHED_PAGES {
+                    self.cache.pop_front();
+                }
+                self.cache.push_back((i, page));
+            }
+        }
+        Ok(&self.cache.back().unwrap().1)
+    }
+
+    fn get(&mut self, id: i64) -> io::Result<Option<Coord>> {
+        let i = match self.first_ids.partition_point(|&first| first <= id) {
+            0 => return Ok(None),
+            i => i - 1,
+        };
+        let page = self.page(i)?;
+        Ok(page
+            .binary_search_by_key(&id, |(id, _)| *id)
+            .ok()
+            .map(|j| page[j].1))
+    }
+
+    /// The locations of `refs`, `None` if any of them is missing.
+    fn coords(&mut self, refs: &[i64]) -> io::Result<Option<Vec<Coord>>> {
+        let mut coords = Vec::with_capacity(refs.len());
+        for r in refs {
+            match self.get(*r)? {
+                Some(c) => coords.push(c),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(coords))
+    }
+}
+
+/// Assembles features from elements in file order.
+struct Assembler {
+    ids: bool,
+    multipolygons: bool,
+    nodes: NodeStore,
+    ways: HashMap<i64, Vec<i64>>,
+}
+
+impl Assembler {
+    fn new(opts: &OsmOptions) -> io::Result<Self> {
+        Ok(Assembler {
+            ids: opts.ids,
+            multipolygons: opts.multipolygons,
+            nodes: NodeStore::new(opts)?,
+            ways: HashMap::new(),
+        })
+    }
+
+    fn feature(
+        &self,
+        kind: &str,
+        id: i64,
+        geometry: geo_types::Geometry<f64>,
+        mut tags: HashMap<String, Value>,
+    ) -> Feature {
+        if self.ids {
+            tags.insert("@type".to_string(), Value::from(kind));
+            tags.insert(ID_KEY.to_string(), Value::Integer(id));
+        }
+        Feature { geometry, tags }
+    }
+
+    fn node(
+        &mut self,
+        id: i64,
+        lon: f64,
+        lat: f64,
+        tags: HashMap<String, Value>,
+    ) -> io::Result<Option<Feature>> {
+        self.nodes.insert(id, Coord { x: lon, y: lat })?;
+        if tags.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.feature(
+            "node",
+            id,
+            Point::new(lon, lat).into(),
+            tags,
+        )))
+    }
+
+    fn way(
+        &mut self,
+        id: i64,
+        refs: Vec<i64>,
+        tags: HashMap<String, Value>,
+    ) -> io::Result<Option<Feature>> {
+        let coords = if tags.is_empty() {
+            None
+        } else {
+            self.nodes.coords(&refs)?
+        };
+        if self.multipolygons {
+            self.ways.insert(id, refs);
+        }
+        let coords = match coords {
+            Some(coords) if coords.len() >= 2 => coords,
+            _ => return Ok(None),
+        };
+        let line = LineString(coords);
+        let geometry = if line.is_closed() && line.0.len() > 3 && is_area(&tags) {
+            Polygon::new(line, vec![]).into()
+        } else {
+            line.into()
+        };
+        Ok(Some(self.feature("way", id, geometry, tags)))
+    }
+
+    fn relation(
+        &mut self,
+        id: i64,
+        ways: Vec<(i64, String)>,
+        mut tags: HashMap<String, Value>,
+    ) -> io::Result<Option<Feature>> {
+        let multipolygon = match tags.get("type") {
+            Some(Value::String(t)) if t == "multipolygon" => true,
+            Some(Value::String(t)) if t == "boundary" => false,
+            _ => return Ok(None),
+        };
+        if !self.multipolygons {
+            return Ok(None);
+        }
+        let (mut outer, mut inner) = (Vec::new(), Vec::new());
+        for (way, role) in ways {
+            let coords = match self.ways.get(&way) {
+                Some(refs) => match self.nodes.coords(refs)? {
+                    Some(coords) => coords,
+                    None => continue,
+                },
+                None => continue,
+            };
+            if role == "inner" {
+                inner.push(coords);
+            } else {
+                outer.push(coords);
+            }
+        }
+        let mut polygons: Vec<Polygon<f64>> = rings(outer)
+            .into_iter()
+            .map(|r| Polygon::new(r, vec![]))
+            .collect();
+        if polygons.is_empty() {
+            return Ok(None);
+        }
+        for ring in rings(inner) {
+            let p = Point(ring.0[0]);
+            if let Some(outer) = polygons.iter_mut().find(|o| o.contains(&p)) {
+                outer.interiors_push(ring);
+            }
+        }
+        if multipolygon {
+            tags.remove("type");
+        }
+        let geometry = MultiPolygon(polygons).into();
+        Ok(Some(self.feature("relation", id, geometry, tags)))
+    }
+}
+
+/// Joins ways end to end into closed rings. Ways that do not end up in a closed ring are dropped.
+fn rings(mut ways: Vec<Vec<Coord>>) -> Vec<LineString<f64>> {
+    ways.retain(|w| w.len() >= 2);
+    let mut out = Vec::new();
+    while let Some(mut ring) = ways.pop() {
+        while ring.first() != ring.last() {
+            let end = ring[ring.len() - 1];
+            let next = match ways
+                .iter()
+                .position(|w| w[0] == end || w[w.len() - 1] == end)
+            {
+                Some(i) => ways.swap_remove(i),
+                None => break,
+            };
+            if next[0] == end {
+                ring.extend(next.into_iter().skip(1));
+            } else {
+                ring.extend(next.into_iter().rev().skip(1));
+            }
+        }
+        if ring.len() > 3 && ring.first() == ring.last() {
+            out.push(LineString(ring));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Assembler, NodeStore, OsmOptions, PAGE_NODES};
+    use crate::Value;
+    use geo_types::{Coord, Geometry, LineString};
+    use std::collections::HashMap;
+
+    fn tags(kv: &[(&str, &str)]) -> HashMap<String, Value> {
+        kv.iter()
+            .map(|(k, v)| (k.to_string(), Value::from(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn assemble() {
+        let mut a = Assembler::new(&OsmOptions::default()).unwrap();
+        // outer square 1-4 split into two ways, inner square 5-8 as one way
+        let square = [(0., 0.), (4., 0.), (4., 4.), (0., 4.)];
+        let hole = [(1., 1.), (2., 1.), (2., 2.), (1., 2.)];
+        for (i, (x, y)) in square.iter().chain(hole.iter()).enumerate() {
+            assert!(a.node(i as i64 + 1, *x, *y, tags(&[])).unwrap().is_none());
+        }
+        let poi = a
+            .node(9, 3., 3., tags(&[("amenity", "bench")]))
+            .unwrap()
+            .unwrap();
+        assert!(a.node(9, 3., 3., tags(&[])).is_err());
+        assert_eq!(poi.geometry, geo_types::Point::new(3., 3.).into());
+        assert_eq!(poi.tags["@type"], Value::from("node"));
+        assert_eq!(poi.tags["@id"], Value::Integer(9));
+        assert_eq!(poi.id(), Some(9));
+
+        assert!(a.way(10, vec![1, 2, 3], tags(&[])).unwrap().is_none());
+        let line = a
+            .way(11, vec![3, 4, 1], tags(&[("highway", "path")]))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(line.geometry, Geometry::LineString(_)));
+        let area = a
+            .way(12, vec![5, 6, 7, 8, 5], tags(&[("building", "yes")]))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(area.geometry, Geometry::Polygon(_)));
+        let no_area = a
+            .way(
+                13,
+                vec![5, 6, 7, 8, 5],
+                tags(&[("building", "yes"), ("area", "no")]),
+            )
+            .unwrap()
+            .unwrap();
+        assert!(matches!(no_area.geometry, Geometry::LineString(_)));
+        assert!(a
+            .way(14, vec![99, 1], tags(&[("highway", "path")]))
+            .unwrap()
+            .is_none());
+        // not stitched across the gap
+        assert!(a
+            .way(15, vec![1, 99, 2], tags(&[("highway", "path")]))
+            .unwrap()
+            .is_none());
+        assert!(a.node(100, 0., 0., tags(&[])).is_err());
+
+        let members = vec![
+            (12, "inner".to_string()),
+            (10, "outer".to_string()),
+            (11, "outer".to_string()),
+            (404, "outer".to_string()),
+        ];
+        let mp = a
+            .relation(
+                20,
+                members.clone(),
+                tags(&[("type", "multipolygon"), ("landuse", "forest")]),
+            )
+            .unwrap()
+            .unwrap();
+        assert!(!mp.tags.contains_key("type"));
+        assert_eq!(mp.tags["@type"], Value::from("relation"));
+        match mp.geometry {
+            Geometry::MultiPolygon(mp) => {
+                assert_eq!(mp.0.len(), 1);
+                assert_eq!(mp.0[0].exterior().0.len(), 5);
+                assert_eq!(mp.0[0].interiors().len(), 1);
+            }
+            g => panic!("unexpected geometry {:?}", g),
+        }
+        assert!(a
+            .relation(21, members, tags(&[("type", "route")]))
+            .unwrap()
+            .is_none());
+        assert!(a
+            .relation(
+                22,
+                vec![(11, "outer".to_string())],
+                tags(&[("type", "boundary")])
+            )
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn node_store() {
+        let mut nodes = NodeStore::new(&OsmOptions::default()).unwrap();
+        let n = 3 * PAGE_NODES as i64 + 5;
+        for id in 0..n {
+            let c = Coord {
+                x: id as f64,
+                y: -id as f64,
+            };
+            nodes.insert(2 * id + 1, c).unwrap();
+        }
+        for id in (0..n).rev().step_by(97).chain(0..n) {
+            let c = nodes.get(2 * id + 1).unwrap().unwrap();
+            assert_eq!((c.x, c.y), (id as f64, -id as f64));
+            assert!(nodes.get(2 * id).unwrap().is_none());
+        }
+        assert!(nodes.get(2 * n + 1).unwrap().is_none());
+        assert!(nodes.get(i64::MIN).unwrap().is_none());
+
+        let mut nodes = NodeStore::new(&OsmOptions::default()).unwrap();
+        nodes.insert(2, Coord::zero()).unwrap();
+        assert!(nodes.insert(1, Coord::zero()).is_err());
+    }
+
+    #[test]
+    fn rings() {
+        let ls = |c: &[(f64, f64)]| LineString::from(c.to_vec()).0;
+        let rings = super::rings(vec![
+            ls(&[(0., 0.), (1., 0.)]),
+            ls(&[(1., 1.), (1., 0.)]),
+            ls(&[(1., 1.), (0., 0.)]),
+            ls(&[(5., 5.), (6., 6.)]),
+        ]);
+        assert_eq!(rings.len(), 1);
+        assert!(rings[0].is_closed());
+        assert_eq!(rings[0].0.len(), 4);
+    }
+}
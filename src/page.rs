@@ -0,0 +1,374 @@
+//! Resumable pagination over seekable Spaten files.
+//!
+//! A [`PageCursor`] points at a block offset and a position within that block, so a page can
+//! be served by seeking directly to the right block instead of re-reading the file from the
+//! start. Cursors can be passed to web clients as strings.
+//!
+//! An [`IndexedReader`] goes one step further: it scans the file once and then serves any block
+//! or range of features directly, e.g. to jump to page 50 without requesting pages 1 to 49.
+
+use crate::{
+    decode_features, fileformat, open_block, read_body_with_options, read_raw_block,
+    try_read_block, try_read_file_header, Error, Feature, ReaderOptions,
+};
+use protobuf::Message;
+use std::fmt;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// The position of the next feature to be returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageCursor {
+    /// Byte offset of the block that contains the next feature.
+    pub offset: u64,
+    /// Number of features of that block that have already been returned.
+    pub skip: usize,
+}
+
+impl PageCursor {
+    /// Points at the very first feature of a file.
+    pub fn start() -> Self {
+        PageCursor { offset: 0, skip: 0 }
+    }
+}
+
+impl Default for PageCursor {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+/// Formats the cursor as `offset:skip`.
+impl fmt::Display for PageCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.offset, self.skip)
+    }
+}
+
+impl FromStr for PageCursor {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (offset, skip) = s.split_once(':').ok_or("Invalid page cursor")?;
+        Ok(PageCursor {
+            offset: offset.parse().map_err(|_| "Invalid page cursor offset")?,
+            skip: skip.parse().map_err(|_| "Invalid page cursor position")?,
+        })
+    }
+}
+
+/// Pages are collected in a buffer of at most this many features at first, since the page size
+/// usually comes from a client.
+const MAX_PREALLOC: usize = 1024;
+
+/// Returns up to `page_size` features starting at `cursor`, and the cursor of the following
+/// page, or `None` if the end of the file has been reached. Fails if `page_size` is 0.
+/// ```
+/// use spaten::page::{page, PageCursor};
+/// use std::io::Cursor;
+///
+/// let mut file = Cursor::new(b"SPAT\0\0\0\0\0\0\0\0".to_vec());
+/// let (fts, next) = page(&mut file, PageCursor::start(), 100).unwrap();
+/// assert!(fts.is_empty());
+/// assert_eq!(next, None);
+/// ```
+pub fn page<R: Read + Seek>(
+    r: &mut R,
+    cursor: PageCursor,
+    page_size: usize,
+) -> io::Result<(Vec<Feature>, Option<PageCursor>)> {
+    if page_size == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Page size must not be 0",
+        ));
+    }
+    r.seek(SeekFrom::Start(cursor.offset))?;
+    if cursor.offset == 0 {
+        try_read_file_header(r)?;
+    }
+
+    let mut skip = cursor.skip;
+    let mut out = Vec::with_capacity(page_size.min(MAX_PREALLOC));
+    loop {
+        let offset = r.stream_position()?;
+        let body = match try_read_block(r)? {
+            Some(body) => body,
+            None => return Ok((out, None)),
+        };
+        let fts = read_body_with_options(body, &ReaderOptions::default())?;
+        let total = fts.len();
+        let take = (page_size - out.len()).min(total.saturating_sub(skip));
+        out.extend(fts.into_iter().skip(skip).take(take));
+
+        if out.len() == page_size {
+            let next = if skip + take < total {
+                PageCursor {
+                    offset,
+                    skip: skip + take,
+                }
+            } else {
+                PageCursor {
+                    offset: r.stream_position()?,
+                    skip: 0,
+                }
+            };
+            return Ok((out, Some(next)));
+        }
+        skip = 0;
+    }
+}
+
+/// Position and size of a block, as recorded by [`IndexedReader`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Byte offset of the block.
+    pub offset: u64,
+    /// Number of features in the block.
+    pub features: usize,
+}
+
+/// Random access to the blocks and features of a seekable file.
+/// ```
+/// use spaten::page::IndexedReader;
+/// use spaten::sink::FeatureSink;
+/// use spaten::{Feature, FeatureWriter};
+/// use std::io::Cursor;
+///
+/// let mut w = FeatureWriter::with_block_size(Vec::new(), 10);
+/// for i in 0..95 {
+///     w.accept(Feature {
+///         geometry: geo_types::Point::new(f64::from(i), 0.).into(),
+///         tags: Default::default(),
+///     })?;
+/// }
+/// w.finish()?;
+///
+/// let mut r = IndexedReader::new(Cursor::new(w.into_inner()))?;
+/// assert_eq!(r.blocks().len(), 10);
+/// assert_eq!(r.len(), 95);
+/// let fts = r.features(42..45)?;
+/// assert_eq!(fts[0].geometry, geo_types::Point::new(42., 0.).into());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct IndexedReader<R> {
+    r: R,
+    blocks: Vec<BlockInfo>,
+    /// Index of the first feature of every block, followed by the number of features.
+    starts: Vec<u64>,
+    /// Byte offset of the terminating block.
+    end: u64,
+    options: ReaderOptions,
+}
+
+impl<R: Read + Seek> IndexedReader<R> {
+    /// Reads the file once to record the offset and feature count of every block.
+    pub fn new(r: R) -> Result<Self, Error> {
+        Self::with_options(r, ReaderOptions::default())
+    }
+
+    /// Like [`new`](IndexedReader::new), but decodes according to `options`.
+    pub fn with_options(mut r: R, options: ReaderOptions) -> Result<Self, Error> {
+        r.seek(SeekFrom::Start(0))?;
+        try_read_file_header(&mut r)?;
+        let mut blocks = Vec::new();
+        let mut starts = Vec::new();
+        let mut total = 0;
+        let end = loop {
+            let offset = r.stream_position()?;
+            let body = match Self::read_body(&mut r, &options)? {
+                Some(body) => body,
+                None => break offset,
+            };
+            blocks.push(BlockInfo {
+                offset,
+                features: body.feature.len(),
+            });
+            starts.push(total);
+            total += body.feature.len() as u64;
+        };
+        starts.push(total);
+        Ok(IndexedReader {
+            r,
+            blocks,
+            starts,
+            end,
+            options,
+        })
+    }
+
+    fn read_body(r: &mut R, options: &ReaderOptions) -> Result<Option<fileformat::Body>, Error> {
+        let max_len = options.limits.max_block_size;
+        let (header, raw) = match read_raw_block(r, max_len)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let body = open_block(header, raw, options.key.as_ref(), max_len)?;
+        Ok(Some(fileformat::Body::parse_from_bytes(&body)?))
+    }
+
+    pub fn blocks(&self) -> &[BlockInfo] {
+        &self.blocks
+    }
+
+    /// The number of features in the file.
+    pub fn len(&self) -> u64 {
+        self.starts[self.blocks.len()]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_inner(self) -> R {
+        self.r
+    }
+
+    /// Reads the features of block `n`, or returns `None` if there is no such block.
+    pub fn block(&mut self, n: usize) -> Result<Option<Vec<Feature>>, Error> {
+        let offset = match self.blocks.get(n) {
+            Some(b) => b.offset,
+            None => return Ok(None),
+        };
+        self.r.seek(SeekFrom::Start(offset))?;
+        let body = Self::read_body(&mut self.r, &self.options)?.ok_or(Error::Truncated)?;
+        let fts = decode_features(body.feature.into_vec(), &self.options, None)?;
+        Ok(Some(fts))
+    }
+
+    /// The cursor that points at the feature with index `i`, for use with [`page`].
+    pub fn cursor(&self, i: u64) -> PageCursor {
+        if i >= self.len() {
+            return PageCursor {
+                offset: self.end,
+                skip: 0,
+            };
+        }
+        let n = self.block_of(i);
+        PageCursor {
+            offset: self.blocks[n].offset,
+            skip: (i - self.starts[n]) as usize,
+        }
+    }
+
+    /// The block that holds the feature with index `i`, which must exist.
+    fn block_of(&self, i: u64) -> usize {
+        self.starts.partition_point(|&s| s <= i) - 1
+    }
+
+    /// Reads the features with the indices in `range`, only touching the blocks that hold them.
+    /// Indices past the end of the file are ignored.
+    pub fn features(&mut self, range: Range<u64>) -> Result<Vec<Feature>, Error> {
+        let end = range.end.min(self.len());
+        let mut out = Vec::new();
+        let mut i = range.start;
+        while i < end {
+            let n = self.block_of(i);
+            let fts = self.block(n)?.unwrap_or_default();
+            let skip = (i - self.starts[n]) as usize;
+            let take = (end - i).min((fts.len() - skip) as u64) as usize;
+            out.extend(fts.into_iter().skip(skip).take(take));
+            i += take as u64;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{page, IndexedReader, PageCursor};
+    use crate::fileformat;
+    use crate::Value;
+    use protobuf::Message;
+    use std::io::Cursor;
+
+    fn block(ids: std::ops::Range<i64>) -> Vec<u8> {
+        let mut body = fileformat::Body::new();
+        for id in ids {
+            let mut ft = fileformat::Feature::new();
+            ft.geom = wkb::geom_to_wkb(&geo_types::Point::new(1.0, 2.0).into()).unwrap();
+            let mut tag = fileformat::Tag::new();
+            tag.key = "id".to_string();
+            tag.value = id.to_le_bytes().to_vec();
+            tag.field_type = fileformat::Tag_ValueType::INT;
+            ft.tags.push(tag);
+            body.feature.push(ft);
+        }
+        let body = body.write_to_bytes().unwrap();
+        let mut buf = (body.len() as u32).to_le_bytes().to_vec();
+        buf.extend(b"\0\0\0\0");
+        buf.extend(body);
+        buf
+    }
+
+    #[test]
+    fn pages() {
+        let mut buf = b"SPAT\0\0\0\0".to_vec();
+        buf.extend(block(0..4));
+        buf.extend(block(4..7));
+        buf.extend(b"\0\0\0\0");
+        let mut file = Cursor::new(buf);
+
+        let mut cursor = Some(PageCursor::start());
+        let mut pages = Vec::new();
+        while let Some(c) = cursor {
+            // round trip through the string representation, like a web client would
+            let c: PageCursor = c.to_string().parse().unwrap();
+            let (fts, next) = page(&mut file, c, 3).unwrap();
+            let ids: Vec<i64> = fts
+                .iter()
+                .map(|ft| match ft.tags["id"] {
+                    Value::Integer(i) => i,
+                    _ => unreachable!(),
+                })
+                .collect();
+            pages.push(ids);
+            cursor = next;
+        }
+        assert_eq!(pages, vec![vec![0, 1, 2], vec![3, 4, 5], vec![6]]);
+
+        let (fts, next) = page(&mut file, PageCursor::start(), usize::MAX).unwrap();
+        assert_eq!((fts.len(), next), (7, None));
+        let err = page(&mut file, PageCursor::start(), 0).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn indexed() {
+        let mut buf = b"SPAT\0\0\0\0".to_vec();
+        buf.extend(block(0..4));
+        buf.extend(block(4..7));
+        buf.extend(b"\0\0\0\0");
+        let mut r = IndexedReader::new(Cursor::new(buf)).unwrap();
+        let counts: Vec<usize> = r.blocks().iter().map(|b| b.features).collect();
+        assert_eq!(counts, [4, 3]);
+        assert_eq!(r.len(), 7);
+        assert_eq!(r.block(1).unwrap().unwrap().len(), 3);
+        assert!(r.block(2).unwrap().is_none());
+
+        let ids = |fts: Vec<crate::Feature>| -> Vec<Value> {
+            fts.into_iter().map(|ft| ft.tags["id"].clone()).collect()
+        };
+        let fts = r.features(2..6).unwrap();
+        assert_eq!(ids(fts), (2..6).map(Value::Integer).collect::<Vec<_>>());
+        assert_eq!(r.features(6..100).unwrap().len(), 1);
+        assert!(r.features(7..9).unwrap().is_empty());
+
+        let cursor = r.cursor(5);
+        assert_eq!(cursor.skip, 1);
+        assert_eq!(cursor.offset, r.blocks()[1].offset);
+        let mut file = r.into_inner();
+        let (fts, next) = page(&mut file, cursor, 10).unwrap();
+        assert_eq!(ids(fts), [Value::Integer(5), Value::Integer(6)]);
+        assert_eq!(next, None);
+
+        let r = IndexedReader::new(file).unwrap();
+        let end = r.cursor(7);
+        let mut file = r.into_inner();
+        let (fts, next) = page(&mut file, end, 10).unwrap();
+        assert!(fts.is_empty());
+        assert_eq!(next, None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Conversion between features and polars DataFrames (requires the `polars` feature).
+//!
+//! A DataFrame has one geometry column and one column per tag key. Tag columns are typed:
+//! Int64 if all values of a key are integers, Float64 if they are numeric, String otherwise.
+//! Features without a tag get a null in that column.
+
+use crate::source::FeatureSource;
+use crate::{wkbfast, Feature, Value};
+use ::polars::prelude::{Column, DataFrame, DataType};
+use geo_types::{Geometry, GeometryCollection, LineString};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use wkt::{ToWkt, TryFromWkt};
+
+/// How the geometry column is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeometryEncoding {
+    /// Binary column with WKB.
+    Wkb,
+    /// String column with WKT.
+    Wkt,
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn value_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// `g` with Lines as LineStrings and Rects and Triangles as Polygons, which WKB and WKT have
+/// no types for.
+fn simple_features(g: &Geometry<f64>) -> Geometry<f64> {
+    match g {
+        Geometry::Line(l) => LineString::from(*l).into(),
+        Geometry::Rect(r) => r.to_polygon().into(),
+        Geometry::Triangle(t) => t.to_polygon().into(),
+        Geometry::GeometryCollection(gc) => Geometry::GeometryCollection(GeometryCollection(
+            gc.iter().map(simple_features).collect(),
+        )),
+        g => g.clone(),
+    }
+}
+
+/// Reads all features of `src` into a DataFrame, with the geometry in the first column.
+/// ```
+/// use spaten::polars::{from_polars, to_polars, GeometryEncoding};
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("lanes".to_string(), Value::Integer(2));
+/// let fts = vec![Feature {
+///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+///     tags,
+/// }];
+/// let df = to_polars(&mut fts.into_iter(), "geometry", GeometryEncoding::Wkt).unwrap();
+/// assert_eq!(df.shape(), (1, 2));
+/// let back = from_polars(&df, "geometry").unwrap();
+/// assert_eq!(back[0].tags["lanes"], Value::Integer(2));
+/// ```
+pub fn to_polars<S: FeatureSource>(
+    src: &mut S,
+    geometry_col: &str,
+    encoding: GeometryEncoding,
+) -> io::Result<DataFrame> {
+    let mut fts = Vec::new();
+    while let Some(mut ft) = src.next_feature()? {
+        ft.geometry = simple_features(&ft.geometry);
+        fts.push(ft);
+    }
+
+    let mut keys: BTreeMap<&str, DataType> = BTreeMap::new();
+    for (k, v) in fts.iter().flat_map(|ft| ft.tags.iter()) {
+        if k == geometry_col {
+            return Err(invalid_data(format!(
+                "tag {} collides with the geometry column",
+                k
+            )));
+        }
+        let dtype = match v {
+            Value::Integer(_) => DataType::Int64,
+            Value::Float(_) => DataType::Float64,
+            _ => DataType::String,
+        };
+        keys.entry(k)
+            .and_modify(|t| {
+                *t = match (&*t, &dtype) {
+                    (a, b) if a == b => dtype.clone(),
+                    (DataType::String, _) | (_, DataType::String) => DataType::String,
+                    _ => DataType::Float64,
+                }
+            })
+            .or_insert(dtype);
+    }
+
+    let mut columns = Vec::with_capacity(keys.len() + 1);
+    columns.push(match encoding {
+        GeometryEncoding::Wkb => {
+            let wkb = fts
+                .iter()
+                .map(|ft| {
+                    wkb::geom_to_wkb(&ft.geometry).map_err(|e| invalid_data(format!("{:?}", e)))
+                })
+                .collect::<io::Result<Vec<Vec<u8>>>>()?;
+            Column::new(geometry_col.into(), wkb)
+        }
+        GeometryEncoding::Wkt => Column::new(
+            geometry_col.into(),
+            fts.iter()
+                .map(|ft| ft.geometry.wkt_string())
+                .collect::<Vec<String>>(),
+        ),
+    });
+    for (key, dtype) in keys {
+        let vals = fts.iter().map(|ft| ft.tags.get(key));
+        columns.push(match dtype {
+            DataType::Int64 => Column::new(
+                key.into(),
+                vals.map(|v| match v {
+                    Some(Value::Integer(i)) => Some(*i),
+                    _ => None,
+                })
+                .collect::<Vec<Option<i64>>>(),
+            ),
+            DataType::Float64 => Column::new(
+                key.into(),
+                vals.map(|v| match v {
+                    Some(Value::Integer(i)) => Some(*i as f64),
+                    Some(Value::Float(f)) => Some(*f),
+                    _ => None,
+                })
+                .collect::<Vec<Option<f64>>>(),
+            ),
+            _ => Column::new(
+                key.into(),
+                vals.map(|v| v.map(value_text))
+                    .collect::<Vec<Option<String>>>(),
+            ),
+        });
+    }
+    DataFrame::new(fts.len(), columns).map_err(invalid_data)
+}
+
+/// Converts a DataFrame into features. The geometry column may hold WKB (Binary) or WKT
+/// (String); integer, float, boolean and string columns become tags, other column types are
+/// converted to strings. Null cells are omitted.
+pub fn from_polars(df: &DataFrame, geometry_col: &str) -> io::Result<Vec<Feature>> {
+    let geom = df.column(geometry_col).map_err(invalid_data)?;
+    let geometries: Vec<Geometry<f64>> = match geom.dtype() {
+        DataType::Binary => geom
+            .binary()
+            .map_err(invalid_data)?
+            .iter()
+            .map(|g| wkbfast::decode(g.ok_or("Missing geometry")?))
+            .collect::<Result<_, &'static str>>()
+            .map_err(invalid_data)?,
+        DataType::String => geom
+            .str()
+            .map_err(invalid_data)?
+            .iter()
+            .map(|g| {
+                Geometry::try_from_wkt_str(g.ok_or("Missing geometry")?).map_err(|e| e.to_string())
+            })
+            .collect::<Result<_, String>>()
+            .map_err(invalid_data)?,
+        t => {
+            return Err(invalid_data(format!(
+                "unsupported geometry column type {}",
+                t
+            )))
+        }
+    };
+
+    let mut fts: Vec<Feature> = geometries
+        .into_iter()
+        .map(|geometry| Feature {
+            geometry,
+            tags: HashMap::new(),
+        })
+        .collect();
+    for col in df.columns() {
+        let key = col.name().as_str();
+        if key == geometry_col {
+            continue;
+        }
+        let vals: Vec<Option<Value>> = match col.dtype() {
+            t if t.is_integer() || t.is_bool() => col
+                .cast(&DataType::Int64)
+                .map_err(invalid_data)?
+                .i64()
+                .map_err(invalid_data)?
+                .iter()
+                .map(|v| v.map(Value::Integer))
+                .collect(),
+            t if t.is_float() => col
+                .cast(&DataType::Float64)
+                .map_err(invalid_data)?
+                .f64()
+                .map_err(invalid_data)?
+                .iter()
+                .map(|v| v.map(Value::Float))
+                .collect(),
+            _ => col
+                .cast(&DataType::String)
+                .map_err(invalid_data)?
+                .str()
+                .map_err(invalid_data)?
+                .iter()
+                .map(|v| v.map(|s| Value::String(s.to_string())))
+                .collect(),
+        };
+        for (ft, v) in fts.iter_mut().zip(vals) {
+            if let Some(v) = v {
+                ft.tags.insert(key.to_string(), v);
+            }
+        }
+    }
+    Ok(fts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_polars, to_polars, GeometryEncoding};
+    use crate::{Feature, Value};
+    use ::polars::prelude::DataType;
+    use geo_types::{line_string, Geometry, GeometryCollection, Line, Point, Rect, Triangle};
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trip() {
+        let mut a = HashMap::new();
+        a.insert("id".to_string(), Value::Integer(1));
+        a.insert("width".to_string(), Value::Integer(3));
+        a.insert("name".to_string(), Value::String("Main".to_string()));
+        let mut b = HashMap::new();
+        b.insert("id".to_string(), Value::Integer(2));
+        b.insert("width".to_string(), Value::Float(2.5));
+        let fts = vec![
+            Feature {
+                geometry: line_string![(x: 0., y: 0.), (x: 1., y: 1.)].into(),
+                tags: a,
+            },
+            Feature {
+                geometry: Point::new(3., 4.).into(),
+                tags: b,
+            },
+        ];
+
+        let df = to_polars(&mut fts.clone().into_iter(), "geom", GeometryEncoding::Wkb).unwrap();
+        assert_eq!(df.column("geom").unwrap().dtype(), &DataType::Binary);
+        assert_eq!(df.column("id").unwrap().dtype(), &DataType::Int64);
+        assert_eq!(df.column("width").unwrap().dtype(), &DataType::Float64);
+        assert_eq!(df.column("name").unwrap().dtype(), &DataType::String);
+
+        let back = from_polars(&df, "geom").unwrap();
+        assert_eq!(back[0].geometry, fts[0].geometry);
+        assert_eq!(back[0].tags["width"], Value::Float(3.));
+        assert_eq!(back[1].tags["id"], Value::Integer(2));
+        assert!(!back[1].tags.contains_key("name"));
+    }
+
+    #[test]
+    fn line_rect_triangle() {
+        let geometries: Vec<Geometry<f64>> = vec![
+            Line::new((0., 0.), (1., 1.)).into(),
+            Rect::new((0., 0.), (2., 1.)).into(),
+            Geometry::GeometryCollection(GeometryCollection(vec![Triangle::new(
+                (0., 0.).into(),
+                (1., 0.).into(),
+                (0., 1.).into(),
+            )
+            .into()])),
+        ];
+        let fts: Vec<Feature> = geometries
+            .iter()
+            .map(|g| Feature {
+                geometry: g.clone(),
+                tags: HashMap::new(),
+            })
+            .collect();
+        for encoding in [GeometryEncoding::Wkb, GeometryEncoding::Wkt] {
+            let df = to_polars(&mut fts.clone().into_iter(), "geom", encoding).unwrap();
+            let back = from_polars(&df, "geom").unwrap();
+            assert_eq!(
+                back[0].geometry,
+                line_string![(x: 0., y: 0.), (x: 1., y: 1.)].into()
+            );
+            assert_eq!(
+                back[1].geometry,
+                Rect::new((0., 0.), (2., 1.)).to_polygon().into()
+            );
+            match &back[2].geometry {
+                Geometry::GeometryCollection(gc) => assert!(matches!(gc[0], Geometry::Polygon(_))),
+                g => panic!("unexpected {:?}", g),
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! A quick look at a file before an expensive conversion.
+//!
+//! [`preflight`] only parses the block frames and the feature headers: geometries and tags stay
+//! undecoded, so a scan takes a fraction of the time of a full read.
+
+use crate::{
+    fileformat, open_block, read_raw_message, stored_bbox, try_read_file_header, Error, Limits,
+};
+use geo_types::{coord, Rect};
+use protobuf::Message;
+use std::fmt;
+use std::io;
+
+/// What [`preflight`] found out about a file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preflight {
+    pub blocks: u64,
+    pub features: u64,
+    /// The union of the feature bounding boxes stored in the file, `None` if no feature has one.
+    pub extent: Option<Rect<f64>>,
+    /// Number of features without a stored bounding box. They are not part of the `extent`.
+    pub without_bbox: u64,
+    /// Size of the file in bytes.
+    pub file_bytes: u64,
+    /// Size of the block bodies after decompression.
+    pub data_bytes: u64,
+}
+
+impl Preflight {
+    /// Estimates the size of a conversion whose output takes `ratio` times as many bytes as the
+    /// uncompressed Spaten data. GeoJSON, for example, is typically 2 to 3 times as large.
+    pub fn estimated_output(&self, ratio: f64) -> u64 {
+        (self.data_bytes as f64 * ratio) as u64
+    }
+}
+
+impl fmt::Display for Preflight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} features in {} blocks, {} bytes ({} uncompressed)",
+            self.features, self.blocks, self.file_bytes, self.data_bytes
+        )?;
+        if let Some(e) = self.extent {
+            write!(
+                f,
+                ", extent {} {} {} {}",
+                e.min().x,
+                e.min().y,
+                e.max().x,
+                e.max().y
+            )?;
+        }
+        if self.without_bbox > 0 {
+            write!(f, ", {} features without bbox", self.without_bbox)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scans a file without decoding its features. Fails on [encrypted](crate::encryption) files.
+/// ```
+/// use spaten::preflight::preflight;
+/// use spaten::sink::FeatureSink;
+/// use spaten::{Feature, FeatureWriter};
+///
+/// let mut w = FeatureWriter::new(Vec::new());
+/// for (x, y) in [(7.0, 51.0), (8.0, 50.0)] {
+///     w.accept(Feature {
+///         geometry: geo_types::Point::new(x, y).into(),
+///         tags: Default::default(),
+///     })?;
+/// }
+/// w.finish()?;
+/// let buf = w.into_inner();
+///
+/// let p = preflight(&mut &buf[..])?;
+/// assert_eq!(p.features, 2);
+/// assert_eq!(p.extent, Some(geo_types::Rect::new((7.0, 50.0), (8.0, 51.0))));
+/// assert_eq!(p.file_bytes, buf.len() as u64);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn preflight(r: &mut impl io::Read) -> Result<Preflight, Error> {
+    preflight_with_limits(r, &Limits::default())
+}
+
+/// Like [`preflight`], but refuses blocks larger than the `limits` allow.
+pub fn preflight_with_limits(r: &mut impl io::Read, limits: &Limits) -> Result<Preflight, Error> {
+    try_read_file_header(r)?;
+    let mut p = Preflight {
+        file_bytes: 12,
+        ..Default::default()
+    };
+    while let Some((header, raw)) = read_raw_message(r, limits.max_block_size)? {
+        p.file_bytes += 8 + raw.len() as u64;
+        if header.meta {
+            continue;
+        }
+        p.blocks += 1;
+        let body = open_block(header, raw, None, limits.max_block_size)?;
+        p.data_bytes += body.len() as u64;
+        let body = fileformat::Body::parse_from_bytes(&body)?;
+        for ft in body.feature.iter() {
+            p.features += 1;
+            match stored_bbox(ft) {
+                Some(bbox) => p.extent = Some(extend(p.extent, bbox)),
+                None => p.without_bbox += 1,
+            }
+        }
+    }
+    Ok(p)
+}
+
+fn extend(extent: Option<Rect<f64>>, bbox: Rect<f64>) -> Rect<f64> {
+    match extent {
+        Some(e) => Rect::new(
+            coord! { x: e.min().x.min(bbox.min().x), y: e.min().y.min(bbox.min().y) },
+            coord! { x: e.max().x.max(bbox.max().x), y: e.max().y.max(bbox.max().y) },
+        ),
+        None => bbox,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::preflight;
+    use crate::sink::FeatureSink;
+    use crate::{Error, Feature, FeatureWriter, WriterOptions};
+    use geo_types::{line_string, Geometry, GeometryCollection, Rect};
+
+    #[test]
+    fn scan() {
+        let opts = WriterOptions {
+            block_size: 2,
+            gzip_level: Some(6),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        for i in 0..5 {
+            let x = f64::from(i);
+            w.accept(Feature {
+                geometry: line_string![(x: x, y: -x), (x: x + 1., y: 2.)].into(),
+                tags: Default::default(),
+            })
+            .unwrap();
+        }
+        w.accept(Feature {
+            geometry: Geometry::GeometryCollection(GeometryCollection(vec![])),
+            tags: Default::default(),
+        })
+        .unwrap();
+        w.finish().unwrap();
+        let buf = w.into_inner();
+
+        let p = preflight(&mut &buf[..]).unwrap();
+        assert_eq!(p.blocks, 3);
+        assert_eq!(p.features, 6);
+        assert_eq!(p.extent, Some(Rect::new((0., -4.), (5., 2.))));
+        assert_eq!(p.without_bbox, 1);
+        assert_eq!(p.file_bytes, buf.len() as u64);
+        assert!(p.data_bytes > 0);
+        assert_eq!(p.estimated_output(2.), p.data_bytes * 2);
+        assert!(p.to_string().starts_with("6 features in 3 blocks"));
+        assert!(p.to_string().ends_with(", 1 features without bbox"));
+
+        let empty = preflight(&mut &b"SPAT\0\0\0\0\0\0\0\0"[..]).unwrap();
+        assert_eq!(empty.extent, None);
+        assert_eq!(empty.file_bytes, 12);
+        assert!(matches!(
+            preflight(&mut &buf[..buf.len() - 8]),
+            Err(Error::Truncated)
+        ));
+    }
+}
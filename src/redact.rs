@@ -0,0 +1,133 @@
+//! Masking of sensitive tag values when printing or exporting features.
+
+use crate::{Feature, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+const MASK: &str = "***";
+
+/// The set of tag keys whose values must not be printed, e.g. `email` or `phone`.
+/// ```
+/// use spaten::redact::Redaction;
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("email".to_string(), Value::String("jane@example.com".to_string()));
+/// let ft = Feature {
+///     geometry: geo_types::Point::new(1.0, 2.0).into(),
+///     tags,
+/// };
+/// let redaction = Redaction::new(["email"]);
+/// assert!(!format!("{:?}", ft.redacted(&redaction)).contains("jane"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Redaction {
+    keys: HashSet<String>,
+}
+
+impl Redaction {
+    pub fn new<K: Into<String>>(keys: impl IntoIterator<Item = K>) -> Self {
+        Redaction {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns whether values of tag `key` are masked.
+    pub fn is_redacted(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Replaces the values of all redacted keys of `ft` with the string `***`, e.g. before the
+    /// feature is written.
+    pub fn apply(&self, ft: &mut Feature) {
+        for (k, v) in ft.tags.iter_mut() {
+            if self.is_redacted(k) {
+                *v = Value::String(MASK.to_string());
+            }
+        }
+    }
+}
+
+/// A feature that masks redacted values when printed. Created by [`Feature::redacted`].
+pub struct Redacted<'a> {
+    ft: &'a Feature,
+    redaction: &'a Redaction,
+}
+
+impl Feature {
+    /// Wraps the feature for printing, with the values of all keys in `redaction` masked.
+    pub fn redacted<'a>(&'a self, redaction: &'a Redaction) -> Redacted<'a> {
+        Redacted {
+            ft: self,
+            redaction,
+        }
+    }
+}
+
+struct Masked;
+
+impl fmt::Debug for Masked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tags: BTreeMap<&String, &dyn fmt::Debug> = self
+            .ft
+            .tags
+            .iter()
+            .map(|(k, v)| {
+                let v: &dyn fmt::Debug = if self.redaction.is_redacted(k) {
+                    &Masked
+                } else {
+                    v
+                };
+                (k, v)
+            })
+            .collect();
+        f.debug_struct("Feature")
+            .field("geometry", &self.ft.geometry)
+            .field("tags", &tags)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Redaction;
+    use crate::{Feature, Value};
+    use std::collections::HashMap;
+
+    #[test]
+    fn redacted_debug() {
+        let mut tags = HashMap::new();
+        tags.insert("phone".to_string(), Value::String("+49 123".to_string()));
+        tags.insert("name".to_string(), Value::String("Bakery".to_string()));
+        let ft = Feature {
+            geometry: geo_types::Point::new(1.0, 2.0).into(),
+            tags,
+        };
+        let out = format!("{:?}", ft.redacted(&Redaction::new(["phone"])));
+        assert!(out.contains(r#""phone": ***"#));
+        assert!(out.contains(r#""name": "Bakery""#));
+        assert!(!out.contains("+49"));
+    }
+
+    #[test]
+    fn apply() {
+        let mut tags = HashMap::new();
+        tags.insert("phone".to_string(), Value::Integer(49123));
+        tags.insert("name".to_string(), Value::String("Bakery".to_string()));
+        let mut ft = Feature {
+            geometry: geo_types::Point::new(1.0, 2.0).into(),
+            tags,
+        };
+        Redaction::new(["phone", "email"]).apply(&mut ft);
+        assert_eq!(ft.tags["phone"], Value::String("***".to_string()));
+        assert_eq!(ft.tags["name"], Value::String("Bakery".to_string()));
+        assert!(!ft.tags.contains_key("email"));
+    }
+}
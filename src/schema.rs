@@ -0,0 +1,389 @@
+//! Validating the tags of written features against a documented schema.
+//!
+//! A [`Schema`] lists the keys a feature may have, their types and optionally the values they
+//! may take. Set as [`WriterOptions::schema`](crate::WriterOptions::schema), it makes the writer
+//! refuse features that do not conform, with a [`SchemaError`] that lists every violation.
+//! [`coercing`](Schema::coercing) schemas first try to repair features, e.g. by parsing numbers
+//! from strings.
+//! ```
+//! use spaten::schema::{Schema, SchemaError, TagType};
+//! use spaten::sink::FeatureSink;
+//! use spaten::{Feature, FeatureWriter, Value, WriterOptions};
+//! use std::collections::HashMap;
+//!
+//! let schema = Schema::new()
+//!     .required("highway", TagType::String)
+//!     .allowed("highway", &["motorway", "primary"])
+//!     .optional("lanes", TagType::Integer);
+//! let opts = WriterOptions {
+//!     schema: Some(schema),
+//!     ..Default::default()
+//! };
+//! let mut w = FeatureWriter::with_options(Vec::new(), opts);
+//!
+//! let mut tags = HashMap::new();
+//! tags.insert("highway".to_string(), Value::from("path"));
+//! tags.insert("lanes".to_string(), Value::from("two"));
+//! let err = w
+//!     .accept(Feature {
+//!         geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!         tags,
+//!     })
+//!     .unwrap_err();
+//! let report = err.get_ref().unwrap().downcast_ref::<SchemaError>().unwrap();
+//! assert_eq!(report.violations().len(), 2);
+//! assert_eq!(
+//!     err.to_string(),
+//!     "feature violates schema: highway: value \"path\" is not allowed; \
+//!      lanes: expected integer, found string"
+//! );
+//! ```
+
+use crate::{Feature, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The type of a tag value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagType {
+    String,
+    Integer,
+    Float,
+    Bytes,
+    List,
+}
+
+impl TagType {
+    fn of(v: &Value) -> TagType {
+        match v {
+            Value::String(_) => TagType::String,
+            Value::Integer(_) => TagType::Integer,
+            Value::Float(_) => TagType::Float,
+            Value::Bytes(_) => TagType::Bytes,
+            Value::List(_) => TagType::List,
+        }
+    }
+}
+
+impl fmt::Display for TagType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TagType::String => "string",
+            TagType::Integer => "integer",
+            TagType::Float => "float",
+            TagType::Bytes => "bytes",
+            TagType::List => "list",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Field {
+    ty: TagType,
+    required: bool,
+    allowed: Option<Vec<Value>>,
+}
+
+/// The keys, types and values that features may have. Keys that are not part of the schema
+/// are allowed unless the schema is [`closed`](Schema::closed).
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    fields: BTreeMap<String, Field>,
+    closed: bool,
+    coerce: bool,
+}
+
+/// One way in which a feature does not conform to a [`Schema`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    Missing(String),
+    WrongType {
+        key: String,
+        expected: TagType,
+        found: TagType,
+    },
+    NotAllowed {
+        key: String,
+        value: Value,
+    },
+    UnknownKey(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Missing(key) => write!(f, "{}: required tag is missing", key),
+            Violation::WrongType {
+                key,
+                expected,
+                found,
+            } => write!(f, "{}: expected {}, found {}", key, expected, found),
+            Violation::NotAllowed { key, value } => {
+                write!(f, "{}: value {:?} is not allowed", key, value)
+            }
+            Violation::UnknownKey(key) => write!(f, "{}: tag is not part of the schema", key),
+        }
+    }
+}
+
+/// The violations of a feature that was refused, ordered by key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaError {
+    violations: Vec<Violation>,
+}
+
+impl SchemaError {
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "feature violates schema: ")?;
+        for (i, v) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", v)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires every feature to have the tag `key` of type `ty`.
+    pub fn required(mut self, key: &str, ty: TagType) -> Self {
+        self.field(key, ty, true);
+        self
+    }
+
+    /// Allows the tag `key`, which must be of type `ty` if present.
+    pub fn optional(mut self, key: &str, ty: TagType) -> Self {
+        self.field(key, ty, false);
+        self
+    }
+
+    fn field(&mut self, key: &str, ty: TagType, required: bool) {
+        let allowed = self.fields.remove(key).and_then(|f| f.allowed);
+        self.fields.insert(
+            key.to_string(),
+            Field {
+                ty,
+                required,
+                allowed,
+            },
+        );
+    }
+
+    /// Restricts the tag `key`, which has to be declared first, to `values`.
+    ///
+    /// # Panics
+    ///
+    /// If `key` is not declared.
+    pub fn allowed<V: Clone + Into<Value>>(mut self, key: &str, values: &[V]) -> Self {
+        let field = self
+            .fields
+            .get_mut(key)
+            .unwrap_or_else(|| panic!("tag {} is not declared", key));
+        field.allowed = Some(values.iter().cloned().map(Into::into).collect());
+        self
+    }
+
+    /// Refuses tags that are not declared.
+    pub fn closed(mut self) -> Self {
+        self.closed = true;
+        self
+    }
+
+    /// Repairs violations where possible before refusing a feature: values are converted to the
+    /// declared type if that loses no information, e.g. `"3"` to an integer or `2` to a float,
+    /// and undeclared tags are dropped from closed schemas.
+    pub fn coercing(mut self) -> Self {
+        self.coerce = true;
+        self
+    }
+
+    /// Checks `ft` against the schema, coercing it if the schema is
+    /// [`coercing`](Schema::coercing). Returns the feature as it should be written.
+    pub fn apply(&self, mut ft: Feature) -> Result<Feature, SchemaError> {
+        let mut violations = Vec::new();
+        if self.coerce {
+            coerce(&self.fields, self.closed, &mut ft.tags);
+        }
+        for (key, field) in &self.fields {
+            let value = match ft.tags.get(key) {
+                Some(v) => v,
+                None if field.required => {
+                    violations.push(Violation::Missing(key.clone()));
+                    continue;
+                }
+                None => continue,
+            };
+            if TagType::of(value) != field.ty {
+                violations.push(Violation::WrongType {
+                    key: key.clone(),
+                    expected: field.ty,
+                    found: TagType::of(value),
+                });
+            } else if field.allowed.as_ref().is_some_and(|a| !a.contains(value)) {
+                violations.push(Violation::NotAllowed {
+                    key: key.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        if self.closed {
+            let mut unknown: Vec<&String> = ft
+                .tags
+                .keys()
+                .filter(|k| !self.fields.contains_key(*k))
+                .collect();
+            unknown.sort();
+            violations.extend(unknown.into_iter().cloned().map(Violation::UnknownKey));
+        }
+        if violations.is_empty() {
+            Ok(ft)
+        } else {
+            violations.sort_by(|a, b| key_of(a).cmp(key_of(b)));
+            Err(SchemaError { violations })
+        }
+    }
+}
+
+fn key_of(v: &Violation) -> &str {
+    match v {
+        Violation::Missing(key)
+        | Violation::WrongType { key, .. }
+        | Violation::NotAllowed { key, .. }
+        | Violation::UnknownKey(key) => key,
+    }
+}
+
+fn coerce(fields: &BTreeMap<String, Field>, closed: bool, tags: &mut HashMap<String, Value>) {
+    if closed {
+        tags.retain(|k, _| fields.contains_key(k));
+    }
+    for (key, field) in fields {
+        if let Some(v) = tags.get_mut(key) {
+            if let Some(c) = convert(v, field.ty) {
+                *v = c;
+            }
+        }
+    }
+}
+
+/// Converts `v` to `ty` if that is lossless.
+fn convert(v: &Value, ty: TagType) -> Option<Value> {
+    match (v, ty) {
+        (Value::String(s), TagType::Integer) => s.trim().parse().ok().map(Value::Integer),
+        (Value::String(s), TagType::Float) => s.trim().parse().ok().map(Value::Float),
+        (Value::Integer(n), TagType::Float) if n.unsigned_abs() <= 1 << 53 => {
+            Some(Value::Float(*n as f64))
+        }
+        (Value::Float(f), TagType::Integer)
+            if f.fract() == 0. && f.abs() <= (1u64 << 53) as f64 =>
+        {
+            Some(Value::Integer(*f as i64))
+        }
+        (Value::Integer(n), TagType::String) => Some(Value::String(n.to_string())),
+        (Value::Float(f), TagType::String) => Some(Value::String(f.to_string())),
+        (v, TagType::List) if TagType::of(v) != TagType::List => Some(Value::List(vec![v.clone()])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Schema, TagType, Violation};
+    use crate::{Feature, Value};
+    use std::collections::HashMap;
+
+    fn feature(tags: &[(&str, Value)]) -> Feature {
+        Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let schema = Schema::new()
+            .required("name", TagType::String)
+            .optional("lanes", TagType::Integer)
+            .allowed("lanes", &[1, 2, 3])
+            .closed();
+        let ok = feature(&[("name", Value::from("A 1")), ("lanes", Value::from(2))]);
+        assert!(schema.apply(ok).is_ok());
+
+        let err = schema
+            .apply(feature(&[
+                ("lanes", Value::from(4)),
+                ("width", Value::from(3.5)),
+            ]))
+            .unwrap_err();
+        assert_eq!(
+            err.violations(),
+            [
+                Violation::NotAllowed {
+                    key: "lanes".to_string(),
+                    value: Value::from(4)
+                },
+                Violation::Missing("name".to_string()),
+                Violation::UnknownKey("width".to_string()),
+            ]
+        );
+
+        let err = schema
+            .apply(feature(&[
+                ("name", Value::from(1)),
+                ("lanes", Value::from("2")),
+            ]))
+            .unwrap_err();
+        assert_eq!(err.violations().len(), 2);
+    }
+
+    #[test]
+    fn coerce() {
+        let schema = Schema::new()
+            .required("lanes", TagType::Integer)
+            .optional("width", TagType::Float)
+            .optional("ref", TagType::String)
+            .optional("names", TagType::List)
+            .closed()
+            .coercing();
+        let ft = schema
+            .apply(feature(&[
+                ("lanes", Value::from(" 2")),
+                ("width", Value::from(3)),
+                ("ref", Value::from(9)),
+                ("names", Value::from("Rhein")),
+                ("note", Value::from("dropped")),
+            ]))
+            .unwrap();
+        assert_eq!(ft.tags["lanes"], Value::Integer(2));
+        assert_eq!(ft.tags["width"], Value::Float(3.));
+        assert_eq!(ft.tags["ref"], Value::from("9"));
+        assert_eq!(ft.tags["names"], Value::List(vec![Value::from("Rhein")]));
+        assert!(!ft.tags.contains_key("note"));
+
+        assert!(schema
+            .apply(feature(&[("lanes", Value::from(2.5))]))
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "tag lanes is not declared")]
+    fn undeclared() {
+        Schema::new().allowed("lanes", &[1]);
+    }
+}
@@ -0,0 +1,188 @@
+//! `Serialize` and `Deserialize` for [`Feature`] and [`Value`] (requires the `serde` feature).
+//!
+//! Values map onto the serde data model like GeoJSON properties, see [`crate::geojson`]: strings,
+//! integers, floats and bytes become the corresponding primitives and lists sequences. When
+//! deserializing, booleans become the integers 0 and 1, and maps their JSON text.
+//!
+//! Features are serialized as GeoJSON Feature objects, with the tags as properties sorted by key.
+//! Deserializing accepts any GeoJSON Feature with a geometry; null properties are omitted.
+//! ```
+//! use spaten::{Feature, Value};
+//! use std::collections::HashMap;
+//!
+//! let mut tags = HashMap::new();
+//! tags.insert("name".to_string(), Value::from("Bonn"));
+//! let ft = Feature {
+//!     geometry: geo_types::Point::new(7.1, 50.7).into(),
+//!     tags,
+//! };
+//! let json = serde_json::to_string(&ft)?;
+//! assert_eq!(
+//!     json,
+//!     r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[7.1,50.7]},"properties":{"name":"Bonn"}}"#
+//! );
+//! let back: Feature = serde_json::from_str(&json)?;
+//! assert_eq!(back.tags, ft.tags);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use crate::{Feature, Value};
+use ::geojson::JsonValue;
+use ::serde::de::{self, MapAccess, SeqAccess, Visitor};
+use ::serde::ser::SerializeStruct;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::String(v) => s.serialize_str(v),
+            Value::Integer(v) => s.serialize_i64(*v),
+            Value::Float(v) => s.serialize_f64(*v),
+            Value::Bytes(v) => s.serialize_bytes(v),
+            Value::List(v) => s.collect_seq(v),
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string, number, bytes or sequence")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(match i64::try_from(v) {
+            Ok(v) => Value::Integer(v),
+            Err(_) => Value::Float(v as f64),
+        })
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut l = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            l.push(v);
+        }
+        Ok(Value::List(l))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Value, A::Error> {
+        let json = JsonValue::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        Ok(Value::String(json.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        d.deserialize_any(ValueVisitor)
+    }
+}
+
+impl Serialize for Feature {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let properties: BTreeMap<&String, &Value> = self.tags.iter().collect();
+        let mut st = s.serialize_struct("Feature", 3)?;
+        st.serialize_field("type", "Feature")?;
+        st.serialize_field("geometry", &::geojson::Geometry::from(&self.geometry))?;
+        st.serialize_field("properties", &properties)?;
+        st.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "::serde")]
+struct GeoJsonFeature {
+    geometry: ::geojson::Geometry,
+    #[serde(default)]
+    properties: Option<HashMap<String, Option<Value>>>,
+}
+
+impl<'de> Deserialize<'de> for Feature {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let ft = GeoJsonFeature::deserialize(d)?;
+        let geometry = geo_types::Geometry::try_from(ft.geometry).map_err(de::Error::custom)?;
+        let tags = ft
+            .properties
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| Some((k, v?)))
+            .collect();
+        Ok(Feature { geometry, tags })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Feature, Value};
+    use geo_types::line_string;
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trip() {
+        let mut tags = HashMap::new();
+        tags.insert("name".to_string(), Value::from("Rhein"));
+        tags.insert("lanes".to_string(), Value::from(2));
+        tags.insert("width".to_string(), Value::from(1.5));
+        tags.insert(
+            "ref".to_string(),
+            Value::List(vec![Value::from(1), Value::from("a")]),
+        );
+        let ft = Feature {
+            geometry: line_string![(x: 7.0, y: 51.0), (x: 7.5, y: 51.5)].into(),
+            tags,
+        };
+        let json = serde_json::to_value(&ft).unwrap();
+        assert_eq!(json["properties"]["ref"], serde_json::json!([1, "a"]));
+        let back: Feature = serde_json::from_value(json).unwrap();
+        assert_eq!(back.geometry, ft.geometry);
+        assert_eq!(back.tags, ft.tags);
+
+        let back: Feature = serde_json::from_str(
+            r#"{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1, 2]},
+                "properties": {"bridge": true, "note": null, "big": 18446744073709551615,
+                               "addr": {"city": "Bonn"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(back.tags["bridge"], Value::Integer(1));
+        assert!(!back.tags.contains_key("note"));
+        assert_eq!(back.tags["big"], Value::Float(u64::MAX as f64));
+        assert_eq!(back.tags["addr"], Value::from(r#"{"city":"Bonn"}"#));
+
+        let missing: Result<Feature, _> =
+            serde_json::from_str(r#"{"type": "Feature", "properties": {}}"#);
+        assert!(missing.is_err());
+    }
+}
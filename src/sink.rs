@@ -0,0 +1,208 @@
+//! Pluggable outputs for features.
+//!
+//! Every output format implements [`FeatureSink`], so pipelines can hand features to any
+//! writer without knowing its concrete type. Third-party crates can add their own outputs
+//! by implementing the trait.
+
+use crate::{Feature, Value};
+use std::collections::BTreeMap;
+use std::io;
+
+/// A destination for features.
+///
+/// Features are handed over one at a time via [`accept`](FeatureSink::accept). Once all
+/// features have been passed, [`finish`](FeatureSink::finish) must be called exactly once,
+/// so the sink can flush buffers and write trailers.
+/// ```
+/// use spaten::sink::FeatureSink;
+/// use spaten::Feature;
+///
+/// let mut out: Vec<Feature> = Vec::new();
+/// out.finish().unwrap();
+/// assert!(out.is_empty());
+/// ```
+pub trait FeatureSink {
+    /// Consumes a single feature.
+    fn accept(&mut self, ft: Feature) -> io::Result<()>;
+
+    /// Flushes all pending data. No features may be accepted afterwards.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+impl<S: FeatureSink + ?Sized> FeatureSink for Box<S> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        (**self).accept(ft)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+/// Collects features in memory, mostly useful for tests and small datasets.
+impl FeatureSink for Vec<Feature> {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        self.push(ft);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Routes every feature to one of several sinks, e.g. to split a dataset into one file per
+/// `admin_level`.
+///
+/// The `route` closure determines the key of the output a feature belongs to; features for
+/// which it returns `None` are dropped. Outputs are created lazily by `open` the first time a
+/// key is encountered and are all finished together.
+/// ```
+/// use spaten::sink::{DemuxWriter, FeatureSink};
+/// use spaten::Feature;
+///
+/// let mut demux = DemuxWriter::by_tag("admin_level", |_key| Ok(Vec::<Feature>::new()));
+/// demux.finish().unwrap();
+/// assert!(demux.into_inner().is_empty());
+/// ```
+pub struct DemuxWriter<S, F, O> {
+    route: F,
+    open: O,
+    sinks: BTreeMap<String, S>,
+}
+
+impl<S, F, O> DemuxWriter<S, F, O>
+where
+    S: FeatureSink,
+    F: FnMut(&Feature) -> Option<String>,
+    O: FnMut(&str) -> io::Result<S>,
+{
+    pub fn new(route: F, open: O) -> Self {
+        DemuxWriter {
+            route,
+            open,
+            sinks: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the sinks that have been opened so far, by key.
+    pub fn into_inner(self) -> BTreeMap<String, S> {
+        self.sinks
+    }
+}
+
+impl<S, O> DemuxWriter<S, Box<dyn FnMut(&Feature) -> Option<String>>, O>
+where
+    S: FeatureSink,
+    O: FnMut(&str) -> io::Result<S>,
+{
+    /// Routes features by the value of the tag `key`. Features without that tag are dropped.
+    /// Strings and numbers are passed to `open` as plain text, e.g. `8` for an integer
+    /// `admin_level`, so they can be used in file names.
+    pub fn by_tag(key: &str, open: O) -> Self {
+        let key = key.to_string();
+        DemuxWriter::new(
+            Box::new(move |ft: &Feature| {
+                ft.tags.get(&key).map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    Value::Integer(i) => i.to_string(),
+                    Value::Float(f) => f.to_string(),
+                    other => format!("{:?}", other),
+                })
+            }),
+            open,
+        )
+    }
+}
+
+impl<S, F, O> FeatureSink for DemuxWriter<S, F, O>
+where
+    S: FeatureSink,
+    F: FnMut(&Feature) -> Option<String>,
+    O: FnMut(&str) -> io::Result<S>,
+{
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        let key = match (self.route)(&ft) {
+            Some(k) => k,
+            None => return Ok(()),
+        };
+        if !self.sinks.contains_key(&key) {
+            let sink = (self.open)(&key)?;
+            self.sinks.insert(key.clone(), sink);
+        }
+        self.sinks.get_mut(&key).unwrap().accept(ft)
+    }
+
+    /// Finishes all sinks, even if some of them fail. The first error is returned.
+    fn finish(&mut self) -> io::Result<()> {
+        let mut res = Ok(());
+        for sink in self.sinks.values_mut() {
+            if let Err(e) = sink.finish() {
+                if res.is_ok() {
+                    res = Err(e);
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeatureSink;
+    use crate::Feature;
+    use std::collections::HashMap;
+
+    #[test]
+    fn boxed_sink() {
+        let mut sink: Box<dyn FeatureSink> = Box::new(Vec::<Feature>::new());
+        sink.accept(Feature {
+            geometry: geo_types::Point::new(1.0, 2.0).into(),
+            tags: HashMap::new(),
+        })
+        .unwrap();
+        sink.finish().unwrap();
+    }
+
+    #[test]
+    fn demux_by_tag() {
+        use super::DemuxWriter;
+        use crate::Value;
+
+        let mut opened = Vec::new();
+        let mut demux = DemuxWriter::by_tag("admin_level", |key| {
+            opened.push(key.to_string());
+            Ok(Vec::<Feature>::new())
+        });
+        for lvl in [2, 4, 4, 6] {
+            let mut tags = HashMap::new();
+            tags.insert("admin_level".to_string(), Value::Integer(lvl));
+            demux
+                .accept(Feature {
+                    geometry: geo_types::Point::new(1.0, 2.0).into(),
+                    tags,
+                })
+                .unwrap();
+        }
+        demux
+            .accept(Feature {
+                geometry: geo_types::Point::new(1.0, 2.0).into(),
+                tags: HashMap::new(),
+            })
+            .unwrap();
+        let mut tags = HashMap::new();
+        tags.insert("admin_level".to_string(), Value::Float(8.5));
+        demux
+            .accept(Feature {
+                geometry: geo_types::Point::new(1.0, 2.0).into(),
+                tags,
+            })
+            .unwrap();
+        demux.finish().unwrap();
+
+        let sinks = demux.into_inner();
+        let counts: Vec<(&str, usize)> = sinks.iter().map(|(k, v)| (k.as_str(), v.len())).collect();
+        assert_eq!(counts, vec![("2", 1), ("4", 2), ("6", 1), ("8.5", 1)]);
+        assert_eq!(opened, ["2", "4", "6", "8.5"]);
+    }
+}
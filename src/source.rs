@@ -0,0 +1,75 @@
+//! Pluggable inputs for features.
+//!
+//! Every input format implements [`FeatureSource`]. Together with
+//! [`FeatureSink`](crate::sink::FeatureSink), a conversion between any two formats is a
+//! single call to [`copy`].
+
+use crate::sink::FeatureSink;
+use crate::Feature;
+use std::io;
+
+/// An origin of features.
+pub trait FeatureSource {
+    /// Returns the next feature, or `None` once the source is exhausted.
+    fn next_feature(&mut self) -> io::Result<Option<Feature>>;
+}
+
+impl<S: FeatureSource + ?Sized> FeatureSource for Box<S> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        (**self).next_feature()
+    }
+}
+
+/// Serves features from memory, mostly useful for tests and small datasets.
+impl FeatureSource for std::vec::IntoIter<Feature> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        Ok(self.next())
+    }
+}
+
+/// Moves all features from `src` into `dst` and finishes the sink afterwards.
+/// Returns the number of features that have been copied.
+/// ```
+/// use spaten::source::copy;
+/// use spaten::Feature;
+///
+/// let mut out: Vec<Feature> = Vec::new();
+/// let n = copy(&mut Vec::new().into_iter(), &mut out).unwrap();
+/// assert_eq!(n, 0);
+/// ```
+pub fn copy(
+    src: &mut (impl FeatureSource + ?Sized),
+    dst: &mut (impl FeatureSink + ?Sized),
+) -> io::Result<u64> {
+    let mut n = 0;
+    while let Some(ft) = src.next_feature()? {
+        dst.accept(ft)?;
+        n += 1;
+    }
+    dst.finish()?;
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::copy;
+    use crate::Feature;
+    use std::collections::HashMap;
+
+    #[test]
+    fn copy_vec() {
+        let fts = vec![
+            Feature {
+                geometry: geo_types::Point::new(1.0, 2.0).into(),
+                tags: HashMap::new(),
+            },
+            Feature {
+                geometry: geo_types::Point::new(3.0, 4.0).into(),
+                tags: HashMap::new(),
+            },
+        ];
+        let mut out: Vec<Feature> = Vec::new();
+        assert_eq!(copy(&mut fts.into_iter(), &mut out).unwrap(), 2);
+        assert_eq!(out.len(), 2);
+    }
+}
@@ -0,0 +1,512 @@
+//! Export into a SpatiaLite database (requires the `spatialite` feature).
+//!
+//! The features are written to one table with an integer primary key `pk_uid`, a `geometry`
+//! column and one column per tag key. Columns are added as new keys appear, since SQLite
+//! columns accept values of any type: strings become `TEXT`, integers `INTEGER`, floats `REAL`,
+//! bytes `BLOB` and lists their JSON text. Keys that differ only in case from an existing column
+//! (SQLite compares names case-insensitively), or that are named like one of the fixed columns,
+//! get a numbered suffix.
+//!
+//! SQLite limits the number of columns of a table, 2000 by default. Once
+//! [`MAX_TAG_COLUMNS`] tag columns exist, further keys are not given a column of their own:
+//! their values are collected in the `other_tags` column as one JSON object per row.
+//!
+//! Geometries are stored as SpatiaLite BLOBs in EPSG:4326, and the table is registered in the
+//! `geometry_columns` and `spatial_ref_sys` metadata tables, which are created if necessary. So
+//! the database can be opened with SpatiaLite, QGIS or GDAL without loading the SpatiaLite
+//! extension here. The geometry type is registered as generic `GEOMETRY` and no spatial index
+//! is created; `SELECT CreateSpatialIndex('table', 'geometry')` adds one. Empty geometries are
+//! stored as `NULL`.
+//! ```
+//! use spaten::sink::FeatureSink;
+//! use spaten::spatialite::SpatiaLiteWriter;
+//! use spaten::{Feature, Value};
+//! use std::collections::HashMap;
+//!
+//! let mut w = SpatiaLiteWriter::new(rusqlite::Connection::open_in_memory()?, "places")?;
+//! let mut tags = HashMap::new();
+//! tags.insert("name".to_string(), Value::from("Bonn"));
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.1, 50.7).into(),
+//!     tags,
+//! })?;
+//! w.finish()?;
+//! let db = w.into_inner();
+//! let name: String = db.query_row("SELECT name FROM places", [], |r| r.get(0))?;
+//! assert_eq!(name, "Bonn");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::geojson::value_to_json;
+use crate::sink::FeatureSink;
+use crate::{Feature, Value};
+use ::geojson::JsonObject;
+use geo::BoundingRect;
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// The spatial reference system of the geometries, WGS 84.
+pub const SRID: i32 = 4326;
+
+const WGS84_PROJ4: &str = "+proj=longlat +datum=WGS84 +no_defs";
+const WGS84_WKT: &str = "GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,\
+    298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],\
+    PRIMEM[\"Greenwich\",0,AUTHORITY[\"EPSG\",\"8901\"]],\
+    UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],AUTHORITY[\"EPSG\",\"4326\"]]";
+
+/// The number of tag keys that get a column of their own, see the [module](self) docs.
+pub const MAX_TAG_COLUMNS: usize = 1000;
+
+const PRIMARY_KEY: &str = "pk_uid";
+const GEOMETRY_COLUMN: &str = "geometry";
+const OTHER_TAGS_COLUMN: &str = "other_tags";
+
+fn sql_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Writes features into a table of a SpatiaLite database. All features are inserted in one
+/// transaction, which is committed by [`finish`](FeatureSink::finish).
+pub struct SpatiaLiteWriter {
+    conn: Connection,
+    table: String,
+    /// Column name of each tag key.
+    columns: HashMap<String, String>,
+    max_columns: usize,
+    other_tags: bool,
+}
+
+impl SpatiaLiteWriter {
+    /// Opens or creates the database at `path` and creates the table `table` in it.
+    pub fn create(path: impl AsRef<Path>, table: &str) -> io::Result<Self> {
+        Self::new(Connection::open(path).map_err(sql_error)?, table)
+    }
+
+    /// Creates the table `table` in the database `conn`. Fails if the table exists.
+    pub fn new(conn: Connection, table: &str) -> io::Result<Self> {
+        let table = table.to_lowercase();
+        conn.execute_batch(&format!(
+            "BEGIN;
+            CREATE TABLE IF NOT EXISTS spatial_ref_sys (
+                srid INTEGER NOT NULL PRIMARY KEY,
+                auth_name TEXT NOT NULL,
+                auth_srid INTEGER NOT NULL,
+                ref_sys_name TEXT NOT NULL DEFAULT 'Unknown',
+                proj4text TEXT NOT NULL,
+                srtext TEXT NOT NULL DEFAULT 'Undefined');
+            CREATE TABLE IF NOT EXISTS geometry_columns (
+                f_table_name TEXT NOT NULL,
+                f_geometry_column TEXT NOT NULL,
+                geometry_type INTEGER NOT NULL,
+                coord_dimension INTEGER NOT NULL,
+                srid INTEGER NOT NULL,
+                spatial_index_enabled INTEGER NOT NULL,
+                CONSTRAINT pk_geom_cols PRIMARY KEY (f_table_name, f_geometry_column));
+            CREATE TABLE {} ({} INTEGER PRIMARY KEY AUTOINCREMENT, {} BLOB);",
+            quote(&table),
+            PRIMARY_KEY,
+            GEOMETRY_COLUMN
+        ))
+        .map_err(sql_error)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO spatial_ref_sys VALUES (?, 'epsg', ?, 'WGS 84', ?, ?)",
+            rusqlite::params![SRID, SRID, WGS84_PROJ4, WGS84_WKT],
+        )
+        .map_err(sql_error)?;
+        // generic GEOMETRY type with XY coordinates
+        conn.execute(
+            "INSERT INTO geometry_columns VALUES (?, ?, 0, 2, ?, 0)",
+            rusqlite::params![table, GEOMETRY_COLUMN, SRID],
+        )
+        .map_err(sql_error)?;
+        Ok(SpatiaLiteWriter {
+            conn,
+            table,
+            columns: HashMap::new(),
+            max_columns: MAX_TAG_COLUMNS,
+            other_tags: false,
+        })
+    }
+
+    /// Gives at most `n` tag keys a column of their own instead of [`MAX_TAG_COLUMNS`].
+    pub fn with_max_columns(mut self, n: usize) -> Self {
+        self.max_columns = n;
+        self
+    }
+
+    pub fn into_inner(self) -> Connection {
+        self.conn
+    }
+
+    fn add_column(&self, column: &str) -> io::Result<()> {
+        self.conn
+            .execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {}",
+                quote(&self.table),
+                quote(column)
+            ))
+            .map_err(sql_error)
+    }
+
+    /// Returns the column of `key`, adding it to the table if necessary, or `None` if the table
+    /// has no room for another tag column.
+    fn column(&mut self, key: &str) -> io::Result<Option<String>> {
+        if let Some(c) = self.columns.get(key) {
+            return Ok(Some(c.clone()));
+        }
+        if self.columns.len() >= self.max_columns {
+            return Ok(None);
+        }
+        let taken = |c: &str| {
+            let c = c.to_lowercase();
+            c == PRIMARY_KEY
+                || c == GEOMETRY_COLUMN
+                || c == OTHER_TAGS_COLUMN
+                || self.columns.values().any(|v| v.to_lowercase() == c)
+        };
+        let mut column = key.to_string();
+        let mut n = 1;
+        while taken(&column) {
+            n += 1;
+            column = format!("{}_{}", key, n);
+        }
+        self.add_column(&column)?;
+        self.columns.insert(key.to_string(), column.clone());
+        Ok(Some(column))
+    }
+}
+
+fn sql_value(v: &Value) -> SqlValue {
+    match v {
+        Value::String(s) => SqlValue::Text(s.clone()),
+        Value::Integer(n) => SqlValue::Integer(*n),
+        Value::Float(f) => SqlValue::Real(*f),
+        Value::Bytes(b) => SqlValue::Blob(b.clone()),
+        Value::List(_) => SqlValue::Text(value_to_json(v).to_string()),
+    }
+}
+
+impl FeatureSink for SpatiaLiteWriter {
+    fn accept(&mut self, ft: Feature) -> io::Result<()> {
+        let mut keys: Vec<&String> = ft.tags.keys().collect();
+        keys.sort();
+        let mut columns = vec![GEOMETRY_COLUMN.to_string()];
+        let mut values = vec![match spatialite_blob(&ft.geometry) {
+            Some(blob) => SqlValue::Blob(blob),
+            None => SqlValue::Null,
+        }];
+        let mut other_tags = JsonObject::new();
+        for key in keys {
+            match self.column(key)? {
+                Some(column) => {
+                    columns.push(quote(&column));
+                    values.push(sql_value(&ft.tags[key]));
+                }
+                None => {
+                    other_tags.insert(key.clone(), value_to_json(&ft.tags[key]));
+                }
+            }
+        }
+        if !other_tags.is_empty() {
+            if !self.other_tags {
+                self.add_column(OTHER_TAGS_COLUMN)?;
+                self.other_tags = true;
+            }
+            columns.push(OTHER_TAGS_COLUMN.to_string());
+            values.push(SqlValue::Text(
+                ::geojson::JsonValue::Object(other_tags).to_string(),
+            ));
+        }
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote(&self.table),
+            columns.join(", "),
+            vec!["?"; values.len()].join(", ")
+        );
+        self.conn
+            .prepare_cached(&sql)
+            .and_then(|mut stmt| stmt.execute(rusqlite::params_from_iter(values)))
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.conn.execute_batch("COMMIT").map_err(sql_error)
+    }
+}
+
+const POINT: i32 = 1;
+const LINESTRING: i32 = 2;
+const POLYGON: i32 = 3;
+const MULTIPOINT: i32 = 4;
+const MULTILINESTRING: i32 = 5;
+const MULTIPOLYGON: i32 = 6;
+const GEOMETRYCOLLECTION: i32 = 7;
+
+/// Encodes `g` in the SpatiaLite BLOB format, `None` if it is empty.
+fn spatialite_blob(g: &Geometry<f64>) -> Option<Vec<u8>> {
+    let bbox = g.bounding_rect()?;
+    let mut buf = vec![0x00, 0x01];
+    buf.extend(SRID.to_le_bytes());
+    for v in [bbox.min().x, bbox.min().y, bbox.max().x, bbox.max().y] {
+        buf.extend(v.to_le_bytes());
+    }
+    buf.push(0x7C);
+    match g {
+        Geometry::Point(p) => {
+            buf.extend(POINT.to_le_bytes());
+            write_coord(&mut buf, p.0);
+        }
+        Geometry::Line(l) => {
+            buf.extend(LINESTRING.to_le_bytes());
+            write_line(&mut buf, &LineString::from(*l));
+        }
+        Geometry::LineString(ls) => {
+            buf.extend(LINESTRING.to_le_bytes());
+            write_line(&mut buf, ls);
+        }
+        Geometry::Polygon(p) => {
+            buf.extend(POLYGON.to_le_bytes());
+            write_polygon(&mut buf, p);
+        }
+        Geometry::Rect(r) => {
+            buf.extend(POLYGON.to_le_bytes());
+            write_polygon(&mut buf, &r.to_polygon());
+        }
+        Geometry::Triangle(t) => {
+            buf.extend(POLYGON.to_le_bytes());
+            write_polygon(&mut buf, &t.to_polygon());
+        }
+        Geometry::MultiPoint(mp) => {
+            buf.extend(MULTIPOINT.to_le_bytes());
+            buf.extend((mp.0.len() as i32).to_le_bytes());
+            for p in mp {
+                write_entity(&mut buf, &Geometry::Point(*p));
+            }
+        }
+        Geometry::MultiLineString(mls) => {
+            buf.extend(MULTILINESTRING.to_le_bytes());
+            buf.extend((mls.0.len() as i32).to_le_bytes());
+            for ls in mls {
+                write_entity(&mut buf, &Geometry::LineString(ls.clone()));
+            }
+        }
+        Geometry::MultiPolygon(mp) => {
+            buf.extend(MULTIPOLYGON.to_le_bytes());
+            buf.extend((mp.0.len() as i32).to_le_bytes());
+            for p in mp {
+                write_entity(&mut buf, &Geometry::Polygon(p.clone()));
+            }
+        }
+        Geometry::GeometryCollection(_) => {
+            // collections may only hold points, lines and polygons
+            let mut parts = Vec::new();
+            elementary(g, &mut parts);
+            buf.extend(GEOMETRYCOLLECTION.to_le_bytes());
+            buf.extend((parts.len() as i32).to_le_bytes());
+            for p in parts {
+                write_entity(&mut buf, &p);
+            }
+        }
+    }
+    buf.push(0xFE);
+    Some(buf)
+}
+
+/// Splits `g` into points, lines and polygons.
+fn elementary(g: &Geometry<f64>, out: &mut Vec<Geometry<f64>>) {
+    match g {
+        Geometry::MultiPoint(mp) => out.extend(mp.iter().map(|p| Geometry::Point(*p))),
+        Geometry::MultiLineString(mls) => out.extend(mls.iter().cloned().map(Geometry::from)),
+        Geometry::MultiPolygon(mp) => out.extend(mp.iter().cloned().map(Geometry::from)),
+        Geometry::GeometryCollection(gc) => gc.iter().for_each(|g| elementary(g, out)),
+        Geometry::Line(l) => out.push(LineString::from(*l).into()),
+        Geometry::Rect(r) => out.push(r.to_polygon().into()),
+        Geometry::Triangle(t) => out.push(t.to_polygon().into()),
+        g => out.push(g.clone()),
+    }
+}
+
+/// Writes a point, line or polygon as member of a collection.
+fn write_entity(buf: &mut Vec<u8>, g: &Geometry<f64>) {
+    buf.push(0x69);
+    match g {
+        Geometry::Point(p) => {
+            buf.extend(POINT.to_le_bytes());
+            write_coord(buf, p.0);
+        }
+        Geometry::LineString(ls) => {
+            buf.extend(LINESTRING.to_le_bytes());
+            write_line(buf, ls);
+        }
+        Geometry::Polygon(p) => {
+            buf.extend(POLYGON.to_le_bytes());
+            write_polygon(buf, p);
+        }
+        _ => unreachable!("not an elementary geometry"),
+    }
+}
+
+fn write_coord(buf: &mut Vec<u8>, c: Coord) {
+    buf.extend(c.x.to_le_bytes());
+    buf.extend(c.y.to_le_bytes());
+}
+
+fn write_line(buf: &mut Vec<u8>, ls: &LineString<f64>) {
+    buf.extend((ls.0.len() as i32).to_le_bytes());
+    for c in ls {
+        write_coord(buf, *c);
+    }
+}
+
+fn write_polygon(buf: &mut Vec<u8>, p: &Polygon<f64>) {
+    buf.extend((1 + p.interiors().len() as i32).to_le_bytes());
+    write_line(buf, p.exterior());
+    for ring in p.interiors() {
+        write_line(buf, ring);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spatialite_blob, SpatiaLiteWriter};
+    use crate::sink::FeatureSink;
+    use crate::{Feature, Value};
+    use geo_types::{line_string, Geometry, GeometryCollection, Point};
+    use rusqlite::types::Value as SqlValue;
+    use rusqlite::Connection;
+    use std::collections::HashMap;
+
+    #[test]
+    fn blob() {
+        let blob = spatialite_blob(&Point::new(1., 2.).into()).unwrap();
+        let mut expected = vec![0x00, 0x01, 0xE6, 0x10, 0, 0];
+        for v in [1f64, 2., 1., 2., 1.] {
+            if expected.len() == 38 {
+                expected.extend([0x7C, 1, 0, 0, 0]);
+            }
+            expected.extend(v.to_le_bytes());
+        }
+        expected.extend(2f64.to_le_bytes());
+        expected.push(0xFE);
+        assert_eq!(blob, expected);
+
+        let gc = GeometryCollection(vec![
+            Point::new(0., 0.).into(),
+            Geometry::GeometryCollection(GeometryCollection(vec![
+                line_string![(x: 1., y: 1.), (x: 2., y: 2.)].into(),
+            ])),
+        ]);
+        let blob = spatialite_blob(&Geometry::GeometryCollection(gc)).unwrap();
+        assert_eq!(blob[39..47], [7, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(blob[47], 0x69);
+        assert_eq!(blob.len(), 39 + 8 + (1 + 4 + 16) + (1 + 4 + 4 + 32) + 1);
+        assert!(
+            spatialite_blob(&Geometry::GeometryCollection(GeometryCollection(vec![]))).is_none()
+        );
+    }
+
+    #[test]
+    fn write() {
+        let feature = |geometry: Geometry<f64>, tags: &[(&str, Value)]| Feature {
+            geometry,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<HashMap<_, _>>(),
+        };
+        let mut w = SpatiaLiteWriter::new(Connection::open_in_memory().unwrap(), "Roads").unwrap();
+        w.accept(feature(
+            line_string![(x: 7., y: 51.), (x: 8., y: 52.)].into(),
+            &[("name", Value::from("A 1")), ("lanes", Value::from(2))],
+        ))
+        .unwrap();
+        w.accept(feature(
+            Geometry::GeometryCollection(GeometryCollection(vec![])),
+            &[
+                ("Name", Value::from("a")),
+                ("geometry", Value::from(1.5)),
+                ("ref", Value::List(vec![Value::from(1), Value::from(2)])),
+            ],
+        ))
+        .unwrap();
+        w.finish().unwrap();
+        let db = w.into_inner();
+
+        let registered: (String, String, i32, i32) = db
+            .query_row(
+                "SELECT f_table_name, f_geometry_column, geometry_type, srid FROM geometry_columns",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            registered,
+            ("roads".to_string(), "geometry".to_string(), 0, 4326)
+        );
+        let rows: Vec<Vec<SqlValue>> = db
+            .prepare("SELECT geometry, name, lanes, Name_2 FROM roads ORDER BY pk_uid")
+            .unwrap()
+            .query_map([], |r| (0..4).map(|i| r.get(i)).collect())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(rows[0][0], SqlValue::Blob(_)));
+        assert_eq!(rows[0][1], SqlValue::Text("A 1".to_string()));
+        assert_eq!(rows[0][2], SqlValue::Integer(2));
+        assert_eq!(rows[1][0], SqlValue::Null);
+        assert_eq!(rows[1][3], SqlValue::Text("a".to_string()));
+        let (geometry, r): (f64, String) = db
+            .query_row(
+                "SELECT geometry_2, ref FROM roads WHERE pk_uid = 2",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(geometry, 1.5);
+        assert_eq!(r, "[1,2]");
+    }
+
+    #[test]
+    fn other_tags() {
+        let mut w = SpatiaLiteWriter::new(Connection::open_in_memory().unwrap(), "pois")
+            .unwrap()
+            .with_max_columns(2);
+        for keys in [&["a", "other_tags"][..], &["a", "b", "c"], &["d"]] {
+            w.accept(Feature {
+                geometry: Point::new(1., 2.).into(),
+                tags: keys
+                    .iter()
+                    .map(|k| (k.to_string(), Value::from(*k)))
+                    .collect(),
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let db = w.into_inner();
+        let rows: Vec<Vec<SqlValue>> = db
+            .prepare("SELECT a, other_tags_2, other_tags FROM pois ORDER BY pk_uid")
+            .unwrap()
+            .query_map([], |r| (0..3).map(|i| r.get(i)).collect())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows[0][1], SqlValue::Text("other_tags".to_string()));
+        assert_eq!(rows[0][2], SqlValue::Null);
+        assert_eq!(
+            rows[1][2],
+            SqlValue::Text(r#"{"b":"b","c":"c"}"#.to_string())
+        );
+        assert_eq!(rows[2][0], SqlValue::Null);
+        assert_eq!(rows[2][2], SqlValue::Text(r#"{"d":"d"}"#.to_string()));
+    }
+}
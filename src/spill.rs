@@ -0,0 +1,715 @@
+//! Sorting, dissolving and deduplicating datasets that do not fit into memory.
+//!
+//! Features are buffered up to a memory budget. Whenever the budget is exceeded, the buffer is
+//! sorted and written to a temporary file, and the sorted runs are merged while reading.
+//! Temporary files are removed automatically, also if the operation fails.
+
+use crate::sink::FeatureSink;
+use crate::source::FeatureSource;
+#[cfg(feature = "geo")]
+use crate::transform::dissolve_by;
+use crate::transform::{dedup_geometry, DuplicateGeometry};
+use crate::{Feature, Value};
+use geo_types::{
+    Coord, Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon,
+    Rect, Triangle,
+};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// Where and when to spill.
+#[derive(Clone, Debug)]
+pub struct SpillOptions {
+    /// Directory for the temporary files.
+    pub temp_dir: PathBuf,
+    /// Approximate number of bytes of features that are kept in memory before spilling.
+    pub memory_budget: usize,
+}
+
+impl Default for SpillOptions {
+    /// The system's temporary directory and a budget of 256 MiB.
+    fn default() -> Self {
+        SpillOptions {
+            temp_dir: std::env::temp_dir(),
+            memory_budget: 256 << 20,
+        }
+    }
+}
+
+/// A rough estimate of the memory a value occupies.
+fn value_size(v: &Value) -> usize {
+    match v {
+        Value::String(s) => 24 + s.len(),
+        Value::Bytes(b) => 24 + b.len(),
+        Value::List(l) => 24 + l.iter().map(value_size).sum::<usize>(),
+        _ => 16,
+    }
+}
+
+/// A rough estimate of the memory a feature occupies.
+fn estimate_size(ft: &Feature) -> usize {
+    let tags: usize = ft
+        .tags
+        .iter()
+        .map(|(k, v)| 48 + k.len() + value_size(v))
+        .sum();
+    64 + 16 * crate::coords_count(&ft.geometry) + tags
+}
+
+/// A sorted sequence of features, either in memory or in a temporary file.
+enum Run {
+    Memory(std::vec::IntoIter<Feature>),
+    File {
+        r: BufReader<File>,
+        remaining: usize,
+    },
+}
+
+impl Run {
+    fn spill(fts: Vec<Feature>, options: &SpillOptions) -> io::Result<Run> {
+        let f = tempfile::tempfile_in(&options.temp_dir)?;
+        let mut w = BufWriter::new(f);
+        for ft in &fts {
+            write_feature(&mut w, ft)?;
+        }
+        let mut f = w.into_inner().map_err(|e| e.into_error())?;
+        f.seek(SeekFrom::Start(0))?;
+        Ok(Run::File {
+            r: BufReader::new(f),
+            remaining: fts.len(),
+        })
+    }
+
+    fn next(&mut self) -> io::Result<Option<Feature>> {
+        match self {
+            Run::Memory(fts) => Ok(fts.next()),
+            Run::File { remaining: 0, .. } => Ok(None),
+            Run::File { r, remaining } => {
+                *remaining -= 1;
+                read_feature(r).map(Some)
+            }
+        }
+    }
+}
+
+// Run files use a private encoding instead of Spaten, which would turn Lines, Rects and
+// Triangles into LineStrings and Polygons, so that the result does not depend on whether a
+// feature was spilled.
+
+fn write_u64(w: &mut impl Write, n: u64) -> io::Result<()> {
+    w.write_all(&n.to_le_bytes())
+}
+
+fn write_len(w: &mut impl Write, n: usize) -> io::Result<()> {
+    write_u64(w, n as u64)
+}
+
+fn write_bytes(w: &mut impl Write, b: &[u8]) -> io::Result<()> {
+    write_len(w, b.len())?;
+    w.write_all(b)
+}
+
+fn write_coords<'c>(
+    w: &mut impl Write,
+    coords: impl ExactSizeIterator<Item = &'c Coord<f64>>,
+) -> io::Result<()> {
+    write_len(w, coords.len())?;
+    for c in coords {
+        write_u64(w, c.x.to_bits())?;
+        write_u64(w, c.y.to_bits())?;
+    }
+    Ok(())
+}
+
+fn write_polygon(w: &mut impl Write, p: &Polygon<f64>) -> io::Result<()> {
+    write_len(w, 1 + p.interiors().len())?;
+    for ring in std::iter::once(p.exterior()).chain(p.interiors()) {
+        write_coords(w, ring.0.iter())?;
+    }
+    Ok(())
+}
+
+fn write_geometry(w: &mut impl Write, g: &Geometry<f64>) -> io::Result<()> {
+    match g {
+        Geometry::Point(p) => {
+            w.write_all(&[0])?;
+            write_coords(w, [p.0].iter())
+        }
+        Geometry::Line(l) => {
+            w.write_all(&[1])?;
+            write_coords(w, [l.start, l.end].iter())
+        }
+        Geometry::LineString(ls) => {
+            w.write_all(&[2])?;
+            write_coords(w, ls.0.iter())
+        }
+        Geometry::Polygon(p) => {
+            w.write_all(&[3])?;
+            write_polygon(w, p)
+        }
+        Geometry::MultiPoint(mp) => {
+            w.write_all(&[4])?;
+            write_coords(w, mp.0.iter().map(|p| &p.0))
+        }
+        Geometry::MultiLineString(mls) => {
+            w.write_all(&[5])?;
+            write_len(w, mls.0.len())?;
+            mls.iter().try_for_each(|ls| write_coords(w, ls.0.iter()))
+        }
+        Geometry::MultiPolygon(mp) => {
+            w.write_all(&[6])?;
+            write_len(w, mp.0.len())?;
+            mp.iter().try_for_each(|p| write_polygon(w, p))
+        }
+        Geometry::GeometryCollection(gc) => {
+            w.write_all(&[7])?;
+            write_len(w, gc.0.len())?;
+            gc.iter().try_for_each(|g| write_geometry(w, g))
+        }
+        Geometry::Rect(r) => {
+            w.write_all(&[8])?;
+            write_coords(w, [r.min(), r.max()].iter())
+        }
+        Geometry::Triangle(t) => {
+            w.write_all(&[9])?;
+            write_coords(w, t.to_array().iter())
+        }
+    }
+}
+
+fn write_value(w: &mut impl Write, v: &Value) -> io::Result<()> {
+    match v {
+        Value::String(s) => {
+            w.write_all(&[0])?;
+            write_bytes(w, s.as_bytes())
+        }
+        Value::Integer(i) => {
+            w.write_all(&[1])?;
+            w.write_all(&i.to_le_bytes())
+        }
+        Value::Float(f) => {
+            w.write_all(&[2])?;
+            write_u64(w, f.to_bits())
+        }
+        Value::Bytes(b) => {
+            w.write_all(&[3])?;
+            write_bytes(w, b)
+        }
+        Value::List(l) => {
+            w.write_all(&[4])?;
+            write_len(w, l.len())?;
+            l.iter().try_for_each(|v| write_value(w, v))
+        }
+    }
+}
+
+fn write_feature(w: &mut impl Write, ft: &Feature) -> io::Result<()> {
+    write_geometry(w, &ft.geometry)?;
+    write_len(w, ft.tags.len())?;
+    for (k, v) in &ft.tags {
+        write_bytes(w, k.as_bytes())?;
+        write_value(w, v)?;
+    }
+    Ok(())
+}
+
+fn invalid_run() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid spill file")
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut b = [0; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_len(r: &mut impl Read) -> io::Result<usize> {
+    usize::try_from(read_u64(r)?).map_err(|_| invalid_run())
+}
+
+fn read_bytes(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut b = vec![0; read_len(r)?];
+    r.read_exact(&mut b)?;
+    Ok(b)
+}
+
+fn read_string(r: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(r)?).map_err(|_| invalid_run())
+}
+
+fn read_coords(r: &mut impl Read) -> io::Result<Vec<Coord<f64>>> {
+    (0..read_len(r)?)
+        .map(|_| {
+            Ok(Coord {
+                x: f64::from_bits(read_u64(r)?),
+                y: f64::from_bits(read_u64(r)?),
+            })
+        })
+        .collect()
+}
+
+fn read_polygon(r: &mut impl Read) -> io::Result<Polygon<f64>> {
+    let mut rings = (0..read_len(r)?).map(|_| read_coords(r).map(LineString));
+    let exterior = rings.next().ok_or_else(invalid_run)??;
+    Ok(Polygon::new(exterior, rings.collect::<io::Result<_>>()?))
+}
+
+fn read_geometry(r: &mut impl Read) -> io::Result<Geometry<f64>> {
+    let coords = |r: &mut _, n| match read_coords(r)? {
+        c if c.len() == n => Ok(c),
+        _ => Err(invalid_run()),
+    };
+    Ok(match read_u8(r)? {
+        0 => Point(coords(r, 1)?[0]).into(),
+        1 => {
+            let c = coords(r, 2)?;
+            Line::new(c[0], c[1]).into()
+        }
+        2 => LineString(read_coords(r)?).into(),
+        3 => read_polygon(r)?.into(),
+        4 => MultiPoint(read_coords(r)?.into_iter().map(Point).collect()).into(),
+        5 => (0..read_len(r)?)
+            .map(|_| read_coords(r).map(LineString))
+            .collect::<io::Result<MultiLineString<f64>>>()?
+            .into(),
+        6 => (0..read_len(r)?)
+            .map(|_| read_polygon(r))
+            .collect::<io::Result<MultiPolygon<f64>>>()?
+            .into(),
+        7 => Geometry::GeometryCollection(
+            (0..read_len(r)?)
+                .map(|_| read_geometry(r))
+                .collect::<io::Result<_>>()?,
+        ),
+        8 => {
+            let c = coords(r, 2)?;
+            Rect::new(c[0], c[1]).into()
+        }
+        9 => {
+            let c = coords(r, 3)?;
+            Triangle(c[0], c[1], c[2]).into()
+        }
+        _ => return Err(invalid_run()),
+    })
+}
+
+fn read_value(r: &mut impl Read) -> io::Result<Value> {
+    Ok(match read_u8(r)? {
+        0 => Value::String(read_string(r)?),
+        1 => Value::Integer(read_u64(r)? as i64),
+        2 => Value::Float(f64::from_bits(read_u64(r)?)),
+        3 => Value::Bytes(read_bytes(r)?),
+        4 => Value::List(
+            (0..read_len(r)?)
+                .map(|_| read_value(r))
+                .collect::<io::Result<_>>()?,
+        ),
+        _ => return Err(invalid_run()),
+    })
+}
+
+fn read_feature(r: &mut impl Read) -> io::Result<Feature> {
+    let geometry = read_geometry(r)?;
+    let tags = (0..read_len(r)?)
+        .map(|_| Ok((read_string(r)?, read_value(r)?)))
+        .collect::<io::Result<_>>()?;
+    Ok(Feature { geometry, tags })
+}
+
+/// The smallest feature of a run. Ties are resolved by the run index, which keeps the sort
+/// stable.
+struct Head<K> {
+    key: K,
+    run: usize,
+    ft: Feature,
+}
+
+impl<K: Ord> Ord for Head<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&other.key, other.run).cmp(&(&self.key, self.run))
+    }
+}
+
+impl<K: Ord> PartialOrd for Head<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for Head<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for Head<K> {}
+
+/// Sorted features, returned by [`sort_by_key`].
+pub struct Sorted<K, F> {
+    runs: Vec<Run>,
+    heap: BinaryHeap<Head<K>>,
+    key: F,
+}
+
+impl<K: Ord, F: Fn(&Feature) -> K> Sorted<K, F> {
+    fn push_next(&mut self, run: usize) -> io::Result<()> {
+        if let Some(ft) = self.runs[run].next()? {
+            let key = (self.key)(&ft);
+            self.heap.push(Head { key, run, ft });
+        }
+        Ok(())
+    }
+}
+
+impl<K: Ord, F: Fn(&Feature) -> K> FeatureSource for Sorted<K, F> {
+    fn next_feature(&mut self) -> io::Result<Option<Feature>> {
+        let head = match self.heap.pop() {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+        self.push_next(head.run)?;
+        Ok(Some(head.ft))
+    }
+}
+
+impl<K: Ord, F: Fn(&Feature) -> K> Iterator for Sorted<K, F> {
+    type Item = io::Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_feature().transpose()
+    }
+}
+
+/// Sorts all features of `src` by `key`, spilling to disk if they exceed the memory budget.
+/// The sort is stable. Keys are computed again for features read back from disk, so they
+/// should only depend on the feature.
+/// ```
+/// use spaten::spill::{sort_by_key, SpillOptions};
+/// use spaten::Feature;
+///
+/// let fts: Vec<Feature> = (0..100)
+///     .map(|i| Feature {
+///         geometry: geo_types::Point::new(f64::from(i % 7), 0.).into(),
+///         tags: Default::default(),
+///     })
+///     .collect();
+/// let opts = SpillOptions { memory_budget: 1000, ..Default::default() };
+/// let key = |ft: &Feature| match ft.geometry {
+///     geo_types::Geometry::Point(p) => p.x() as i64,
+///     _ => 0,
+/// };
+/// let sorted: Vec<Feature> = sort_by_key(&mut fts.into_iter(), key, &opts)?
+///     .collect::<Result<_, _>>()?;
+/// assert_eq!(sorted.len(), 100);
+/// assert!(sorted.windows(2).all(|w| key(&w[0]) <= key(&w[1])));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn sort_by_key<S, K, F>(src: &mut S, key: F, options: &SpillOptions) -> io::Result<Sorted<K, F>>
+where
+    S: FeatureSource + ?Sized,
+    K: Ord,
+    F: Fn(&Feature) -> K,
+{
+    sort_by_sized_key(src, key, |_| std::mem::size_of::<K>(), options)
+}
+
+/// Like [`sort_by_key`], but counts the memory of the keys with `key_size`.
+fn sort_by_sized_key<S, K, F>(
+    src: &mut S,
+    key: F,
+    key_size: impl Fn(&K) -> usize,
+    options: &SpillOptions,
+) -> io::Result<Sorted<K, F>>
+where
+    S: FeatureSource + ?Sized,
+    K: Ord,
+    F: Fn(&Feature) -> K,
+{
+    let mut runs = Vec::new();
+    let mut buf: Vec<(K, Feature)> = Vec::new();
+    let mut size = 0;
+    loop {
+        let ft = src.next_feature()?;
+        if ft.is_none() || size > options.memory_budget {
+            buf.sort_by(|a, b| a.0.cmp(&b.0));
+            let fts: Vec<Feature> = buf.drain(..).map(|(_, ft)| ft).collect();
+            if ft.is_none() {
+                runs.push(Run::Memory(fts.into_iter()));
+                break;
+            }
+            runs.push(Run::spill(fts, options)?);
+            size = 0;
+        }
+        if let Some(ft) = ft {
+            let k = key(&ft);
+            size += estimate_size(&ft) + key_size(&k);
+            buf.push((k, ft));
+        }
+    }
+
+    let mut sorted = Sorted {
+        heap: BinaryHeap::with_capacity(runs.len()),
+        runs,
+        key,
+    };
+    for run in 0..sorted.runs.len() {
+        sorted.push_next(run)?;
+    }
+    Ok(sorted)
+}
+
+/// Features in Hilbert order, returned by [`sort_hilbert`].
+pub type HilbertSorted = Sorted<u64, fn(&Feature) -> u64>;
+
+/// Sorts features in Hilbert order, like [`hilbert::sort`](crate::hilbert::sort).
+pub fn sort_hilbert<S: FeatureSource + ?Sized>(
+    src: &mut S,
+    options: &SpillOptions,
+) -> io::Result<HilbertSorted> {
+    sort_by_key(
+        src,
+        crate::hilbert::feature_index as fn(&Feature) -> u64,
+        options,
+    )
+}
+
+/// Ends iteration at the first error and keeps it, so fallible sources can feed the
+/// transforms.
+struct Stash<'a, I> {
+    inner: I,
+    err: &'a RefCell<Option<io::Error>>,
+}
+
+impl<I: Iterator<Item = io::Result<Feature>>> Iterator for Stash<'_, I> {
+    type Item = Feature;
+
+    fn next(&mut self) -> Option<Feature> {
+        match self.inner.next()? {
+            Ok(ft) => Some(ft),
+            Err(e) => {
+                self.err.replace(Some(e));
+                None
+            }
+        }
+    }
+}
+
+/// Passes the transformed features to `dst` and finishes it, unless the sorted input failed.
+fn drain(
+    fts: impl Iterator<Item = Feature>,
+    err: &RefCell<Option<io::Error>>,
+    dst: &mut (impl FeatureSink + ?Sized),
+) -> io::Result<u64> {
+    let mut n = 0;
+    for ft in fts {
+        dst.accept(ft)?;
+        n += 1;
+    }
+    if let Some(e) = err.take() {
+        return Err(e);
+    }
+    dst.finish()?;
+    Ok(n)
+}
+
+/// Like [`transform::dissolve_by`](crate::transform::dissolve_by), but sorts the input by `key`
+/// first, so all features with the same value are dissolved. Returns the number of features
+/// written to `dst`, which is finished afterwards. Needs the `geo` feature.
+#[cfg(feature = "geo")]
+pub fn dissolve<S, D>(
+    src: &mut S,
+    key: &str,
+    options: &SpillOptions,
+    dst: &mut D,
+) -> io::Result<u64>
+where
+    S: FeatureSource + ?Sized,
+    D: FeatureSink + ?Sized,
+{
+    let sort_key = key.to_string();
+    let sorted = sort_by_sized_key(
+        src,
+        move |ft| ft.tags.get(&sort_key).cloned(),
+        |k| k.as_ref().map_or(8, value_size),
+        options,
+    )?;
+    let err = RefCell::new(None);
+    let fts = Stash {
+        inner: sorted,
+        err: &err,
+    };
+    drain(dissolve_by(fts, key), &err, dst)
+}
+
+/// Like [`transform::dedup_geometry`](crate::transform::dedup_geometry), but detects duplicates
+/// anywhere in the input by sorting it by geometry first. The output is in that order.
+pub fn dedup<S, D>(
+    src: &mut S,
+    mode: DuplicateGeometry,
+    options: &SpillOptions,
+    dst: &mut D,
+) -> io::Result<u64>
+where
+    S: FeatureSource + ?Sized,
+    D: FeatureSink + ?Sized,
+{
+    let sorted = sort_by_sized_key(
+        src,
+        |ft| wkb::geom_to_wkb(&ft.geometry).ok(),
+        |k| 24 + k.as_ref().map_or(0, Vec::len),
+        options,
+    )?;
+    let err = RefCell::new(None);
+    let fts = Stash {
+        inner: sorted,
+        err: &err,
+    };
+    drain(dedup_geometry(fts, mode), &err, dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dedup, sort_by_key, sort_hilbert, SpillOptions};
+    use crate::transform::DuplicateGeometry;
+    use crate::{Feature, Value};
+    use geo_types::{polygon, Geometry, GeometryCollection, Line, Point, Rect, Triangle};
+    use std::collections::HashMap;
+
+    fn small() -> SpillOptions {
+        SpillOptions {
+            memory_budget: 2000,
+            ..Default::default()
+        }
+    }
+
+    fn point(x: f64, tag: i64) -> Feature {
+        let mut tags = HashMap::new();
+        tags.insert("t".to_string(), Value::Integer(tag));
+        tags.insert(
+            "l".to_string(),
+            Value::List(vec![Value::Integer(1), Value::Integer(2)]),
+        );
+        Feature {
+            geometry: Point::new(x, 0.).into(),
+            tags,
+        }
+    }
+
+    #[test]
+    fn stable_external_sort() {
+        let fts: Vec<Feature> = (0..500)
+            .map(|i| point(f64::from(i % 13), i.into()))
+            .collect();
+        let x = |ft: &Feature| match ft.geometry {
+            Geometry::Point(p) => p.x() as i64,
+            _ => unreachable!(),
+        };
+        let sorted: Vec<Feature> = sort_by_key(&mut fts.clone().into_iter(), x, &small())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(sorted.len(), 500);
+        let tag = |ft: &Feature| ft.tags["t"].clone();
+        assert!(sorted
+            .windows(2)
+            .all(|w| x(&w[0]) < x(&w[1]) || (x(&w[0]) == x(&w[1]) && tag(&w[0]) < tag(&w[1]))));
+        assert_eq!(sorted[0].tags["l"], fts[0].tags["l"]);
+
+        let n = sort_hilbert(&mut fts.into_iter(), &small())
+            .unwrap()
+            .count();
+        assert_eq!(n, 500);
+    }
+
+    #[test]
+    fn lossless_runs() {
+        let geometries: Vec<Geometry> = vec![
+            Line::new((0., 0.), (1., 1.)).into(),
+            Rect::new((0., 0.), (1., 1.)).into(),
+            Triangle::from([(0., 0.), (1., 0.), (0., 1.)]).into(),
+            polygon![(x: 0., y: 0.), (x: 1., y: 0.), (x: 0., y: 1.)].into(),
+            Geometry::GeometryCollection(GeometryCollection(vec![
+                Rect::new((2., 2.), (3., 3.)).into()
+            ])),
+        ];
+        let fts: Vec<Feature> = (0..200)
+            .map(|i| {
+                let mut tags = HashMap::new();
+                tags.insert("i".to_string(), Value::Integer(i));
+                tags.insert("b".to_string(), Value::Bytes(b"utf-8".to_vec()));
+                tags.insert("l".to_string(), Value::List(vec![Value::Float(f64::NAN)]));
+                Feature {
+                    geometry: geometries[i as usize % geometries.len()].clone(),
+                    tags,
+                }
+            })
+            .collect();
+        let i = |ft: &Feature| match ft.tags["i"] {
+            Value::Integer(i) => i,
+            _ => unreachable!(),
+        };
+        for budget in [100, usize::MAX].iter() {
+            let opts = SpillOptions {
+                memory_budget: *budget,
+                ..Default::default()
+            };
+            let sorted: Vec<Feature> = sort_by_key(&mut fts.clone().into_iter(), i, &opts)
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(sorted.len(), fts.len());
+            for (a, b) in sorted.iter().zip(&fts) {
+                assert_eq!((&a.geometry, &a.tags), (&b.geometry, &b.tags));
+            }
+        }
+    }
+
+    #[test]
+    fn dissolve_and_dedup() {
+        let square = |x: f64, name: &str| {
+            let mut tags = HashMap::new();
+            tags.insert("name".to_string(), Value::String(name.to_string()));
+            Feature {
+                geometry:
+                    polygon![(x: x, y: 0.), (x: x + 1., y: 0.), (x: x + 1., y: 1.), (x: x, y: 1.)]
+                        .into(),
+                tags,
+            }
+        };
+        let fts: Vec<Feature> = (0..60)
+            .map(|i| square(f64::from(i), if i % 2 == 0 { "a" } else { "b" }))
+            .collect();
+        #[cfg(feature = "geo")]
+        {
+            let mut out: Vec<Feature> = Vec::new();
+            let n =
+                super::dissolve(&mut fts.clone().into_iter(), "name", &small(), &mut out).unwrap();
+            assert_eq!(n, 2);
+            assert_eq!(out.len(), 2);
+        }
+
+        let mut dups = fts.clone();
+        dups.extend(fts);
+        let mut out: Vec<Feature> = Vec::new();
+        let n = dedup(
+            &mut dups.into_iter(),
+            DuplicateGeometry::Drop,
+            &small(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(n, 60);
+    }
+}
@@ -0,0 +1,328 @@
+//! Statistics of a whole dataset, to sanity-check a file before it is shipped.
+//!
+//! Unlike [`preflight`](crate::preflight), which leaves the features undecoded, a [`Summary`]
+//! decodes every feature: it counts the geometry types, computes the bounding box from the
+//! geometries and lists the tag keys with the types of their values. It also reports how the
+//! blocks are compressed and how large they are.
+//! ```
+//! use spaten::sink::FeatureSink;
+//! use spaten::stats::Summary;
+//! use spaten::{Feature, FeatureWriter, Value};
+//! use std::collections::HashMap;
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! for (i, name) in vec![Value::from("Rhein"), Value::from(7)].into_iter().enumerate() {
+//!     let mut tags = HashMap::new();
+//!     tags.insert("name".to_string(), name);
+//!     w.accept(Feature {
+//!         geometry: geo_types::Point::new(7.0 + i as f64, 51.0).into(),
+//!         tags,
+//!     })?;
+//! }
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let s = Summary::from_reader(&mut &buf[..])?;
+//! assert_eq!(s.features, 2);
+//! assert_eq!(s.geometry_types["Point"], 2);
+//! assert_eq!(s.bbox, Some(geo_types::Rect::new((7.0, 51.0), (8.0, 51.0))));
+//! assert_eq!((s.keys["name"].string, s.keys["name"].integer), (1, 1));
+//! println!("{}", s);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{
+    bounding_rect, decode_body, open_block, read_raw_message, try_read_file_header, Compression,
+    Error, ReaderOptions, Value,
+};
+use geo_types::{Geometry, Rect};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+
+/// How many values of each type a tag key has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValueTypes {
+    pub string: u64,
+    pub integer: u64,
+    pub float: u64,
+    pub bytes: u64,
+    pub list: u64,
+}
+
+impl ValueTypes {
+    /// The number of features with the key.
+    pub fn total(&self) -> u64 {
+        self.string + self.integer + self.float + self.bytes + self.list
+    }
+
+    fn add(&mut self, v: &Value) {
+        match v {
+            Value::String(_) => self.string += 1,
+            Value::Integer(_) => self.integer += 1,
+            Value::Float(_) => self.float += 1,
+            Value::Bytes(_) => self.bytes += 1,
+            Value::List(_) => self.list += 1,
+        }
+    }
+}
+
+/// What [`Summary::from_reader`] found in a file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub features: u64,
+    /// Number of features per geometry type, e.g. `"Point"` or `"MultiPolygon"`.
+    pub geometry_types: BTreeMap<&'static str, u64>,
+    /// The union of the bounding boxes of the geometries, `None` if all are empty.
+    pub bbox: Option<Rect<f64>>,
+    /// The tag keys with the types of their values.
+    pub keys: BTreeMap<String, ValueTypes>,
+    /// Number of blocks with features.
+    pub blocks: u64,
+    /// Number of blocks with [`metadata`](crate::metadata).
+    pub meta_blocks: u64,
+    /// Number of blocks of either type per compression.
+    pub compressions: HashMap<Compression, u64>,
+    /// Number of [encrypted](crate::encryption) blocks.
+    pub encrypted_blocks: u64,
+    /// Size of the file in bytes.
+    pub file_bytes: u64,
+    /// Size of the feature block bodies after decompression.
+    pub data_bytes: u64,
+}
+
+impl Summary {
+    /// Reads the whole file. Fails on the first invalid block.
+    pub fn from_reader(r: &mut impl io::Read) -> Result<Self, Error> {
+        Self::from_reader_with_options(r, &ReaderOptions::default())
+    }
+
+    /// Like [`from_reader`](Summary::from_reader), but decodes and decrypts according to
+    /// `options`.
+    pub fn from_reader_with_options(
+        r: &mut impl io::Read,
+        options: &ReaderOptions,
+    ) -> Result<Self, Error> {
+        try_read_file_header(r)?;
+        let max_len = options.limits.max_block_size;
+        let mut s = Summary {
+            file_bytes: 12,
+            ..Default::default()
+        };
+        while let Some((header, raw)) = read_raw_message(r, max_len)? {
+            s.file_bytes += 8 + raw.len() as u64;
+            *s.compressions.entry(header.compression).or_default() += 1;
+            s.encrypted_blocks += u64::from(header.encrypted);
+            if header.meta {
+                s.meta_blocks += 1;
+                continue;
+            }
+            s.blocks += 1;
+            let body = open_block(header, raw, options.key.as_ref(), max_len)?;
+            s.data_bytes += body.len() as u64;
+            for ft in decode_body(&body, options, None)? {
+                s.features += 1;
+                if s.features > options.limits.max_features {
+                    return Err(Error::LimitExceeded("Feature count limit exceeded"));
+                }
+                *s.geometry_types.entry(type_name(&ft.geometry)).or_default() += 1;
+                if let Some(r) = bounding_rect(&ft.geometry) {
+                    s.bbox = Some(match s.bbox {
+                        Some(e) => Rect::new(
+                            (e.min().x.min(r.min().x), e.min().y.min(r.min().y)),
+                            (e.max().x.max(r.max().x), e.max().y.max(r.max().y)),
+                        ),
+                        None => r,
+                    });
+                }
+                for (k, v) in &ft.tags {
+                    match s.keys.get_mut(k) {
+                        Some(types) => types.add(v),
+                        None => s.keys.entry(k.clone()).or_default().add(v),
+                    }
+                }
+            }
+        }
+        Ok(s)
+    }
+}
+
+fn type_name(g: &Geometry<f64>) -> &'static str {
+    match g {
+        Geometry::Point(_) => "Point",
+        Geometry::Line(_) => "Line",
+        Geometry::LineString(_) => "LineString",
+        Geometry::Polygon(_) => "Polygon",
+        Geometry::MultiPoint(_) => "MultiPoint",
+        Geometry::MultiLineString(_) => "MultiLineString",
+        Geometry::MultiPolygon(_) => "MultiPolygon",
+        Geometry::GeometryCollection(_) => "GeometryCollection",
+        Geometry::Rect(_) => "Rect",
+        Geometry::Triangle(_) => "Triangle",
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "features:    {} in {} blocks, {} metadata blocks",
+            self.features, self.blocks, self.meta_blocks
+        )?;
+        match self.bbox {
+            Some(e) => writeln!(
+                f,
+                "bbox:        {},{},{},{}",
+                e.min().x,
+                e.min().y,
+                e.max().x,
+                e.max().y
+            )?,
+            None => writeln!(f, "bbox:        -")?,
+        }
+        writeln!(
+            f,
+            "bytes:       {} ({} uncompressed)",
+            self.file_bytes, self.data_bytes
+        )?;
+        let mut compressions: Vec<(String, u64)> = self
+            .compressions
+            .iter()
+            .map(|(c, &n)| {
+                let name = match c {
+                    Compression::None => "none".to_string(),
+                    Compression::Gzip => "gzip".to_string(),
+                    Compression::Registered(code) => format!("codec {}", code),
+                };
+                (name, n)
+            })
+            .collect();
+        compressions.sort();
+        write!(f, "compression:")?;
+        for (name, n) in compressions {
+            write!(f, " {} {},", name, n)?;
+        }
+        writeln!(f, " {} encrypted", self.encrypted_blocks)?;
+
+        writeln!(f, "geometries:")?;
+        for (t, n) in &self.geometry_types {
+            writeln!(f, "    {} {}", t, n)?;
+        }
+        writeln!(f, "tag keys:")?;
+        for (k, types) in &self.keys {
+            let counts = [
+                ("string", types.string),
+                ("integer", types.integer),
+                ("float", types.float),
+                ("bytes", types.bytes),
+                ("list", types.list),
+            ];
+            let counts: Vec<String> = counts
+                .iter()
+                .filter(|(_, n)| *n > 0)
+                .map(|(t, n)| format!("{} {}", t, n))
+                .collect();
+            writeln!(f, "    {} {} ({})", k, types.total(), counts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Summary;
+    use crate::metadata::Metadata;
+    use crate::sink::FeatureSink;
+    use crate::{
+        Compression, Error, Feature, FeatureWriter, Limits, ReaderOptions, Value, WriterOptions,
+    };
+    use geo_types::{line_string, Geometry, GeometryCollection, Point, Rect};
+    use std::collections::HashMap;
+
+    fn file(options: WriterOptions) -> Vec<u8> {
+        let opts = WriterOptions {
+            block_size: 2,
+            ..options
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        w.write_metadata(&Metadata::now()).unwrap();
+        for i in 0..5 {
+            let x = f64::from(i);
+            let mut tags = HashMap::new();
+            tags.insert("i".to_string(), Value::Integer(i.into()));
+            if i % 2 == 0 {
+                tags.insert("name".to_string(), Value::from("a"));
+            } else {
+                tags.insert("name".to_string(), Value::Float(x));
+            }
+            w.accept(Feature {
+                geometry: match i {
+                    4 => Geometry::GeometryCollection(GeometryCollection(vec![])),
+                    _ if i % 2 == 0 => Point::new(x, -x).into(),
+                    _ => line_string![(x: x, y: 0.), (x: x + 1., y: 2.)].into(),
+                },
+                tags,
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        w.into_inner()
+    }
+
+    #[test]
+    fn summary() {
+        let buf = file(WriterOptions {
+            gzip_level: Some(6),
+            ..Default::default()
+        });
+        let s = Summary::from_reader(&mut &buf[..]).unwrap();
+        assert_eq!((s.features, s.blocks, s.meta_blocks), (5, 3, 1));
+        assert_eq!(s.geometry_types["Point"], 2);
+        assert_eq!(s.geometry_types["LineString"], 2);
+        assert_eq!(s.geometry_types["GeometryCollection"], 1);
+        assert_eq!(s.bbox, Some(Rect::new((0., -2.), (4., 2.))));
+        assert_eq!(s.keys["i"].integer, 5);
+        assert_eq!((s.keys["name"].string, s.keys["name"].float), (3, 2));
+        assert_eq!(s.keys["name"].total(), 5);
+        assert_eq!(s.compressions[&Compression::Gzip], 4);
+        assert_eq!(s.encrypted_blocks, 0);
+        assert_eq!(s.file_bytes, buf.len() as u64);
+        assert!(s.data_bytes > 0);
+        assert!(s.to_string().contains("    name 5 (string 3, float 2)"));
+
+        let opts = ReaderOptions {
+            limits: Limits {
+                max_features: 4,
+                ..Limits::unlimited()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            Summary::from_reader_with_options(&mut &buf[..], &opts),
+            Err(Error::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn encrypted() {
+        let key = crate::encryption::Key::new([7; 32]);
+        let buf = file(WriterOptions {
+            key: Some(key.clone()),
+            ..Default::default()
+        });
+        assert!(matches!(
+            Summary::from_reader(&mut &buf[..]),
+            Err(Error::Decrypt(_))
+        ));
+        let opts = ReaderOptions {
+            key: Some(key),
+            ..Default::default()
+        };
+        let s = Summary::from_reader_with_options(&mut &buf[..], &opts).unwrap();
+        assert_eq!(s.features, 5);
+        // including the empty last block
+        assert_eq!(s.encrypted_blocks, 5);
+        assert_eq!(s.compressions[&Compression::None], 5);
+    }
+}
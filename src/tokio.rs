@@ -0,0 +1,395 @@
+//! Reading from and writing to asynchronous streams, e.g. HTTP or S3 transfers (requires the
+//! `tokio` feature).
+
+use crate::metadata::Metadata;
+use crate::metrics::Metrics;
+use crate::sink::FeatureSink;
+use crate::{
+    check_block_header, decode_raw_block, encryption, open_block_in, try_read_file_header,
+    BlockHeader, Error, Feature, FeatureWriter, RawFeature, ReaderOptions, WriterOptions,
+    MAX_PREALLOC,
+};
+use ::futures_util::stream::{self, Stream, StreamExt};
+use ::tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The asynchronous counterpart of [`FeatureIterator`](crate::FeatureIterator).
+/// ```
+/// use futures_util::StreamExt;
+/// use spaten::sink::FeatureSink;
+/// use spaten::tokio::AsyncFeatureReader;
+/// use spaten::{Feature, FeatureWriter};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut w = FeatureWriter::new(Vec::new());
+/// w.accept(Feature {
+///     geometry: geo_types::Point::new(7.0, 51.0).into(),
+///     tags: Default::default(),
+/// })?;
+/// w.finish()?;
+/// let buf = w.into_inner();
+///
+/// let fts = AsyncFeatureReader::new(&buf[..]).await?.into_stream();
+/// let fts: Vec<_> = fts.collect().await;
+/// assert_eq!(fts.len(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap()
+/// ```
+pub struct AsyncFeatureReader<R> {
+    r: R,
+    queue: VecDeque<RawFeature>,
+    options: ReaderOptions,
+    metrics: Arc<Metrics>,
+    features: u64,
+    done: bool,
+    sequence: encryption::Sequence,
+}
+
+impl<R: AsyncRead + Unpin> AsyncFeatureReader<R> {
+    /// Reads the file header. Fails if it is invalid.
+    pub async fn new(r: R) -> Result<Self, Error> {
+        Self::with_options(r, ReaderOptions::default()).await
+    }
+
+    /// Like [`new`](AsyncFeatureReader::new), but decodes according to `options`.
+    pub async fn with_options(mut r: R, options: ReaderOptions) -> Result<Self, Error> {
+        let mut buf = [0; 8];
+        r.read_exact(&mut buf).await?;
+        try_read_file_header(&mut &buf[..])?;
+        let metrics = Arc::new(Metrics::new());
+        metrics.add_bytes(8);
+        Ok(AsyncFeatureReader {
+            r,
+            queue: VecDeque::new(),
+            options,
+            metrics,
+            features: 0,
+            done: false,
+            sequence: encryption::Sequence::default(),
+        })
+    }
+
+    /// Returns a handle to the reading statistics, which can be passed to other threads.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Returns the next feature, or `None` at the end of the file. Reading stops after the first
+    /// error.
+    pub async fn next_feature(&mut self) -> Result<Option<Feature>, Error> {
+        while self.queue.is_empty() {
+            if self.done {
+                return Ok(None);
+            }
+            match self.read_next_block().await {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    self.metrics.add_bytes(4);
+                    return Ok(None);
+                }
+                Err(e) => return Err(self.fail(e)),
+            }
+        }
+        let ft = self.queue.pop_front().map(|ft| {
+            let instrument = self.options.instrument.then(|| &*self.metrics);
+            ft.decode(instrument)
+        });
+        match ft.transpose() {
+            Ok(ft) => Ok(ft),
+            Err(e) => Err(self.fail(e)),
+        }
+    }
+
+    fn fail(&mut self, e: Error) -> Error {
+        self.done = true;
+        self.queue.clear();
+        self.metrics.add_decode_error();
+        e
+    }
+
+    /// Turns the reader into a stream of features.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Feature, Error>> {
+        stream::unfold(self, |mut r| async move {
+            let ft = r.next_feature().await.transpose()?;
+            Some((ft, r))
+        })
+    }
+
+    /// Fills the queue from the next block, returns false at the end of the file.
+    async fn read_next_block(&mut self) -> Result<bool, Error> {
+        let start = self.options.instrument.then(Instant::now);
+        let (header, raw) = match self.read_raw_block().await? {
+            Some(block) => block,
+            None => {
+                if self.options.key.is_some() {
+                    self.sequence.finish()?;
+                }
+                return Ok(false);
+            }
+        };
+        if header.meta {
+            if let Some(key) = &self.options.key {
+                let max_len = self.options.limits.max_block_size;
+                open_block_in(header, raw, Some(key), Some(&mut self.sequence), max_len)?;
+            }
+            return Ok(true);
+        }
+        let fts = decode_raw_block(
+            header,
+            raw,
+            start,
+            None,
+            &self.options,
+            &self.metrics,
+            &mut self.sequence,
+        )?;
+        self.features += fts.len() as u64;
+        if self.features > self.options.limits.max_features {
+            return Err(Error::LimitExceeded("Feature count limit exceeded"));
+        }
+        self.metrics.add_features(fts.len() as u64);
+        self.queue = fts.into();
+        Ok(true)
+    }
+
+    /// Mirrors the synchronous `read_raw_block`.
+    async fn read_raw_block(&mut self) -> Result<Option<(BlockHeader, Vec<u8>)>, Error> {
+        let mut bodylen_b = [0; 4];
+        let mut n = 0;
+        while n < 4 {
+            match self.r.read(&mut bodylen_b[n..]).await {
+                Ok(0) => break,
+                Ok(m) => n += m,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+        match n {
+            0 => return Ok(None),
+            4 => {}
+            _ => return Err(Error::Truncated),
+        }
+        let bodylen = u32::from_le_bytes(bodylen_b);
+        if bodylen == 0 {
+            return Ok(None);
+        }
+        if bodylen > self.options.limits.max_block_size {
+            return Err(Error::LimitExceeded("Block size limit exceeded"));
+        }
+
+        let mut header = [0; 4];
+        self.r.read_exact(&mut header).await?;
+        let header = check_block_header(header)?;
+
+        let mut body = Vec::with_capacity(bodylen.min(MAX_PREALLOC) as usize);
+        (&mut self.r)
+            .take(u64::from(bodylen))
+            .read_to_end(&mut body)
+            .await?;
+        if body.len() < bodylen as usize {
+            return Err(Error::Truncated);
+        }
+        Ok(Some((header, body)))
+    }
+}
+
+/// The asynchronous counterpart of [`FeatureWriter`]. Blocks are encoded in memory and written
+/// as soon as they are complete, so at most one block is buffered.
+///
+/// The `max_bytes_per_sec` and `sync` [options](WriterOptions) are ignored, as they would block
+/// the runtime.
+/// ```
+/// use futures_util::{stream, StreamExt};
+/// use spaten::tokio::AsyncFeatureWriter;
+/// use spaten::{Feature, FeatureIterator};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let fts = stream::iter(0..10).map(|i| Feature {
+///     geometry: geo_types::Point::new(f64::from(i), 51.0).into(),
+///     tags: Default::default(),
+/// });
+/// let mut w = AsyncFeatureWriter::new(Vec::new());
+/// assert_eq!(w.write_stream(fts).await?, 10);
+/// w.finish().await?;
+/// let buf = w.into_inner();
+///
+/// assert_eq!(FeatureIterator::try_new(&mut &buf[..])?.count(), 10);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap()
+/// ```
+pub struct AsyncFeatureWriter<W> {
+    w: W,
+    /// Encodes into a buffer that is drained into `w` after every call.
+    enc: FeatureWriter<Vec<u8>>,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncFeatureWriter<W> {
+    pub fn new(w: W) -> Self {
+        Self::with_options(w, WriterOptions::default())
+    }
+
+    /// Like [`new`](AsyncFeatureWriter::new), but encodes according to `options`.
+    pub fn with_options(w: W, options: WriterOptions) -> Self {
+        let options = WriterOptions {
+            max_bytes_per_sec: None,
+            ..options
+        };
+        AsyncFeatureWriter {
+            w,
+            enc: FeatureWriter::with_options(Vec::new(), options),
+        }
+    }
+
+    /// Returns the underlying writer. Call [`finish`](AsyncFeatureWriter::finish) first.
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Adds a feature, and writes the block once it is full.
+    pub async fn write(&mut self, ft: Feature) -> io::Result<()> {
+        self.enc.accept(ft)?;
+        self.drain().await
+    }
+
+    /// Writes all features of `fts` and returns their number. The next feature is only taken
+    /// from the stream once the previous one is encoded and any full block is written, so a slow
+    /// writer slows down the producer instead of features piling up in memory.
+    pub async fn write_stream(&mut self, fts: impl Stream<Item = Feature>) -> io::Result<u64> {
+        let mut fts = std::pin::pin!(fts);
+        let mut n = 0;
+        while let Some(ft) = fts.next().await {
+            self.write(ft).await?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Writes the pending features, followed by a block with `metadata`, like
+    /// [`FeatureWriter::write_metadata`].
+    pub async fn write_metadata(&mut self, metadata: &Metadata) -> io::Result<()> {
+        self.enc.write_metadata(metadata)?;
+        self.drain().await
+    }
+
+    /// Writes the pending features and the end of the file, and flushes the writer.
+    pub async fn finish(&mut self) -> io::Result<()> {
+        self.enc.finish()?;
+        self.drain().await?;
+        self.w.flush().await
+    }
+
+    async fn drain(&mut self) -> io::Result<()> {
+        if !self.enc.w.is_empty() {
+            self.w.write_all(&self.enc.w).await?;
+            self.enc.w.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncFeatureReader, AsyncFeatureWriter};
+    use crate::metadata::Metadata;
+    use crate::sink::FeatureSink;
+    use crate::{Error, Feature, FeatureIterator, FeatureWriter, Value, WriterOptions};
+    use futures_util::{stream, StreamExt};
+    use std::collections::HashMap;
+
+    fn options() -> WriterOptions {
+        WriterOptions {
+            block_size: 3,
+            gzip_level: Some(1),
+            ..Default::default()
+        }
+    }
+
+    fn feature(i: i64) -> Feature {
+        let mut tags = HashMap::new();
+        tags.insert("i".to_string(), Value::Integer(i));
+        Feature {
+            geometry: geo_types::Point::new(1., 2.).into(),
+            tags,
+        }
+    }
+
+    fn file(n: i64) -> Vec<u8> {
+        let mut w = FeatureWriter::with_options(Vec::new(), options());
+        for i in 0..n {
+            w.accept(feature(i)).unwrap();
+        }
+        w.finish().unwrap();
+        w.into_inner()
+    }
+
+    #[tokio::test]
+    async fn stream() {
+        let buf = file(10);
+        let r = AsyncFeatureReader::new(&buf[..]).await.unwrap();
+        let metrics = r.metrics();
+        let fts: Vec<Feature> = r.into_stream().map(Result::unwrap).collect().await;
+        let sync: Vec<Feature> = FeatureIterator::try_new(&mut &buf[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(fts.len(), 10);
+        for (a, b) in fts.iter().zip(&sync) {
+            assert_eq!(a.tags, b.tags);
+        }
+        assert_eq!(metrics.snapshot().features, 10);
+
+        assert!(matches!(
+            AsyncFeatureReader::new(&b"SPAX\0\0\0\0"[..]).await,
+            Err(Error::InvalidMagic)
+        ));
+    }
+
+    #[tokio::test]
+    async fn truncated() {
+        let buf = file(10);
+        let mut r = AsyncFeatureReader::new(&buf[..buf.len() - 10])
+            .await
+            .unwrap();
+        let mut n = 0;
+        let err = loop {
+            match r.next_feature().await {
+                Ok(Some(_)) => n += 1,
+                Ok(None) => panic!("missing error"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(n, 9);
+        assert!(matches!(err, Error::Truncated));
+        assert!(r.next_feature().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn write_stream() {
+        let mut w = AsyncFeatureWriter::with_options(Vec::new(), options());
+        assert_eq!(
+            w.write_stream(stream::iter(0..10).map(feature))
+                .await
+                .unwrap(),
+            10
+        );
+        // a full block is written before the next feature is taken
+        let mut written = &w.w[..];
+        assert_eq!(FeatureIterator::try_new(&mut written).unwrap().count(), 9);
+        w.finish().await.unwrap();
+        assert_eq!(w.into_inner(), file(10));
+
+        let mut w = AsyncFeatureWriter::new(Vec::new());
+        w.write_metadata(&Metadata::now()).await.unwrap();
+        w.write(feature(1)).await.unwrap();
+        w.finish().await.unwrap();
+        let buf = w.into_inner();
+        let mut r = &buf[..];
+        let mut fts = FeatureIterator::try_new(&mut r).unwrap();
+        assert_eq!(fts.next().unwrap().unwrap().tags, feature(1).tags);
+        assert!(fts.metadata().unwrap().producer.is_some());
+    }
+}
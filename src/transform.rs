@@ -0,0 +1,1358 @@
+//! Transformations that operate on collections of features.
+
+use crate::{Feature, Value};
+use geo_types::{Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Polygon};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Merges LineStrings that share identical tags and touch end to end into longer lines.
+///
+/// Lines are only joined at nodes where exactly two line ends meet, so junctions are kept
+/// intact. All other geometry types are passed through unchanged. This greatly reduces the
+/// number of features of road networks exported from OSM, where ways are split frequently.
+/// ```
+/// use geo_types::line_string;
+/// use spaten::transform::merge_lines;
+/// use spaten::Feature;
+/// use std::collections::HashMap;
+///
+/// let fts = vec![
+///     Feature {
+///         geometry: line_string![(x: 0., y: 0.), (x: 1., y: 0.)].into(),
+///         tags: HashMap::new(),
+///     },
+///     Feature {
+///         geometry: line_string![(x: 1., y: 0.), (x: 2., y: 0.)].into(),
+///         tags: HashMap::new(),
+///     },
+/// ];
+/// assert_eq!(merge_lines(fts).len(), 1);
+/// ```
+pub fn merge_lines(fts: impl IntoIterator<Item = Feature>) -> Vec<Feature> {
+    let mut out = Vec::new();
+    let mut groups: BTreeMap<Vec<(String, Value)>, Vec<LineString<f64>>> = BTreeMap::new();
+
+    for ft in fts {
+        match ft.geometry {
+            Geometry::LineString(ls) if ls.0.len() >= 2 => {
+                groups.entry(sorted_tags(&ft.tags)).or_default().push(ls)
+            }
+            geometry => out.push(Feature {
+                geometry,
+                tags: ft.tags,
+            }),
+        }
+    }
+
+    for (tags, lines) in groups {
+        for ls in chain_lines(lines) {
+            out.push(Feature {
+                geometry: ls.into(),
+                tags: tags.iter().cloned().collect(),
+            });
+        }
+    }
+    out
+}
+
+fn sorted_tags(tags: &HashMap<String, Value>) -> Vec<(String, Value)> {
+    let mut v: Vec<(String, Value)> = tags.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    v.sort();
+    v
+}
+
+type Node = (u64, u64);
+
+/// The node key of a coordinate. Adding zero turns -0.0 into 0.0, so both meet at one node.
+fn node(c: &Coord<f64>) -> Node {
+    ((c.x + 0.0).to_bits(), (c.y + 0.0).to_bits())
+}
+
+/// Disjoint sets of line indices, joined at nodes where exactly two line ends meet.
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind((0..n).collect())
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.0[i] != i {
+            self.0[i] = self.0[self.0[i]];
+            i = self.0[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // the smaller index becomes the root, so components keep the input order
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+fn chain_lines(lines: Vec<LineString<f64>>) -> Vec<LineString<f64>> {
+    let mut ends: HashMap<Node, Vec<usize>> = HashMap::new();
+    for (i, ls) in lines.iter().enumerate() {
+        ends.entry(node(&ls.0[0])).or_default().push(i);
+        ends.entry(node(&ls.0[ls.0.len() - 1])).or_default().push(i);
+    }
+    let mut sets = UnionFind::new(lines.len());
+    for candidates in ends.values() {
+        if let [a, b] = candidates[..] {
+            sets.union(a, b);
+        }
+    }
+    let mut components: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..lines.len() {
+        components.entry(sets.find(i)).or_default().push(i);
+    }
+
+    let mut lines: Vec<Option<LineString<f64>>> = lines.into_iter().map(Some).collect();
+    let mut out = Vec::new();
+    for (root, members) in components {
+        let mut coords = lines[root].take().unwrap().0;
+        if members.len() > 1 {
+            extend_forward(&mut coords, &ends, &mut lines);
+            coords.reverse();
+            extend_forward(&mut coords, &ends, &mut lines);
+            coords.reverse();
+        }
+        out.push(LineString(coords));
+    }
+    out
+}
+
+/// Appends the unmerged lines of a component to the end of `coords` in the order in which
+/// they are connected.
+fn extend_forward(
+    coords: &mut Vec<Coord<f64>>,
+    ends: &HashMap<Node, Vec<usize>>,
+    lines: &mut [Option<LineString<f64>>],
+) {
+    loop {
+        let end = node(&coords[coords.len() - 1]);
+        let candidates = &ends[&end];
+        if candidates.len() != 2 {
+            return;
+        }
+        let next = match candidates.iter().find(|&&j| lines[j].is_some()) {
+            Some(&j) => lines[j].take().unwrap(),
+            None => return,
+        };
+        let mut next = next.0;
+        if node(&next[0]) != end {
+            next.reverse();
+        }
+        coords.extend(next.into_iter().skip(1));
+    }
+}
+
+/// Unions adjacent and overlapping polygons that share the same value for tag `key`.
+///
+/// Only consecutive features are dissolved, so memory is bounded by the size of a single
+/// group. For a complete dissolve, the input needs to be sorted by `key`. Each group results
+/// in one MultiPolygon feature that only carries the `key` tag. Features that are not
+/// polygonal or lack the tag are passed through unchanged. Needs the `geo` feature.
+#[cfg(feature = "geo")]
+pub fn dissolve_by<I: Iterator<Item = Feature>>(fts: I, key: &str) -> Dissolve<I> {
+    Dissolve {
+        inner: fts,
+        key: key.to_string(),
+        group: None,
+    }
+}
+
+/// Iterator returned by [`dissolve_by`].
+#[cfg(feature = "geo")]
+pub struct Dissolve<I> {
+    inner: I,
+    key: String,
+    group: Option<(Value, Vec<Polygon<f64>>)>,
+}
+
+#[cfg(feature = "geo")]
+impl<I> Dissolve<I> {
+    fn flush(&mut self) -> Option<Feature> {
+        let (val, polys) = self.group.take()?;
+        let mut tags = HashMap::with_capacity(1);
+        tags.insert(self.key.clone(), val);
+        Some(Feature {
+            geometry: geo::unary_union(polys.iter()).into(),
+            tags,
+        })
+    }
+}
+
+#[cfg(feature = "geo")]
+impl<I: Iterator<Item = Feature>> Iterator for Dissolve<I> {
+    type Item = Feature;
+
+    fn next(&mut self) -> Option<Feature> {
+        loop {
+            let ft = match self.inner.next() {
+                Some(ft) => ft,
+                None => return self.flush(),
+            };
+            let val = match (&ft.geometry, ft.tags.get(&self.key)) {
+                (Geometry::Polygon(_), Some(v)) | (Geometry::MultiPolygon(_), Some(v)) => v.clone(),
+                _ => return Some(ft),
+            };
+            let polys = match ft.geometry {
+                Geometry::Polygon(p) => vec![p],
+                Geometry::MultiPolygon(mp) => mp.0,
+                _ => unreachable!(),
+            };
+
+            match &mut self.group {
+                Some((v, group)) if *v == val => group.extend(polys),
+                Some(_) => {
+                    let done = self.flush();
+                    self.group = Some((val, polys));
+                    return done;
+                }
+                None => self.group = Some((val, polys)),
+            }
+        }
+    }
+}
+
+/// What [`dedup_geometry`] does with a feature whose geometry equals the previous one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateGeometry {
+    /// Drop the feature including its tags.
+    Drop,
+    /// Drop the feature, but add its tags to the first feature of the run. Keys that are
+    /// already present keep their first value.
+    MergeTags,
+}
+
+/// Removes features whose geometry is exactly equal to that of the preceding feature.
+///
+/// Only consecutive duplicates are detected, so this works in constant memory on streams
+/// where duplicates are adjacent, e.g. because features were split by tags upstream.
+/// ```
+/// use spaten::transform::{dedup_geometry, DuplicateGeometry};
+/// use spaten::Feature;
+/// use std::collections::HashMap;
+///
+/// let ft = Feature {
+///     geometry: geo_types::Point::new(1., 2.).into(),
+///     tags: HashMap::new(),
+/// };
+/// let fts = vec![ft.clone(), ft.clone(), ft];
+/// assert_eq!(dedup_geometry(fts.into_iter(), DuplicateGeometry::Drop).count(), 1);
+/// ```
+pub fn dedup_geometry<I: Iterator<Item = Feature>>(
+    fts: I,
+    mode: DuplicateGeometry,
+) -> DedupGeometry<I> {
+    DedupGeometry {
+        inner: fts,
+        mode,
+        pending: None,
+    }
+}
+
+/// Iterator returned by [`dedup_geometry`].
+pub struct DedupGeometry<I> {
+    inner: I,
+    mode: DuplicateGeometry,
+    pending: Option<Feature>,
+}
+
+impl<I: Iterator<Item = Feature>> Iterator for DedupGeometry<I> {
+    type Item = Feature;
+
+    fn next(&mut self) -> Option<Feature> {
+        loop {
+            let ft = match self.inner.next() {
+                Some(ft) => ft,
+                None => return self.pending.take(),
+            };
+            match &mut self.pending {
+                Some(prev) if prev.geometry == ft.geometry => {
+                    if self.mode == DuplicateGeometry::MergeTags {
+                        for (k, v) in ft.tags {
+                            prev.tags.entry(k).or_insert(v);
+                        }
+                    }
+                }
+                Some(_) => return self.pending.replace(ft),
+                None => self.pending = Some(ft),
+            }
+        }
+    }
+}
+
+/// Keeps at most `per_cell` features per cell of a square grid with `cell_size`, preferring
+/// those with the highest numeric value of tag `rank_key`.
+///
+/// Features are assigned to the cell that contains the center of their bounding box. Integer,
+/// float and numeric string tags are used as rank, features without one rank last, and ties are
+/// resolved in favor of the earlier feature. The kept features are returned in input order.
+/// Fails unless `cell_size` is finite and positive.
+/// ```
+/// use spaten::transform::thin;
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let city = |x: f64, population: i64| {
+///     let mut tags = HashMap::new();
+///     tags.insert("population".to_string(), Value::Integer(population));
+///     Feature { geometry: geo_types::Point::new(x, 0.5).into(), tags }
+/// };
+/// let fts = vec![city(0.1, 500), city(0.2, 9000), city(1.5, 20)];
+/// let kept = thin(fts, 1.0, 1, "population")?;
+/// assert_eq!(kept.len(), 2);
+/// assert_eq!(kept[0].tags["population"], Value::Integer(9000));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn thin(
+    fts: impl IntoIterator<Item = Feature>,
+    cell_size: f64,
+    per_cell: usize,
+    rank_key: &str,
+) -> io::Result<Vec<Feature>> {
+    check_cell_size(cell_size)?;
+    let fts: Vec<Feature> = fts.into_iter().collect();
+    let mut cells: HashMap<(i64, i64), Vec<(f64, usize)>> = HashMap::new();
+    for (i, ft) in fts.iter().enumerate() {
+        let center = match crate::bounding_rect(&ft.geometry) {
+            Some(r) => r.center(),
+            None => continue,
+        };
+        let cell = (
+            (center.x / cell_size).floor() as i64,
+            (center.y / cell_size).floor() as i64,
+        );
+        let rank = match ft.tags.get(rank_key) {
+            Some(Value::Integer(v)) => *v as f64,
+            Some(Value::Float(v)) => *v,
+            Some(Value::String(s)) => s.trim().parse().unwrap_or(f64::NAN),
+            _ => f64::NAN,
+        };
+        let rank = if rank.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            rank
+        };
+        cells.entry(cell).or_default().push((rank, i));
+    }
+
+    let mut keep = vec![false; fts.len()];
+    for mut members in cells.into_values() {
+        members.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        for (_, i) in members.into_iter().take(per_cell) {
+            keep[i] = true;
+        }
+    }
+    Ok(fts
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(ft, keep)| keep.then_some(ft))
+        .collect())
+}
+
+/// Draws `n` features uniformly at random, in a single pass that only keeps the sample in memory.
+/// The same `seed` gives the same sample, and the sample is returned in input order.
+/// ```
+/// use spaten::transform::sample;
+/// use spaten::Feature;
+///
+/// let fts = (0..100).map(|i| Feature {
+///     geometry: geo_types::Point::new(f64::from(i), 0.).into(),
+///     tags: Default::default(),
+/// });
+/// assert_eq!(sample(fts, 10, 42).len(), 10);
+/// ```
+pub fn sample(fts: impl IntoIterator<Item = Feature>, n: usize, seed: u64) -> Vec<Feature> {
+    let mut rng = SplitMix64(seed);
+    let mut reservoir = Reservoir::new(n);
+    for (i, ft) in fts.into_iter().enumerate() {
+        reservoir.offer(i, ft, &mut rng);
+    }
+    let mut kept = reservoir.kept;
+    kept.sort_by_key(|(i, _)| *i);
+    kept.into_iter().map(|(_, ft)| ft).collect()
+}
+
+/// Like [`sample`], but draws up to `per_class` features for every value of tag `key`, so that
+/// rare classes are represented as well as common ones. Features without the tag form a class
+/// of their own.
+/// ```
+/// use spaten::transform::sample_stratified;
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let road = |highway: &str| {
+///     let mut tags = HashMap::new();
+///     tags.insert("highway".to_string(), Value::from(highway));
+///     Feature { geometry: geo_types::Point::new(1., 2.).into(), tags }
+/// };
+/// let mut fts = vec![road("motorway")];
+/// fts.extend((0..1000).map(|_| road("residential")));
+/// let sampled = sample_stratified(fts, "highway", 5, 0);
+/// assert_eq!(sampled.len(), 6);
+/// assert_eq!(sampled[0].tags["highway"], Value::from("motorway"));
+/// ```
+pub fn sample_stratified(
+    fts: impl IntoIterator<Item = Feature>,
+    key: &str,
+    per_class: usize,
+    seed: u64,
+) -> Vec<Feature> {
+    let mut rng = SplitMix64(seed);
+    let mut classes: BTreeMap<Option<Value>, Reservoir> = BTreeMap::new();
+    for (i, ft) in fts.into_iter().enumerate() {
+        classes
+            .entry(ft.tags.get(key).cloned())
+            .or_insert_with(|| Reservoir::new(per_class))
+            .offer(i, ft, &mut rng);
+    }
+    let mut kept: Vec<(usize, Feature)> = classes.into_values().flat_map(|r| r.kept).collect();
+    kept.sort_by_key(|(i, _)| *i);
+    kept.into_iter().map(|(_, ft)| ft).collect()
+}
+
+/// A uniform sample of up to `n` of the offered features, with their input positions.
+struct Reservoir {
+    n: usize,
+    seen: u64,
+    kept: Vec<(usize, Feature)>,
+}
+
+impl Reservoir {
+    fn new(n: usize) -> Self {
+        Reservoir {
+            n,
+            seen: 0,
+            kept: Vec::new(),
+        }
+    }
+
+    fn offer(&mut self, i: usize, ft: Feature, rng: &mut SplitMix64) {
+        self.seen += 1;
+        if self.kept.len() < self.n {
+            self.kept.push((i, ft));
+        } else {
+            let j = rng.below(self.seen) as usize;
+            if j < self.n {
+                self.kept[j] = (i, ft);
+            }
+        }
+    }
+}
+
+/// A small, fast generator whose output only depends on the seed, which is all sampling needs.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next()) * u128::from(n)) >> 64) as u64
+    }
+}
+
+/// Mean earth radius in meters, as used by the local projection of [`buffer`].
+#[cfg(feature = "geo")]
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Replaces every geometry by the polygonal area within `distance_m` meters around it.
+///
+/// Coordinates are expected to be longitude/latitude. Each geometry is projected onto a local
+/// equirectangular plane around its center, buffered in meters and projected back, which is
+/// accurate for distances that are small compared to the earth's radius. Needs the `geo`
+/// feature.
+#[cfg(feature = "geo")]
+pub fn buffer<I: Iterator<Item = Feature>>(
+    fts: I,
+    distance_m: f64,
+) -> impl Iterator<Item = Feature> {
+    use geo::{BoundingRect, Buffer, MapCoords};
+
+    fts.map(move |ft| {
+        let center = match ft.geometry.bounding_rect() {
+            Some(r) => r.center(),
+            None => return ft,
+        };
+        let scale = EARTH_RADIUS * std::f64::consts::PI / 180.;
+        let cos_lat = center.y.to_radians().cos().max(1e-9);
+
+        let projected = ft.geometry.map_coords(|c| Coord {
+            x: (c.x - center.x) * cos_lat * scale,
+            y: (c.y - center.y) * scale,
+        });
+        let buffered = projected.buffer(distance_m).map_coords(|c| Coord {
+            x: c.x / (cos_lat * scale) + center.x,
+            y: c.y / scale + center.y,
+        });
+        Feature {
+            geometry: buffered.into(),
+            tags: ft.tags,
+        }
+    })
+}
+
+/// Line simplification algorithms supported by [`simplify`].
+#[cfg(feature = "geo")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Simplification {
+    /// Ramer–Douglas–Peucker, `epsilon` is a distance. May create self-intersections.
+    DouglasPeucker,
+    /// Visvalingam–Whyatt, `epsilon` is an area. May create self-intersections.
+    VisvalingamWhyatt,
+    /// Visvalingam–Whyatt that never introduces self-intersections, so polygons stay valid
+    /// even at low zoom levels. `epsilon` is an area.
+    TopologyPreserving,
+}
+
+/// Simplifies lines and polygons using `method`. Points are passed through unchanged. Needs the
+/// `geo` feature.
+#[cfg(feature = "geo")]
+pub fn simplify<I: Iterator<Item = Feature>>(
+    fts: I,
+    epsilon: f64,
+    method: Simplification,
+) -> impl Iterator<Item = Feature> {
+    fts.map(move |ft| Feature {
+        geometry: simplify_geometry(ft.geometry, epsilon, method),
+        tags: ft.tags,
+    })
+}
+
+#[cfg(feature = "geo")]
+fn simplify_geometry(g: Geometry<f64>, epsilon: f64, method: Simplification) -> Geometry<f64> {
+    use geo::{Simplify, SimplifyVw, SimplifyVwPreserve};
+
+    macro_rules! apply {
+        ($g:expr) => {
+            match method {
+                Simplification::DouglasPeucker => $g.simplify(epsilon),
+                Simplification::VisvalingamWhyatt => $g.simplify_vw(epsilon),
+                Simplification::TopologyPreserving => $g.simplify_vw_preserve(epsilon),
+            }
+        };
+    }
+
+    match g {
+        Geometry::LineString(g) => apply!(g).into(),
+        Geometry::MultiLineString(g) => apply!(g).into(),
+        Geometry::Polygon(g) => apply!(g).into(),
+        Geometry::MultiPolygon(g) => apply!(g).into(),
+        Geometry::GeometryCollection(gc) => Geometry::GeometryCollection(
+            gc.into_iter()
+                .map(|g| simplify_geometry(g, epsilon, method))
+                .collect(),
+        ),
+        g => g,
+    }
+}
+
+/// Rounds all coordinates to the nearest multiple of `cell_size`.
+///
+/// Consecutive duplicate vertices that result from snapping are removed, as are parts that
+/// become degenerate (lines with less than two vertices, rings with less than three distinct
+/// vertices). Features whose geometry collapses entirely are dropped. Fails unless
+/// `cell_size` is finite and positive.
+pub fn snap_to_grid<I: Iterator<Item = Feature>>(
+    fts: I,
+    cell_size: f64,
+) -> io::Result<impl Iterator<Item = Feature>> {
+    check_cell_size(cell_size)?;
+    Ok(fts.filter_map(move |ft| {
+        Some(Feature {
+            geometry: snap_geometry(ft.geometry, cell_size)?,
+            tags: ft.tags,
+        })
+    }))
+}
+
+fn check_cell_size(cell_size: f64) -> io::Result<()> {
+    if !(cell_size.is_finite() && cell_size > 0.) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Cell size must be finite and positive",
+        ));
+    }
+    Ok(())
+}
+
+fn snap_coord(c: Coord<f64>, cell_size: f64) -> Coord<f64> {
+    Coord {
+        x: (c.x / cell_size).round() * cell_size,
+        y: (c.y / cell_size).round() * cell_size,
+    }
+}
+
+fn snap_line(ls: LineString<f64>, cell_size: f64, min_len: usize) -> Option<LineString<f64>> {
+    let mut coords: Vec<Coord<f64>> = ls.0.into_iter().map(|c| snap_coord(c, cell_size)).collect();
+    coords.dedup();
+    if coords.len() < min_len {
+        return None;
+    }
+    Some(LineString(coords))
+}
+
+fn snap_polygon(p: Polygon<f64>, cell_size: f64) -> Option<Polygon<f64>> {
+    let (exterior, interiors) = p.into_inner();
+    let exterior = snap_line(exterior, cell_size, 4)?;
+    let interiors = interiors
+        .into_iter()
+        .filter_map(|r| snap_line(r, cell_size, 4))
+        .collect();
+    Some(Polygon::new(exterior, interiors))
+}
+
+fn snap_geometry(g: Geometry<f64>, cell_size: f64) -> Option<Geometry<f64>> {
+    let g: Geometry<f64> = match g {
+        Geometry::Point(p) => Geometry::Point(snap_coord(p.0, cell_size).into()),
+        Geometry::MultiPoint(mp) => {
+            let mut pts: Vec<geo_types::Point<f64>> = Vec::with_capacity(mp.0.len());
+            for p in mp {
+                let p = snap_coord(p.0, cell_size).into();
+                if !pts.contains(&p) {
+                    pts.push(p);
+                }
+            }
+            MultiPoint(pts).into()
+        }
+        Geometry::Line(l) => snap_line(l.into(), cell_size, 2)?.into(),
+        Geometry::LineString(ls) => snap_line(ls, cell_size, 2)?.into(),
+        Geometry::MultiLineString(mls) => MultiLineString(
+            mls.into_iter()
+                .filter_map(|ls| snap_line(ls, cell_size, 2))
+                .collect(),
+        )
+        .into(),
+        Geometry::Polygon(p) => snap_polygon(p, cell_size)?.into(),
+        Geometry::Rect(r) => snap_polygon(r.to_polygon(), cell_size)?.into(),
+        Geometry::Triangle(t) => snap_polygon(t.to_polygon(), cell_size)?.into(),
+        Geometry::MultiPolygon(mp) => MultiPolygon(
+            mp.into_iter()
+                .filter_map(|p| snap_polygon(p, cell_size))
+                .collect(),
+        )
+        .into(),
+        Geometry::GeometryCollection(gc) => Geometry::GeometryCollection(
+            gc.into_iter()
+                .filter_map(|g| snap_geometry(g, cell_size))
+                .collect(),
+        ),
+    };
+
+    let empty = match &g {
+        Geometry::MultiPoint(mp) => mp.0.is_empty(),
+        Geometry::MultiLineString(mls) => mls.0.is_empty(),
+        Geometry::MultiPolygon(mp) => mp.0.is_empty(),
+        Geometry::GeometryCollection(gc) => gc.0.is_empty(),
+        _ => false,
+    };
+    if empty {
+        None
+    } else {
+        Some(g)
+    }
+}
+
+/// Splits features with more than `max_vertices` vertices into several features.
+///
+/// Lines are cut into consecutive pieces that share their end vertices, polygons are
+/// subdivided by recursively halving their bounding box, and multi-geometries are split into
+/// groups of parts. Every resulting feature keeps the original tags and gets an additional
+/// integer tag `part_key` with the index of the part. Features within the budget are passed
+/// through unchanged. `max_vertices` is raised to at least 8, as clipping polygons adds
+/// vertices. Needs the `geo` feature.
+#[cfg(feature = "geo")]
+pub fn split_by_vertex_count<I: Iterator<Item = Feature>>(
+    fts: I,
+    max_vertices: usize,
+    part_key: &str,
+) -> impl Iterator<Item = Feature> {
+    let max_vertices = max_vertices.max(8);
+    let part_key = part_key.to_string();
+    fts.flat_map(move |ft| {
+        if crate::coords_count(&ft.geometry) <= max_vertices {
+            return vec![ft];
+        }
+        let tags = ft.tags;
+        split_geometry(ft.geometry, max_vertices)
+            .into_iter()
+            .enumerate()
+            .map(|(i, geometry)| {
+                let mut tags = tags.clone();
+                tags.insert(part_key.clone(), Value::Integer(i as i64));
+                Feature { geometry, tags }
+            })
+            .collect()
+    })
+}
+
+#[cfg(feature = "geo")]
+fn split_geometry(g: Geometry<f64>, max_vertices: usize) -> Vec<Geometry<f64>> {
+    match g {
+        Geometry::LineString(ls) => split_line(ls, max_vertices)
+            .into_iter()
+            .map(Geometry::from)
+            .collect(),
+        Geometry::MultiLineString(mls) => mls
+            .into_iter()
+            .flat_map(|ls| split_line(ls, max_vertices))
+            .map(Geometry::from)
+            .collect(),
+        Geometry::Polygon(p) => subdivide(p, max_vertices, 0)
+            .into_iter()
+            .map(Geometry::from)
+            .collect(),
+        Geometry::MultiPolygon(mp) => mp
+            .into_iter()
+            .flat_map(|p| subdivide(p, max_vertices, 0))
+            .map(Geometry::from)
+            .collect(),
+        Geometry::MultiPoint(mp) => {
+            mp.0.chunks(max_vertices)
+                .map(|c| MultiPoint(c.to_vec()).into())
+                .collect()
+        }
+        Geometry::GeometryCollection(gc) => gc
+            .into_iter()
+            .flat_map(|g| split_geometry(g, max_vertices))
+            .collect(),
+        g => vec![g],
+    }
+}
+
+#[cfg(feature = "geo")]
+fn split_line(ls: LineString<f64>, max_vertices: usize) -> Vec<LineString<f64>> {
+    if ls.0.len() <= max_vertices {
+        return vec![ls];
+    }
+    // consecutive pieces overlap by one vertex to stay connected
+    let mut out = Vec::new();
+    let mut start = 0;
+    while start + 1 < ls.0.len() {
+        let end = (start + max_vertices).min(ls.0.len());
+        out.push(LineString(ls.0[start..end].to_vec()));
+        start = end - 1;
+    }
+    out
+}
+
+/// Limits the recursion for polygons that do not get smaller when being clipped, e.g. because
+/// of precision issues.
+#[cfg(feature = "geo")]
+const MAX_SUBDIVIDE_DEPTH: usize = 32;
+
+#[cfg(feature = "geo")]
+fn subdivide(p: Polygon<f64>, max_vertices: usize, depth: usize) -> Vec<Polygon<f64>> {
+    use geo::{BooleanOps, BoundingRect, CoordsIter};
+
+    if p.coords_count() <= max_vertices || depth >= MAX_SUBDIVIDE_DEPTH {
+        return vec![p];
+    }
+    let bbox = match p.bounding_rect() {
+        Some(b) => b,
+        None => return vec![p],
+    };
+    let (min, max, center) = (bbox.min(), bbox.max(), bbox.center());
+    let halves = if bbox.width() >= bbox.height() {
+        [
+            geo_types::Rect::new(
+                min,
+                Coord {
+                    x: center.x,
+                    y: max.y,
+                },
+            ),
+            geo_types::Rect::new(
+                Coord {
+                    x: center.x,
+                    y: min.y,
+                },
+                max,
+            ),
+        ]
+    } else {
+        [
+            geo_types::Rect::new(
+                min,
+                Coord {
+                    x: max.x,
+                    y: center.y,
+                },
+            ),
+            geo_types::Rect::new(
+                Coord {
+                    x: min.x,
+                    y: center.y,
+                },
+                max,
+            ),
+        ]
+    };
+
+    halves
+        .iter()
+        .flat_map(|half| p.intersection(&half.to_polygon()))
+        .flat_map(|piece| subdivide(piece, max_vertices, depth + 1))
+        .collect()
+}
+
+/// Groups features into batches of `n`, e.g. for bulk inserts into a database. The last batch
+/// holds the remaining features and may be smaller. Works with any item type, so the results of
+/// a [`FeatureIterator`](crate::FeatureIterator) can be batched before handling errors.
+/// ```
+/// use spaten::transform::batches;
+/// use spaten::Feature;
+///
+/// let ft = Feature {
+///     geometry: geo_types::Point::new(1., 2.).into(),
+///     tags: Default::default(),
+/// };
+/// let sizes: Vec<usize> = batches(vec![ft; 5].into_iter(), 2).map(|b| b.len()).collect();
+/// assert_eq!(sizes, [2, 2, 1]);
+/// ```
+///
+/// # Panics
+///
+/// If `n` is 0.
+pub fn batches<I: Iterator>(items: I, n: usize) -> Batches<I> {
+    assert!(n > 0, "batch size must not be 0");
+    Batches { inner: items, n }
+}
+
+/// Iterator returned by [`batches`].
+pub struct Batches<I> {
+    inner: I,
+    n: usize,
+}
+
+impl<I: Iterator> Iterator for Batches<I> {
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        let batch: Vec<I::Item> = self.inner.by_ref().take(self.n).collect();
+        if batch.is_empty() {
+            None
+        } else {
+            Some(batch)
+        }
+    }
+}
+
+/// Marks the batch processing as failed when a worker stops early, whether by an error or a
+/// panic, and takes the remaining batches off the channel, which keeps the reading thread from
+/// blocking on a full channel until it sees the failure.
+struct Drain<'a, T> {
+    rx: &'a Mutex<mpsc::Receiver<T>>,
+    failed: &'a AtomicBool,
+    done: bool,
+}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        self.failed.store(true, Ordering::Relaxed);
+        // the lock is never held while `f` runs, so it cannot be poisoned by a panic in `f`
+        let rx = self.rx.lock().unwrap_or_else(|e| e.into_inner());
+        while rx.recv().is_ok() {}
+    }
+}
+
+/// Calls `f` with [`batches`] of `n` features on `threads` worker threads, while the features
+/// are read on the calling thread. Stops reading after the first error of `f` and returns it
+/// once the running calls are done. At most `threads` batches wait for a worker, which bounds
+/// memory when the workers are slower than the input.
+/// ```
+/// use spaten::transform::for_each_batch_parallel;
+/// use spaten::Feature;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let ft = Feature {
+///     geometry: geo_types::Point::new(1., 2.).into(),
+///     tags: Default::default(),
+/// };
+/// let inserted = AtomicUsize::new(0);
+/// for_each_batch_parallel(vec![ft; 1000], 100, 4, |batch| {
+///     inserted.fetch_add(batch.len(), Ordering::Relaxed);
+///     Ok::<(), std::io::Error>(())
+/// })?;
+/// assert_eq!(inserted.into_inner(), 1000);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Panics
+///
+/// If `n` or `threads` is 0, or if `f` panics.
+pub fn for_each_batch_parallel<I, E, F>(items: I, n: usize, threads: usize, f: F) -> Result<(), E>
+where
+    I: IntoIterator,
+    I::Item: Send,
+    E: Send,
+    F: Fn(Vec<I::Item>) -> Result<(), E> + Sync,
+{
+    assert!(threads > 0, "number of threads must not be 0");
+    let batches = batches(items.into_iter(), n);
+    let (tx, rx) = mpsc::sync_channel::<Vec<I::Item>>(threads);
+    let rx = Mutex::new(rx);
+    let failed = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut drain = Drain {
+                        rx: &rx,
+                        failed: &failed,
+                        done: false,
+                    };
+                    loop {
+                        let batch = match rx.lock().unwrap().recv() {
+                            Ok(batch) => batch,
+                            Err(_) => {
+                                drain.done = true;
+                                return Ok(());
+                            }
+                        };
+                        f(batch)?;
+                    }
+                })
+            })
+            .collect();
+        for batch in batches {
+            if failed.load(Ordering::Relaxed) || tx.send(batch).is_err() {
+                break;
+            }
+        }
+        drop(tx);
+        let mut result = Ok(());
+        for w in workers {
+            let r = w.join().unwrap_or_else(|e| std::panic::resume_unwind(e));
+            if result.is_ok() {
+                result = r;
+            }
+        }
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Feature, Value};
+    use geo_types::{line_string, Geometry};
+    use std::collections::HashMap;
+
+    fn line(coords: &[(f64, f64)], highway: &str) -> Feature {
+        let mut tags = HashMap::new();
+        tags.insert("highway".to_string(), Value::String(highway.to_string()));
+        Feature {
+            geometry: geo_types::LineString::from(coords.to_vec()).into(),
+            tags,
+        }
+    }
+
+    #[test]
+    fn merge_lines() {
+        use super::merge_lines;
+
+        let fts = vec![
+            line(&[(1., 0.), (2., 0.)], "primary"),
+            line(&[(3., 0.), (2., 0.)], "primary"),
+            line(&[(0., 0.), (1., 0.)], "primary"),
+            // different tags
+            line(&[(3., 0.), (4., 0.)], "secondary"),
+            // junction at (5, 0)
+            line(&[(5., 0.), (6., 0.)], "primary"),
+            line(&[(5., 0.), (5., 1.)], "primary"),
+            line(&[(5., 0.), (5., -1.)], "primary"),
+        ];
+        let merged = merge_lines(fts);
+        assert_eq!(merged.len(), 5);
+        let long = merged
+            .iter()
+            .find(|ft| matches!(&ft.geometry, Geometry::LineString(ls) if ls.0.len() == 4))
+            .unwrap();
+        assert_eq!(
+            long.geometry,
+            line_string![(x: 0., y: 0.), (x: 1., y: 0.), (x: 2., y: 0.), (x: 3., y: 0.)].into()
+        );
+    }
+
+    #[test]
+    fn merge_ring() {
+        use super::merge_lines;
+
+        let fts = vec![
+            line(&[(0., 0.), (1., 0.)], "primary"),
+            line(&[(1., 0.), (1., 1.)], "primary"),
+            line(&[(1., 1.), (0., 0.)], "primary"),
+        ];
+        let merged = merge_lines(fts);
+        assert_eq!(merged.len(), 1);
+        match &merged[0].geometry {
+            Geometry::LineString(ls) => assert_eq!(ls.0.len(), 4),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn merge_signed_zero() {
+        use super::merge_lines;
+
+        let fts = vec![
+            line(&[(-1., 0.), (0., -0.)], "primary"),
+            line(&[(-0., 0.), (1., 0.)], "primary"),
+        ];
+        let merged = merge_lines(fts);
+        assert_eq!(merged.len(), 1);
+        match &merged[0].geometry {
+            Geometry::LineString(ls) => assert_eq!(ls.0.len(), 3),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dedup_geometry() {
+        use super::DuplicateGeometry;
+
+        let fts = vec![
+            line(&[(0., 0.), (1., 1.)], "primary"),
+            line(&[(0., 0.), (1., 1.)], "secondary"),
+            line(&[(1., 1.), (0., 0.)], "primary"),
+            line(&[(0., 0.), (1., 1.)], "primary"),
+        ];
+        let mut with_ref = fts.clone();
+        with_ref[1]
+            .tags
+            .insert("ref".to_string(), Value::String("B1".to_string()));
+
+        let out: Vec<Feature> =
+            super::dedup_geometry(with_ref.clone().into_iter(), DuplicateGeometry::Drop).collect();
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].tags, fts[0].tags);
+        assert_eq!(out[2].tags, fts[3].tags);
+
+        let out: Vec<Feature> =
+            super::dedup_geometry(with_ref.into_iter(), DuplicateGeometry::MergeTags).collect();
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].tags["highway"], Value::String("primary".to_string()));
+        assert_eq!(out[0].tags["ref"], Value::String("B1".to_string()));
+        assert!(!out[1].tags.contains_key("ref"));
+    }
+
+    #[test]
+    #[cfg(feature = "geo")]
+    fn dissolve_by() {
+        use geo::Area;
+        use geo_types::{polygon, Point};
+
+        let square = |x: f64, lvl: i64| {
+            let mut tags = HashMap::new();
+            tags.insert("admin_level".to_string(), Value::Integer(lvl));
+            tags.insert("name".to_string(), Value::String(format!("{}", x)));
+            Feature {
+                geometry: polygon![
+                    (x: x, y: 0.), (x: x + 1., y: 0.), (x: x + 1., y: 1.), (x: x, y: 1.)
+                ]
+                .into(),
+                tags,
+            }
+        };
+        let fts = vec![
+            square(0., 4),
+            square(1., 4),
+            Feature {
+                geometry: Point::new(0., 0.).into(),
+                tags: HashMap::new(),
+            },
+            square(2., 4),
+            square(5., 6),
+        ];
+        let out: Vec<Feature> = super::dissolve_by(fts.into_iter(), "admin_level").collect();
+        assert_eq!(out.len(), 3);
+        assert!(matches!(out[0].geometry, Geometry::Point(_)));
+        match &out[1].geometry {
+            Geometry::MultiPolygon(mp) => {
+                assert_eq!(mp.0.len(), 1);
+                assert_eq!(mp.unsigned_area(), 3.);
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(out[1].tags.len(), 1);
+        assert_eq!(out[2].tags["admin_level"], Value::Integer(6));
+    }
+
+    #[test]
+    #[cfg(feature = "geo")]
+    fn buffer() {
+        use geo::{BoundingRect, Contains};
+        use geo_types::Point;
+
+        let fts = vec![Feature {
+            geometry: Point::new(7.0, 51.0).into(),
+            tags: HashMap::new(),
+        }];
+        let out: Vec<Feature> = super::buffer(fts.into_iter(), 1000.).collect();
+        let rect = out[0].geometry.bounding_rect().unwrap();
+        // 1 km is ~0.009 degrees latitude
+        assert!((rect.height() / 2. - 0.008993).abs() < 1e-4);
+        assert!(rect.width() > rect.height());
+        assert!(out[0].geometry.contains(&Point::new(7.0, 51.0)));
+    }
+
+    #[test]
+    #[cfg(feature = "geo")]
+    fn simplify() {
+        use super::Simplification;
+
+        let fts = vec![line(
+            &[
+                (0., 0.),
+                (1., 0.1),
+                (2., -0.1),
+                (3., 5.),
+                (4., 6.),
+                (5., 7.),
+                (6., 8.1),
+                (7., 9.),
+            ],
+            "primary",
+        )];
+        for method in [
+            Simplification::DouglasPeucker,
+            Simplification::VisvalingamWhyatt,
+            Simplification::TopologyPreserving,
+        ] {
+            let out: Vec<Feature> = super::simplify(fts.clone().into_iter(), 1., method).collect();
+            match &out[0].geometry {
+                Geometry::LineString(ls) => {
+                    assert!(ls.0.len() < 8, "{:?} did not simplify", method);
+                    assert_eq!(ls.0[0], (0., 0.).into());
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn snap_to_grid() {
+        use geo_types::{polygon, LineString};
+
+        let fts = vec![
+            line(&[(0.1, 0.1), (0.2, 0.3), (1.1, 0.9), (1.9, 2.2)], "primary"),
+            // collapses into a single vertex
+            line(&[(0.1, 0.1), (0.3, 0.2)], "primary"),
+            Feature {
+                geometry: polygon![(x: 0., y: 0.), (x: 0.2, y: 0.), (x: 0.2, y: 0.2)].into(),
+                tags: HashMap::new(),
+            },
+        ];
+        for cell_size in [0., -1., f64::NAN, f64::INFINITY] {
+            assert!(super::snap_to_grid(fts.clone().into_iter(), cell_size).is_err());
+        }
+        let out: Vec<Feature> = super::snap_to_grid(fts.into_iter(), 1.).unwrap().collect();
+        assert_eq!(out.len(), 1);
+        assert_eq!(
+            out[0].geometry,
+            LineString::from(vec![(0., 0.), (1., 1.), (2., 2.)]).into()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "geo")]
+    fn split_by_vertex_count() {
+        use geo::{Area, CoordsIter};
+        use geo_types::{Coord, LineString, Polygon};
+
+        let coords: Vec<(f64, f64)> = (0..100).map(|i| (i as f64, 0.)).collect();
+        let out: Vec<Feature> =
+            super::split_by_vertex_count(vec![line(&coords, "primary")].into_iter(), 10, "part")
+                .collect();
+        assert_eq!(out.len(), 11);
+        assert_eq!(out[3].tags["part"], Value::Integer(3));
+        assert_eq!(out[3].tags["highway"], Value::String("primary".to_string()));
+        for w in out.windows(2) {
+            match (&w[0].geometry, &w[1].geometry) {
+                (Geometry::LineString(a), Geometry::LineString(b)) => {
+                    assert_eq!(a.0.last(), b.0.first())
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // a circle with 200 vertices
+        let ring: Vec<Coord<f64>> = (0..=200)
+            .map(|i| {
+                let a = (i % 200) as f64 / 200. * std::f64::consts::TAU;
+                Coord {
+                    x: a.cos(),
+                    y: a.sin(),
+                }
+            })
+            .collect();
+        let circle = Polygon::new(LineString(ring), vec![]);
+        let area = circle.unsigned_area();
+        let fts = vec![Feature {
+            geometry: circle.into(),
+            tags: HashMap::new(),
+        }];
+        let out: Vec<Feature> = super::split_by_vertex_count(fts.into_iter(), 30, "part").collect();
+        assert!(out.len() > 1);
+        assert!(out.iter().all(|ft| ft.geometry.coords_count() <= 30));
+        let total: f64 = out.iter().map(|ft| ft.geometry.unsigned_area()).sum();
+        assert!((total - area).abs() < 1e-6, "{} != {}", total, area);
+    }
+
+    #[test]
+    fn thin() {
+        use geo_types::Point;
+
+        let place = |x: f64, y: f64, rank: Option<Value>| {
+            let mut tags = HashMap::new();
+            if let Some(rank) = rank {
+                tags.insert("importance".to_string(), rank);
+            }
+            Feature {
+                geometry: Point::new(x, y).into(),
+                tags,
+            }
+        };
+        let fts = vec![
+            place(0.5, 0.5, Some(Value::Integer(3))),
+            place(0.6, 0.5, None),
+            place(0.7, 0.5, Some(Value::String("7.5".to_string()))),
+            place(0.8, 0.5, Some(Value::Float(3.))),
+            place(-0.5, 0.5, None),
+            place(1.5, 0.5, Some(Value::String("high".to_string()))),
+        ];
+        let xs = |fts: Vec<Feature>| -> Vec<f64> {
+            fts.iter()
+                .map(|ft| match &ft.geometry {
+                    Geometry::Point(p) => p.x(),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        assert_eq!(
+            xs(super::thin(fts.clone(), 1., 2, "importance").unwrap()),
+            [0.5, 0.7, -0.5, 1.5]
+        );
+        assert_eq!(
+            xs(super::thin(fts.clone(), 10., 1, "importance").unwrap()),
+            [0.7, -0.5]
+        );
+        assert!(super::thin(fts.clone(), 1., 0, "importance")
+            .unwrap()
+            .is_empty());
+        assert!(super::thin(fts.clone(), 0., 1, "importance").is_err());
+        assert!(super::thin(fts, f64::NAN, 1, "importance").is_err());
+    }
+
+    #[test]
+    fn sample() {
+        let xs = |fts: Vec<Feature>| -> Vec<f64> {
+            fts.iter()
+                .map(|ft| match ft.geometry {
+                    Geometry::Point(p) => p.x(),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        let points = |n: i32| {
+            (0..n).map(|i| Feature {
+                geometry: geo_types::Point::new(f64::from(i), 0.).into(),
+                tags: HashMap::new(),
+            })
+        };
+        let a = xs(super::sample(points(100), 10, 1));
+        assert_eq!(a.len(), 10);
+        assert!(a.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(a, xs(super::sample(points(100), 10, 1)));
+        assert_ne!(a, xs(super::sample(points(100), 10, 2)));
+        assert_eq!(super::sample(points(3), 10, 1).len(), 3);
+        let low = (0..1000)
+            .filter(|seed| xs(super::sample(points(100), 1, *seed))[0] < 50.)
+            .count();
+        assert!((400..600).contains(&low), "{}", low);
+
+        let classes = points(504).map(|mut ft| {
+            let x = match ft.geometry {
+                Geometry::Point(p) => p.x(),
+                _ => unreachable!(),
+            };
+            match x as i32 {
+                0..=2 => {}
+                3 => {
+                    ft.tags
+                        .insert("highway".to_string(), Value::from("motorway"));
+                }
+                _ => {
+                    ft.tags
+                        .insert("highway".to_string(), Value::from("residential"));
+                }
+            }
+            ft
+        });
+        let sampled = super::sample_stratified(classes, "highway", 2, 7);
+        let highways: Vec<Option<&Value>> =
+            sampled.iter().map(|ft| ft.tags.get("highway")).collect();
+        assert_eq!(sampled.len(), 5);
+        assert_eq!(highways[..3], [None, None, Some(&Value::from("motorway"))]);
+        assert!(xs(sampled).windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn batches() {
+        use std::sync::Mutex;
+
+        let sizes: Vec<usize> = super::batches(0..10, 3).map(|b| b.len()).collect();
+        assert_eq!(sizes, [3, 3, 3, 1]);
+        assert_eq!(super::batches(0..0, 3).count(), 0);
+
+        let seen = Mutex::new(Vec::new());
+        super::for_each_batch_parallel(0..1000, 7, 3, |batch| {
+            seen.lock().unwrap().extend(batch);
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, (0..1000).collect::<Vec<_>>());
+
+        let r = super::for_each_batch_parallel(0..1000, 10, 2, |batch| {
+            if batch.contains(&500) {
+                Err(batch[0])
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(r, Err(500));
+
+        // all workers fail while the input is not read yet
+        let r = super::for_each_batch_parallel(0..100, 1, 1, |_| Err("boom"));
+        assert_eq!(r, Err("boom"));
+        let r = super::for_each_batch_parallel(0..100, 1, 4, |_| Err("boom"));
+        assert_eq!(r, Err("boom"));
+    }
+
+    #[test]
+    #[should_panic(expected = "worker failed")]
+    fn parallel_batches_panic() {
+        let _ = super::for_each_batch_parallel(0..1000, 1, 2, |_| -> Result<(), ()> {
+            panic!("worker failed")
+        });
+    }
+}
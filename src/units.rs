@@ -0,0 +1,149 @@
+//! Parsing of OSM-style measurements with units into numbers with canonical units.
+//!
+//! Speeds are normalized to km/h and lengths to meters, following the conventions of the
+//! OSM wiki (a plain number is in km/h or meters respectively).
+
+use crate::{Feature, Value};
+
+/// The kind of measurement a tag holds, which determines the accepted units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantity {
+    /// `maxspeed=50 mph`, normalized to km/h.
+    Speed,
+    /// `height=12 m`, `width=3'6"`, normalized to meters.
+    Length,
+}
+
+impl Quantity {
+    pub fn parse(&self, s: &str) -> Option<f64> {
+        match self {
+            Quantity::Speed => parse_speed(s),
+            Quantity::Length => parse_length(s),
+        }
+    }
+}
+
+/// Parses a speed such as `50`, `50 mph` or `10 knots` into km/h.
+pub fn parse_speed(s: &str) -> Option<f64> {
+    let (num, unit) = split_number(s)?;
+    let factor = match unit {
+        "" | "km/h" | "kmh" | "kph" => 1.,
+        "mph" => 1.609344,
+        "knots" | "kn" => 1.852,
+        _ => return None,
+    };
+    Some(num * factor)
+}
+
+/// Parses a length such as `12`, `12 m`, `0.5 km`, `10 ft` or `3'6"` into meters.
+pub fn parse_length(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if let Some(m) = parse_feet_inches(s) {
+        return Some(m);
+    }
+    let (num, unit) = split_number(s)?;
+    let factor = match unit {
+        "" | "m" => 1.,
+        "km" => 1000.,
+        "cm" => 0.01,
+        "mm" => 0.001,
+        "mi" => 1609.344,
+        "nmi" => 1852.,
+        "ft" => 0.3048,
+        "in" => 0.0254,
+        _ => return None,
+    };
+    Some(num * factor)
+}
+
+/// Parses the imperial notation `3'6"`, `3'` or `6"`.
+fn parse_feet_inches(s: &str) -> Option<f64> {
+    let (feet, rest) = match s.split_once('\'') {
+        Some((f, rest)) => (f.trim().parse::<f64>().ok()?, rest.trim()),
+        None => (0., s),
+    };
+    let inches = if rest.is_empty() {
+        if !s.contains('\'') {
+            return None;
+        }
+        0.
+    } else {
+        rest.strip_suffix('"')?.trim().parse::<f64>().ok()?
+    };
+    Some(feet * 0.3048 + inches * 0.0254)
+}
+
+/// Splits a string into a leading number (accepting `,` as decimal separator) and the
+/// remaining unit.
+fn split_number(s: &str) -> Option<(f64, &str)> {
+    let s = s.trim();
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',' || c == '-'))
+        .unwrap_or(s.len());
+    let num = s[..end].replace(',', ".").parse::<f64>().ok()?;
+    Some((num, s[end..].trim()))
+}
+
+/// Replaces string tags that hold measurements by float tags in canonical units. `rules`
+/// maps tag keys to the quantity they hold. Values that cannot be parsed are left unchanged.
+/// ```
+/// use spaten::units::{normalize, Quantity};
+/// use spaten::{Feature, Value};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("maxspeed".to_string(), Value::String("30 mph".to_string()));
+/// let ft = Feature {
+///     geometry: geo_types::Point::new(0., 0.).into(),
+///     tags,
+/// };
+/// let rules = [("maxspeed", Quantity::Speed)];
+/// let ft = normalize(vec![ft].into_iter(), &rules).next().unwrap();
+/// assert!(matches!(ft.tags["maxspeed"], Value::Float(v) if (v - 48.28).abs() < 0.01));
+/// ```
+pub fn normalize<'a, I: Iterator<Item = Feature> + 'a>(
+    fts: I,
+    rules: &'a [(&'a str, Quantity)],
+) -> impl Iterator<Item = Feature> + 'a {
+    fts.map(move |mut ft| {
+        for (key, quantity) in rules {
+            if let Some(Value::String(s)) = ft.tags.get(*key) {
+                if let Some(v) = quantity.parse(s) {
+                    ft.tags.insert(key.to_string(), Value::Float(v));
+                }
+            }
+        }
+        ft
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_length, parse_speed};
+
+    fn approx(a: Option<f64>, b: f64) -> bool {
+        matches!(a, Some(a) if (a - b).abs() < 1e-6)
+    }
+
+    #[test]
+    fn speeds() {
+        assert!(approx(parse_speed("50"), 50.));
+        assert!(approx(parse_speed("50 mph"), 80.4672));
+        assert!(approx(parse_speed("5 knots"), 9.26));
+        assert_eq!(parse_speed("walk"), None);
+        assert_eq!(parse_speed("50 furlongs"), None);
+    }
+
+    #[test]
+    fn lengths() {
+        assert!(approx(parse_length("12"), 12.));
+        assert!(approx(parse_length("12 m"), 12.));
+        assert!(approx(parse_length("12m"), 12.));
+        assert!(approx(parse_length("2,5 km"), 2500.));
+        assert!(approx(parse_length("10 ft"), 3.048));
+        assert!(approx(parse_length("3'6\""), 1.0668));
+        assert!(approx(parse_length("3'"), 0.9144));
+        assert!(approx(parse_length("6\""), 0.1524));
+        assert_eq!(parse_length("tall"), None);
+    }
+}
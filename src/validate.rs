@@ -0,0 +1,511 @@
+//! Checking a whole file for problems.
+//!
+//! [`validate`] reads the blocks in order and decodes them on all cores, so its speed is mostly
+//! bound by the disk. Problems are reported as [`Finding`]s with a [`Severity`], sorted by the
+//! offset of the block they are in:
+//!
+//! * errors make the file unreadable, at least in part: broken frames, blocks that cannot be
+//!   decompressed or decrypted, invalid protobuf messages, geometries or tag values,
+//! * warnings point to data that readers may handle differently than intended: bounding boxes
+//!   that do not cover their geometry and are thus skipped by spatial filters, geometry types
+//!   that do not match the header, coordinates outside of WGS 84, non-finite floats and files
+//!   without end marker,
+//! * infos are worth knowing: empty geometries and keys that occur several times in a feature,
+//!   which are only read as lists with [`DuplicateTags::Collect`](crate::DuplicateTags::Collect).
+//! ```
+//! use spaten::sink::FeatureSink;
+//! use spaten::validate::{validate, Severity, ValidateOptions};
+//! use spaten::{Feature, FeatureWriter};
+//!
+//! let mut w = FeatureWriter::new(Vec::new());
+//! w.accept(Feature {
+//!     geometry: geo_types::Point::new(7.0, 51.0).into(),
+//!     tags: Default::default(),
+//! })?;
+//! w.finish()?;
+//! let buf = w.into_inner();
+//!
+//! let v = validate(&mut &buf[..], &ValidateOptions::default());
+//! assert!(v.is_valid());
+//! assert_eq!(v.features, 1);
+//! let v = validate(&mut &buf[..buf.len() - 4], &ValidateOptions::default());
+//! assert_eq!(v.findings[0].severity, Severity::Warning);
+//! assert_eq!(
+//!     v.findings[0].message,
+//!     "end marker is missing, the file may be truncated"
+//! );
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::transform::for_each_batch_parallel;
+use crate::{
+    encryption, fileformat, metadata, open_block, read_raw_message, try_read_file_header, wkbfast,
+    BlockHeader, Value,
+};
+use fileformat::Feature_GeomType;
+use geo_types::Geometry;
+use protobuf::Message;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+
+/// Findings reported per block at most, so that a broken block does not flood the report.
+pub const MAX_FINDINGS_PER_BLOCK: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found by [`validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// Offset of the block in the file, or of the problem if it is outside a block.
+    pub offset: u64,
+    /// Index of the feature within the block.
+    pub feature: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset {}", self.offset)?;
+        if let Some(i) = self.feature {
+            write!(f, ", feature {}", i)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// The result of [`validate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validation {
+    pub blocks: u64,
+    pub features: u64,
+    /// The findings, sorted by offset and feature.
+    pub findings: Vec<Finding>,
+}
+
+impl Validation {
+    /// Whether there are no errors. Warnings and infos are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.count(Severity::Error) == 0
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(f, "{}", finding)?;
+        }
+        write!(
+            f,
+            "{} blocks, {} features: {} errors, {} warnings, {} infos",
+            self.blocks,
+            self.features,
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidateOptions {
+    /// Number of threads decoding blocks.
+    pub threads: usize,
+    /// Decrypts the blocks with this key, see [`encryption`].
+    pub key: Option<encryption::Key>,
+    /// Blocks larger than this are reported as errors, before they are allocated.
+    pub max_block_size: u32,
+}
+
+impl Default for ValidateOptions {
+    /// Uses all cores.
+    fn default() -> Self {
+        ValidateOptions {
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            key: None,
+            max_block_size: u32::MAX,
+        }
+    }
+}
+
+/// Counts the bytes read, to know the offsets of the blocks.
+struct Counting<'a, R> {
+    r: &'a mut R,
+    n: u64,
+}
+
+impl<R: io::Read> io::Read for Counting<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.r.read(buf)?;
+        self.n += n as u64;
+        Ok(n)
+    }
+}
+
+/// Reads the whole file and reports its problems. Never fails: unreadable parts are reported as
+/// errors, and reading stops at the first broken block frame.
+pub fn validate(r: &mut impl io::Read, opts: &ValidateOptions) -> Validation {
+    let mut r = Counting { r, n: 0 };
+    let result = Mutex::new(Validation::default());
+    let finding = |offset, severity, message: String| Finding {
+        offset,
+        feature: None,
+        severity,
+        message,
+    };
+    if let Err(e) = try_read_file_header(&mut r) {
+        return Validation {
+            findings: vec![finding(0, Severity::Error, e.to_string())],
+            ..Default::default()
+        };
+    }
+
+    let mut end = None;
+    let blocks = std::iter::from_fn(|| {
+        let offset = r.n;
+        match read_raw_message(&mut r, opts.max_block_size) {
+            Ok(Some((header, body))) => Some((offset, header, body)),
+            Ok(None) => {
+                end = match r.n - offset {
+                    0 => Some(finding(
+                        offset,
+                        Severity::Warning,
+                        "end marker is missing, the file may be truncated".to_string(),
+                    )),
+                    _ if io::Read::read(&mut r, &mut [0]).is_ok_and(|n| n > 0) => Some(finding(
+                        r.n - 1,
+                        Severity::Warning,
+                        "data after end marker".to_string(),
+                    )),
+                    _ => None,
+                };
+                None
+            }
+            Err(e) => {
+                end = Some(finding(offset, Severity::Error, e.to_string()));
+                None
+            }
+        }
+    });
+    let checked = for_each_batch_parallel(blocks, 1, opts.threads.max(1), |batch| {
+        for (offset, header, body) in batch {
+            let (features, findings) = check_block(offset, header, body, opts);
+            let mut result = result.lock().unwrap();
+            result.blocks += 1;
+            result.features += features;
+            result.findings.extend(findings);
+        }
+        Ok::<(), Infallible>(())
+    });
+    match checked {
+        Ok(()) => {}
+        Err(e) => match e {},
+    }
+
+    let mut result = result.into_inner().unwrap();
+    result.findings.extend(end);
+    result.findings.sort_by_key(|f| (f.offset, f.feature));
+    result
+}
+
+/// Decodes a block and returns its number of features and its findings.
+fn check_block(
+    offset: u64,
+    header: BlockHeader,
+    body: Vec<u8>,
+    opts: &ValidateOptions,
+) -> (u64, Vec<Finding>) {
+    let mut findings = Vec::new();
+    let mut report = |feature, severity, message: String| {
+        findings.push(Finding {
+            offset,
+            feature,
+            severity,
+            message,
+        })
+    };
+    let body = match open_block(header, body, opts.key.as_ref(), opts.max_block_size) {
+        Ok(body) => body,
+        Err(e) => {
+            report(None, Severity::Error, e.to_string());
+            return (0, findings);
+        }
+    };
+    if header.meta {
+        if let Err(e) = metadata::Metadata::decode(&body) {
+            report(None, Severity::Error, format!("invalid metadata: {}", e));
+        }
+        return (0, findings);
+    }
+    let body = match fileformat::Body::parse_from_bytes(&body) {
+        Ok(body) => body,
+        Err(e) => {
+            report(None, Severity::Error, format!("invalid block body: {}", e));
+            return (0, findings);
+        }
+    };
+    for (i, ft) in body.feature.iter().enumerate() {
+        for (severity, message) in check_feature(ft) {
+            report(Some(i), severity, message);
+        }
+    }
+    if findings.len() > MAX_FINDINGS_PER_BLOCK {
+        findings.truncate(MAX_FINDINGS_PER_BLOCK);
+        findings.push(Finding {
+            offset,
+            feature: None,
+            severity: Severity::Info,
+            message: "further findings in this block are omitted".to_string(),
+        });
+    }
+    (body.feature.len() as u64, findings)
+}
+
+fn check_feature(ft: &fileformat::Feature) -> Vec<(Severity, String)> {
+    let mut findings = Vec::new();
+    match wkbfast::decode(&ft.geom) {
+        Ok(g) => check_geometry(ft, &g, &mut findings),
+        Err(e) => findings.push((Severity::Error, format!("invalid geometry: {}", e))),
+    }
+
+    let mut keys: HashMap<&str, usize> = HashMap::new();
+    for tag in ft.tags.iter() {
+        *keys.entry(&tag.key).or_default() += 1;
+        match Value::from_bytes(tag.value.clone(), tag.field_type) {
+            Ok(Value::Float(f)) if !f.is_finite() => findings.push((
+                Severity::Warning,
+                format!("tag {}: non-finite float {}", tag.key, f),
+            )),
+            Ok(_) => {}
+            Err(e) => findings.push((Severity::Error, format!("tag {}: {}", tag.key, e))),
+        }
+    }
+    let mut repeated: Vec<(&str, usize)> = keys.into_iter().filter(|(_, n)| *n > 1).collect();
+    repeated.sort();
+    for (key, n) in repeated {
+        findings.push((
+            Severity::Info,
+            format!(
+                "tag {} occurs {} times, read as list only when collected",
+                key, n
+            ),
+        ));
+    }
+    findings
+}
+
+fn check_geometry(ft: &fileformat::Feature, g: &Geometry<f64>, out: &mut Vec<(Severity, String)>) {
+    let geomtype = match g {
+        Geometry::Point(_) | Geometry::MultiPoint(_) => Feature_GeomType::POINT,
+        Geometry::LineString(_) | Geometry::MultiLineString(_) => Feature_GeomType::LINE,
+        Geometry::Polygon(_) | Geometry::MultiPolygon(_) => Feature_GeomType::POLYGON,
+        _ => Feature_GeomType::UNKNOWN,
+    };
+    if ft.geomtype != Feature_GeomType::UNKNOWN && ft.geomtype != geomtype {
+        out.push((
+            Severity::Warning,
+            format!(
+                "geometry type is {:?}, but the header says {:?}",
+                geomtype, ft.geomtype
+            ),
+        ));
+    }
+    let r = match crate::bounding_rect(g) {
+        Some(r) => r,
+        None => {
+            out.push((Severity::Info, "empty geometry".to_string()));
+            return;
+        }
+    };
+    let (min, max) = (r.min(), r.max());
+    if ![min.x, min.y, max.x, max.y].iter().all(|c| c.is_finite()) {
+        out.push((Severity::Warning, "non-finite coordinates".to_string()));
+        return;
+    }
+    if min.x < -180. || max.x > 180. || min.y < -90. || max.y > 90. {
+        out.push((
+            Severity::Warning,
+            "coordinates outside of WGS 84 bounds".to_string(),
+        ));
+    }
+    if ft.left > min.x || ft.bottom > min.y || ft.right < max.x || ft.top < max.y {
+        out.push((
+            Severity::Warning,
+            "bounding box does not cover the geometry".to_string(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, Severity, ValidateOptions, MAX_FINDINGS_PER_BLOCK};
+    use crate::fileformat;
+    use crate::sink::FeatureSink;
+    use crate::{Feature, FeatureWriter, Value, WriterOptions};
+    use geo_types::{line_string, Point};
+    use protobuf::Message;
+    use std::collections::HashMap;
+
+    /// Frames `bodies` as uncompressed blocks of a file with end marker.
+    fn file(bodies: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = b"SPAT\0\0\0\0".to_vec();
+        for body in bodies {
+            buf.extend((body.len() as u32).to_le_bytes());
+            buf.extend([0; 4]);
+            buf.extend(body);
+        }
+        buf.extend([0; 4]);
+        buf
+    }
+
+    fn point(x: f64, y: f64) -> fileformat::Feature {
+        let mut ft = fileformat::Feature::new();
+        ft.geom = wkb::geom_to_wkb(&Point::new(x, y).into()).unwrap();
+        ft.geomtype = fileformat::Feature_GeomType::POINT;
+        (ft.left, ft.right, ft.bottom, ft.top) = (x, x, y, y);
+        ft
+    }
+
+    fn body(fts: Vec<fileformat::Feature>) -> Vec<u8> {
+        let mut body = fileformat::Body::new();
+        body.feature = fts.into();
+        body.write_to_bytes().unwrap()
+    }
+
+    #[test]
+    fn valid() {
+        let opts = WriterOptions {
+            block_size: 3,
+            gzip_level: Some(1),
+            ..Default::default()
+        };
+        let mut w = FeatureWriter::with_options(Vec::new(), opts);
+        for i in 0..100 {
+            let mut tags = HashMap::new();
+            tags.insert("i".to_string(), Value::Integer(i));
+            let x = i as f64 / 10.;
+            w.accept(Feature {
+                geometry: line_string![(x: x, y: 0.), (x: 1., y: x)].into(),
+                tags,
+            })
+            .unwrap();
+        }
+        w.finish().unwrap();
+        let buf = w.into_inner();
+        let opts = ValidateOptions {
+            threads: 4,
+            ..Default::default()
+        };
+        let v = validate(&mut &buf[..], &opts);
+        assert_eq!(v.findings, []);
+        assert_eq!((v.blocks, v.features), (34, 100));
+        assert_eq!(
+            v.to_string(),
+            "34 blocks, 100 features: 0 errors, 0 warnings, 0 infos"
+        );
+    }
+
+    #[test]
+    fn findings() {
+        let mut shifted = point(1., 2.);
+        shifted.left = 1.5;
+        let mut typed = point(200., 2.);
+        typed.geomtype = fileformat::Feature_GeomType::LINE;
+        let mut tagged = point(0., 0.);
+        for (key, field_type, value) in [
+            ("a", fileformat::Tag_ValueType::STRING, b"x".to_vec()),
+            ("a", fileformat::Tag_ValueType::STRING, b"y".to_vec()),
+            ("n", fileformat::Tag_ValueType::INT, vec![1, 2, 3]),
+            (
+                "f",
+                fileformat::Tag_ValueType::DOUBLE,
+                f64::NAN.to_le_bytes().to_vec(),
+            ),
+        ] {
+            let mut tag = fileformat::Tag::new();
+            tag.key = key.to_string();
+            tag.field_type = field_type;
+            tag.value = value;
+            tagged.tags.push(tag);
+        }
+        let mut broken = point(0., 0.);
+        broken.geom.truncate(10);
+
+        let first = body(vec![point(1., 2.), shifted, typed]);
+        let second = body(vec![tagged, broken]);
+        let offset = 8 + 8 + first.len() as u64;
+        let mut buf = file(&[first, second, b"\xff\xff".to_vec()]);
+        let v = validate(&mut &buf[..], &ValidateOptions::default());
+        let found: Vec<(u64, Option<usize>, Severity)> = v
+            .findings
+            .iter()
+            .map(|f| (f.offset, f.feature, f.severity))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (8, Some(1), Severity::Warning),
+                (8, Some(2), Severity::Warning),
+                (8, Some(2), Severity::Warning),
+                (offset, Some(0), Severity::Error),
+                (offset, Some(0), Severity::Warning),
+                (offset, Some(0), Severity::Info),
+                (offset, Some(1), Severity::Error),
+                (v.findings[7].offset, None, Severity::Error),
+            ]
+        );
+        assert_eq!((v.blocks, v.features), (3, 5));
+        assert!(!v.is_valid());
+        assert_eq!(
+            v.findings[0].to_string(),
+            "offset 8, feature 1: warning: bounding box does not cover the geometry"
+        );
+
+        // trailing data, and a frame that is cut off
+        buf.push(0);
+        let v = validate(&mut &buf[..], &ValidateOptions::default());
+        assert_eq!(v.findings.last().unwrap().message, "data after end marker");
+        let v = validate(&mut &buf[..20], &ValidateOptions::default());
+        assert_eq!(v.blocks, 0);
+        assert_eq!(v.findings[0].severity, Severity::Error);
+        let v = validate(&mut &b"SPAX"[..], &ValidateOptions::default());
+        assert_eq!(v.findings[0].offset, 0);
+    }
+
+    #[test]
+    fn capped() {
+        let fts = (0..MAX_FINDINGS_PER_BLOCK + 1)
+            .map(|_| point(1., 100.))
+            .collect();
+        let buf = file(&[body(fts)]);
+        let v = validate(&mut &buf[..], &ValidateOptions::default());
+        assert_eq!(v.findings.len(), MAX_FINDINGS_PER_BLOCK + 1);
+        assert_eq!(v.findings[0].feature, None);
+        assert_eq!(v.count(Severity::Warning), MAX_FINDINGS_PER_BLOCK);
+    }
+}
@@ -0,0 +1,252 @@
+//! Specialized WKB decoding for the most common geometry types.
+//!
+//! Points, LineStrings and Polygons in little endian 2D WKB make up the vast majority of
+//! Spaten files. Their coordinate arrays are decoded in bulk here, without the per-coordinate
+//! reader calls of the generic `wkb` crate, which is used for everything else.
+
+use geo_types::{Coord, Geometry, LineString, Point, Polygon};
+use std::io::Cursor;
+use wkb::WKBReadExt;
+
+const LITTLE_ENDIAN: u8 = 1;
+const WKB_POINT: u32 = 1;
+const WKB_LINESTRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTIPOINT: u32 = 4;
+const WKB_MULTILINESTRING: u32 = 5;
+const WKB_MULTIPOLYGON: u32 = 6;
+const WKB_GEOMETRYCOLLECTION: u32 = 7;
+
+const INVALID: &str = "Invalid WKB geometry";
+
+/// GeometryCollection nesting supported by the generic decoder, which recurses per level.
+const MAX_DEPTH: usize = 256;
+
+/// Decodes a WKB geometry, using the fast path when possible.
+pub(crate) fn decode(buf: &[u8]) -> Result<Geometry<f64>, &'static str> {
+    if let Some(g) = decode_fast(buf) {
+        return Ok(g);
+    }
+    // the generic decoder panics or over-allocates on some malformed input
+    inspect(buf, MAX_DEPTH)?;
+    Cursor::new(buf).read_wkb().map_err(|_| INVALID)
+}
+
+fn decode_fast(buf: &[u8]) -> Option<Geometry<f64>> {
+    if *buf.first()? != LITTLE_ENDIAN {
+        return None;
+    }
+    let geomtype = read_u32(buf, 1)?;
+    let mut pos = 5;
+    let g = match geomtype {
+        WKB_POINT => {
+            let c = read_coords(buf, &mut pos, 1)?;
+            Point(c[0]).into()
+        }
+        WKB_LINESTRING => {
+            let n = read_u32(buf, pos)? as usize;
+            pos += 4;
+            LineString(read_coords(buf, &mut pos, n)?).into()
+        }
+        WKB_POLYGON => {
+            let nrings = read_u32(buf, pos)? as usize;
+            pos += 4;
+            let mut rings = Vec::with_capacity(nrings.min(buf.len() / 4));
+            for _ in 0..nrings {
+                let n = read_u32(buf, pos)? as usize;
+                pos += 4;
+                rings.push(LineString(read_coords(buf, &mut pos, n)?));
+            }
+            let mut rings = rings.into_iter();
+            let exterior = rings.next().unwrap_or_else(|| LineString(Vec::new()));
+            Polygon::new(exterior, rings.collect()).into()
+        }
+        _ => return None,
+    };
+    Some(g)
+}
+
+/// Validates the structure of a WKB geometry without decoding it and returns its number of
+/// vertices. Fails for GeometryCollections nested deeper than `max_depth`, for parts of
+/// MultiLineStrings and MultiPolygons that are not LineStrings and Polygons, and for counts that
+/// exceed the buffer, so that the generic decoder never recurses or allocates unboundedly.
+pub(crate) fn inspect(buf: &[u8], max_depth: usize) -> Result<usize, &'static str> {
+    inspect_at(buf, &mut 0, max_depth)
+}
+
+fn inspect_at(buf: &[u8], pos: &mut usize, depth: usize) -> Result<usize, &'static str> {
+    if *buf.get(*pos).ok_or(INVALID)? != LITTLE_ENDIAN {
+        return Err("Unsupported WKB byte order");
+    }
+    let geomtype = read_u32(buf, *pos + 1).ok_or(INVALID)?;
+    *pos += 5;
+    match geomtype {
+        WKB_POINT => skip_coords(buf, pos, 1),
+        WKB_LINESTRING | WKB_MULTIPOINT => {
+            let n = read_count(buf, pos)?;
+            skip_coords(buf, pos, n)
+        }
+        WKB_POLYGON => {
+            let rings = read_count(buf, pos)?;
+            if rings == 0 {
+                return Err(INVALID);
+            }
+            let mut vertices = 0;
+            for _ in 0..rings {
+                let n = read_count(buf, pos)?;
+                vertices += skip_coords(buf, pos, n)?;
+            }
+            Ok(vertices)
+        }
+        WKB_MULTILINESTRING | WKB_MULTIPOLYGON | WKB_GEOMETRYCOLLECTION => {
+            let depth = if geomtype == WKB_GEOMETRYCOLLECTION {
+                depth
+                    .checked_sub(1)
+                    .ok_or("GeometryCollection nested too deeply")?
+            } else {
+                depth
+            };
+            let n = read_count(buf, pos)?;
+            // every part needs at least a header, which bounds n by the buffer size
+            if n > (buf.len() - *pos) / 5 {
+                return Err(INVALID);
+            }
+            // the parts of multi geometries must be simple, otherwise they could nest without
+            // counting against the depth
+            let part = match geomtype {
+                WKB_MULTILINESTRING => Some(WKB_LINESTRING),
+                WKB_MULTIPOLYGON => Some(WKB_POLYGON),
+                _ => None,
+            };
+            let mut vertices = 0;
+            for _ in 0..n {
+                if part.is_some() && read_u32(buf, *pos + 1) != part {
+                    return Err(INVALID);
+                }
+                vertices += inspect_at(buf, pos, depth)?;
+            }
+            Ok(vertices)
+        }
+        _ => Err("Unsupported WKB geometry type"),
+    }
+}
+
+fn read_count(buf: &[u8], pos: &mut usize) -> Result<usize, &'static str> {
+    let n = read_u32(buf, *pos).ok_or(INVALID)? as usize;
+    *pos += 4;
+    Ok(n)
+}
+
+fn skip_coords(buf: &[u8], pos: &mut usize, n: usize) -> Result<usize, &'static str> {
+    *pos = n
+        .checked_mul(16)
+        .and_then(|len| pos.checked_add(len))
+        .filter(|&end| end <= buf.len())
+        .ok_or(INVALID)?;
+    Ok(n)
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    let b = buf.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_coords(buf: &[u8], pos: &mut usize, n: usize) -> Option<Vec<Coord<f64>>> {
+    let len = n.checked_mul(16)?;
+    let raw = buf.get(*pos..pos.checked_add(len)?)?;
+    *pos += len;
+    Some(
+        raw.chunks_exact(16)
+            .map(|c| {
+                let mut x = [0; 8];
+                let mut y = [0; 8];
+                x.copy_from_slice(&c[..8]);
+                y.copy_from_slice(&c[8..]);
+                Coord {
+                    x: f64::from_le_bytes(x),
+                    y: f64::from_le_bytes(y),
+                }
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, decode_fast, inspect};
+    use geo_types::{line_string, point, polygon, Geometry, MultiPoint};
+
+    #[test]
+    fn matches_generic_decoder() {
+        let geoms: Vec<Geometry<f64>> = vec![
+            point!(x: 1.5, y: -2.).into(),
+            line_string![(x: 0., y: 0.), (x: 1., y: 2.), (x: 3., y: 4.)].into(),
+            polygon!(
+                exterior: [(x: 0., y: 0.), (x: 4., y: 0.), (x: 4., y: 4.), (x: 0., y: 0.)],
+                interiors: [[(x: 1., y: 1.), (x: 2., y: 1.), (x: 2., y: 2.), (x: 1., y: 1.)]],
+            )
+            .into(),
+        ];
+        for g in geoms {
+            let buf = wkb::geom_to_wkb(&g).unwrap();
+            assert_eq!(decode_fast(&buf), Some(g));
+        }
+
+        let mp: Geometry<f64> = MultiPoint::from(vec![(1., 2.), (3., 4.)]).into();
+        let buf = wkb::geom_to_wkb(&mp).unwrap();
+        assert_eq!(decode_fast(&buf), None);
+        assert_eq!(decode(&buf).unwrap(), mp);
+    }
+
+    #[test]
+    fn truncated() {
+        let g: Geometry<f64> = line_string![(x: 0., y: 0.), (x: 1., y: 2.)].into();
+        let buf = wkb::geom_to_wkb(&g).unwrap();
+        assert_eq!(decode_fast(&buf[..buf.len() - 1]), None);
+        assert!(decode(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn inspect_structure() {
+        use geo_types::GeometryCollection;
+
+        let ls: Geometry<f64> = line_string![(x: 0., y: 0.), (x: 1., y: 2.)].into();
+        let mut g = ls.clone();
+        for _ in 0..3 {
+            g = Geometry::GeometryCollection(GeometryCollection(vec![g, ls.clone()]));
+        }
+        let buf = wkb::geom_to_wkb(&g).unwrap();
+        assert_eq!(inspect(&buf, 3), Ok(8));
+        assert!(inspect(&buf, 2).is_err());
+        assert!(inspect(&buf[..buf.len() - 1], 3).is_err());
+
+        // a collection claiming more parts than the buffer could hold
+        let mut buf = vec![1, 7, 0, 0, 0];
+        buf.extend(u32::MAX.to_le_bytes());
+        assert!(inspect(&buf, 8).is_err());
+        // the generic decoder panics on unknown byte orders and types
+        assert!(inspect(&[2, 1, 0, 0, 0], 8).is_err());
+        assert!(inspect(&[1, 99, 0, 0, 0], 8).is_err());
+    }
+
+    #[test]
+    fn nested_multi_geometries() {
+        // MultiLineStrings and MultiPolygons nested into themselves, each with one part
+        for geomtype in [5u8, 6].iter() {
+            let mut buf = Vec::new();
+            for _ in 0..200_000 {
+                buf.extend([1, *geomtype, 0, 0, 0, 1, 0, 0, 0].iter());
+            }
+            assert!(inspect(&buf, 256).is_err());
+            assert!(decode(&buf).is_err());
+        }
+
+        let g: Geometry<f64> = geo_types::MultiPolygon(vec![polygon!(
+            (x: 0., y: 0.), (x: 1., y: 0.), (x: 1., y: 1.), (x: 0., y: 0.)
+        )])
+        .into();
+        let buf = wkb::geom_to_wkb(&g).unwrap();
+        assert_eq!(inspect(&buf, 0), Ok(4));
+        assert_eq!(decode(&buf).unwrap(), g);
+    }
+}
@@ -0,0 +1,19 @@
+//! Installs the counting allocator, which replaces the allocator of the whole test binary.
+
+use spaten::metrics::{allocation_stats, CountingAllocator};
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+#[test]
+fn counting_allocator() {
+    let before = allocation_stats();
+    let v: Vec<u8> = std::hint::black_box(vec![0; 1 << 20]);
+    let after = allocation_stats();
+    // other threads of the test harness may allocate at the same time, so only lower bounds
+    // hold
+    assert!(after.allocations > before.allocations);
+    assert!(after.allocated_bytes - before.allocated_bytes >= 1 << 20);
+    assert!(after.peak_bytes >= 1 << 20);
+    drop(v);
+}